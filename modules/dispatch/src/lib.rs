@@ -29,11 +29,12 @@
 use codec::Encode;
 // darwinia-network
 use bp_message_dispatch::{
-	CallOrigin, CallValidate, IntoDispatchOrigin, MessageDispatch, MessagePayload, SpecVersion,
+	CallOrigin, CallValidate, IntoDispatchOrigin, MessageDispatch, MessagePayload, PayloadVersion,
+	SpecVersion, VersionedMessagePayload,
 };
 use bp_runtime::{
 	derive_account_id,
-	messages::{DispatchFeePayment, MessageDispatchResult},
+	messages::{DispatchFeePayment, MessageDispatchResult, MessageDispatchStatus},
 	ChainId, SourceAccount,
 };
 // substrate
@@ -116,7 +117,13 @@ pub mod pallet {
 
 	type BridgeMessageIdOf<T, I> = <T as Config<I>>::BridgeMessageId;
 
+	/// The current storage version.
+	///
+	/// The pallet has no storage items, so there are no migrations yet.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(0);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T, I = ()>(PhantomData<(T, I)>);
 
 	#[pallet::hooks]
@@ -130,6 +137,9 @@ pub mod pallet {
 	pub enum Event<T: Config<I>, I: 'static = ()> {
 		/// Message has been rejected before reaching dispatch.
 		MessageRejected(ChainId, BridgeMessageIdOf<T, I>),
+		/// Message has been rejected by dispatcher because its payload version is not supported.
+		/// Last argument is the version of the payload.
+		MessagePayloadVersionUnsupported(ChainId, BridgeMessageIdOf<T, I>, PayloadVersion),
 		/// Message has been rejected by dispatcher because of spec version mismatch.
 		/// Last two arguments are: expected and passed spec version.
 		MessageVersionSpecMismatch(ChainId, BridgeMessageIdOf<T, I>, SpecVersion, SpecVersion),
@@ -154,11 +164,33 @@ pub mod pallet {
 		/// Phantom member, never used. Needed to handle multiple pallet instances.
 		_Dummy(PhantomData<I>),
 	}
+
+	/// Errors that are reported in the `MessageDispatchResult::dispatch_error` when the message
+	/// has not been dispatched.
+	#[pallet::error]
+	pub enum Error<T, I = ()> {
+		/// Message has been rejected before reaching dispatch.
+		MessageRejected,
+		/// The message payload version is not supported.
+		UnsupportedPayloadVersion,
+		/// The message spec version doesn't match the spec version of the runtime.
+		SpecVersionMismatch,
+		/// Failed to decode Call from the message.
+		CallDecodeFailed,
+		/// The proof of the message origin is invalid.
+		InvalidOriginProof,
+		/// The call from the message has been rejected by the call validator.
+		CallValidationFailed,
+		/// The message weight is lower than the weight of the call.
+		CallWeightMismatch,
+		/// The origin account has failed to pay fee for dispatching the message.
+		DispatchFeePaymentFailed,
+	}
 }
 pub use pallet::*;
 
 impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId> for Pallet<T, I> {
-	type Message = MessagePayload<
+	type Message = VersionedMessagePayload<
 		T::SourceChainAccountId,
 		T::TargetChainAccountPublic,
 		T::TargetChainSignature,
@@ -166,7 +198,7 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 	>;
 
 	fn dispatch_weight(message: &Self::Message) -> bp_message_dispatch::Weight {
-		message.weight
+		message.weight()
 	}
 
 	fn pre_dispatch(
@@ -174,14 +206,14 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 		message: Result<&Self::Message, ()>,
 	) -> Result<(), &'static str> {
 		match message {
-			Ok(raw_message) =>
+			Ok(VersionedMessagePayload::V1(raw_message)) =>
 				if let Ok(call) = raw_message.clone().call.into() {
 					return T::CallValidator::check_receiving_before_dispatch(
 						relayer_account,
 						&call,
 					);
 				},
-			Err(_) => {
+			Ok(VersionedMessagePayload::Unsupported(..)) | Err(_) => {
 				log::trace!(
 					target: "runtime::bridge-dispatch",
 					"Message will be rejected in dispatch, still Ok here",
@@ -215,6 +247,34 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 					dispatch_result: false,
 					unspent_weight: Weight::zero(),
 					dispatch_fee_paid_during_dispatch: false,
+					dispatch_error: Some(Error::<T, I>::MessageRejected.into()),
+					dispatch_status: Some(MessageDispatchStatus::Rejected),
+				};
+			},
+		};
+
+		// we only know how to dispatch payloads of supported versions
+		let message = match message {
+			VersionedMessagePayload::V1(message) => message,
+			VersionedMessagePayload::Unsupported(version, _) => {
+				log::trace!(
+					target: "runtime::bridge-dispatch",
+					"Message {:?}/{:?}: unsupported payload version {}",
+					source_chain,
+					id,
+					version,
+				);
+				Self::deposit_event(Event::MessagePayloadVersionUnsupported(
+					source_chain,
+					id,
+					version,
+				));
+				return MessageDispatchResult {
+					dispatch_result: false,
+					unspent_weight: Weight::zero(),
+					dispatch_fee_paid_during_dispatch: false,
+					dispatch_error: Some(Error::<T, I>::UnsupportedPayloadVersion.into()),
+					dispatch_status: Some(MessageDispatchStatus::Rejected),
 				};
 			},
 		};
@@ -225,6 +285,8 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 			dispatch_result: false,
 			unspent_weight: message.weight,
 			dispatch_fee_paid_during_dispatch: false,
+			dispatch_error: None,
+			dispatch_status: None,
 		};
 		let expected_version = <T as frame_system::Config>::Version::get().spec_version;
 		if message.spec_version != expected_version {
@@ -241,6 +303,8 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 				expected_version,
 				message.spec_version,
 			));
+			dispatch_result.dispatch_error = Some(Error::<T, I>::SpecVersionMismatch.into());
+			dispatch_result.dispatch_status = Some(MessageDispatchStatus::SpecVersionMismatch);
 			return dispatch_result;
 		}
 
@@ -255,6 +319,8 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 					id,
 				);
 				Self::deposit_event(Event::MessageCallDecodeFailed(source_chain, id));
+				dispatch_result.dispatch_error = Some(Error::<T, I>::CallDecodeFailed.into());
+				dispatch_result.dispatch_status = Some(MessageDispatchStatus::CallDecodeFailed);
 				return dispatch_result;
 			},
 		};
@@ -288,6 +354,8 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 						target_signature,
 					);
 					Self::deposit_event(Event::MessageSignatureMismatch(source_chain, id));
+					dispatch_result.dispatch_error = Some(Error::<T, I>::InvalidOriginProof.into());
+					dispatch_result.dispatch_status = Some(MessageDispatchStatus::InvalidOrigin);
 					return dispatch_result;
 				}
 
@@ -317,6 +385,8 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 				call,
 			);
 			Self::deposit_event(Event::MessageCallValidateFailed(source_chain, id, e));
+			dispatch_result.dispatch_error = Some(Error::<T, I>::CallValidationFailed.into());
+			dispatch_result.dispatch_status = Some(MessageDispatchStatus::CallValidationFailed);
 			return dispatch_result;
 		}
 
@@ -340,6 +410,8 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 				expected_weight,
 				message.weight,
 			));
+			dispatch_result.dispatch_error = Some(Error::<T, I>::CallWeightMismatch.into());
+			dispatch_result.dispatch_status = Some(MessageDispatchStatus::WeightMismatch);
 			return dispatch_result;
 		}
 
@@ -362,6 +434,8 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 				origin_derived_account,
 				message.weight,
 			));
+			dispatch_result.dispatch_error = Some(Error::<T, I>::DispatchFeePaymentFailed.into());
+			dispatch_result.dispatch_status = Some(MessageDispatchStatus::DispatchFeePaymentFailed);
 			return dispatch_result;
 		}
 		dispatch_result.dispatch_fee_paid_during_dispatch = pay_dispatch_fee_at_target_chain;
@@ -370,6 +444,12 @@ impl<T: Config<I>, I: 'static> MessageDispatch<T::AccountId, T::BridgeMessageId>
 		let result = call.dispatch(dispatch_origin);
 		let actual_call_weight = extract_actual_weight(&result, &dispatch_info);
 		dispatch_result.dispatch_result = result.is_ok();
+		dispatch_result.dispatch_error = result.as_ref().err().map(|e| e.error);
+		dispatch_result.dispatch_status = Some(if result.is_ok() {
+			MessageDispatchStatus::Dispatched
+		} else {
+			MessageDispatchStatus::CallFailed
+		});
 		dispatch_result.unspent_weight = message.weight.saturating_sub(actual_call_weight);
 
 		log::trace!(
@@ -498,6 +578,8 @@ mod tests {
 
 	type AccountId = u64;
 	type BridgeMessageId = [u8; 4];
+	type TestMessagePayload =
+		MessagePayload<AccountId, TestAccountPublic, TestSignature, EncodedCall>;
 
 	type Block = MockBlock<TestRuntime>;
 	type UncheckedExtrinsic = MockUncheckedExtrinsic<TestRuntime>;
@@ -642,10 +724,7 @@ mod tests {
 	fn prepare_message(
 		origin: CallOrigin<AccountId, TestAccountPublic, TestSignature>,
 		call: RuntimeCall,
-	) -> <Pallet<TestRuntime> as MessageDispatch<
-		AccountId,
-		<TestRuntime as Config>::BridgeMessageId,
-	>>::Message {
+	) -> TestMessagePayload {
 		MessagePayload {
 			spec_version: TEST_SPEC_VERSION,
 			weight: TEST_WEIGHT,
@@ -655,31 +734,16 @@ mod tests {
 		}
 	}
 
-	fn prepare_root_message(
-		call: RuntimeCall,
-	) -> <Pallet<TestRuntime> as MessageDispatch<
-		AccountId,
-		<TestRuntime as Config>::BridgeMessageId,
-	>>::Message {
+	fn prepare_root_message(call: RuntimeCall) -> TestMessagePayload {
 		prepare_message(CallOrigin::SourceRoot, call)
 	}
 
-	fn prepare_target_message(
-		call: RuntimeCall,
-	) -> <Pallet<TestRuntime> as MessageDispatch<
-		AccountId,
-		<TestRuntime as Config>::BridgeMessageId,
-	>>::Message {
+	fn prepare_target_message(call: RuntimeCall) -> TestMessagePayload {
 		let origin = CallOrigin::TargetAccount(1, TestAccountPublic(1), TestSignature(1));
 		prepare_message(origin, call)
 	}
 
-	fn prepare_source_message(
-		call: RuntimeCall,
-	) -> <Pallet<TestRuntime> as MessageDispatch<
-		AccountId,
-		<TestRuntime as Config>::BridgeMessageId,
-	>>::Message {
+	fn prepare_source_message(call: RuntimeCall) -> TestMessagePayload {
 		let origin = CallOrigin::SourceAccount(1);
 		prepare_message(origin, call)
 	}
@@ -704,11 +768,12 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| unreachable!(),
 			);
 			assert_eq!(result.unspent_weight, weight);
 			assert!(!result.dispatch_result);
+			assert_eq!(result.dispatch_status, Some(MessageDispatchStatus::SpecVersionMismatch));
 
 			assert_eq!(
 				System::events(),
@@ -748,11 +813,16 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| unreachable!(),
 			);
 			assert_eq!(result.unspent_weight, Weight::from_parts(7, 0));
 			assert!(!result.dispatch_result);
+			assert_eq!(
+				result.dispatch_error,
+				Some(Error::<TestRuntime, ()>::CallWeightMismatch.into())
+			);
+			assert_eq!(result.dispatch_status, Some(MessageDispatchStatus::WeightMismatch));
 
 			assert_eq!(
 				System::events(),
@@ -791,7 +861,7 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| unreachable!(),
 			);
 			assert_eq!(result.unspent_weight, weight);
@@ -842,6 +912,48 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn should_fail_on_unsupported_payload_version() {
+		new_test_ext().execute_with(|| {
+			let id = [0; 4];
+			let relayer_account = 1;
+
+			const UNSUPPORTED_PAYLOAD_VERSION: PayloadVersion = 42;
+			let message =
+				VersionedMessagePayload::Unsupported(UNSUPPORTED_PAYLOAD_VERSION, vec![1, 2, 3]);
+			assert_eq!(Dispatch::dispatch_weight(&message), Weight::zero());
+			assert_eq!(Dispatch::pre_dispatch(&relayer_account, Ok(&message)), Ok(()));
+
+			System::set_block_number(1);
+			let result = Dispatch::dispatch(
+				SOURCE_CHAIN_ID,
+				TARGET_CHAIN_ID,
+				&relayer_account,
+				id,
+				Ok(message),
+				|_, _| unreachable!(),
+			);
+			assert_eq!(result.unspent_weight, Weight::zero());
+			assert!(!result.dispatch_result);
+			assert_eq!(result.dispatch_status, Some(MessageDispatchStatus::Rejected));
+
+			assert_eq!(
+				System::events(),
+				vec![EventRecord {
+					phase: Phase::Initialization,
+					event: RuntimeEvent::Dispatch(
+						call_dispatch::Event::<TestRuntime>::MessagePayloadVersionUnsupported(
+							SOURCE_CHAIN_ID,
+							id,
+							UNSUPPORTED_PAYLOAD_VERSION,
+						)
+					),
+					topics: vec![],
+				}],
+			);
+		});
+	}
+
 	#[test]
 	fn should_fail_on_call_decode() {
 		new_test_ext().execute_with(|| {
@@ -861,7 +973,7 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| unreachable!(),
 			);
 			assert_eq!(result.unspent_weight, weight);
@@ -902,7 +1014,7 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| unreachable!(),
 			);
 			assert_eq!(result.unspent_weight, weight);
@@ -945,7 +1057,7 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| Err(()),
 			);
 			assert_eq!(result.unspent_weight, weight);
@@ -990,7 +1102,7 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| Ok(()),
 			);
 			assert!(result.dispatch_fee_paid_during_dispatch);
@@ -1028,11 +1140,13 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| unreachable!(),
 			);
 			assert!(!result.dispatch_fee_paid_during_dispatch);
 			assert!(!result.dispatch_result);
+			assert_eq!(result.dispatch_error, Some(sp_runtime::DispatchError::BadOrigin));
+			assert_eq!(result.dispatch_status, Some(MessageDispatchStatus::CallFailed));
 
 			assert_eq!(
 				System::events(),
@@ -1066,7 +1180,7 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| unreachable!(),
 			);
 			assert!(!result.dispatch_fee_paid_during_dispatch);
@@ -1104,7 +1218,7 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| unreachable!(),
 			);
 			assert!(!result.dispatch_fee_paid_during_dispatch);
@@ -1142,7 +1256,7 @@ mod tests {
 				TARGET_CHAIN_ID,
 				&relayer_account,
 				id,
-				Ok(message),
+				Ok(message.into()),
 				|_, _| unreachable!(),
 			);
 			assert!(!result.dispatch_fee_paid_during_dispatch);
@@ -129,6 +129,17 @@ pub mod pallet {
 		),
 		/// Reward distribute of the order. \[lane_id, message_nonce, rewards\]
 		OrderReward(LaneId, MessageNonce, RewardItem<T::AccountId, BalanceOf<T, I>>),
+		/// Re-price (or create) the order after its message fee has been increased. \[lane_id,
+		/// message_nonce, order_fee, assigned_relayers, out_of_slots_time\]
+		OrderRepriced(
+			LaneId,
+			MessageNonce,
+			BalanceOf<T, I>,
+			Vec<T::AccountId>,
+			Option<T::BlockNumber>,
+		),
+		/// Refund the unused additional message fee. \[lane_id, message_nonce, payer, amount\]
+		OrderFeeTopUpRefunded(LaneId, MessageNonce, T::AccountId, BalanceOf<T, I>),
 	}
 
 	#[pallet::error]
@@ -184,6 +195,24 @@ pub mod pallet {
 		Order<T::AccountId, T::BlockNumber, BalanceOf<T, I>>,
 		OptionQuery,
 	>;
+	// Orders of messages that have expired before they have been delivered. The assigned
+	// relayers of such orders are not slashed.
+	#[pallet::storage]
+	#[pallet::getter(fn expired_order)]
+	pub type ExpiredOrders<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, (LaneId, MessageNonce), (), OptionQuery>;
+
+	// Additional message fees that have not (yet) been used to re-price the order. They are
+	// refunded to their payers when the message is confirmed or cancelled.
+	#[pallet::storage]
+	#[pallet::getter(fn order_fee_top_ups)]
+	pub type OrderFeeTopUps<T: Config<I>, I: 'static = ()> = StorageMap<
+		_,
+		Blake2_128Concat,
+		(LaneId, MessageNonce),
+		Vec<(T::AccountId, BalanceOf<T, I>)>,
+		ValueQuery,
+	>;
 
 	#[pallet::storage]
 	#[pallet::getter(fn collateral_slash_protect)]
@@ -225,8 +254,14 @@ pub mod pallet {
 		}
 	}
 
+	/// The current storage version.
+	///
+	/// The storage layout has never been changed, so there are no migrations yet.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(0);
+
 	#[pallet::pallet]
 	#[pallet::without_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T, I = ()>(_);
 
 	#[pallet::hooks]
@@ -54,8 +54,8 @@ use sp_runtime::{
 use crate::{
 	self as pallet_fee_market,
 	s2s::{
-		payment::calculate_rewards, FeeMarketMessageAcceptedHandler,
-		FeeMarketMessageConfirmedHandler,
+		payment::{calculate_rewards, refund_fee_top_ups},
+		FeeMarketMessageAcceptedHandler, FeeMarketMessageConfirmedHandler, FeeMarketPayment,
 	},
 	*,
 };
@@ -296,6 +296,10 @@ impl MessageDeliveryAndDispatchPayment<RuntimeOrigin, AccountId, TestMessageFee>
 		received_range: &RangeInclusive<MessageNonce>,
 		relayer_fund_account: &AccountId,
 	) {
+		for nonce in received_range.clone() {
+			refund_fee_top_ups::<Test, ()>(lane_id, nonce, relayer_fund_account);
+		}
+
 		let rewards_items = calculate_rewards::<Test, ()>(
 			lane_id,
 			messages_relayers,
@@ -350,6 +354,34 @@ impl MessageDeliveryAndDispatchPayment<RuntimeOrigin, AccountId, TestMessageFee>
 		let treasury_key = (b":relayer-reward:", &treasury_account, treasury_sum).encode();
 		frame_support::storage::unhashed::put(&treasury_key, &true);
 	}
+
+	fn refund_fee(
+		lane_id: LaneId,
+		nonce: MessageNonce,
+		_sender: &AccountId,
+		_fee: &TestMessageFee,
+		relayer_fund_account: &AccountId,
+	) -> Result<(), VerificationError> {
+		Orders::<Test>::remove((lane_id, nonce));
+		refund_fee_top_ups::<Test, ()>(lane_id, nonce, relayer_fund_account);
+		Ok(())
+	}
+
+	fn on_message_fee_increased(
+		lane_id: LaneId,
+		nonce: MessageNonce,
+		submitter: &RuntimeOrigin,
+		additional_fee: &Balance,
+		fee: &Balance,
+	) -> Weight {
+		FeeMarketPayment::<Test, (), Balances>::on_message_fee_increased(
+			lane_id,
+			nonce,
+			submitter,
+			additional_fee,
+			fee,
+		)
+	}
 }
 /// Source header chain that is used in tests.
 #[derive(Debug)]
@@ -366,6 +398,10 @@ impl SourceHeaderChain<TestMessageFee> for TestSourceHeaderChain {
 			.map(|proof| proof.into_iter().collect())
 			.map_err(|_| VerificationError::Other(TEST_ERROR))
 	}
+
+	fn lanes_count(proof: &Self::MessagesProof) -> u32 {
+		proof.result.as_ref().map(|lanes| lanes.len().max(1) as u32).unwrap_or(1)
+	}
 }
 
 /// Source header chain that is used in tests.
@@ -409,6 +445,9 @@ impl sp_runtime::traits::Convert<H256, AccountId> for AccountIdConverter {
 // >>> End mock pallet-bridges-message config data
 
 frame_support::parameter_types! {
+	pub const MaxFailedMessagesAtInboundLane: u64 = 2;
+	pub const FailedMessageLifetime: u64 = 10;
+	pub const MessageCancellationTimeout: u64 = 10;
 	pub const MaxMessagesToPruneAtOnce: u64 = 10;
 	pub const MaxUnrewardedRelayerEntriesAtInboundLane: u64 = 16;
 	pub const MaxUnconfirmedMessagesAtInboundLane: u64 = 32;
@@ -419,24 +458,34 @@ frame_support::parameter_types! {
 impl pallet_bridge_messages::Config for Test {
 	type AccountIdConverter = AccountIdConverter;
 	type BridgedChainId = TestBridgedChainId;
+	type DepositLegacyEvents = frame_support::traits::ConstBool<false>;
+	type FailedMessageLifetime = FailedMessageLifetime;
 	type InboundMessageFee = TestMessageFee;
 	type InboundPayload = TestPayload;
 	type InboundRelayer = TestRelayer;
 	type LaneMessageVerifier = TestLaneMessageVerifier;
+	type MaxDeferredMessagesAtInboundLane = frame_support::traits::ConstU32<0>;
+	type MaxFailedMessagePayloadSize = frame_support::traits::ConstU32<1024>;
+	type MaxFailedMessagesAtInboundLane = MaxFailedMessagesAtInboundLane;
 	type MaxMessagesToPruneAtOnce = MaxMessagesToPruneAtOnce;
+	type MaxOutboundMessagesInBatch = frame_support::traits::ConstU32<4>;
 	type MaxUnconfirmedMessagesAtInboundLane = MaxUnconfirmedMessagesAtInboundLane;
 	type MaxUnrewardedRelayerEntriesAtInboundLane = MaxUnrewardedRelayerEntriesAtInboundLane;
 	type MaximalOutboundPayloadSize = frame_support::traits::ConstU32<4096>;
+	type MessageCancellationTimeout = MessageCancellationTimeout;
 	type MessageDeliveryAndDispatchPayment = TestMessageDeliveryAndDispatchPayment;
 	type MessageDispatch = TestMessageDispatch;
+	type MessagesCommitmentHasher = BlakeTwo256;
 	type OnDeliveryConfirmed = FeeMarketMessageConfirmedHandler<Self, ()>;
 	type OnMessageAccepted = FeeMarketMessageAcceptedHandler<Self, ()>;
+	type OnMessageDeliveryResult = ();
 	type OutboundMessageFee = TestMessageFee;
 	type OutboundPayload = TestPayload;
 	type Parameter = TestMessagesParameter;
 	type RuntimeEvent = RuntimeEvent;
 	type SourceHeaderChain = TestSourceHeaderChain;
 	type TargetHeaderChain = TestTargetHeaderChain;
+	type UnixTime = Timestamp;
 	type WeightInfo = ();
 }
 
@@ -553,6 +602,8 @@ pub const fn dispatch_result(unspent_weight: Weight) -> MessageDispatchResult {
 		dispatch_result: true,
 		unspent_weight,
 		dispatch_fee_paid_during_dispatch: true,
+		dispatch_error: None,
+		dispatch_status: None,
 	}
 }
 
@@ -572,6 +623,7 @@ pub(crate) fn unrewarded_relayer(
 			} else {
 				Default::default()
 			},
+			dispatch_statuses: Vec::new(),
 		},
 	}
 }
@@ -87,4 +87,18 @@ impl<T: Config<I>, I: 'static> OnDeliveryConfirmed for FeeMarketMessageConfirmed
 		// Storage: FeeMarket Orders (r:1 w:1)
 		<T as frame_system::Config>::DbWeight::get().reads_writes(1, 1)
 	}
+
+	fn on_messages_expired(lane: &LaneId, messages: &[MessageNonce]) -> Weight {
+		// The expired message is delivered, but is not dispatched. Relayers may not be blamed for
+		// that, so its order is remembered to be rewarded without slashing.
+		for message_nonce in messages {
+			if <Orders<T, I>>::contains_key((lane, message_nonce)) {
+				<ExpiredOrders<T, I>>::insert((*lane, *message_nonce), ());
+			}
+		}
+
+		// Storage: FeeMarket Orders (r:1 w:0)
+		// Storage: FeeMarket ExpiredOrders (r:0 w:1)
+		<T as frame_system::Config>::DbWeight::get().reads_writes(1, 1)
+	}
 }
@@ -19,7 +19,7 @@
 // crates.io
 use scale_info::TypeInfo;
 // darwinia-network
-use crate::{Config, Orders, Pallet, *};
+use crate::{types::Order, Config, ExpiredOrders, OrderFeeTopUps, Orders, Pallet, *};
 use bp_messages::{
 	source_chain::{MessageDeliveryAndDispatchPayment, SenderOrigin},
 	MessageNonce, UnrewardedRelayer, VerificationError,
@@ -91,6 +91,11 @@ where
 		received_range: &RangeInclusive<MessageNonce>,
 		relayer_fund_account: &T::AccountId,
 	) {
+		// additional fees that have not been used to re-price orders are returned to their payers
+		for nonce in received_range.clone() {
+			refund_fee_top_ups::<T, I>(lane_id, nonce, relayer_fund_account);
+		}
+
 		let rewards_items = calculate_rewards::<T, I>(
 			lane_id,
 			messages_relayers,
@@ -144,6 +149,172 @@ where
 			treasury_sum,
 		);
 	}
+
+	fn refund_fee(
+		lane_id: LaneId,
+		nonce: MessageNonce,
+		sender: &T::AccountId,
+		fee: &BalanceOf<T, I>,
+		relayer_fund_account: &T::AccountId,
+	) -> Result<(), VerificationError> {
+		// the fee is refunded right before relayers are rewarded for the confirmed messages, so
+		// nobody is rewarded or slashed for the cancelled message that has not been dispatched
+		<Orders<T, I>>::remove((lane_id, nonce));
+		refund_fee_top_ups::<T, I>(lane_id, nonce, relayer_fund_account);
+
+		<T as Config<I>>::Currency::transfer(
+			relayer_fund_account,
+			sender,
+			*fee,
+			// the relayer fund account must stay above ED (needs to be pre-funded)
+			ExistenceRequirement::KeepAlive,
+		)
+		.map_err(|e| VerificationError::Other(e.into()))
+	}
+
+	fn on_message_fee_increased(
+		lane_id: LaneId,
+		nonce: MessageNonce,
+		submitter: &T::RuntimeOrigin,
+		additional_fee: &BalanceOf<T, I>,
+		fee: &BalanceOf<T, I>,
+	) -> Weight {
+		let db_weight = <T as frame_system::Config>::DbWeight::get();
+		// Storage: FeeMarket AssignedRelayers (r:1 w:0)
+		// Storage: FeeMarket Orders (r:1 w:0)
+		// Storage: FeeMarket OrderFeeTopUps (r:1 w:1)
+		let mut weight = db_weight.reads_writes(3, 1);
+
+		// The order is only re-priced if it has been priced below the current market fee and the
+		// new message fee covers it.
+		let market_fee = match Pallet::<T, I>::market_fee() {
+			Some(market_fee) if *fee >= market_fee => market_fee,
+			_ => {
+				record_fee_top_up::<T, I>(lane_id, nonce, submitter, additional_fee);
+				return weight;
+			},
+		};
+		let order = match <Orders<T, I>>::get((lane_id, nonce)) {
+			// The assigned relayers of the existing order keep their slots (and obligations),
+			// only the order fee is raised to the current market fee.
+			Some(mut order) if !order.is_confirmed() && order.fee() < market_fee => {
+				if let Some(last_relayer) = order.assigned_relayers.last_mut() {
+					last_relayer.fee = market_fee;
+				}
+				<Orders<T, I>>::insert((lane_id, nonce), order.clone());
+
+				// Storage: FeeMarket Orders (r:0 w:1)
+				weight = weight.saturating_add(db_weight.writes(1));
+				order
+			},
+			// There are no assigned relayers to re-price, so the order is created the same way
+			// as if the message has been sent right now.
+			None => {
+				let assigned_relayers = match Pallet::<T, I>::assigned_relayers() {
+					Some(assigned_relayers) => assigned_relayers,
+					None => {
+						record_fee_top_up::<T, I>(lane_id, nonce, submitter, additional_fee);
+						return weight;
+					},
+				};
+				let order = Order::new(
+					lane_id,
+					nonce,
+					frame_system::Pallet::<T>::block_number(),
+					T::CollateralPerOrder::get(),
+					assigned_relayers,
+					T::Slot::get(),
+				);
+				<Orders<T, I>>::insert((lane_id, nonce), order.clone());
+				// Once order is created, the assigned relayers's order capacity should reduce by
+				// one. Thus, the whole market needs to re-sort to generate new assigned relayers
+				// set.
+				let _ = Pallet::<T, I>::update_market(|| Ok(()), None);
+
+				// Storage: FeeMarket AssignedRelayers (r:1 w:1)
+				// Storage: FeeMarket Orders (r:0 w:1)
+				// Storage: FeeMarket Relayers (r:2 w:0)
+				// Storage: FeeMarket RelayersMap (r:N w:0)
+				// Storage: Balances Locks (r:N w:0)
+				let relayers_count =
+					Pallet::<T, I>::relayers().map(|ids| ids.len()).unwrap_or_default() as u64;
+				weight = weight.saturating_add(
+					db_weight
+						.reads_writes(3u64.saturating_add(relayers_count.saturating_mul(2)), 2),
+				);
+				order
+			},
+			_ => {
+				record_fee_top_up::<T, I>(lane_id, nonce, submitter, additional_fee);
+				return weight;
+			},
+		};
+
+		// all pending top-ups (including this one) have been used to pay for the re-priced order
+		<OrderFeeTopUps<T, I>>::remove((lane_id, nonce));
+
+		let ids: Vec<T::AccountId> =
+			order.assigned_relayers_slice().iter().map(|r| r.id.clone()).collect();
+		Pallet::<T, I>::deposit_event(Event::OrderRepriced(
+			order.lane,
+			order.message,
+			order.fee(),
+			ids,
+			order.range_end(),
+		));
+
+		weight
+	}
+}
+
+/// Remember the additional message fee, so that it may be refunded to its payer if it is never
+/// used to re-price the order.
+fn record_fee_top_up<T, I>(
+	lane_id: LaneId,
+	nonce: MessageNonce,
+	submitter: &T::RuntimeOrigin,
+	additional_fee: &BalanceOf<T, I>,
+) where
+	T: frame_system::Config + Config<I>,
+	I: 'static,
+	T::RuntimeOrigin: SenderOrigin<T::AccountId>,
+{
+	let payer = match submitter.linked_account() {
+		Some(payer) if !additional_fee.is_zero() => payer,
+		_ => return,
+	};
+
+	<OrderFeeTopUps<T, I>>::mutate((lane_id, nonce), |top_ups| {
+		match top_ups.iter_mut().find(|(id, _)| *id == payer) {
+			Some((_, amount)) => *amount = amount.saturating_add(*additional_fee),
+			None => top_ups.push((payer, *additional_fee)),
+		}
+	});
+}
+
+/// Refund the additional message fees that have not been used to re-price the order back to
+/// their payers.
+pub fn refund_fee_top_ups<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+	nonce: MessageNonce,
+	relayer_fund_account: &T::AccountId,
+) {
+	for (payer, amount) in <OrderFeeTopUps<T, I>>::take((lane_id, nonce)) {
+		let refund_result = <T as Config<I>>::Currency::transfer(
+			relayer_fund_account,
+			&payer,
+			amount,
+			// the relayer fund account must stay above ED (needs to be pre-funded)
+			ExistenceRequirement::KeepAlive,
+		);
+
+		match refund_result {
+			Ok(_) => Pallet::<T, I>::deposit_event(Event::OrderFeeTopUpRefunded(
+				lane_id, nonce, payer, amount,
+			)),
+			Err(e) => log::error!("Refund, to {:?} amount {:?}: {:?}", payer, amount, e),
+		}
+	}
 }
 
 /// Calculate rewards for messages_relayers, confirmation relayers, treasury_sum,
@@ -170,7 +341,12 @@ where
 				let mut reward_item = RewardItem::new();
 				let order_collater = order.collateral_per_assigned_relayer;
 
+				let is_expired = <ExpiredOrders<T, I>>::take((lane_id, message_nonce)).is_some();
 				let (message_reward, treasury_reward) = match order.confirmed_info() {
+					// When the message has expired before it has been delivered, the assigned
+					// relayers are not slashed. All reward will distribute to the message deliver
+					// relayer and message confirm relayer.
+					_ if is_expired => (order.fee(), None),
 					// When the order is confirmed at the first slot, no assigned relayers will be
 					// not slashed in this case. The total reward to the message deliver relayer and
 					// message confirm relayer is the confirmed slot price(first slot price), the
@@ -19,19 +19,24 @@
 // --- std ---
 use std::collections::BTreeMap;
 // --- paritytech ---
-use frame_support::{assert_err, assert_ok, traits::OnFinalize};
+use frame_support::{
+	assert_err, assert_ok,
+	traits::{Currency, OnFinalize},
+};
 use sp_runtime::{traits::AccountIdConversion, DispatchError, ModuleError};
 // --- darwinia-network ---
 use crate::{
 	assert_market_storage, assert_relayer_info,
 	mock::{
 		receive_messages_delivery_proof, send_regular_message, unrewarded_relayer, AccountId,
-		Balances, ExtBuilder, FeeMarket, Messages, RuntimeEvent, RuntimeOrigin, System, Test,
-		TestMessageDeliveryAndDispatchPayment, REGULAR_PAYLOAD, TEST_LANE_ID, TEST_RELAYER_A,
-		TEST_RELAYER_B,
+		AccountIdConverter, Balances, ExtBuilder, FeeMarket, Messages, RuntimeEvent, RuntimeOrigin,
+		System, Test, TestMessageDeliveryAndDispatchPayment, REGULAR_PAYLOAD, TEST_LANE_ID,
+		TEST_RELAYER_A, TEST_RELAYER_B,
 	},
 	Config, Error, RewardItem, SlashReport,
 };
+use bp_messages::MessageExpiry;
+use bp_runtime::messages::MessageDispatchStatus;
 
 // enroll_and_lock_collateral
 
@@ -1345,6 +1350,126 @@ fn test_order_confirm_then_order_capacity_increase_by_one() {
 		});
 }
 
+#[test]
+fn test_order_repriced_after_message_fee_increased() {
+	let collater_per_order = <Test as Config>::CollateralPerOrder::get();
+	ExtBuilder::default()
+		.with_balances(vec![
+			(1, collater_per_order * 5),
+			(2, collater_per_order * 5),
+			(3, collater_per_order * 5),
+		])
+		.with_relayers(vec![
+			(1, collater_per_order * 4, Some(30)),
+			(2, collater_per_order * 4, Some(50)),
+			(3, collater_per_order * 4, Some(100)),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(2);
+			let (lane, message_nonce) = send_regular_message(1, 100);
+			assert_eq!(FeeMarket::order((&lane, &message_nonce)).unwrap().fee(), 100);
+
+			// the market fee grows, so the order is underpriced now
+			assert_ok!(FeeMarket::update_relay_fee(RuntimeOrigin::signed(3), 150));
+			assert_eq!(FeeMarket::market_fee(), Some(150));
+
+			// the new message fee doesn't cover the market fee => the order is not changed
+			System::set_block_number(10);
+			assert_ok!(Messages::increase_message_fee(
+				RuntimeOrigin::signed(1),
+				lane,
+				message_nonce,
+				20,
+			));
+			let order = FeeMarket::order((&lane, &message_nonce)).unwrap();
+			assert_eq!((order.fee(), order.sent_time), (100, 2));
+			assert_eq!(FeeMarket::order_fee_top_ups((&lane, &message_nonce)), vec![(1, 20)]);
+
+			// the new message fee covers the market fee => the order is re-priced, but the
+			// assigned relayers keep their slots
+			let original_slots: Vec<_> =
+				order.assigned_relayers_slice().iter().map(|r| r.valid_range.clone()).collect();
+			assert_ok!(Messages::increase_message_fee(
+				RuntimeOrigin::signed(2),
+				lane,
+				message_nonce,
+				30,
+			));
+			let order = FeeMarket::order((&lane, &message_nonce)).unwrap();
+			assert_eq!((order.fee(), order.sent_time), (150, 2));
+			assert_eq!(
+				order
+					.assigned_relayers_slice()
+					.iter()
+					.map(|r| r.valid_range.clone())
+					.collect::<Vec<_>>(),
+				original_slots,
+			);
+			assert!(FeeMarket::order_fee_top_ups((&lane, &message_nonce)).is_empty());
+			System::assert_has_event(RuntimeEvent::FeeMarket(crate::Event::OrderRepriced(
+				lane,
+				message_nonce,
+				150,
+				vec![1, 2, 3],
+				order.range_end(),
+			)));
+		});
+}
+
+#[test]
+fn test_unused_message_fee_top_up_refunded_on_confirmation() {
+	let collater_per_order = <Test as Config>::CollateralPerOrder::get();
+	ExtBuilder::default()
+		.with_balances(vec![
+			(1, collater_per_order * 5),
+			(2, collater_per_order * 5),
+			(3, collater_per_order * 5),
+			(4, 1_000),
+		])
+		.with_relayers(vec![
+			(1, collater_per_order * 4, Some(30)),
+			(2, collater_per_order * 4, Some(50)),
+			(3, collater_per_order * 4, Some(100)),
+		])
+		.build()
+		.execute_with(|| {
+			let relayer_fund_account =
+				pallet_bridge_messages::relayer_fund_account_id::<AccountId, AccountIdConverter>();
+			let _ = Balances::deposit_creating(&relayer_fund_account, 1_000);
+
+			System::set_block_number(2);
+			let (lane, message_nonce) = send_regular_message(1, 100);
+
+			// the order is priced at the market fee already => the top-up is not used
+			assert_ok!(Messages::increase_message_fee(
+				RuntimeOrigin::signed(4),
+				lane,
+				message_nonce,
+				20,
+			));
+			assert_eq!(FeeMarket::order((&lane, &message_nonce)).unwrap().fee(), 100);
+			assert_eq!(FeeMarket::order_fee_top_ups((&lane, &message_nonce)), vec![(4, 20)]);
+
+			// the top-up is refunded to its payer when the message is confirmed
+			System::set_block_number(3);
+			receive_messages_delivery_proof(
+				1,
+				vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)],
+				1,
+				1,
+			);
+			assert!(FeeMarket::order_fee_top_ups((&lane, &message_nonce)).is_empty());
+			assert_eq!(Balances::free_balance(&relayer_fund_account), 980);
+			System::assert_has_event(RuntimeEvent::FeeMarket(crate::Event::OrderFeeTopUpRefunded(
+				lane,
+				message_nonce,
+				4,
+				20,
+			)));
+		});
+}
+
 // Test payment
 
 #[test]
@@ -1623,6 +1748,57 @@ fn test_payment_with_multiple_message_out_of_deadline() {
 		});
 }
 
+#[test]
+fn test_payment_with_expired_message_out_of_deadline() {
+	let collater_per_order = <Test as Config>::CollateralPerOrder::get();
+	ExtBuilder::default()
+		.with_balances(vec![
+			(1, collater_per_order * 5),
+			(2, collater_per_order * 5),
+			(3, collater_per_order * 5),
+		])
+		.with_relayers(vec![
+			(1, collater_per_order * 4, Some(30)),
+			(2, collater_per_order * 4, Some(50)),
+			(3, collater_per_order * 4, Some(100)),
+		])
+		.build()
+		.execute_with(|| {
+			System::set_block_number(2);
+
+			// Send message that expires at the bridged chain block 100
+			let market_fee = FeeMarket::market_fee().unwrap();
+			assert_ok!(Messages::send_message_with_expiry(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				market_fee,
+				MessageExpiry::BlockNumber(100),
+			));
+
+			// Receive delivery message proof, where the message has expired
+			System::set_block_number(250);
+			let mut relayer_entry = unrewarded_relayer(1, 1, TEST_RELAYER_A);
+			relayer_entry.messages.dispatch_results.set(0, false);
+			relayer_entry.messages.dispatch_statuses = vec![MessageDispatchStatus::Expired];
+			receive_messages_delivery_proof(4, vec![relayer_entry], 1, 1);
+
+			// Rewards order Analysis:
+			//  1. The order's assigned_relayers: [(1, 30, 2-52),(2, 50, 52-102),(3, 100, 102-152)]
+			//  2. The order's fee: 100
+			//  3. The order confirmed out of slot(250 > 152), but the message has expired, so
+			//     assigned relayers are not slashed.
+
+			// delivery_relayer = order_fee * MessageRelayersRewardRatio = 100 * 80% = 80
+			// confirm_relayer = order_fee * ConfirmRelayersRewardRatio = 100 * 20% = 20
+			assert!(TestMessageDeliveryAndDispatchPayment::is_reward_paid(4, 20));
+			assert!(TestMessageDeliveryAndDispatchPayment::is_reward_paid(TEST_RELAYER_A, 80));
+			assert_eq!(Balances::free_balance(2), collater_per_order * 5);
+			assert_eq!(Balances::free_balance(3), collater_per_order * 5);
+			assert!(FeeMarket::expired_order((TEST_LANE_ID, 1)).is_none());
+		});
+}
+
 #[test]
 fn test_payment_cal_reward_with_duplicated_delivery_proof() {
 	let collater_per_order = <Test as Config>::CollateralPerOrder::get();
@@ -57,6 +57,19 @@ impl<
 			return InvalidTransaction::Stale.into();
 		}
 
+		if let Some(pending_change) = crate::PendingAuthoritySetChange::<T, I>::get() {
+			if pending_change.enact_at < bundled_block_number {
+				log::trace!(
+					target: crate::LOG_TARGET,
+					"Rejecting bridged header that skips mandatory header: bundled {:?}, mandatory {:?}",
+					bundled_block_number,
+					pending_change.enact_at,
+				);
+
+				return InvalidTransaction::Call.into();
+			}
+		}
+
 		Ok(ValidTransaction::default())
 	}
 }
@@ -67,7 +80,8 @@ mod tests {
 	use super::FilterCall;
 	use crate::{
 		mock::{run_test, test_header, RuntimeCall, TestNumber, TestRuntime},
-		BestFinalized,
+		storage_types::StoredPendingAuthoritySetChange,
+		BestFinalized, PendingAuthoritySetChange,
 	};
 	use bp_test_utils::make_default_justification;
 
@@ -116,4 +130,21 @@ mod tests {
 			assert!(validate_block_submit(15));
 		});
 	}
+
+	#[test]
+	fn extension_rejects_header_that_skips_mandatory_header() {
+		run_test(|| {
+			// when current best finalized is #10, the pending authority set change is enacted at
+			// #12 and we're trying to import header#15 => tx is rejected
+			sync_to_header_10();
+			PendingAuthoritySetChange::<TestRuntime, ()>::put(StoredPendingAuthoritySetChange {
+				next_authorities: Default::default(),
+				enact_at: 12,
+			});
+			assert!(!validate_block_submit(15));
+
+			// but header#12 is accepted
+			assert!(validate_block_submit(12));
+		});
+	}
 }
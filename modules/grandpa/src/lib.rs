@@ -24,13 +24,21 @@
 //!
 //! The pallet is responsible for tracking GRANDPA validator set hand-offs. We only import headers
 //! with justifications signed by the current validator set we know of. The header is inspected for
-//! a `ScheduledChanges` digest item, which is then used to update to next validator set.
+//! a `ScheduledChanges` digest item, which is then used to update to next validator set. If the
+//! change has non-zero delay, it is enacted when the header at `signal_block + delay` is imported.
+//! Both headers are mandatory and relayers don't pay for importing them. If a relayer has skipped
+//! the header that schedules the delayed change, anyone may import it later along with the ancestry
+//! proof, using the `submit_skipped_authority_set_change` call.
 //!
 //! Since this pallet only tracks finalized headers it does not deal with forks. Forks can only
 //! occur if the GRANDPA validator set on the bridged chain is either colluding or there is a severe
 //! bug causing resulting in an equivocation. Such events are outside the scope of this pallet.
 //! Shall the fork occur on the bridged chain governance intervention will be required to
 //! re-initialize the bridge and track the right fork.
+//!
+//! Forced authority set changes are not supported either. After the bridged chain has performed
+//! such change, the pallet owner (or root) must replace the authority set using the
+//! `force_set_authorities` call.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 // Runtime-generated enums
@@ -46,6 +54,8 @@ pub use weights::WeightInfo;
 #[cfg(feature = "runtime-benchmarks")]
 pub mod benchmarking;
 
+pub mod migration;
+
 mod extension;
 mod storage_types;
 
@@ -56,13 +66,13 @@ use bp_header_chain::{justification::GrandpaJustification, InitializationData};
 use bp_runtime::{
 	BlockNumberOf, BoundedStorageValue, Chain, HashOf, HasherOf, HeaderOf, OwnedBridgeModule,
 };
-use storage_types::StoredAuthoritySet;
+use storage_types::{StoredAuthorityList, StoredAuthoritySet, StoredPendingAuthoritySetChange};
 // substrate
 use frame_support::{ensure, fail, log};
 use frame_system::ensure_signed;
-use sp_consensus_grandpa::{ConsensusLog, GRANDPA_ENGINE_ID};
-use sp_runtime::traits::{Header as HeaderT, Zero};
-use sp_std::{boxed::Box, convert::TryInto};
+use sp_consensus_grandpa::{AuthorityList, ConsensusLog, SetId, GRANDPA_ENGINE_ID};
+use sp_runtime::traits::{Header as HeaderT, Saturating, Zero};
+use sp_std::{boxed::Box, convert::TryInto, vec::Vec};
 
 /// The target that will be used when publishing logs related to this pallet.
 pub const LOG_TARGET: &str = "runtime::bridge-grandpa";
@@ -90,6 +100,10 @@ pub mod pallet {
 
 	#[pallet::config]
 	pub trait Config<I: 'static = ()>: frame_system::Config {
+		/// The overarching event type.
+		type RuntimeEvent: From<Event<Self, I>>
+			+ IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
 		/// The chain we are bridging to here.
 		type BridgedChain: Chain;
 
@@ -129,7 +143,11 @@ pub mod pallet {
 		type WeightInfo: WeightInfo;
 	}
 
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T, I = ()>(PhantomData<(T, I)>);
 
 	#[pallet::hooks]
@@ -162,7 +180,10 @@ pub mod pallet {
 		#[pallet::weight(T::WeightInfo::submit_finality_proof(
 			justification.commit.precommits.len().try_into().unwrap_or(u32::MAX),
 			justification.votes_ancestries.len().try_into().unwrap_or(u32::MAX),
-		))]
+		)
+		// the pending authority set change is read and then written (along with the
+		// `AuthoritySetEnactedAt`) when it is enacted
+		.saturating_add(T::DbWeight::get().reads_writes(1, 2)))]
 		pub fn submit_finality_proof(
 			origin: OriginFor<T>,
 			finality_target: Box<BridgedHeader<T, I>>,
@@ -200,12 +221,18 @@ pub mod pallet {
 			// hard-fork).
 			ensure!(best_finalized.number() < number, <Error<T, I>>::OldHeader);
 
+			// Descendants of the header that enacts the pending authority set change are
+			// finalized by the next set. So we can't import them before we know that the change
+			// has been enacted.
+			if let Some(pending_change) = <PendingAuthoritySetChange<T, I>>::get() {
+				ensure!(*number <= pending_change.enact_at, <Error<T, I>>::MissingMandatoryHeader);
+			}
+
 			let authority_set = <CurrentAuthoritySet<T, I>>::get();
 			let set_id = authority_set.set_id;
 			verify_justification::<T, I>(&justification, hash, *number, authority_set.into())?;
 
-			let is_authorities_change_enacted =
-				try_enact_authority_change::<T, I>(&finality_target, set_id)?;
+			let is_mandatory_header = try_enact_authority_change::<T, I>(&finality_target, set_id)?;
 			let finality_target = StoredBridgedHeader::<T, I>::try_from_inner(*finality_target)
 				.map_err(|e| {
 					log::error!(
@@ -226,12 +253,12 @@ pub mod pallet {
 				hash
 			);
 
-			// mandatory header is a header that changes authorities set. The pallet can't go
-			// further without importing this header. So every bridge MUST import mandatory headers.
+			// mandatory header is a header that schedules or enacts authorities set change. The
+			// pallet can't go further without importing this header. So every bridge MUST import
+			// mandatory headers.
 			//
 			// We don't want to charge extra costs for mandatory operations. So relayer is not
 			// paying fee for mandatory headers import transactions.
-			let is_mandatory_header = is_authorities_change_enacted;
 			let pays_fee = if is_mandatory_header { Pays::No } else { Pays::Yes };
 
 			Ok(pays_fee.into())
@@ -247,7 +274,7 @@ pub mod pallet {
 		/// with practically no checks in terms of the validity of the data. It is important that
 		/// you ensure that valid data is being passed in.
 		#[pallet::call_index(1)]
-		#[pallet::weight((T::DbWeight::get().reads_writes(2, 5), DispatchClass::Operational))]
+		#[pallet::weight((T::DbWeight::get().reads_writes(2, 7), DispatchClass::Operational))]
 		pub fn initialize(
 			origin: OriginFor<T>,
 			init_data: super::InitializationData<BridgedHeader<T, I>>,
@@ -287,6 +314,153 @@ pub mod pallet {
 		) -> DispatchResult {
 			<Self as OwnedBridgeModule<_>>::set_operating_mode(origin, operating_mode)
 		}
+
+		/// Replace the current GRANDPA authority set.
+		///
+		/// The pallet is unable to follow forced authority set changes of the bridged chain, so
+		/// this call is the way to recover after such change. If `best_finalized` header is
+		/// provided, it is imported as the new best finalized header. Its number must be greater
+		/// than the number of the current best finalized header. Previously imported headers are
+		/// kept.
+		///
+		/// Like the `initialize`, this call writes to storage with practically no checks in terms
+		/// of the validity of the data.
+		///
+		/// May only be called either by root, or by `PalletOwner`.
+		#[pallet::call_index(4)]
+		#[pallet::weight((T::DbWeight::get().reads_writes(4, 8), DispatchClass::Operational))]
+		pub fn force_set_authorities(
+			origin: OriginFor<T>,
+			authority_list: AuthorityList,
+			set_id: SetId,
+			best_finalized: Option<Box<BridgedHeader<T, I>>>,
+		) -> DispatchResultWithPostInfo {
+			Self::ensure_owner_or_root(origin)?;
+
+			let (best_finalized_number, _) =
+				<BestFinalized<T, I>>::get().ok_or(<Error<T, I>>::NotInitialized)?;
+			let authority_set = StoredAuthoritySet::<T, I>::try_new(authority_list, set_id)
+				.map_err(|_| <Error<T, I>>::TooManyAuthoritiesInSet)?;
+			let best_finalized = match best_finalized {
+				Some(header) => {
+					let (hash, number) = (header.hash(), *header.number());
+					ensure!(best_finalized_number < number, <Error<T, I>>::OldHeader);
+					let header = StoredBridgedHeader::<T, I>::try_from_inner(*header)
+						.map_err(|_| <Error<T, I>>::TooLargeHeader)?;
+					Some((header, number, hash))
+				},
+				None => None,
+			};
+
+			<CurrentAuthoritySet<T, I>>::put(authority_set);
+			// the pending change has been scheduled by the previous set and is obsolete now
+			<PendingAuthoritySetChange<T, I>>::kill();
+			let best_finalized = best_finalized.map(|(header, number, hash)| {
+				insert_header::<T, I>(header, hash);
+				(number, hash)
+			});
+			<AuthoritySetEnactedAt<T, I>>::put(
+				best_finalized.map(|(number, _)| number).unwrap_or(best_finalized_number),
+			);
+
+			log::info!(
+				target: LOG_TARGET,
+				"Authority set has been forcibly replaced with set {}. New best finalized header: {:?}",
+				set_id,
+				best_finalized,
+			);
+			Self::deposit_event(Event::AuthoritySetForced { set_id, best_finalized });
+
+			Ok(().into())
+		}
+
+		/// Import the header that schedules the delayed GRANDPA authority set change, which has
+		/// been skipped by relayers.
+		///
+		/// A relayer may import a descendant of the header that schedules the delayed change,
+		/// because it is still finalized by the current set. Then the change is unknown to the
+		/// pallet and it stops importing headers once the change is enacted at the bridged chain.
+		/// The `ancestry` must contain all headers from the child of the `signal_header` to some
+		/// imported header, proving that the `signal_header` is finalized too.
+		///
+		/// If the best finalized header enacts the change, the change is enacted immediately.
+		/// Otherwise it becomes the pending change. Like importing mandatory headers, this call is
+		/// free.
+		#[pallet::call_index(5)]
+		#[pallet::weight(T::WeightInfo::submit_finality_proof(
+			0,
+			ancestry.len().try_into().unwrap_or(u32::MAX),
+		)
+		.saturating_add(T::DbWeight::get().reads_writes(5, 2)))]
+		pub fn submit_skipped_authority_set_change(
+			origin: OriginFor<T>,
+			signal_header: Box<BridgedHeader<T, I>>,
+			ancestry: Vec<BridgedHeader<T, I>>,
+		) -> DispatchResultWithPostInfo {
+			Self::ensure_not_halted().map_err(Error::<T, I>::BridgeModule)?;
+			let _ = ensure_signed(origin)?;
+
+			let (best_finalized_number, _) =
+				<BestFinalized<T, I>>::get().ok_or(<Error<T, I>>::NotInitialized)?;
+
+			// the header is finalized if it is an ancestor of the imported header
+			let mut hash = signal_header.hash();
+			for header in &ancestry {
+				ensure!(*header.parent_hash() == hash, <Error<T, I>>::InvalidAncestryProof);
+				hash = header.hash();
+			}
+			ensure!(
+				!ancestry.is_empty() && <ImportedHeaders<T, I>>::contains_key(hash),
+				<Error<T, I>>::InvalidAncestryProof
+			);
+
+			ensure!(
+				super::find_forced_change(&*signal_header).is_none(),
+				<Error<T, I>>::UnsupportedScheduledChange
+			);
+			let change = super::find_scheduled_change(&*signal_header)
+				.filter(|change| !change.delay.is_zero())
+				.ok_or(<Error<T, I>>::NoDelayedAuthoritySetChange)?;
+			let signal_number = *signal_header.number();
+			let enact_at = signal_number.saturating_add(change.delay);
+
+			// the change must be scheduled by the current set and must not be enacted yet
+			ensure!(
+				signal_number > <AuthoritySetEnactedAt<T, I>>::get()
+					&& enact_at >= best_finalized_number,
+				<Error<T, I>>::ObsoleteAuthoritySetChange
+			);
+			ensure!(
+				!<PendingAuthoritySetChange<T, I>>::exists(),
+				<Error<T, I>>::UnsupportedScheduledChange
+			);
+
+			let next_authorities: StoredAuthorityList<T::MaxBridgedAuthorities> = change
+				.next_authorities
+				.try_into()
+				.map_err(|_| Error::<T, I>::TooManyAuthoritiesInSet)?;
+			let current_set_id = <CurrentAuthoritySet<T, I>>::get().set_id;
+			if enact_at == best_finalized_number {
+				// the best finalized header has been finalized by the current set, so we may
+				// switch to the next set now
+				enact_authority_set::<T, I>(next_authorities, current_set_id, enact_at);
+			} else {
+				schedule_authority_set_change::<T, I>(next_authorities, current_set_id, enact_at);
+			}
+
+			Ok(Pays::No.into())
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config<I>, I: 'static = ()> {
+		/// The current authority set has been replaced by the pallet owner or root. If the best
+		/// finalized header has been changed too, its number and hash are included.
+		AuthoritySetForced {
+			set_id: SetId,
+			best_finalized: Option<(BridgedBlockNumber<T, I>, BridgedBlockHash<T, I>)>,
+		},
 	}
 
 	/// The current number of requests which have written to storage.
@@ -330,6 +504,20 @@ pub mod pallet {
 	pub(super) type CurrentAuthoritySet<T: Config<I>, I: 'static = ()> =
 		StorageValue<_, StoredAuthoritySet<T, I>, ValueQuery>;
 
+	/// The GRANDPA authority set change that has been scheduled with non-zero delay and will be
+	/// enacted by the header with the given number.
+	#[pallet::storage]
+	pub(super) type PendingAuthoritySetChange<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, StoredPendingAuthoritySetChange<T, I>, OptionQuery>;
+
+	/// Number of the header that has enacted the current GRANDPA authority set.
+	///
+	/// Authority set changes that have been scheduled by this header or its ancestors are already
+	/// known to the pallet.
+	#[pallet::storage]
+	pub(super) type AuthoritySetEnactedAt<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, BridgedBlockNumber<T, I>, ValueQuery>;
+
 	/// Optional pallet owner.
 	///
 	/// Pallet owner has a right to halt all pallet operations and then resume it. If it is
@@ -393,7 +581,8 @@ pub mod pallet {
 		UnknownHeader,
 		/// The scheduled authority set change found in the header is unsupported by the pallet.
 		///
-		/// This is the case for non-standard (e.g forced) authority set changes.
+		/// This is the case for non-standard (e.g forced) authority set changes and for changes
+		/// that are scheduled while another change is pending.
 		UnsupportedScheduledChange,
 		/// The pallet is not yet initialized.
 		NotInitialized,
@@ -405,22 +594,37 @@ pub mod pallet {
 		TooManyAuthoritiesInSet,
 		/// Too large header.
 		TooLargeHeader,
+		/// The header is a descendant of the header that enacts the pending authority set change,
+		/// which hasn't been imported yet.
+		MissingMandatoryHeader,
+		/// The ancestry proof doesn't connect the header to any imported header.
+		InvalidAncestryProof,
+		/// The header doesn't schedule the authority set change with non-zero delay.
+		NoDelayedAuthoritySetChange,
+		/// The authority set change has been scheduled before the current set has been enacted,
+		/// or it should have been enacted before the best finalized header.
+		ObsoleteAuthoritySetChange,
 		/// Error generated by the `OwnedBridgeModule` trait.
 		BridgeModule(bp_runtime::OwnedBridgeModuleError),
 	}
 
-	/// Check the given header for a GRANDPA scheduled authority set change. If a change
-	/// is found it will be enacted immediately.
+	/// Check the given header for a GRANDPA scheduled authority set change.
+	///
+	/// If the header enacts the pending change, the change is enacted. If a change without delay
+	/// is found in the header, it is enacted immediately. A change with non-zero delay is saved
+	/// to the storage and is enacted when the header `signal_block + delay` is imported.
 	///
-	/// This function does not support forced changes, or scheduled changes with delays
-	/// since these types of changes are indicative of abnormal behavior from GRANDPA.
+	/// This function does not support forced changes since these types of changes are
+	/// indicative of abnormal behavior from GRANDPA.
 	///
-	/// Returned value will indicate if a change was enacted or not.
+	/// Returned value will indicate if the header is mandatory, i.e. whether it schedules or
+	/// enacts a change.
 	pub(crate) fn try_enact_authority_change<T: Config<I>, I: 'static>(
 		header: &BridgedHeader<T, I>,
 		current_set_id: sp_consensus_grandpa::SetId,
 	) -> Result<bool, sp_runtime::DispatchError> {
-		let mut change_enacted = false;
+		let mut is_mandatory_header = false;
+		let mut current_set_id = current_set_id;
 
 		// We don't support forced changes - at that point governance intervention is required.
 		ensure!(
@@ -428,34 +632,92 @@ pub mod pallet {
 			<Error<T, I>>::UnsupportedScheduledChange
 		);
 
+		// The header has been finalized by the current set, so if it enacts the pending change,
+		// we may switch to the next set now.
+		if let Some(pending_change) = <PendingAuthoritySetChange<T, I>>::get() {
+			if pending_change.enact_at == *header.number() {
+				<PendingAuthoritySetChange<T, I>>::kill();
+				enact_authority_set::<T, I>(
+					pending_change.next_authorities,
+					current_set_id,
+					*header.number(),
+				);
+				current_set_id += 1;
+				is_mandatory_header = true;
+			}
+		}
+
 		if let Some(change) = super::find_scheduled_change(header) {
-			// GRANDPA only includes a `delay` for forced changes, so this isn't valid.
-			ensure!(change.delay == Zero::zero(), <Error<T, I>>::UnsupportedScheduledChange);
-
-			// TODO [#788]: Stop manually increasing the `set_id` here.
-			let next_authorities = StoredAuthoritySet::<T, I> {
-				authorities: change
-					.next_authorities
-					.try_into()
-					.map_err(|_| Error::<T, I>::TooManyAuthoritiesInSet)?,
-				set_id: current_set_id + 1,
-			};
+			// GRANDPA never schedules the standard change while another one is pending, no matter
+			// what its delay is.
+			ensure!(
+				!<PendingAuthoritySetChange<T, I>>::exists(),
+				<Error<T, I>>::UnsupportedScheduledChange
+			);
 
-			// Since our header schedules a change and we know the delay is 0, it must also enact
-			// the change.
-			<CurrentAuthoritySet<T, I>>::put(&next_authorities);
-			change_enacted = true;
+			let next_authorities: StoredAuthorityList<T::MaxBridgedAuthorities> = change
+				.next_authorities
+				.try_into()
+				.map_err(|_| Error::<T, I>::TooManyAuthoritiesInSet)?;
 
-			log::info!(
-				target: LOG_TARGET,
-				"Transitioned from authority set {} to {}! New authorities are: {:?}",
-				current_set_id,
-				current_set_id + 1,
-				next_authorities,
-			);
+			if change.delay.is_zero() {
+				// Since our header schedules a change and we know the delay is 0, it must also
+				// enact the change.
+				enact_authority_set::<T, I>(next_authorities, current_set_id, *header.number());
+			} else {
+				schedule_authority_set_change::<T, I>(
+					next_authorities,
+					current_set_id,
+					(*header.number()).saturating_add(change.delay),
+				);
+			}
+
+			is_mandatory_header = true;
 		};
 
-		Ok(change_enacted)
+		Ok(is_mandatory_header)
+	}
+
+	/// Save the authority set change that is enacted by the header with given number.
+	fn schedule_authority_set_change<T: Config<I>, I: 'static>(
+		next_authorities: StoredAuthorityList<T::MaxBridgedAuthorities>,
+		current_set_id: sp_consensus_grandpa::SetId,
+		enact_at: BridgedBlockNumber<T, I>,
+	) {
+		log::info!(
+			target: LOG_TARGET,
+			"Authority set {} is going to be changed at header {:?}. New authorities are: {:?}",
+			current_set_id,
+			enact_at,
+			next_authorities,
+		);
+
+		<PendingAuthoritySetChange<T, I>>::put(StoredPendingAuthoritySetChange {
+			next_authorities,
+			enact_at,
+		});
+	}
+
+	/// Replace the current authority set with the next one, enacted by the header with given
+	/// number.
+	fn enact_authority_set<T: Config<I>, I: 'static>(
+		authorities: StoredAuthorityList<T::MaxBridgedAuthorities>,
+		current_set_id: sp_consensus_grandpa::SetId,
+		enacted_at: BridgedBlockNumber<T, I>,
+	) {
+		// TODO [#788]: Stop manually increasing the `set_id` here.
+		let next_authorities =
+			StoredAuthoritySet::<T, I> { authorities, set_id: current_set_id + 1 };
+		<CurrentAuthoritySet<T, I>>::put(&next_authorities);
+		<AuthoritySetEnactedAt<T, I>>::put(enacted_at);
+
+		log::info!(
+			target: LOG_TARGET,
+			"Transitioned from authority set {} to {}! New authorities are: {:?}",
+			current_set_id,
+			current_set_id + 1,
+			next_authorities,
+		);
 	}
 
 	/// Verify a GRANDPA justification (finality proof) for a given header.
@@ -537,21 +799,24 @@ pub mod pallet {
 		let initial_hash = header.hash();
 		let header = StoredBridgedHeader::<T, I>::try_from_inner(*header).map_err(|e| {
 			log::error!(
-					target: LOG_TARGET,
-					"Failed to initialize bridge. Size of header {:?} ({}) is larger that the configured value {}",
-					initial_hash,
-					e.value_size,
-					e.maximal_size,
-				);
+				target: LOG_TARGET,
+				"Failed to initialize bridge. Size of header {:?} ({}) is larger that the configured value {}",
+				initial_hash,
+				e.value_size,
+				e.maximal_size,
+			);
 
 			Error::<T, I>::TooLargeHeader
 		})?;
 
+		let initial_number = *header.number();
 		<InitialHash<T, I>>::put(initial_hash);
 		<ImportedHashesPointer<T, I>>::put(0);
 		insert_header::<T, I>(header, initial_hash);
 
 		<CurrentAuthoritySet<T, I>>::put(authority_set);
+		<PendingAuthoritySetChange<T, I>>::kill();
+		<AuthoritySetEnactedAt<T, I>>::put(initial_number);
 
 		<PalletOperatingMode<T, I>>::put(operating_mode);
 		Ok(())
@@ -671,19 +936,22 @@ pub fn initialize_for_benchmarks<T: Config<I>, I: 'static>(header: BridgedHeader
 mod tests {
 	use super::*;
 	use crate::mock::{
-		run_test, test_header, RuntimeOrigin, TestHeader, TestNumber, TestRuntime,
-		MAX_BRIDGED_AUTHORITIES, MAX_HEADER_SIZE,
+		run_test, test_header, RuntimeEvent as TestEvent, RuntimeOrigin, TestHeader, TestNumber,
+		TestRuntime, MAX_BRIDGED_AUTHORITIES, MAX_HEADER_SIZE,
 	};
 	use bp_runtime::BasicOperatingMode;
 	use bp_test_utils::{
 		authority_list, generate_owned_bridge_module_tests, make_default_justification,
-		make_justification_for_header, JustificationGeneratorParams, ALICE, BOB,
+		make_justification_for_header, test_keyring, Account, JustificationGeneratorParams, ALICE,
+		BOB, CHARLIE, DAVE, EVE,
 	};
 	use codec::Encode;
 	use frame_support::{
 		assert_err, assert_noop, assert_ok, dispatch::PostDispatchInfo,
 		storage::generator::StorageValue,
 	};
+	use frame_system::{EventRecord, Pallet as System, Phase};
+	use sp_consensus_grandpa::AuthorityWeight;
 	use sp_runtime::{Digest, DigestItem, DispatchError};
 
 	fn initialize_substrate_bridge() {
@@ -719,6 +987,36 @@ mod tests {
 		)
 	}
 
+	fn submit_finality_proof_by_set(
+		header: TestHeader,
+		set_id: sp_consensus_grandpa::SetId,
+		authorities: Vec<(Account, AuthorityWeight)>,
+	) -> frame_support::dispatch::DispatchResultWithPostInfo {
+		let justification = make_justification_for_header(JustificationGeneratorParams {
+			header: header.clone(),
+			set_id,
+			authorities,
+			..Default::default()
+		});
+
+		Pallet::<TestRuntime>::submit_finality_proof(
+			RuntimeOrigin::signed(1),
+			Box::new(header),
+			justification,
+		)
+	}
+
+	fn submit_skipped_authority_set_change(
+		signal_header: TestHeader,
+		ancestry: Vec<TestHeader>,
+	) -> frame_support::dispatch::DispatchResultWithPostInfo {
+		Pallet::<TestRuntime>::submit_skipped_authority_set_change(
+			RuntimeOrigin::signed(1),
+			Box::new(signal_header),
+			ancestry,
+		)
+	}
+
 	fn next_block() {
 		use frame_support::traits::OnInitialize;
 
@@ -737,6 +1035,36 @@ mod tests {
 		Digest { logs: vec![DigestItem::Consensus(GRANDPA_ENGINE_ID, consensus_log.encode())] }
 	}
 
+	fn next_set_keyring() -> Vec<(Account, AuthorityWeight)> {
+		vec![(CHARLIE, 1), (DAVE, 1), (EVE, 1)]
+	}
+
+	fn next_set_authority_list() -> sp_consensus_grandpa::AuthorityList {
+		next_set_keyring().into_iter().map(|(id, weight)| (id.into(), weight)).collect()
+	}
+
+	fn next_set_change_log(delay: u64) -> Digest {
+		let consensus_log =
+			ConsensusLog::<TestNumber>::ScheduledChange(sp_consensus_grandpa::ScheduledChange {
+				next_authorities: next_set_authority_list(),
+				delay,
+			});
+
+		Digest { logs: vec![DigestItem::Consensus(GRANDPA_ENGINE_ID, consensus_log.encode())] }
+	}
+
+	fn header_with_digest(number: TestNumber, digest: Digest) -> TestHeader {
+		let mut header = test_header(number);
+		header.digest = digest;
+		header
+	}
+
+	fn child_header(parent: &TestHeader) -> TestHeader {
+		let mut header = test_header(parent.number + 1);
+		header.parent_hash = parent.hash();
+		header
+	}
+
 	fn forced_change_log(delay: u64) -> Digest {
 		let consensus_log = ConsensusLog::<TestNumber>::ForcedChange(
 			delay,
@@ -1018,30 +1346,296 @@ mod tests {
 	}
 
 	#[test]
-	fn importing_header_rejects_header_with_scheduled_change_delay() {
+	fn importing_header_schedules_authority_set_change_with_delay() {
 		run_test(|| {
 			initialize_substrate_bridge();
 
-			// Need to update the header digest to indicate that our header signals an authority set
-			// change. However, the change doesn't happen until the next block.
-			let mut header = test_header(2);
-			header.digest = change_log(1);
+			// Header#2 schedules the change that is enacted by header#5. The header is mandatory,
+			// so it is imported for free
+			let header = header_with_digest(2, next_set_change_log(3));
+			assert_ok!(
+				submit_finality_proof_by_set(header.clone(), 1, test_keyring()),
+				PostDispatchInfo {
+					actual_weight: None,
+					pays_fee: frame_support::dispatch::Pays::No,
+				},
+			);
+			assert_eq!(<BestFinalized<TestRuntime>>::get().unwrap().1, header.hash());
 
-			// Create a valid justification for the header
-			let justification = make_default_justification(&header);
+			// The authority set is not changed yet
+			assert_eq!(
+				<CurrentAuthoritySet<TestRuntime>>::get(),
+				StoredAuthoritySet::<TestRuntime, ()>::try_new(authority_list(), 1).unwrap(),
+			);
+			assert_eq!(
+				<PendingAuthoritySetChange<TestRuntime>>::get(),
+				Some(StoredPendingAuthoritySetChange {
+					next_authorities: next_set_authority_list().try_into().unwrap(),
+					enact_at: 5,
+				}),
+			);
+		})
+	}
 
-			// Should not be allowed to import this header
+	#[test]
+	fn importing_header_enacts_delayed_authority_set_change() {
+		run_test(|| {
+			initialize_substrate_bridge();
+
+			// Header#2 schedules the change that is enacted by header#5
+			assert_ok!(submit_finality_proof_by_set(
+				header_with_digest(2, next_set_change_log(3)),
+				1,
+				test_keyring(),
+			));
+
+			// Headers before header#5 are still finalized by the current set and are not mandatory
+			assert_ok!(
+				submit_finality_proof_by_set(test_header(3), 1, test_keyring()),
+				PostDispatchInfo {
+					actual_weight: None,
+					pays_fee: frame_support::dispatch::Pays::Yes,
+				},
+			);
+			next_block();
 			assert_err!(
-				Pallet::<TestRuntime>::submit_finality_proof(
-					RuntimeOrigin::signed(1),
-					Box::new(header),
-					justification
+				submit_finality_proof_by_set(test_header(4), 2, next_set_keyring()),
+				<Error<TestRuntime>>::InvalidJustification
+			);
+
+			// Header#5 is finalized by the current set and enacts the change. The header is
+			// mandatory, so it is imported for free
+			assert_ok!(
+				submit_finality_proof_by_set(test_header(5), 1, test_keyring()),
+				PostDispatchInfo {
+					actual_weight: None,
+					pays_fee: frame_support::dispatch::Pays::No,
+				},
+			);
+			assert_eq!(
+				<CurrentAuthoritySet<TestRuntime>>::get(),
+				StoredAuthoritySet::<TestRuntime, ()>::try_new(next_set_authority_list(), 2)
+					.unwrap(),
+			);
+			assert_eq!(<PendingAuthoritySetChange<TestRuntime>>::get(), None);
+
+			// Descendants of header#5 are finalized by the next set
+			next_block();
+			assert_err!(
+				submit_finality_proof_by_set(test_header(6), 1, test_keyring()),
+				<Error<TestRuntime>>::InvalidJustification
+			);
+			assert_ok!(
+				submit_finality_proof_by_set(test_header(6), 2, next_set_keyring()),
+				PostDispatchInfo {
+					actual_weight: None,
+					pays_fee: frame_support::dispatch::Pays::Yes,
+				},
+			);
+		})
+	}
+
+	#[test]
+	fn importing_header_rejects_header_that_skips_delayed_authority_set_change() {
+		run_test(|| {
+			initialize_substrate_bridge();
+
+			// Header#2 schedules the change that is enacted by header#5
+			assert_ok!(submit_finality_proof_by_set(
+				header_with_digest(2, next_set_change_log(3)),
+				1,
+				test_keyring(),
+			));
+
+			// Header#6 can't be imported before header#5, no matter which set has finalized it
+			assert_noop!(
+				submit_finality_proof_by_set(test_header(6), 1, test_keyring()),
+				<Error<TestRuntime>>::MissingMandatoryHeader
+			);
+			assert_noop!(
+				submit_finality_proof_by_set(test_header(6), 2, next_set_keyring()),
+				<Error<TestRuntime>>::MissingMandatoryHeader
+			);
+		})
+	}
+
+	#[test]
+	fn importing_header_rejects_header_that_schedules_change_while_another_is_pending() {
+		run_test(|| {
+			initialize_substrate_bridge();
+
+			// Header#2 schedules the change that is enacted by header#5
+			assert_ok!(submit_finality_proof_by_set(
+				header_with_digest(2, next_set_change_log(3)),
+				1,
+				test_keyring(),
+			));
+
+			// Header#3 schedules another change that is enacted by header#4
+			assert_noop!(
+				submit_finality_proof_by_set(
+					header_with_digest(3, next_set_change_log(1)),
+					1,
+					test_keyring(),
 				),
 				<Error<TestRuntime>>::UnsupportedScheduledChange
 			);
 		})
 	}
 
+	#[test]
+	fn importing_header_rejects_header_that_enacts_change_while_another_is_pending() {
+		run_test(|| {
+			initialize_substrate_bridge();
+
+			// Header#2 schedules the change that is enacted by header#5
+			assert_ok!(submit_finality_proof_by_set(
+				header_with_digest(2, next_set_change_log(3)),
+				1,
+				test_keyring(),
+			));
+
+			// Header#3 schedules and enacts another change
+			assert_noop!(
+				submit_finality_proof_by_set(
+					header_with_digest(3, change_log(0)),
+					1,
+					test_keyring()
+				),
+				<Error<TestRuntime>>::UnsupportedScheduledChange
+			);
+		})
+	}
+
+	#[test]
+	fn skipped_delayed_authority_set_change_is_scheduled() {
+		run_test(|| {
+			initialize_substrate_bridge();
+
+			// Header#2 schedules the change that is enacted by header#5, but the relayer has
+			// imported header#3 instead
+			let signal_header = header_with_digest(2, next_set_change_log(3));
+			let header3 = child_header(&signal_header);
+			assert_ok!(submit_finality_proof_by_set(header3.clone(), 1, test_keyring()));
+			assert_eq!(<PendingAuthoritySetChange<TestRuntime>>::get(), None);
+
+			// The header#2 is proved by the imported header#3
+			assert_ok!(
+				submit_skipped_authority_set_change(signal_header, vec![header3]),
+				PostDispatchInfo {
+					actual_weight: None,
+					pays_fee: frame_support::dispatch::Pays::No,
+				},
+			);
+			assert_eq!(
+				<PendingAuthoritySetChange<TestRuntime>>::get(),
+				Some(StoredPendingAuthoritySetChange {
+					next_authorities: next_set_authority_list().try_into().unwrap(),
+					enact_at: 5,
+				}),
+			);
+
+			// Header#5 enacts the change
+			next_block();
+			assert_noop!(
+				submit_finality_proof_by_set(test_header(6), 1, test_keyring()),
+				<Error<TestRuntime>>::MissingMandatoryHeader
+			);
+			assert_ok!(submit_finality_proof_by_set(test_header(5), 1, test_keyring()));
+			assert_eq!(
+				<CurrentAuthoritySet<TestRuntime>>::get(),
+				StoredAuthoritySet::<TestRuntime, ()>::try_new(next_set_authority_list(), 2)
+					.unwrap(),
+			);
+		})
+	}
+
+	#[test]
+	fn skipped_delayed_authority_set_change_is_enacted_if_best_finalized_header_enacts_it() {
+		run_test(|| {
+			initialize_substrate_bridge();
+
+			// Header#2 schedules the change that is enacted by header#5, but the relayer has
+			// imported header#3 and header#5 without knowing about the change
+			let signal_header = header_with_digest(2, next_set_change_log(3));
+			let header3 = child_header(&signal_header);
+			assert_ok!(submit_finality_proof_by_set(header3.clone(), 1, test_keyring()));
+			assert_ok!(submit_finality_proof_by_set(test_header(5), 1, test_keyring()));
+
+			// Descendants of header#5 are finalized by the next set, which is unknown
+			next_block();
+			assert_err!(
+				submit_finality_proof_by_set(test_header(6), 2, next_set_keyring()),
+				<Error<TestRuntime>>::InvalidJustification
+			);
+
+			// The change is enacted immediately, because header#5 is already imported
+			assert_ok!(submit_skipped_authority_set_change(signal_header, vec![header3]));
+			assert_eq!(
+				<CurrentAuthoritySet<TestRuntime>>::get(),
+				StoredAuthoritySet::<TestRuntime, ()>::try_new(next_set_authority_list(), 2)
+					.unwrap(),
+			);
+			assert_eq!(<PendingAuthoritySetChange<TestRuntime>>::get(), None);
+			assert_eq!(<AuthoritySetEnactedAt<TestRuntime>>::get(), 5);
+			assert_ok!(submit_finality_proof_by_set(test_header(6), 2, next_set_keyring()));
+		})
+	}
+
+	#[test]
+	fn submit_skipped_authority_set_change_rejects_invalid_data() {
+		run_test(|| {
+			initialize_substrate_bridge();
+
+			// Header#0 doesn't schedule any changes
+			assert_ok!(submit_finality_proof(1));
+			assert_noop!(
+				submit_skipped_authority_set_change(test_header(0), vec![test_header(1)]),
+				<Error<TestRuntime>>::NoDelayedAuthoritySetChange
+			);
+
+			// Header#3 is not imported yet and the ancestry is required
+			let signal_header = header_with_digest(2, next_set_change_log(3));
+			let header3 = child_header(&signal_header);
+			assert_noop!(
+				submit_skipped_authority_set_change(signal_header.clone(), vec![header3.clone()]),
+				<Error<TestRuntime>>::InvalidAncestryProof
+			);
+			assert_noop!(
+				submit_skipped_authority_set_change(signal_header.clone(), vec![]),
+				<Error<TestRuntime>>::InvalidAncestryProof
+			);
+
+			// Header#3 is not a child of another header#2
+			assert_ok!(submit_finality_proof_by_set(header3.clone(), 1, test_keyring()));
+			assert_noop!(
+				submit_skipped_authority_set_change(
+					header_with_digest(2, next_set_change_log(4)),
+					vec![header3.clone()],
+				),
+				<Error<TestRuntime>>::InvalidAncestryProof
+			);
+
+			// The change can't be scheduled twice
+			assert_ok!(submit_skipped_authority_set_change(
+				signal_header.clone(),
+				vec![header3.clone()]
+			));
+			assert_noop!(
+				submit_skipped_authority_set_change(signal_header.clone(), vec![header3.clone()]),
+				<Error<TestRuntime>>::UnsupportedScheduledChange
+			);
+
+			// And it can't be enacted twice
+			next_block();
+			assert_ok!(submit_finality_proof_by_set(test_header(5), 1, test_keyring()));
+			assert_noop!(
+				submit_skipped_authority_set_change(signal_header, vec![header3]),
+				<Error<TestRuntime>>::ObsoleteAuthoritySetChange
+			);
+		})
+	}
+
 	#[test]
 	fn importing_header_rejects_header_with_forced_changes() {
 		run_test(|| {
@@ -1067,6 +1661,158 @@ mod tests {
 		})
 	}
 
+	#[test]
+	fn force_set_authorities_may_only_be_called_by_root_or_owner() {
+		run_test(|| {
+			assert_noop!(
+				Pallet::<TestRuntime>::force_set_authorities(
+					RuntimeOrigin::root(),
+					next_set_authority_list(),
+					2,
+					None,
+				),
+				Error::<TestRuntime>::NotInitialized,
+			);
+
+			initialize_substrate_bridge();
+			assert_noop!(
+				Pallet::<TestRuntime>::force_set_authorities(
+					RuntimeOrigin::signed(1),
+					next_set_authority_list(),
+					2,
+					None,
+				),
+				DispatchError::BadOrigin,
+			);
+
+			PalletOwner::<TestRuntime>::put(2);
+			assert_ok!(Pallet::<TestRuntime>::force_set_authorities(
+				RuntimeOrigin::signed(2),
+				next_set_authority_list(),
+				2,
+				None,
+			));
+		})
+	}
+
+	#[test]
+	fn force_set_authorities_recovers_pallet_after_forced_change() {
+		run_test(|| {
+			System::<TestRuntime>::set_block_number(1);
+			initialize_substrate_bridge();
+			assert_ok!(submit_finality_proof(1));
+			let header1_hash = test_header(1).hash();
+
+			// Header#2 signals the forced change, so the pallet can't go further
+			let header2 = header_with_digest(2, forced_change_log(0));
+			assert_noop!(
+				submit_finality_proof_by_set(header2.clone(), 1, test_keyring()),
+				<Error<TestRuntime>>::UnsupportedScheduledChange
+			);
+			next_block();
+			assert_err!(
+				submit_finality_proof_by_set(test_header(3), 2, next_set_keyring()),
+				<Error<TestRuntime>>::InvalidJustification
+			);
+
+			// Owner replaces the authority set and imports header#2
+			assert_ok!(Pallet::<TestRuntime>::force_set_authorities(
+				RuntimeOrigin::root(),
+				next_set_authority_list(),
+				2,
+				Some(Box::new(header2.clone())),
+			));
+			assert_eq!(
+				<CurrentAuthoritySet<TestRuntime>>::get(),
+				StoredAuthoritySet::<TestRuntime, ()>::try_new(next_set_authority_list(), 2)
+					.unwrap(),
+			);
+			assert_eq!(<BestFinalized<TestRuntime>>::get(), Some((2, header2.hash())));
+			assert!(Pallet::<TestRuntime>::is_known_header(header1_hash));
+			assert!(Pallet::<TestRuntime>::is_known_header(header2.hash()));
+			assert_eq!(
+				System::<TestRuntime>::events(),
+				vec![EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Grandpa(Event::AuthoritySetForced {
+						set_id: 2,
+						best_finalized: Some((2, header2.hash())),
+					}),
+					topics: vec![],
+				}],
+			);
+
+			// Descendants of header#2 are finalized by the new set
+			assert_ok!(submit_finality_proof_by_set(test_header(3), 2, next_set_keyring()));
+			assert_eq!(<BestFinalized<TestRuntime>>::get().unwrap().1, test_header(3).hash());
+		})
+	}
+
+	#[test]
+	fn force_set_authorities_keeps_best_finalized_header_if_not_provided() {
+		run_test(|| {
+			initialize_substrate_bridge();
+			assert_ok!(submit_finality_proof_by_set(
+				header_with_digest(2, next_set_change_log(3)),
+				1,
+				test_keyring(),
+			));
+			let best_finalized = <BestFinalized<TestRuntime>>::get();
+
+			assert_ok!(Pallet::<TestRuntime>::force_set_authorities(
+				RuntimeOrigin::root(),
+				next_set_authority_list(),
+				2,
+				None,
+			));
+			assert_eq!(<BestFinalized<TestRuntime>>::get(), best_finalized);
+			assert_eq!(
+				<CurrentAuthoritySet<TestRuntime>>::get(),
+				StoredAuthoritySet::<TestRuntime, ()>::try_new(next_set_authority_list(), 2)
+					.unwrap(),
+			);
+			assert_eq!(<PendingAuthoritySetChange<TestRuntime>>::get(), None);
+		})
+	}
+
+	#[test]
+	fn force_set_authorities_rejects_invalid_data() {
+		run_test(|| {
+			initialize_substrate_bridge();
+			assert_ok!(submit_finality_proof(2));
+
+			assert_noop!(
+				Pallet::<TestRuntime>::force_set_authorities(
+					RuntimeOrigin::root(),
+					next_set_authority_list(),
+					2,
+					Some(Box::new(test_header(2))),
+				),
+				Error::<TestRuntime>::OldHeader,
+			);
+			assert_noop!(
+				Pallet::<TestRuntime>::force_set_authorities(
+					RuntimeOrigin::root(),
+					std::iter::repeat(authority_list().remove(0))
+						.take(MAX_BRIDGED_AUTHORITIES as usize + 1)
+						.collect(),
+					2,
+					None,
+				),
+				Error::<TestRuntime>::TooManyAuthoritiesInSet,
+			);
+			assert_noop!(
+				Pallet::<TestRuntime>::force_set_authorities(
+					RuntimeOrigin::root(),
+					next_set_authority_list(),
+					2,
+					Some(Box::new(header_with_digest(3, large_digest()))),
+				),
+				Error::<TestRuntime>::TooLargeHeader,
+			);
+		})
+	}
+
 	#[test]
 	fn importing_header_rejects_header_with_too_many_authorities() {
 		run_test(|| {
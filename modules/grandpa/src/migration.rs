@@ -0,0 +1,287 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage migrations of the GRANDPA pallet.
+
+// crates.io
+use codec::DecodeAll;
+#[cfg(feature = "try-runtime")]
+use codec::{Decode, Encode};
+// darwinia-network
+use crate::{
+	AuthoritySetEnactedAt, BestFinalized, BridgedBlockHash, BridgedBlockNumber, Config,
+	ImportedHeaders, Pallet, PalletOperatingMode, LOG_TARGET,
+};
+use bp_runtime::BasicOperatingMode;
+// substrate
+#[cfg(feature = "try-runtime")]
+use frame_support::ensure;
+use frame_support::{
+	log, migration,
+	storage::unhashed,
+	traits::{Get, GetStorageVersion, OnRuntimeUpgrade, PalletInfoAccess, StorageVersion},
+	weights::Weight,
+};
+use sp_runtime::traits::Header as HeaderT;
+use sp_std::marker::PhantomData;
+#[cfg(feature = "try-runtime")]
+use sp_std::prelude::*;
+
+/// Migration to the storage version 1.
+pub mod v1 {
+	// darwinia-network
+	use super::*;
+
+	/// Migrates storage of the pallet that has no storage version (version 0) to version 1.
+	///
+	/// Every value is only migrated if it is still in the legacy layout:
+	///
+	/// - the `IsHalted` flag is replaced with the `PalletOperatingMode`;
+	/// - the hash of the best finalized header in the `BestFinalized` is extended with the header
+	///   number. The number is read from the imported header.
+	pub struct MigrateToV1<T, I = ()>(PhantomData<(T, I)>);
+
+	impl<T: Config<I>, I: 'static> OnRuntimeUpgrade for MigrateToV1<T, I> {
+		fn on_runtime_upgrade() -> Weight {
+			let db_weight = T::DbWeight::get();
+			if Pallet::<T, I>::on_chain_storage_version() != 0 {
+				log::info!(
+					target: LOG_TARGET,
+					"Skipping migration to v1: storage version is {:?}",
+					Pallet::<T, I>::on_chain_storage_version(),
+				);
+				return db_weight.reads(1);
+			}
+
+			let (mut reads, mut writes) = (3, 1);
+
+			let pallet_name = <Pallet<T, I> as PalletInfoAccess>::name().as_bytes();
+			if let Some(is_halted) =
+				migration::take_storage_value::<bool>(pallet_name, b"IsHalted", &[])
+			{
+				PalletOperatingMode::<T, I>::put(if is_halted {
+					BasicOperatingMode::Halted
+				} else {
+					BasicOperatingMode::Normal
+				});
+				writes += 2;
+			}
+
+			let legacy_best_finalized = unhashed::get_raw(&BestFinalized::<T, I>::hashed_key())
+				.filter(|raw| {
+					<(BridgedBlockNumber<T, I>, BridgedBlockHash<T, I>)>::decode_all(&mut &raw[..])
+						.is_err()
+				})
+				.and_then(|raw| BridgedBlockHash::<T, I>::decode_all(&mut &raw[..]).ok());
+			if let Some(hash) = legacy_best_finalized {
+				reads += 1;
+				match ImportedHeaders::<T, I>::get(hash) {
+					Some(header) => {
+						BestFinalized::<T, I>::put((*header.number(), hash));
+						writes += 1;
+					},
+					None => log::error!(
+						target: LOG_TARGET,
+						"Failed to migrate best finalized header {:?}: the header is missing",
+						hash,
+					),
+				}
+			}
+
+			StorageVersion::new(1).put::<Pallet<T, I>>();
+			log::info!(target: LOG_TARGET, "Migrated storage to v1");
+
+			db_weight.reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			Ok(Vec::new())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(
+				Pallet::<T, I>::on_chain_storage_version() == 1,
+				"Storage version must be 1 after the migration"
+			);
+			ensure!(
+				!BestFinalized::<T, I>::exists() || BestFinalized::<T, I>::get().is_some(),
+				"Best finalized header has not been migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+/// Migration to the storage version 2.
+pub mod v2 {
+	// darwinia-network
+	use super::*;
+
+	/// Migrates storage of the pallet from version 1 to version 2.
+	///
+	/// The header that has enacted the current authority set is unknown, so the
+	/// `AuthoritySetEnactedAt` is seeded with the number of the best finalized header. Delayed
+	/// authority set changes that have been signalled by this header or its ancestors before the
+	/// migration can't be imported with `submit_skipped_authority_set_change` after it.
+	pub struct MigrateToV2<T, I = ()>(PhantomData<(T, I)>);
+
+	impl<T: Config<I>, I: 'static> OnRuntimeUpgrade for MigrateToV2<T, I> {
+		fn on_runtime_upgrade() -> Weight {
+			let db_weight = T::DbWeight::get();
+			if Pallet::<T, I>::on_chain_storage_version() != 1 {
+				log::info!(
+					target: LOG_TARGET,
+					"Skipping migration to v2: storage version is {:?}",
+					Pallet::<T, I>::on_chain_storage_version(),
+				);
+				return db_weight.reads(1);
+			}
+
+			let (reads, mut writes) = (3, 1);
+			if !AuthoritySetEnactedAt::<T, I>::exists() {
+				if let Some((best_finalized_number, _)) = BestFinalized::<T, I>::get() {
+					AuthoritySetEnactedAt::<T, I>::put(best_finalized_number);
+					writes += 1;
+				}
+			}
+
+			StorageVersion::new(2).put::<Pallet<T, I>>();
+			log::info!(target: LOG_TARGET, "Migrated storage to v2");
+
+			db_weight.reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let expected_enacted_at = if AuthoritySetEnactedAt::<T, I>::exists() {
+				Some(AuthoritySetEnactedAt::<T, I>::get())
+			} else {
+				BestFinalized::<T, I>::get().map(|(number, _)| number)
+			};
+
+			Ok(expected_enacted_at.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(
+				Pallet::<T, I>::on_chain_storage_version() == 2,
+				"Storage version must be 2 after the migration"
+			);
+
+			let expected_enacted_at =
+				Option::<BridgedBlockNumber<T, I>>::decode(&mut &state[..])
+					.map_err(|_| "Failed to decode the expected authority set enactment number")?;
+			if let Some(expected_enacted_at) = expected_enacted_at {
+				ensure!(
+					AuthoritySetEnactedAt::<T, I>::exists()
+						&& AuthoritySetEnactedAt::<T, I>::get() == expected_enacted_at,
+					"Authority set enactment number has not been migrated"
+				);
+			}
+
+			Ok(())
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// darwinia-network
+	use super::*;
+	use crate::{
+		mock::{run_test, test_header, TestRuntime},
+		StoredBridgedHeader,
+	};
+
+	#[test]
+	fn migrate_to_v1_works() {
+		run_test(|| {
+			let header = test_header(5);
+			let hash = header.hash();
+			ImportedHeaders::<TestRuntime>::insert(
+				hash,
+				StoredBridgedHeader::<TestRuntime, ()>::try_from_inner(header).unwrap(),
+			);
+			unhashed::put(&BestFinalized::<TestRuntime>::hashed_key(), &hash);
+			let pallet_name = <Pallet<TestRuntime> as PalletInfoAccess>::name().as_bytes();
+			migration::put_storage_value(pallet_name, b"IsHalted", &[], true);
+
+			v1::MigrateToV1::<TestRuntime>::on_runtime_upgrade();
+
+			assert_eq!(Pallet::<TestRuntime>::on_chain_storage_version(), 1);
+			assert_eq!(BestFinalized::<TestRuntime>::get(), Some((5, hash)));
+			assert_eq!(PalletOperatingMode::<TestRuntime>::get(), BasicOperatingMode::Halted);
+			assert_eq!(migration::get_storage_value::<bool>(pallet_name, b"IsHalted", &[]), None,);
+		});
+	}
+
+	#[test]
+	fn migrate_to_v1_keeps_best_finalized_in_current_layout() {
+		run_test(|| {
+			let hash = test_header(5).hash();
+			BestFinalized::<TestRuntime>::put((5, hash));
+
+			v1::MigrateToV1::<TestRuntime>::on_runtime_upgrade();
+
+			assert_eq!(Pallet::<TestRuntime>::on_chain_storage_version(), 1);
+			assert_eq!(BestFinalized::<TestRuntime>::get(), Some((5, hash)));
+		});
+	}
+
+	#[test]
+	fn migrate_to_v2_works() {
+		run_test(|| {
+			StorageVersion::new(1).put::<Pallet<TestRuntime>>();
+			BestFinalized::<TestRuntime>::put((5, test_header(5).hash()));
+
+			v2::MigrateToV2::<TestRuntime>::on_runtime_upgrade();
+
+			assert_eq!(Pallet::<TestRuntime>::on_chain_storage_version(), 2);
+			assert!(AuthoritySetEnactedAt::<TestRuntime>::exists());
+			assert_eq!(AuthoritySetEnactedAt::<TestRuntime>::get(), 5);
+		});
+	}
+
+	#[test]
+	fn migrate_to_v2_keeps_existing_authority_set_enactment_number() {
+		run_test(|| {
+			StorageVersion::new(1).put::<Pallet<TestRuntime>>();
+			BestFinalized::<TestRuntime>::put((5, test_header(5).hash()));
+			AuthoritySetEnactedAt::<TestRuntime>::put(3);
+
+			v2::MigrateToV2::<TestRuntime>::on_runtime_upgrade();
+
+			assert_eq!(Pallet::<TestRuntime>::on_chain_storage_version(), 2);
+			assert_eq!(AuthoritySetEnactedAt::<TestRuntime>::get(), 3);
+		});
+	}
+
+	#[test]
+	fn migrate_to_v2_is_skipped_if_storage_version_is_not_1() {
+		run_test(|| {
+			StorageVersion::new(2).put::<Pallet<TestRuntime>>();
+			BestFinalized::<TestRuntime>::put((5, test_header(5).hash()));
+
+			v2::MigrateToV2::<TestRuntime>::on_runtime_upgrade();
+
+			assert!(!AuthoritySetEnactedAt::<TestRuntime>::exists());
+		});
+	}
+}
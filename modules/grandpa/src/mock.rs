@@ -50,7 +50,7 @@ frame_support::construct_runtime! {
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
-		Grandpa: grandpa::{Pallet, Call},
+		Grandpa: grandpa::{Pallet, Call, Event<T>},
 	}
 }
 
@@ -79,7 +79,7 @@ impl frame_system::Config for TestRuntime {
 	type OnSetCode = ();
 	type PalletInfo = PalletInfo;
 	type RuntimeCall = RuntimeCall;
-	type RuntimeEvent = ();
+	type RuntimeEvent = RuntimeEvent;
 	type RuntimeOrigin = RuntimeOrigin;
 	type SS58Prefix = ();
 	type SystemWeightInfo = ();
@@ -98,6 +98,7 @@ impl grandpa::Config for TestRuntime {
 	type MaxBridgedAuthorities = frame_support::traits::ConstU32<MAX_BRIDGED_AUTHORITIES>;
 	type MaxBridgedHeaderSize = frame_support::traits::ConstU32<MAX_HEADER_SIZE>;
 	type MaxRequests = MaxRequests;
+	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
 }
 
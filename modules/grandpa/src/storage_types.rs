@@ -16,7 +16,7 @@
 
 //! Wrappers for public types that are implementing `MaxEncodedLen`
 
-use crate::Config;
+use crate::{BridgedBlockNumber, Config};
 
 use bp_header_chain::AuthoritySet;
 use codec::{Decode, Encode, MaxEncodedLen};
@@ -64,3 +64,20 @@ impl<T: Config<I>, I: 'static> From<StoredAuthoritySet<T, I>> for AuthoritySet {
 		AuthoritySet { authorities: t.authorities.into(), set_id: t.set_id }
 	}
 }
+
+/// A GRANDPA authority set change that has been scheduled with non-zero delay, but is not yet
+/// enacted.
+#[derive(Clone, Decode, Encode, Eq, TypeInfo, MaxEncodedLen, RuntimeDebugNoBound)]
+#[scale_info(skip_type_params(T, I))]
+pub struct StoredPendingAuthoritySetChange<T: Config<I>, I: 'static> {
+	/// List of GRANDPA authorities of the next set.
+	pub next_authorities: StoredAuthorityList<<T as Config<I>>::MaxBridgedAuthorities>,
+	/// Number of the bridged chain header that enacts the change.
+	pub enact_at: BridgedBlockNumber<T, I>,
+}
+
+impl<T: Config<I>, I: 'static> PartialEq for StoredPendingAuthoritySetChange<T, I> {
+	fn eq(&self, other: &Self) -> bool {
+		self.enact_at == other.enact_at && self.next_authorities == other.next_authorities
+	}
+}
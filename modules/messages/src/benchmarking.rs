@@ -19,7 +19,7 @@
 use crate::{
 	inbound_lane::InboundLaneStorage, inbound_lane_storage, outbound_lane,
 	outbound_lane::ReceivalConfirmationResult, weights_ext::EXPECTED_DEFAULT_MESSAGE_LENGTH, Call,
-	OutboundLanes,
+	OutboundLanes, OutboundMessagesCommitmentLeaves, OutboundMessagesCommitmentRoot,
 };
 
 use bp_messages::{
@@ -29,8 +29,14 @@ use bp_messages::{
 };
 use bp_runtime::{messages::DispatchFeePayment, StorageProofSize};
 use frame_benchmarking::{account, benchmarks_instance_pallet};
-use frame_support::{traits::Get, weights::Weight};
+use frame_support::{
+	traits::{Get, Hooks},
+	weights::Weight,
+	BoundedVec,
+};
 use frame_system::RawOrigin;
+use sp_core::H256;
+use sp_runtime::traits::Zero;
 use sp_std::{collections::vec_deque::VecDeque, ops::RangeInclusive, prelude::*};
 
 const SEED: u32 = 0;
@@ -126,6 +132,7 @@ benchmarks_instance_pallet! {
 	// added.
 	send_minimal_message_worst_case {
 		let lane_id = T::bench_lane_id();
+		open_bench_lane::<T, I>();
 		let sender = account("sender", 0, SEED);
 		T::endow_account(&sender);
 
@@ -157,6 +164,7 @@ benchmarks_instance_pallet! {
 	// `(send_16_kb_message_worst_case - send_1_kb_message_worst_case) / 15`.
 	send_1_kb_message_worst_case {
 		let lane_id = T::bench_lane_id();
+		open_bench_lane::<T, I>();
 		let sender = account("sender", 0, SEED);
 		T::endow_account(&sender);
 
@@ -194,6 +202,7 @@ benchmarks_instance_pallet! {
 	// `(send_16_kb_message_worst_case - send_1_kb_message_worst_case) / 15`.
 	send_16_kb_message_worst_case {
 		let lane_id = T::bench_lane_id();
+		open_bench_lane::<T, I>();
 		let sender = account("sender", 0, SEED);
 		T::endow_account(&sender);
 
@@ -221,6 +230,80 @@ benchmarks_instance_pallet! {
 		);
 	}
 
+	// Benchmark `send_messages` extrinsic with following conditions:
+	// * outbound lane already has state, so it needs to be read and decoded;
+	// * batch has `i` minimal messages.
+	//
+	// The size of every message is accounted separately, the same way it is done for
+	// `send_message` call.
+	send_messages {
+		let i in 1..T::MaxOutboundMessagesInBatch::get();
+
+		let lane_id = T::bench_lane_id();
+		open_bench_lane::<T, I>();
+		let sender = account("sender", 0, SEED);
+		T::endow_account(&sender);
+		send_regular_message::<T, I>();
+
+		let messages: BoundedVec<_, T::MaxOutboundMessagesInBatch> = (0..i)
+			.map(|_| {
+				let (payload, fee) = T::prepare_outbound_message(MessageParams {
+					size: 0,
+					sender_account: sender.clone(),
+				});
+				(lane_id, payload, fee)
+			})
+			.collect::<Vec<_>>()
+			.try_into()
+			.expect("batch has at most `MaxOutboundMessagesInBatch` messages; qed");
+	}: send_messages(RawOrigin::Signed(sender), messages)
+	verify {
+		assert_eq!(
+			crate::OutboundLanes::<T, I>::get(&T::bench_lane_id()).latest_generated_nonce,
+			i as MessageNonce + 1,
+		);
+	}
+
+	// Benchmark `increase_message_fee` with following conditions:
+	// * message has maximal size;
+	// * submitter account is killed because its balance is less than ED after payment.
+	//
+	// Result of this benchmark is directly used by weight formula of the call.
+	maximal_increase_message_fee {
+		let sender = account("sender", 42, SEED);
+		T::endow_account(&sender);
+
+		let additional_fee = T::account_balance(&sender);
+		let lane_id = T::bench_lane_id();
+		let nonce = 1;
+
+		open_bench_lane::<T, I>();
+		send_regular_message_with_payload::<T, I>(vec![42u8; T::maximal_message_size() as _]);
+	}: increase_message_fee(RawOrigin::Signed(sender.clone()), lane_id, nonce, additional_fee)
+	verify {
+		assert_eq!(T::account_balance(&sender), 0.into());
+	}
+
+	// Benchmark `increase_message_fee` with following conditions:
+	// * message size varies from minimal to maximal;
+	// * submitter account is killed because its balance is less than ED after payment.
+	increase_message_fee {
+		let i in 1..T::maximal_message_size();
+
+		let sender = account("sender", 42, SEED);
+		T::endow_account(&sender);
+
+		let additional_fee = T::account_balance(&sender);
+		let lane_id = T::bench_lane_id();
+		let nonce = 1;
+
+		open_bench_lane::<T, I>();
+		send_regular_message_with_payload::<T, I>(vec![42u8; i as _]);
+	}: increase_message_fee(RawOrigin::Signed(sender.clone()), lane_id, nonce, additional_fee)
+	verify {
+		assert_eq!(T::account_balance(&sender), 0.into());
+	}
+
 	// Benchmark `receive_messages_proof` extrinsic with single minimal-weight message and following conditions:
 	// * proof does not include outbound lane state proof;
 	// * inbound lane already has state, so it needs to be read and decoded;
@@ -545,11 +628,70 @@ benchmarks_instance_pallet! {
 	verify {
 		assert_eq!(OutboundLanes::<T, I>::get(T::bench_lane_id()).latest_received_nonce, 2);
 	}
+
+	// Benchmark outbound messages pruning in `on_idle` with following conditions:
+	// * there's single outbound lane;
+	// * lane has `i` confirmed messages that are pruned.
+	//
+	// Result of this benchmark is used to compute weight of visiting single lane and pruning
+	// single message.
+	prune_outbound_messages {
+		let i in 0..T::MaxMessagesToPruneAtOnce::get() as u32;
+
+		open_bench_lane::<T, I>();
+		for _nonce in 0..=i {
+			send_regular_message::<T, I>();
+		}
+		confirm_message_delivery::<T, I>(i as MessageNonce + 1);
+		// the first message is pruned here, so the lane state is not default
+		outbound_lane::<T, I>(T::bench_lane_id()).prune_messages(1);
+	}: {
+		crate::prune_outbound_messages::<T, I>(Weight::MAX)
+	}
+	verify {
+		assert_eq!(
+			OutboundLanes::<T, I>::get(T::bench_lane_id()).oldest_unpruned_nonce,
+			i as MessageNonce + 2,
+		);
+	}
+
+	// Benchmark `on_finalize` that commits to `i` outbound messages, accepted in the block.
+	//
+	// The per-message cost is paid by message senders, the rest is paid by `on_initialize`.
+	commit_outbound_messages {
+		let i in 0..1_000;
+
+		OutboundMessagesCommitmentLeaves::<T, I>::put(
+			(0..i).map(|i| H256::from_low_u64_be(i as _)).collect::<Vec<_>>(),
+		);
+	}: {
+		crate::Pallet::<T, I>::on_finalize(Zero::zero())
+	}
+	verify {
+		assert_eq!(OutboundMessagesCommitmentRoot::<T, I>::get().is_some(), i != 0);
+	}
+}
+
+fn open_bench_lane<T: Config<I>, I: 'static>() {
+	if crate::Lanes::<T, I>::get(T::bench_lane_id()).is_none() {
+		crate::Pallet::<T, I>::do_open_lane(
+			T::bench_lane_id(),
+			None,
+			Default::default(),
+			Default::default(),
+		)
+		.expect("lane is not registered yet; qed");
+	}
 }
 
 fn send_regular_message<T: Config<I>, I: 'static>() {
 	let mut outbound_lane = outbound_lane::<T, I>(T::bench_lane_id());
-	outbound_lane.send_message(MessageData { payload: vec![], fee: T::message_fee() });
+	outbound_lane.send_message(MessageData { payload: vec![], fee: T::message_fee() }.into());
+}
+
+fn send_regular_message_with_payload<T: Config<I>, I: 'static>(payload: Vec<u8>) {
+	let mut outbound_lane = outbound_lane::<T, I>(T::bench_lane_id());
+	outbound_lane.send_message(MessageData { payload, fee: T::message_fee() }.into());
 }
 
 fn confirm_message_delivery<T: Config<I>, I: 'static>(nonce: MessageNonce) {
@@ -23,11 +23,14 @@ use scale_info::{Type, TypeInfo};
 use crate::Config;
 use bp_messages::{
 	target_chain::{DispatchMessage, DispatchMessageData, MessageDispatch},
-	DeliveredMessages, InboundLaneData, LaneId, MessageKey, MessageNonce, OutboundLaneData,
-	ReceivalResult, UnrewardedRelayer,
+	DeliveredMessages, DeliveredNoncesBitVec, FailedMessage, InboundLaneData,
+	InboundLaneDispatchStatuses, LaneId, LaneKind, MessageExpiry, MessageKey, MessageNonce,
+	OutboundLaneData, ReceivalResult, UnrewardedRelayer,
 };
+use bp_runtime::messages::MessageDispatchStatus;
 // substrate
 use frame_support::{traits::Get, RuntimeDebug};
+use sp_std::marker::PhantomData;
 
 /// Inbound lane storage.
 pub trait InboundLaneStorage {
@@ -46,6 +49,38 @@ pub trait InboundLaneStorage {
 	fn data(&self) -> InboundLaneData<Self::Relayer>;
 	/// Update lane data in the storage.
 	fn set_data(&mut self, data: InboundLaneData<Self::Relayer>);
+	/// Get dispatch statuses of unconfirmed messages from the storage.
+	fn dispatch_statuses(&self) -> InboundLaneDispatchStatuses;
+	/// Update dispatch statuses of unconfirmed messages in the storage.
+	fn set_dispatch_statuses(&mut self, dispatch_statuses: InboundLaneDispatchStatuses);
+	/// Return kind of the lane.
+	fn kind(&self) -> LaneKind;
+	/// Get bitmap of messages that have been delivered ahead of the next expected message.
+	///
+	/// Bit `i` corresponds to the message with nonce `last_delivered_nonce() + 1 + i`. It is
+	/// always empty for ordered lanes.
+	fn delivered_ahead(&self) -> DeliveredNoncesBitVec;
+	/// Update bitmap of messages that have been delivered ahead of the next expected message.
+	fn set_delivered_ahead(&mut self, delivered_ahead: DeliveredNoncesBitVec);
+	/// Get relayer, dispatch result and dispatch status of the message that has been delivered
+	/// ahead.
+	fn message_delivered_ahead(
+		&self,
+		nonce: MessageNonce,
+	) -> Option<(Self::Relayer, bool, Option<MessageDispatchStatus>)>;
+	/// Save relayer, dispatch result and dispatch status of the message that has been delivered
+	/// ahead.
+	fn save_message_delivered_ahead(
+		&mut self,
+		nonce: MessageNonce,
+		relayer: Self::Relayer,
+		dispatch_result: bool,
+		dispatch_status: Option<MessageDispatchStatus>,
+	);
+	/// Remove the message that has been delivered ahead.
+	fn remove_message_delivered_ahead(&mut self, nonce: MessageNonce);
+	/// Returns true if message with given expiry may no longer be dispatched.
+	fn is_message_expired(&self, expiry: &MessageExpiry) -> bool;
 }
 
 /// Inbound lane data wrapper that implements `MaxEncodedLen`.
@@ -103,6 +138,103 @@ impl<T: Config<I>, I: 'static> MaxEncodedLen for StoredInboundLaneData<T, I> {
 	}
 }
 
+/// Inbound lane dispatch statuses wrapper that implements `MaxEncodedLen`.
+///
+/// Statuses are never stored for more than `MaxUnconfirmedMessagesAtInboundLane` messages,
+/// because they're pruned once delivery of messages is confirmed.
+#[derive(Encode, Decode, Clone, RuntimeDebug, PartialEq, Eq)]
+pub struct StoredInboundLaneDispatchStatuses<T: Config<I>, I: 'static>(
+	pub InboundLaneDispatchStatuses,
+	PhantomData<(T, I)>,
+);
+impl<T: Config<I>, I: 'static> From<InboundLaneDispatchStatuses>
+	for StoredInboundLaneDispatchStatuses<T, I>
+{
+	fn from(dispatch_statuses: InboundLaneDispatchStatuses) -> Self {
+		StoredInboundLaneDispatchStatuses(dispatch_statuses, Default::default())
+	}
+}
+impl<T: Config<I>, I: 'static> TypeInfo for StoredInboundLaneDispatchStatuses<T, I> {
+	type Identity = Self;
+
+	fn type_info() -> Type {
+		InboundLaneDispatchStatuses::type_info()
+	}
+}
+impl<T: Config<I>, I: 'static> MaxEncodedLen for StoredInboundLaneDispatchStatuses<T, I> {
+	fn max_encoded_len() -> usize {
+		InboundLaneDispatchStatuses::encoded_size_hint(
+			T::MaxUnconfirmedMessagesAtInboundLane::get() as usize,
+		)
+		.unwrap_or(usize::MAX)
+	}
+}
+
+/// Bitmap of messages that have been delivered ahead, that implements `MaxEncodedLen`.
+///
+/// The bitmap never has more than `MaxUnconfirmedMessagesAtInboundLane` bits, because messages
+/// that are too far from the last confirmed message are rejected.
+#[derive(Encode, Decode, Clone, RuntimeDebug, PartialEq, Eq)]
+pub struct StoredDeliveredNonces<T: Config<I>, I: 'static>(
+	pub DeliveredNoncesBitVec,
+	PhantomData<(T, I)>,
+);
+impl<T: Config<I>, I: 'static> Default for StoredDeliveredNonces<T, I> {
+	fn default() -> Self {
+		StoredDeliveredNonces(Default::default(), Default::default())
+	}
+}
+impl<T: Config<I>, I: 'static> From<DeliveredNoncesBitVec> for StoredDeliveredNonces<T, I> {
+	fn from(delivered_ahead: DeliveredNoncesBitVec) -> Self {
+		StoredDeliveredNonces(delivered_ahead, Default::default())
+	}
+}
+impl<T: Config<I>, I: 'static> TypeInfo for StoredDeliveredNonces<T, I> {
+	type Identity = Self;
+
+	fn type_info() -> Type {
+		DeliveredNoncesBitVec::type_info()
+	}
+}
+impl<T: Config<I>, I: 'static> MaxEncodedLen for StoredDeliveredNonces<T, I> {
+	fn max_encoded_len() -> usize {
+		let max_bytes = (T::MaxUnconfirmedMessagesAtInboundLane::get() as usize)
+			.saturating_add(7)
+			.saturating_div(8);
+		codec::Compact::<u32>::max_encoded_len().saturating_add(max_bytes)
+	}
+}
+
+/// Failed inbound message wrapper that implements `MaxEncodedLen`.
+///
+/// Payloads that are larger than `MaxFailedMessagePayloadSize` are never stored.
+#[derive(Encode, Decode, Clone, RuntimeDebug, PartialEq, Eq)]
+pub struct StoredFailedMessage<T: Config<I>, I: 'static>(
+	pub FailedMessage<T::InboundMessageFee, T::BlockNumber>,
+);
+impl<T: Config<I>, I: 'static> From<FailedMessage<T::InboundMessageFee, T::BlockNumber>>
+	for StoredFailedMessage<T, I>
+{
+	fn from(message: FailedMessage<T::InboundMessageFee, T::BlockNumber>) -> Self {
+		StoredFailedMessage(message)
+	}
+}
+impl<T: Config<I>, I: 'static> TypeInfo for StoredFailedMessage<T, I> {
+	type Identity = Self;
+
+	fn type_info() -> Type {
+		FailedMessage::<T::InboundMessageFee, T::BlockNumber>::type_info()
+	}
+}
+impl<T: Config<I>, I: 'static> MaxEncodedLen for StoredFailedMessage<T, I> {
+	fn max_encoded_len() -> usize {
+		codec::Compact::<u32>::max_encoded_len()
+			.saturating_add(T::MaxFailedMessagePayloadSize::get() as usize)
+			.saturating_add(T::InboundMessageFee::max_encoded_len())
+			.saturating_add(T::BlockNumber::max_encoded_len())
+	}
+}
+
 /// Inbound messages lane.
 pub struct InboundLane<S> {
 	storage: S,
@@ -113,6 +245,11 @@ impl<S: InboundLaneStorage> InboundLane<S> {
 		InboundLane { storage }
 	}
 
+	/// Returns kind of the lane.
+	pub fn kind(&self) -> LaneKind {
+		self.storage.kind()
+	}
+
 	/// Receive state of the corresponding outbound lane.
 	pub fn receive_state_update(
 		&mut self,
@@ -145,16 +282,23 @@ impl<S: InboundLaneStorage> InboundLane<S> {
 		// overlap.
 		match data.relayers.front_mut() {
 			Some(entry) if entry.messages.begin < new_confirmed_nonce => {
-				entry.messages.dispatch_results = entry
-					.messages
-					.dispatch_results
-					.split_off((new_confirmed_nonce + 1 - entry.messages.begin) as _);
+				let confirmed_messages = (new_confirmed_nonce + 1 - entry.messages.begin) as usize;
+				entry.messages.dispatch_results =
+					entry.messages.dispatch_results.split_off(confirmed_messages);
 				entry.messages.begin = new_confirmed_nonce + 1;
 			},
 			_ => {},
 		}
 
 		self.storage.set_data(data);
+
+		// statuses of confirmed messages are no longer required
+		let mut dispatch_statuses = self.storage.dispatch_statuses();
+		if !dispatch_statuses.is_empty() {
+			dispatch_statuses.prune(new_confirmed_nonce);
+			self.storage.set_dispatch_statuses(dispatch_statuses);
+		}
+
 		Some(outbound_lane_data.latest_received_nonce)
 	}
 
@@ -165,13 +309,121 @@ impl<S: InboundLaneStorage> InboundLane<S> {
 		relayer_at_this_chain: &AccountId,
 		nonce: MessageNonce,
 		message_data: DispatchMessageData<Dispatch::DispatchPayload, S::MessageFee>,
+	) -> ReceivalResult {
+		self.accept_message(relayer_at_bridged_chain, nonce, message_data, |dispatch_message| {
+			// if there are some extra pre-dispatch validation errors, reject this message.
+			if Dispatch::pre_dispatch(relayer_at_this_chain, &dispatch_message).is_err() {
+				return Err(ReceivalResult::PreDispatchValidateFailed);
+			}
+
+			// then, dispatch message
+			let dispatch_result = Dispatch::dispatch(relayer_at_this_chain, dispatch_message);
+			let is_dispatched = dispatch_result.dispatch_result;
+			Ok((ReceivalResult::Dispatched(dispatch_result), is_dispatched))
+		})
+	}
+
+	/// Receive new message, but defer its dispatch.
+	///
+	/// The message is noted as delivered with the negative dispatch result and the `Deferred`
+	/// dispatch status and is returned back to the caller, which is responsible for dispatching
+	/// it later (see `note_deferred_message_dispatched`). Cancelled and expired messages are never
+	/// deferred, because they are never dispatched.
+	pub fn receive_deferred_message<
+		Dispatch: MessageDispatch<AccountId, S::MessageFee>,
+		AccountId,
+	>(
+		&mut self,
+		relayer_at_bridged_chain: &S::Relayer,
+		relayer_at_this_chain: &AccountId,
+		nonce: MessageNonce,
+		message_data: DispatchMessageData<Dispatch::DispatchPayload, S::MessageFee>,
+	) -> (ReceivalResult, Option<DispatchMessage<Dispatch::DispatchPayload, S::MessageFee>>) {
+		let mut deferred_message = None;
+		let receival_result = self.accept_message(
+			relayer_at_bridged_chain,
+			nonce,
+			message_data,
+			|dispatch_message| {
+				// the same validation is performed when the message is dispatched without delay
+				if Dispatch::pre_dispatch(relayer_at_this_chain, &dispatch_message).is_err() {
+					return Err(ReceivalResult::PreDispatchValidateFailed);
+				}
+
+				deferred_message = Some(dispatch_message);
+				Ok((ReceivalResult::Deferred, false))
+			},
+		);
+
+		(receival_result, deferred_message)
+	}
+
+	/// Replace dispatch result and status of the deferred message with the actual ones, once it
+	/// is dispatched.
+	///
+	/// Returns false if the message delivery has already been confirmed, so the source chain
+	/// keeps the `Deferred` status of this message.
+	pub fn note_deferred_message_dispatched(
+		&mut self,
+		nonce: MessageNonce,
+		dispatch_result: bool,
+		dispatch_status: Option<MessageDispatchStatus>,
+	) -> bool {
+		let mut data = self.storage.data();
+		if let Some(entry) =
+			data.relayers.iter_mut().find(|entry| entry.messages.contains_message(nonce))
+		{
+			entry.messages.set_message_dispatch_result(nonce, dispatch_result);
+			self.storage.set_data(data);
+
+			let mut dispatch_statuses = self.storage.dispatch_statuses();
+			dispatch_statuses.set_dispatch_status(nonce, dispatch_status);
+			self.storage.set_dispatch_statuses(dispatch_statuses);
+			return true;
+		}
+
+		match self.storage.message_delivered_ahead(nonce) {
+			Some((relayer, _, _)) => {
+				self.storage.save_message_delivered_ahead(
+					nonce,
+					relayer,
+					dispatch_result,
+					dispatch_status,
+				);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Accept new message, calling `dispatch` if it needs to be dispatched.
+	///
+	/// The `dispatch` returns either the receival result and dispatch result of the message, or
+	/// the receival result if the message is rejected.
+	fn accept_message<Payload>(
+		&mut self,
+		relayer_at_bridged_chain: &S::Relayer,
+		nonce: MessageNonce,
+		message_data: DispatchMessageData<Payload, S::MessageFee>,
+		dispatch: impl FnOnce(
+			DispatchMessage<Payload, S::MessageFee>,
+		) -> Result<(ReceivalResult, bool), ReceivalResult>,
 	) -> ReceivalResult {
 		let mut data = self.storage.data();
-		let is_correct_message = nonce == data.last_delivered_nonce() + 1;
+		let next_nonce = data.last_delivered_nonce() + 1;
+		let is_ahead_message = nonce > next_nonce && self.storage.kind() == LaneKind::Unordered;
+		let is_correct_message = nonce == next_nonce || is_ahead_message;
 		if !is_correct_message {
 			return ReceivalResult::InvalidNonce;
 		}
 
+		// messages of unordered lanes may already be delivered ahead
+		let mut delivered_ahead = self.storage.delivered_ahead();
+		let ahead_index = (nonce - next_nonce) as usize;
+		if delivered_ahead.get(ahead_index).map(|bit| *bit).unwrap_or(false) {
+			return ReceivalResult::InvalidNonce;
+		}
+
 		// if there are more unrewarded relayer entries than we may accept, reject this message
 		if data.relayers.len() as MessageNonce >= self.storage.max_unrewarded_relayer_entries() {
 			return ReceivalResult::TooManyUnrewardedRelayers;
@@ -183,52 +435,172 @@ impl<S: InboundLaneStorage> InboundLane<S> {
 			return ReceivalResult::TooManyUnconfirmedMessages;
 		}
 
-		let dispatch_message = DispatchMessage {
-			key: MessageKey { lane_id: self.storage.id(), nonce },
-			data: message_data,
+		// the cancelled message is never dispatched, but it is still delivered, so that
+		// following messages may be delivered too
+		let is_expired = message_data
+			.expiry
+			.map(|expiry| self.storage.is_message_expired(&expiry))
+			.unwrap_or(false);
+		let (receival_result, is_dispatched) = if message_data.is_cancelled {
+			(ReceivalResult::Cancelled, false)
+		} else if is_expired {
+			// the expired message is handled the same way as the cancelled one, but the source
+			// chain may use the (false) dispatch bit to refund the submitter
+			(ReceivalResult::Expired, false)
+		} else {
+			let dispatch_message = DispatchMessage {
+				key: MessageKey { lane_id: self.storage.id(), nonce },
+				data: message_data,
+			};
+
+			match dispatch(dispatch_message) {
+				Ok(result) => result,
+				Err(receival_result) => return receival_result,
+			}
+		};
+		let dispatch_status = match receival_result {
+			ReceivalResult::Dispatched(ref dispatch_result) => dispatch_result.dispatch_status,
+			ReceivalResult::Cancelled => Some(MessageDispatchStatus::Cancelled),
+			ReceivalResult::Expired => Some(MessageDispatchStatus::Expired),
+			ReceivalResult::Deferred => Some(MessageDispatchStatus::Deferred),
+			_ => None,
 		};
 
-		// if there are some extra pre-dispatch validation errors, reject this message.
-		if Dispatch::pre_dispatch(relayer_at_this_chain, &dispatch_message).is_err() {
-			return ReceivalResult::PreDispatchValidateFailed;
-		}
+		// the message that is delivered ahead is remembered until all previous messages are
+		// delivered
+		if is_ahead_message {
+			if delivered_ahead.len() <= ahead_index {
+				delivered_ahead.resize(ahead_index + 1, false);
+			}
+			delivered_ahead.set(ahead_index, true);
+			self.storage.save_message_delivered_ahead(
+				nonce,
+				relayer_at_bridged_chain.clone(),
+				is_dispatched,
+				dispatch_status,
+			);
+			self.storage.set_delivered_ahead(delivered_ahead);
 
-		// then, dispatch message
-		let dispatch_result = Dispatch::dispatch(relayer_at_this_chain, dispatch_message);
+			return receival_result;
+		}
 
 		// now let's update inbound lane storage
-		let push_new = match data.relayers.back_mut() {
-			Some(entry) if entry.relayer == *relayer_at_bridged_chain => {
-				entry.messages.note_dispatched_message(dispatch_result.dispatch_result);
-				false
-			},
-			_ => true,
-		};
-		if push_new {
-			data.relayers.push_back(UnrewardedRelayer {
-				relayer: (*relayer_at_bridged_chain).clone(),
-				messages: DeliveredMessages::new(nonce, dispatch_result.dispatch_result),
-			});
+		note_delivered_message(&mut data, relayer_at_bridged_chain, nonce, is_dispatched);
+		if !delivered_ahead.is_empty() {
+			// the first bit corresponds to the message that has just been delivered
+			delivered_ahead.remove(0);
+			self.storage.set_delivered_ahead(delivered_ahead);
 		}
 		self.storage.set_data(data);
+		self.note_dispatch_status(nonce, dispatch_status);
+
+		receival_result
+	}
+
+	/// Append messages that have been delivered ahead to the unrewarded relayers set, once all
+	/// previous messages are delivered.
+	///
+	/// At most `max_messages` messages are appended. Draining also stops when there are no free
+	/// unrewarded relayer entries. It is resumed by the next call, e.g. when some entries are
+	/// confirmed. Returns number of appended messages.
+	pub fn drain_delivered_ahead(&mut self, max_messages: MessageNonce) -> MessageNonce {
+		let mut delivered_ahead = self.storage.delivered_ahead();
+		if delivered_ahead.is_empty() {
+			return 0;
+		}
+
+		let mut data = self.storage.data();
+		let mut dispatch_statuses = self.storage.dispatch_statuses();
+		let mut drained_messages = 0;
+		while drained_messages < max_messages
+			&& delivered_ahead.first().map(|bit| *bit).unwrap_or(false)
+		{
+			let nonce = data.last_delivered_nonce() + 1;
+			let (relayer, dispatch_result, dispatch_status) =
+				match self.storage.message_delivered_ahead(nonce) {
+					Some(message) => message,
+					None => break,
+				};
+			let requires_new_entry =
+				data.relayers.back().map(|entry| entry.relayer != relayer).unwrap_or(true);
+			if requires_new_entry
+				&& data.relayers.len() as MessageNonce
+					>= self.storage.max_unrewarded_relayer_entries()
+			{
+				break;
+			}
+
+			note_delivered_message(&mut data, &relayer, nonce, dispatch_result);
+			dispatch_statuses.note_dispatch_status(nonce, dispatch_status);
+			self.storage.remove_message_delivered_ahead(nonce);
+			delivered_ahead.remove(0);
+			drained_messages += 1;
+		}
+
+		if drained_messages != 0 {
+			self.storage.set_delivered_ahead(delivered_ahead);
+			self.storage.set_data(data);
+			self.storage.set_dispatch_statuses(dispatch_statuses);
+		}
+
+		drained_messages
+	}
 
-		ReceivalResult::Dispatched(dispatch_result)
+	/// Note dispatch status of the message that has just been appended to the unrewarded relayers
+	/// set.
+	fn note_dispatch_status(
+		&mut self,
+		nonce: MessageNonce,
+		dispatch_status: Option<MessageDispatchStatus>,
+	) {
+		let mut dispatch_statuses = self.storage.dispatch_statuses();
+		if dispatch_statuses.is_empty() && dispatch_status.is_none() {
+			return;
+		}
+
+		dispatch_statuses.note_dispatch_status(nonce, dispatch_status);
+		self.storage.set_dispatch_statuses(dispatch_statuses);
+	}
+}
+
+/// Append delivered message to the unrewarded relayers set.
+fn note_delivered_message<Relayer: Clone + PartialEq>(
+	data: &mut InboundLaneData<Relayer>,
+	relayer: &Relayer,
+	nonce: MessageNonce,
+	dispatch_result: bool,
+) {
+	let push_new = match data.relayers.back_mut() {
+		Some(entry) if entry.relayer == *relayer => {
+			entry.messages.note_dispatched_message(dispatch_result);
+			false
+		},
+		_ => true,
+	};
+	if push_new {
+		data.relayers.push_back(UnrewardedRelayer {
+			relayer: relayer.clone(),
+			messages: DeliveredMessages::new(nonce, dispatch_result),
+		});
 	}
 }
 
 #[cfg(test)]
 mod tests {
+	// crates.io
+	use bitvec::prelude::*;
 	// darwinia-network
 	use super::*;
 	use crate::{
 		inbound_lane,
 		mock::{
 			dispatch_result, message_data, run_test, unrewarded_relayer, TestMessageDispatch,
-			TestRuntime, REGULAR_PAYLOAD, TEST_LANE_ID, TEST_RELAYER_A, TEST_RELAYER_B,
-			TEST_RELAYER_C,
+			TestMessageFee, TestPayload, TestRelayer, TestRuntime, TestTimestamp, REGULAR_PAYLOAD,
+			TEST_LANE_ID, TEST_RELAYER_A, TEST_RELAYER_B, TEST_RELAYER_C, TEST_UNORDERED_LANE_ID,
 		},
 		RuntimeInboundLaneStorage,
 	};
+	use bp_messages::MessageData;
 
 	fn receive_regular_message(
 		lane: &mut InboundLane<RuntimeInboundLaneStorage<TestRuntime, ()>>,
@@ -245,6 +617,19 @@ mod tests {
 		);
 	}
 
+	fn receive_message_from(
+		lane: &mut InboundLane<RuntimeInboundLaneStorage<TestRuntime, ()>>,
+		relayer: TestRelayer,
+		nonce: MessageNonce,
+	) -> ReceivalResult {
+		lane.receive_message::<TestMessageDispatch, _>(
+			&relayer,
+			&relayer,
+			nonce,
+			message_data(REGULAR_PAYLOAD).into(),
+		)
+	}
+
 	#[test]
 	fn receive_status_update_ignores_status_from_the_future() {
 		run_test(|| {
@@ -547,4 +932,232 @@ mod tests {
 			);
 		});
 	}
+
+	#[test]
+	fn unordered_lane_dispatches_messages_ahead() {
+		run_test(|| {
+			let mut lane = inbound_lane::<TestRuntime, _>(TEST_UNORDERED_LANE_ID);
+			assert_eq!(
+				receive_message_from(&mut lane, TEST_RELAYER_A, 3),
+				ReceivalResult::Dispatched(dispatch_result(0))
+			);
+			assert_eq!(lane.storage.data().last_delivered_nonce(), 0);
+			assert_eq!(lane.storage.delivered_ahead(), bitvec![u8, Msb0; 0, 0, 1]);
+
+			// message that has been delivered ahead can't be delivered again
+			assert_eq!(
+				receive_message_from(&mut lane, TEST_RELAYER_B, 3),
+				ReceivalResult::InvalidNonce
+			);
+
+			// once the gap is filled, messages are appended to the unrewarded relayers set
+			assert_eq!(
+				receive_message_from(&mut lane, TEST_RELAYER_A, 1),
+				ReceivalResult::Dispatched(dispatch_result(0))
+			);
+			assert_eq!(lane.storage.data().last_delivered_nonce(), 1);
+			assert_eq!(
+				receive_message_from(&mut lane, TEST_RELAYER_B, 2),
+				ReceivalResult::Dispatched(dispatch_result(0))
+			);
+			assert_eq!(lane.storage.data().last_delivered_nonce(), 2);
+			assert_eq!(lane.drain_delivered_ahead(MessageNonce::MAX), 1);
+			assert_eq!(lane.storage.data().last_delivered_nonce(), 3);
+			assert_eq!(
+				lane.storage.data().relayers,
+				vec![
+					unrewarded_relayer(1, 1, TEST_RELAYER_A),
+					unrewarded_relayer(2, 2, TEST_RELAYER_B),
+					unrewarded_relayer(3, 3, TEST_RELAYER_A)
+				]
+			);
+			assert!(lane.storage.delivered_ahead().is_empty());
+			assert_eq!(lane.storage.message_delivered_ahead(3), None);
+		});
+	}
+
+	#[test]
+	fn unordered_lane_rejects_messages_outside_of_unconfirmed_messages_window() {
+		run_test(|| {
+			let mut lane = inbound_lane::<TestRuntime, _>(TEST_UNORDERED_LANE_ID);
+			let max_nonce = <TestRuntime as Config>::MaxUnconfirmedMessagesAtInboundLane::get();
+			assert_eq!(
+				receive_message_from(&mut lane, TEST_RELAYER_A, max_nonce + 1),
+				ReceivalResult::TooManyUnconfirmedMessages
+			);
+			assert_eq!(
+				receive_message_from(&mut lane, TEST_RELAYER_A, max_nonce),
+				ReceivalResult::Dispatched(dispatch_result(0))
+			);
+		});
+	}
+
+	#[test]
+	fn unordered_lane_drains_messages_ahead_when_relayer_entries_are_freed() {
+		run_test(|| {
+			let mut lane = inbound_lane::<TestRuntime, _>(TEST_UNORDERED_LANE_ID);
+			let max_entries =
+				<TestRuntime as Config>::MaxUnrewardedRelayerEntriesAtInboundLane::get();
+			for nonce in 1..max_entries {
+				assert_eq!(
+					receive_message_from(&mut lane, TEST_RELAYER_A + nonce, nonce),
+					ReceivalResult::Dispatched(dispatch_result(0))
+				);
+			}
+
+			// the message ahead requires new unrewarded relayer entry, but the last free entry
+			// is occupied by the previous message
+			assert_eq!(
+				receive_message_from(&mut lane, TEST_RELAYER_A, max_entries + 1),
+				ReceivalResult::Dispatched(dispatch_result(0))
+			);
+			assert_eq!(
+				receive_message_from(&mut lane, TEST_RELAYER_B, max_entries),
+				ReceivalResult::Dispatched(dispatch_result(0))
+			);
+			assert_eq!(lane.drain_delivered_ahead(MessageNonce::MAX), 0);
+			assert_eq!(lane.storage.data().last_delivered_nonce(), max_entries);
+			assert_eq!(lane.storage.delivered_ahead(), bitvec![u8, Msb0; 1]);
+
+			// when some entries are confirmed, the message is drained
+			assert_eq!(
+				lane.receive_state_update(OutboundLaneData {
+					latest_received_nonce: 1,
+					..Default::default()
+				}),
+				Some(1),
+			);
+			assert_eq!(lane.drain_delivered_ahead(MessageNonce::MAX), 1);
+			assert_eq!(lane.storage.data().last_delivered_nonce(), max_entries + 1);
+			assert_eq!(
+				lane.storage.data().relayers.back(),
+				Some(&unrewarded_relayer(max_entries + 1, max_entries + 1, TEST_RELAYER_A)),
+			);
+			assert!(lane.storage.delivered_ahead().is_empty());
+		});
+	}
+
+	#[test]
+	fn cancelled_message_is_received_but_not_dispatched() {
+		run_test(|| {
+			let mut lane = inbound_lane::<TestRuntime, _>(TEST_LANE_ID);
+			let cancelled_message_data = MessageData::cancelled(0);
+			assert_eq!(
+				lane.receive_message::<TestMessageDispatch, _>(
+					&TEST_RELAYER_A,
+					&TEST_RELAYER_A,
+					1,
+					cancelled_message_data.into(),
+				),
+				ReceivalResult::Cancelled
+			);
+			receive_regular_message(&mut lane, 2);
+
+			let mut delivered_messages = DeliveredMessages::new(1, false);
+			delivered_messages.note_dispatched_message(true);
+			assert_eq!(
+				lane.storage.data().relayers,
+				vec![UnrewardedRelayer { relayer: TEST_RELAYER_A, messages: delivered_messages }],
+			);
+		});
+	}
+
+	#[test]
+	fn deferred_message_result_is_replaced_once_it_is_dispatched() {
+		run_test(|| {
+			let mut lane = inbound_lane::<TestRuntime, _>(TEST_LANE_ID);
+			let (receival_result, deferred_message) = lane
+				.receive_deferred_message::<TestMessageDispatch, _>(
+					&TEST_RELAYER_A,
+					&TEST_RELAYER_A,
+					1,
+					message_data(REGULAR_PAYLOAD).into(),
+				);
+			assert_eq!(receival_result, ReceivalResult::Deferred);
+			assert!(deferred_message.is_some());
+			assert_eq!(
+				lane.storage.data().relayers,
+				vec![UnrewardedRelayer {
+					relayer: TEST_RELAYER_A,
+					messages: DeliveredMessages::new(1, false),
+				}],
+			);
+			assert_eq!(
+				lane.storage.dispatch_statuses().dispatch_status(1),
+				Some(MessageDispatchStatus::Deferred)
+			);
+
+			assert!(lane.note_deferred_message_dispatched(
+				1,
+				true,
+				Some(MessageDispatchStatus::Dispatched)
+			));
+			assert_eq!(
+				lane.storage.data().relayers,
+				vec![UnrewardedRelayer {
+					relayer: TEST_RELAYER_A,
+					messages: DeliveredMessages::new(1, true),
+				}],
+			);
+			assert_eq!(
+				lane.storage.dispatch_statuses().dispatch_status(1),
+				Some(MessageDispatchStatus::Dispatched)
+			);
+
+			// once the delivery is confirmed, the result may not be replaced and the status is
+			// pruned
+			lane.receive_state_update(OutboundLaneData {
+				latest_received_nonce: 1,
+				..Default::default()
+			});
+			assert!(lane.storage.dispatch_statuses().is_empty());
+			assert!(!lane.note_deferred_message_dispatched(1, false, None));
+		});
+	}
+
+	#[test]
+	fn expired_message_is_received_but_not_dispatched() {
+		run_test(|| {
+			frame_system::Pallet::<TestRuntime>::set_block_number(10);
+			TestTimestamp::set(&1_000);
+
+			let mut lane = inbound_lane::<TestRuntime, _>(TEST_LANE_ID);
+			let receive_message_with_expiry = |lane: &mut InboundLane<_>, nonce, expiry| {
+				let mut message_data: DispatchMessageData<TestPayload, TestMessageFee> =
+					message_data(REGULAR_PAYLOAD).into();
+				message_data.expiry = Some(expiry);
+				lane.receive_message::<TestMessageDispatch, _>(
+					&TEST_RELAYER_A,
+					&TEST_RELAYER_A,
+					nonce,
+					message_data.into(),
+				)
+			};
+			assert_eq!(
+				receive_message_with_expiry(&mut lane, 1, MessageExpiry::BlockNumber(9)),
+				ReceivalResult::Expired,
+			);
+			assert_eq!(
+				receive_message_with_expiry(&mut lane, 2, MessageExpiry::BlockNumber(10)),
+				ReceivalResult::Dispatched(dispatch_result(0)),
+			);
+			assert_eq!(
+				receive_message_with_expiry(&mut lane, 3, MessageExpiry::Timestamp(999)),
+				ReceivalResult::Expired,
+			);
+			assert_eq!(
+				receive_message_with_expiry(&mut lane, 4, MessageExpiry::Timestamp(1_000)),
+				ReceivalResult::Dispatched(dispatch_result(0)),
+			);
+
+			let mut delivered_messages = DeliveredMessages::new(1, false);
+			delivered_messages.note_dispatched_message(true);
+			delivered_messages.note_dispatched_message(false);
+			delivered_messages.note_dispatched_message(true);
+			assert_eq!(
+				lane.storage.data().relayers,
+				vec![UnrewardedRelayer { relayer: TEST_RELAYER_A, messages: delivered_messages }],
+			);
+		});
+	}
 }
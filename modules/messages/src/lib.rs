@@ -43,11 +43,15 @@ mod mock;
 #[cfg(feature = "runtime-benchmarks")]
 pub mod benchmarking;
 
+pub mod migration;
 pub mod weights;
 pub use weights::WeightInfo;
 
 mod inbound_lane;
-pub use inbound_lane::StoredInboundLaneData;
+pub use inbound_lane::{
+	StoredDeliveredNonces, StoredFailedMessage, StoredInboundLaneData,
+	StoredInboundLaneDispatchStatuses,
+};
 
 mod outbound_lane;
 pub use outbound_lane::StoredMessageData;
@@ -67,22 +71,42 @@ use crate::{
 	outbound_lane::{OutboundLane, OutboundLaneStorage, ReceivalConfirmationResult},
 };
 use bp_messages::{
+	commitment::{
+		merkle_proof, merkle_root, MessageInclusionProof, MessageLeaf, MessagesCommitment,
+		MESSAGES_COMMITMENT_ENGINE_ID,
+	},
 	source_chain::{
-		LaneMessageVerifier, MessageDeliveryAndDispatchPayment, OnDeliveryConfirmed,
-		OnMessageAccepted, SendMessageArtifacts, TargetHeaderChain,
+		DeliveryCallbackId, LaneMessageVerifier, MessageDeliveryAndDispatchPayment,
+		OnDeliveryConfirmed, OnMessageAccepted, OnMessageDeliveryResult, SendMessageArtifacts,
+		TargetHeaderChain,
 	},
 	target_chain::{
-		DispatchMessage, MessageDispatch, ProvedLaneMessages, ProvedMessages, SourceHeaderChain,
+		DispatchMessage, DispatchMessageData, MessageDispatch, ProvedLaneMessages, ProvedMessages,
+		SourceHeaderChain,
 	},
-	total_unrewarded_messages, DeliveredMessages, InboundLaneData, LaneId, MessageData, MessageKey,
-	MessageNonce, MessagesOperatingMode, OutboundLaneData, Parameter as MessagesParameter,
-	UnrewardedRelayersState, VerificationError,
+	total_unrewarded_messages, AllowedSender, DeferredMessage, DeliveredMessages,
+	DeliveredNoncesBitVec, FailedMessage, InboundLaneData, InboundLaneDispatchStatuses, LaneId,
+	LaneKind, LaneMetadata, LaneState, MessageData, MessageExpiry, MessageKey, MessageNonce,
+	MessagePayload, MessagesOperatingMode, OutboundLaneData, OutboundMessageData,
+	OutboundRateLimit, OutboundRateLimitUsage, Parameter as MessagesParameter,
+	ReceivalResultWithDetails, UnrewardedRelayersState, VerificationError,
+	CANCELLED_MESSAGE_PAYLOAD,
+};
+use bp_runtime::{
+	messages::MessageDispatchStatus, BasicOperatingMode, ChainId, OwnedBridgeModule, Size,
 };
-use bp_runtime::{BasicOperatingMode, ChainId, OwnedBridgeModule, Size};
 // substrate
-use frame_support::{dispatch::PostDispatchInfo, ensure, fail, log, traits::Get};
-use sp_core::H256;
-use sp_runtime::traits::Convert;
+use frame_support::{
+	dispatch::PostDispatchInfo,
+	ensure, fail, log,
+	traits::{Get, UnixTime},
+	BoundedVec,
+};
+use sp_core::{Hasher, H256};
+use sp_runtime::{
+	traits::{Convert, Saturating, UniqueSaturatedInto},
+	DigestItem,
+};
 use sp_std::{cell::RefCell, marker::PhantomData, prelude::*};
 
 /// The target that will be used when publishing logs related to this pallet.
@@ -145,6 +169,13 @@ pub mod pallet {
 		/// Maximal size of the outbound payload.
 		#[pallet::constant]
 		type MaximalOutboundPayloadSize: Get<u32>;
+		/// Maximal number of messages in the single `send_messages` call.
+		#[pallet::constant]
+		type MaxOutboundMessagesInBatch: Get<u32>;
+		/// Number of blocks that must pass after the outbound message has been sent, before its
+		/// sender may cancel it.
+		#[pallet::constant]
+		type MessageCancellationTimeout: Get<Self::BlockNumber>;
 		/// Payload type of outbound messages. This payload is dispatched on the bridged chain.
 		type OutboundPayload: Parameter + Size;
 		/// Message fee type of outbound messages. This fee is paid on this chain.
@@ -157,10 +188,31 @@ pub mod pallet {
 			+ Copy
 			+ MaxEncodedLen;
 
+		/// Maximal number of inbound messages, whose dispatch has failed, that are kept at the
+		/// single lane for retry. Once the limit is reached, new failed messages are not kept
+		/// until some of stored messages are dispatched or removed.
+		#[pallet::constant]
+		type MaxFailedMessagesAtInboundLane: Get<MessageNonce>;
+		/// Maximal size of the inbound payload, whose dispatch has failed, that is kept for
+		/// retry. Failed messages with larger payloads are not kept.
+		#[pallet::constant]
+		type MaxFailedMessagePayloadSize: Get<u32>;
+		/// Number of blocks the failed inbound message is kept for retry. Once it is passed, the
+		/// message may not be dispatched and anyone may remove it.
+		#[pallet::constant]
+		type FailedMessageLifetime: Get<Self::BlockNumber>;
+		/// Maximal number of inbound messages, whose dispatch has been deferred, that are kept
+		/// at the single lane.
+		///
+		/// Messages are deferred if the relayer has not declared enough weight to dispatch them.
+		/// They are dispatched later by the `on_idle` hook. If it is zero, messages are never
+		/// deferred and the relayer must deliver them again.
+		#[pallet::constant]
+		type MaxDeferredMessagesAtInboundLane: Get<u32>;
 		/// Payload type of inbound messages. This payload is dispatched on this chain.
-		type InboundPayload: Decode;
+		type InboundPayload: Decode + Encode;
 		/// Message fee type of inbound messages. This fee is paid on the bridged chain.
-		type InboundMessageFee: Decode + Zero;
+		type InboundMessageFee: Parameter + Zero + MaxEncodedLen;
 		/// Identifier of relayer that deliver messages to this chain. Relayer reward is paid on the
 		/// bridged chain.
 		type InboundRelayer: Parameter + MaxEncodedLen;
@@ -190,6 +242,20 @@ pub mod pallet {
 		type OnMessageAccepted: OnMessageAccepted;
 		/// Handler for delivered messages.
 		type OnDeliveryConfirmed: OnDeliveryConfirmed;
+		/// Handlers of dispatch results of messages that have been sent with delivery callbacks.
+		type OnMessageDeliveryResult: OnMessageDeliveryResult;
+		/// Hasher that is used to build the commitment of messages, accepted in every block.
+		///
+		/// If messages are verified by the EVM-based bridged chain, it should be `Keccak256`.
+		type MessagesCommitmentHasher: Hasher<Out = H256>;
+		/// If `true`, the legacy `MessageAccepted` and `MessagesReceived` events are deposited
+		/// along with their second versions, so that event consumers that haven't been upgraded
+		/// yet keep working. Otherwise, only second versions are deposited.
+		///
+		/// The `MessagesReceived` event may contain `ReceivalResult` variants that have been
+		/// added after its first release, so outdated consumers may fail to decode it anyway.
+		#[pallet::constant]
+		type DepositLegacyEvents: Get<bool>;
 
 		// Types that are used by inbound_lane (on target chain).
 
@@ -201,6 +267,8 @@ pub mod pallet {
 			Self::InboundMessageFee,
 			DispatchPayload = Self::InboundPayload,
 		>;
+		/// Time source that is used to check expiry of inbound messages.
+		type UnixTime: UnixTime;
 	}
 
 	/// Shortcut to messages proof type for Config.
@@ -214,7 +282,11 @@ pub mod pallet {
 			<T as frame_system::Config>::AccountId,
 		>>::MessagesDeliveryProof;
 
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T, I = ()>(PhantomData<(T, I)>);
 
 	impl<T: Config<I>, I: 'static> OwnedBridgeModule<T> for Pallet<T, I> {
@@ -225,6 +297,56 @@ pub mod pallet {
 		const LOG_TARGET: &'static str = LOG_TARGET;
 	}
 
+	#[pallet::hooks]
+	impl<T: Config<I>, I: 'static> Hooks<BlockNumberFor<T>> for Pallet<T, I> {
+		fn on_initialize(_n: BlockNumberFor<T>) -> Weight {
+			// leaves of the previous block are no longer needed. Removal of every leaf index has
+			// been paid by the message sender
+			OutboundMessagesCommitmentLeaves::<T, I>::kill();
+			let _ = OutboundMessagesCommitmentLeafIndices::<T, I>::clear(u32::MAX, None);
+
+			// `on_finalize` reads leaves and updates the commitment root. Hashing of every leaf
+			// has been paid by the message sender
+			T::WeightInfo::commit_outbound_messages_overhead(T::DbWeight::get())
+		}
+
+		fn on_finalize(_n: BlockNumberFor<T>) {
+			let leaves = OutboundMessagesCommitmentLeaves::<T, I>::get();
+			if leaves.is_empty() {
+				OutboundMessagesCommitmentRoot::<T, I>::kill();
+				return;
+			}
+
+			let root = merkle_root::<T::MessagesCommitmentHasher>(&leaves);
+			OutboundMessagesCommitmentRoot::<T, I>::put(root);
+			frame_system::Pallet::<T>::deposit_log(DigestItem::Consensus(
+				MESSAGES_COMMITMENT_ENGINE_ID,
+				MessagesCommitment {
+					bridged_chain_id: T::BridgedChainId::get(),
+					root,
+					messages_count: leaves.len() as _,
+				}
+				.encode(),
+			));
+		}
+
+		fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			// dispatching messages is more important than cleaning the storage
+			let dispatch_weight = dispatch_deferred_messages::<T, I>(remaining_weight);
+			let remaining_weight = remaining_weight.saturating_sub(dispatch_weight);
+			let prune_messages_weight = prune_outbound_messages::<T, I>(remaining_weight);
+			let remaining_weight = remaining_weight.saturating_sub(prune_messages_weight);
+			dispatch_weight
+				.saturating_add(prune_messages_weight)
+				.saturating_add(prune_sender_rate_limit_usages::<T, I>(remaining_weight))
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: BlockNumberFor<T>) -> Result<(), &'static str> {
+			Self::do_try_state()
+		}
+	}
+
 	#[pallet::call]
 	impl<T: Config<I>, I: 'static> Pallet<T, I> {
 		/// Change `PalletOwner`.
@@ -275,12 +397,113 @@ pub mod pallet {
 			payload: T::OutboundPayload,
 			delivery_and_dispatch_fee: T::OutboundMessageFee,
 		) -> DispatchResultWithPostInfo {
-			crate::send_message::<T, I>(origin, lane_id, payload, delivery_and_dispatch_fee).map(
-				|sent_message| PostDispatchInfo {
+			crate::send_message::<T, I>(origin, lane_id, payload, delivery_and_dispatch_fee, None)
+				.map(|sent_message| PostDispatchInfo {
 					actual_weight: Some(sent_message.weight),
 					pays_fee: Pays::Yes,
-				},
+				})
+		}
+
+		/// Pay additional fee for the message that is not yet delivered.
+		///
+		/// Anyone may pay the additional fee. The `MessageDeliveryAndDispatchPayment` is notified
+		/// about the new message fee, so that it may adjust relayers rewards.
+		#[pallet::call_index(4)]
+		#[pallet::weight(T::WeightInfo::increase_message_fee_weight(T::DbWeight::get()))]
+		pub fn increase_message_fee(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			nonce: MessageNonce,
+			additional_fee: T::OutboundMessageFee,
+		) -> DispatchResultWithPostInfo {
+			ensure_normal_operating_mode::<T, I>()?;
+			ensure!(!is_lane_halted::<T, I>(lane_id), Error::<T, I>::LaneHalted);
+
+			// if someone tries to pay for already-delivered message, we're rejecting this
+			// intention (otherwise this additional fee will be locked forever in relayers fund)
+			//
+			// if someone tries to pay for not-yet-sent message, we're rejecting this intention,
+			// or we're risking to have mess in the storage
+			let lane_data = OutboundLanes::<T, I>::get(lane_id);
+			ensure!(
+				nonce > lane_data.latest_received_nonce,
+				Error::<T, I>::MessageAlreadyDelivered
+			);
+			ensure!(nonce <= lane_data.latest_generated_nonce, Error::<T, I>::MessageIsNotYetSent);
+
+			// the cancelled message is never dispatched, so there's no point in paying for it
+			let message_key = MessageKey { lane_id, nonce };
+			let mut message_data = OutboundMessages::<T, I>::get(&message_key)
+				.filter(|message_data| !message_data.is_cancelled())
+				.ok_or(Error::<T, I>::MessageIsNotYetSent)?;
+
+			// withdraw additional fee from submitter
+			T::MessageDeliveryAndDispatchPayment::pay_delivery_and_dispatch_fee(
+				&origin,
+				&additional_fee,
+				&relayer_fund_account_id::<T::AccountId, T::AccountIdConverter>(),
+			)
+			.map_err(|err| {
+				log::trace!(
+					target: LOG_TARGET,
+					"Submitter can't pay additional fee {:?} for the message {:?}/{}: {:?}",
+					additional_fee,
+					lane_id,
+					nonce,
+					err,
+				);
+
+				Error::<T, I>::FailedToWithdrawMessageFee
+			})?;
+
+			// and finally update fee in the storage
+			//
+			// saturating_add is fine here - overflow here means that someone controls all
+			// chain funds, which shouldn't ever happen + `pay_delivery_and_dispatch_fee`
+			// above will fail before we reach here
+			message_data.fee = message_data.fee.saturating_add(&additional_fee);
+			let message_size = message_data.payload.len();
+			let new_fee = message_data.fee;
+			OutboundMessages::<T, I>::insert(&message_key, message_data);
+
+			// let the payment implementation know about the new fee
+			let single_message_callback_overhead =
+				T::WeightInfo::single_message_callback_overhead(T::DbWeight::get());
+			let actual_callback_weight =
+				T::MessageDeliveryAndDispatchPayment::on_message_fee_increased(
+					lane_id,
+					nonce,
+					&origin,
+					&additional_fee,
+					&new_fee,
+				);
+			if !actual_callback_weight.all_lte(single_message_callback_overhead) {
+				log::error!(
+					target: LOG_TARGET,
+					"T::MessageDeliveryAndDispatchPayment callback has spent more weight that it is allowed to: \
+					{} vs {}",
+					single_message_callback_overhead,
+					actual_callback_weight,
+				);
+			}
+
+			log::trace!(
+				target: LOG_TARGET,
+				"Increased fee of the message {:?}/{} by {:?}",
+				lane_id,
+				nonce,
+				additional_fee,
+			);
+			Self::deposit_event(Event::MessageFeeIncreased { lane_id, nonce, additional_fee });
+
+			// compute actual dispatch weight that depends on the stored message size
+			let actual_weight = sp_std::cmp::min(
+				T::WeightInfo::maximal_increase_message_fee(),
+				T::WeightInfo::increase_message_fee(message_size as _),
 			)
+			.saturating_add(actual_callback_weight.min(single_message_callback_overhead));
+
+			Ok(PostDispatchInfo { actual_weight: Some(actual_weight), pays_fee: Pays::Yes })
 		}
 
 		/// Receive messages proof from bridged chain.
@@ -288,10 +511,14 @@ pub mod pallet {
 		/// The weight of the call assumes that the transaction always brings outbound lane
 		/// state update. Because of that, the submitter (relayer) has no benefit of not including
 		/// this data in the transaction, so reward confirmations lags should be minimal.
-		///
-		/// Note: To maintain compatibility, the call index is 5 instead of 4 because the
-		/// call(increase_message_fee) with index 4 has been removed. https://github.com/darwinia-network/darwinia-messages-substrate/pull/207
-		#[pallet::weight(T::WeightInfo::receive_messages_proof_weight(proof, *messages_count, *dispatch_weight))]
+		#[pallet::weight(T::WeightInfo::receive_multi_lane_messages_proof_weight(
+			proof,
+			T::SourceHeaderChain::lanes_count(proof),
+			*messages_count,
+			T::MaxUnconfirmedMessagesAtInboundLane::get(),
+			*dispatch_weight,
+			T::DbWeight::get(),
+		))]
 		#[pallet::call_index(5)]
 		pub fn receive_messages_proof(
 			origin: OriginFor<T>,
@@ -319,18 +546,31 @@ pub mod pallet {
 			//
 			// The DeclaredWeight is exactly what's computed here. Unfortunately it is impossible
 			// to get pre-computed value (and it has been already computed by the executive).
-			let declared_weight = T::WeightInfo::receive_messages_proof_weight(
+			let max_drained_messages = T::MaxUnconfirmedMessagesAtInboundLane::get();
+			let declared_weight = T::WeightInfo::receive_multi_lane_messages_proof_weight(
 				&proof,
+				T::SourceHeaderChain::lanes_count(&proof),
 				messages_count,
+				max_drained_messages,
 				dispatch_weight,
+				T::DbWeight::get(),
 			);
 			let mut actual_weight = declared_weight;
+			let unordered_message_overhead =
+				T::WeightInfo::receive_unordered_message_overhead(T::DbWeight::get());
+			let failed_message_overhead =
+				T::WeightInfo::receive_failed_message_overhead(T::DbWeight::get());
+			let deferred_queue_overhead =
+				T::WeightInfo::receive_deferred_messages_queue_overhead(T::DbWeight::get());
+			let drain_delivered_ahead_overhead =
+				T::WeightInfo::drain_delivered_ahead_message_overhead(T::DbWeight::get());
 
 			// verify messages proof && convert proof into messages
 			let messages = verify_and_decode_messages_proof::<
 				T::SourceHeaderChain,
 				T::InboundMessageFee,
 				T::InboundPayload,
+				T::MessagesCommitmentHasher,
 			>(proof, messages_count)
 			.map_err(|err| {
 				log::trace!(target: LOG_TARGET, "Rejecting invalid messages proof: {:?}", err,);
@@ -343,10 +583,19 @@ pub mod pallet {
 			let mut valid_messages = 0;
 			let mut messages_received_status = Vec::with_capacity(messages.len());
 			let mut dispatch_weight_left = dispatch_weight;
+			let mut drained_messages_left = max_drained_messages;
 			for (lane_id, lane_data) in messages {
 				let mut lane = inbound_lane::<T, I>(lane_id);
+				let is_lane_halted = is_lane_halted::<T, I>(lane_id);
+				if is_lane_halted {
+					log::trace!(
+						target: LOG_TARGET,
+						"Rejecting messages delivered to the halted lane {:?}",
+						lane_id,
+					);
+				}
 
-				if let Some(lane_state) = lane_data.lane_state {
+				if let Some(lane_state) = lane_data.lane_state.filter(|_| !is_lane_halted) {
 					let updated_latest_confirmed_nonce = lane.receive_state_update(lane_state);
 					if let Some(updated_latest_confirmed_nonce) = updated_latest_confirmed_nonce {
 						log::trace!(
@@ -362,10 +611,29 @@ pub mod pallet {
 					ReceivedMessages::new(lane_id, Vec::with_capacity(lane_data.messages.len()));
 				let mut is_lane_processing_stopped_no_weight_left = false;
 
-				for mut message in lane_data.messages {
+				// while the lane has deferred messages, all its new messages are deferred too, so
+				// that they are dispatched in order
+				let is_deferral_enabled = T::MaxDeferredMessagesAtInboundLane::get() != 0;
+				let mut deferred_queue = if is_deferral_enabled {
+					DeferredMessagesQueues::<T, I>::get(lane_id)
+				} else {
+					actual_weight = actual_weight.saturating_sub(deferred_queue_overhead);
+					Default::default()
+				};
+				let mut is_deferred_queue_updated = false;
+
+				let is_unordered_lane = lane.kind() == LaneKind::Unordered;
+				for (payload_hash, encoded_payload, mut message) in lane_data.messages {
 					debug_assert_eq!(message.key.lane_id, lane_id);
 					total_messages += 1;
 
+					// the declared weight assumes that all messages are delivered to unordered
+					// lanes and that their dispatch fails
+					if !is_unordered_lane {
+						actual_weight = actual_weight.saturating_sub(unordered_message_overhead);
+					}
+					actual_weight = actual_weight.saturating_sub(failed_message_overhead);
+
 					if is_lane_processing_stopped_no_weight_left {
 						lane_messages_received_status
 							.push_skipped_for_not_enough_weight(message.key.nonce);
@@ -374,9 +642,70 @@ pub mod pallet {
 
 					// ensure that relayer has declared enough weight for dispatching next message
 					// on this lane. We can't dispatch lane messages out-of-order, so if declared
-					// weight is not enough, let's move to next lane
+					// weight is not enough, let's either defer its dispatch, or move to next lane
 					let message_dispatch_weight = T::MessageDispatch::dispatch_weight(&mut message);
-					if message_dispatch_weight.any_gt(dispatch_weight_left) {
+					let is_not_enough_weight = message_dispatch_weight.any_gt(dispatch_weight_left);
+					let should_defer =
+						!is_lane_halted && (!deferred_queue.is_empty() || is_not_enough_weight);
+					if should_defer && is_deferral_enabled && !deferred_queue.is_full() {
+						let (receival_result, deferred_message) = lane
+							.receive_deferred_message::<T::MessageDispatch, T::AccountId>(
+								&relayer_id_at_bridged_chain,
+								&relayer_id_at_this_chain,
+								message.key.nonce,
+								message.data,
+							);
+						if let Some(deferred_message) = deferred_message {
+							DeferredMessages::<T, I>::insert(
+								lane_id,
+								message.key.nonce,
+								DeferredMessage {
+									payload: deferred_message
+										.data
+										.payload
+										.ok()
+										.map(|_| encoded_payload),
+									fee: deferred_message.data.fee,
+									relayer: relayer_id_at_this_chain.clone(),
+								},
+							);
+							// the queue is not full, so it never fails
+							let _ = deferred_queue.try_push(message.key.nonce);
+							is_deferred_queue_updated = true;
+							// the deferred message is stored the same way as the failed message
+							actual_weight = actual_weight.saturating_add(failed_message_overhead);
+						}
+						if matches!(
+							receival_result,
+							ReceivalResult::Deferred
+								| ReceivalResult::Cancelled | ReceivalResult::Expired
+						) {
+							valid_messages += 1;
+						}
+
+						// the message is not dispatched by this call, so the relayer is refunded.
+						// The message that doesn't fit has never been a part of the declared
+						// dispatch weight, so we only refund the declared weight that is still
+						// unused
+						let unused_dispatch_weight =
+							message_dispatch_weight.min(dispatch_weight_left);
+						dispatch_weight_left -= unused_dispatch_weight;
+						actual_weight = actual_weight
+							.saturating_sub(unused_dispatch_weight)
+							.saturating_sub(T::WeightInfo::pay_inbound_dispatch_fee_overhead());
+						lane_messages_received_status.push(
+							message.key.nonce,
+							ReceivalResultWithDetails {
+								result: receival_result,
+								payload_hash,
+								declared_dispatch_weight: message_dispatch_weight,
+								actual_dispatch_weight: Weight::zero(),
+								dispatch_error: None,
+							},
+						);
+						continue;
+					}
+					if should_defer || is_not_enough_weight {
 						log::trace!(
 							target: LOG_TARGET,
 							"Cannot dispatch any more messages on lane {:?}. Weight: declared={}, left={}",
@@ -390,12 +719,23 @@ pub mod pallet {
 						continue;
 					}
 
-					let receival_result = lane.receive_message::<T::MessageDispatch, T::AccountId>(
-						&relayer_id_at_bridged_chain,
-						&relayer_id_at_this_chain,
-						message.key.nonce,
-						message.data,
-					);
+					// the payload is kept if its dispatch fails, so that it may be retried later
+					let failed_message =
+						message.data.payload.as_ref().ok().map(|_| FailedMessage {
+							payload: encoded_payload,
+							fee: message.data.fee.clone(),
+							failed_at: frame_system::Pallet::<T>::block_number(),
+						});
+					let receival_result = if is_lane_halted {
+						ReceivalResult::LaneHalted
+					} else {
+						lane.receive_message::<T::MessageDispatch, T::AccountId>(
+							&relayer_id_at_bridged_chain,
+							&relayer_id_at_this_chain,
+							message.key.nonce,
+							message.data,
+						)
+					};
 
 					// note that we're returning unspent weight to relayer even if message has been
 					// rejected by the lane. This allows relayers to submit spam transactions with
@@ -403,21 +743,49 @@ pub mod pallet {
 					// losing funds for messages dispatch. But keep in mind that relayer pays base
 					// delivery transaction cost anyway. And base cost covers everything except
 					// dispatch, so we have a balance here.
-					let (unspent_weight, refund_pay_dispatch_fee) = match &receival_result {
-						ReceivalResult::Dispatched(dispatch_result) => {
-							valid_messages += 1;
-							(
-								dispatch_result.unspent_weight,
-								!dispatch_result.dispatch_fee_paid_during_dispatch,
-							)
-						},
-						ReceivalResult::InvalidNonce
-						| ReceivalResult::TooManyUnrewardedRelayers
-						| ReceivalResult::PreDispatchValidateFailed
-						| ReceivalResult::TooManyUnconfirmedMessages => (message_dispatch_weight, true),
-					};
-					lane_messages_received_status.push(message.key.nonce, receival_result);
+					let (unspent_weight, refund_pay_dispatch_fee, dispatch_error) =
+						match &receival_result {
+							ReceivalResult::Dispatched(dispatch_result) => {
+								valid_messages += 1;
+								if !dispatch_result.dispatch_result
+									&& failed_message.map_or(false, |failed_message| {
+										save_failed_message::<T, I>(
+											lane_id,
+											message.key.nonce,
+											failed_message,
+										)
+									}) {
+									actual_weight =
+										actual_weight.saturating_add(failed_message_overhead);
+								}
+								(
+									dispatch_result.unspent_weight,
+									!dispatch_result.dispatch_fee_paid_during_dispatch,
+									dispatch_result.dispatch_error,
+								)
+							},
+							ReceivalResult::Cancelled | ReceivalResult::Expired => {
+								valid_messages += 1;
+								(message_dispatch_weight, true, None)
+							},
+							ReceivalResult::InvalidNonce
+							| ReceivalResult::TooManyUnrewardedRelayers
+							| ReceivalResult::PreDispatchValidateFailed
+							| ReceivalResult::TooManyUnconfirmedMessages
+							| ReceivalResult::LaneHalted
+							| ReceivalResult::Deferred => (message_dispatch_weight, true, None),
+						};
 					let unspent_weight = unspent_weight.min(message_dispatch_weight);
+					lane_messages_received_status.push(
+						message.key.nonce,
+						ReceivalResultWithDetails {
+							result: receival_result,
+							payload_hash,
+							declared_dispatch_weight: message_dispatch_weight,
+							actual_dispatch_weight: message_dispatch_weight - unspent_weight,
+							dispatch_error,
+						},
+					);
 					dispatch_weight_left -= message_dispatch_weight - unspent_weight;
 					actual_weight = actual_weight.saturating_sub(unspent_weight).saturating_sub(
 						// delivery call weight formula assumes that the fee is paid at
@@ -431,8 +799,26 @@ pub mod pallet {
 					);
 				}
 
+				if is_deferred_queue_updated {
+					DeferredMessagesQueues::<T, I>::insert(lane_id, deferred_queue);
+				} else if is_deferral_enabled {
+					// the queue has been read, but it is not updated
+					actual_weight = actual_weight.saturating_sub(T::DbWeight::get().writes(1));
+				}
+
+				// messages that have been delivered ahead are appended to the unrewarded relayers
+				// set once all previous messages are delivered
+				if !is_lane_halted {
+					drained_messages_left = drained_messages_left
+						.saturating_sub(lane.drain_delivered_ahead(drained_messages_left));
+				}
+
 				messages_received_status.push(lane_messages_received_status);
+				close_lane_if_drained::<T, I>(lane_id);
 			}
+			actual_weight = actual_weight.saturating_sub(
+				drain_delivered_ahead_overhead.saturating_mul(drained_messages_left),
+			);
 
 			log::debug!(
 				target: LOG_TARGET,
@@ -443,7 +829,16 @@ pub mod pallet {
 				declared_weight,
 			);
 
-			Self::deposit_event(Event::MessagesReceived(messages_received_status));
+			if T::DepositLegacyEvents::get() {
+				Self::deposit_event(Event::MessagesReceived(
+					messages_received_status
+						.iter()
+						.cloned()
+						.map(|lane_status| lane_status.map_results(|details| details.result))
+						.collect(),
+				));
+			}
+			Self::deposit_event(Event::MessagesReceivedV2(messages_received_status));
 
 			Ok(PostDispatchInfo { actual_weight: Some(actual_weight), pays_fee: Pays::Yes })
 		}
@@ -491,6 +886,7 @@ pub mod pallet {
 
 					Error::<T, I>::InvalidMessagesDeliveryProof
 				})?;
+			ensure!(!is_lane_halted::<T, I>(lane_id), Error::<T, I>::LaneHalted);
 
 			// verify that the relayer has declared correct `lane_data::relayers` state
 			// (we only care about total number of entries and messages, because this affects call
@@ -547,8 +943,14 @@ pub mod pallet {
 				// handle messages delivery confirmation
 				let preliminary_callback_overhead =
 					single_message_callback_overhead.saturating_mul(relayers_state.total_messages);
-				let actual_callback_weight =
+				let mut actual_callback_weight =
 					T::OnDeliveryConfirmed::on_messages_delivered(&lane_id, &confirmed_messages);
+				let expired_messages = expired_messages::<T, I>(lane_id, &confirmed_messages);
+				if !expired_messages.is_empty() {
+					actual_callback_weight = actual_callback_weight.saturating_add(
+						T::OnDeliveryConfirmed::on_messages_expired(&lane_id, &expired_messages),
+					);
+				}
 				match preliminary_callback_overhead.checked_sub(&actual_callback_weight) {
 					Some(difference) if difference.is_zero() => (),
 					Some(difference) => {
@@ -577,6 +979,29 @@ pub mod pallet {
 					},
 				}
 
+				// route dispatch results to handlers that have been chosen by message senders
+				let preliminary_delivery_result_overhead =
+					T::WeightInfo::single_message_delivery_result_callback_overhead(
+						T::DbWeight::get(),
+					)
+					.saturating_mul(relayers_state.total_messages);
+				let actual_delivery_result_weight =
+					route_delivery_results::<T, I>(lane_id, &confirmed_messages);
+				actual_weight = actual_weight.saturating_sub(
+					preliminary_delivery_result_overhead
+						.saturating_sub(actual_delivery_result_weight),
+				);
+
+				// refund fees of cancelled messages that have not been dispatched
+				let preliminary_refund_overhead =
+					T::WeightInfo::cancelled_message_refund_overhead(T::DbWeight::get())
+						.saturating_mul(relayers_state.total_messages);
+				let actual_refund_weight =
+					refund_cancelled_messages::<T, I>(lane_id, &confirmed_messages);
+				actual_weight = actual_weight.saturating_sub(
+					preliminary_refund_overhead.saturating_sub(actual_refund_weight),
+				);
+
 				// emit 'delivered' event
 				let received_range = confirmed_messages.begin..=confirmed_messages.end;
 				Self::deposit_event(Event::MessagesDelivered {
@@ -601,1006 +1026,5546 @@ pub mod pallet {
 				lane_id,
 			);
 
+			close_lane_if_drained::<T, I>(lane_id);
+
 			Ok(PostDispatchInfo { actual_weight: Some(actual_weight), pays_fee: Pays::Yes })
 		}
-	}
 
-	#[pallet::event]
-	#[pallet::generate_deposit(pub(super) fn deposit_event)]
-	pub enum Event<T: Config<I>, I: 'static = ()> {
-		/// Pallet parameter has been updated.
-		ParameterUpdated { parameter: T::Parameter },
-		/// Message has been accepted and is waiting to be delivered.
-		MessageAccepted { lane_id: LaneId, nonce: MessageNonce },
-		/// Messages have been received from the bridged chain.
-		MessagesReceived(Vec<ReceivedMessages<ReceivalResult>>),
-		/// Messages in the inclusive range have been delivered to the bridged chain.
-		MessagesDelivered { lane_id: LaneId, messages: DeliveredMessages },
-	}
+		/// Halt or resume all/some operations at the single lane.
+		///
+		/// The pallet-wide operating mode takes precedence over the lane operating mode.
+		///
+		/// May only be called either by root, or by `PalletOwner`.
+		#[pallet::call_index(7)]
+		#[pallet::weight((T::DbWeight::get().reads_writes(1, 2), DispatchClass::Operational))]
+		pub fn set_lane_operating_mode(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			operating_mode: MessagesOperatingMode,
+		) -> DispatchResult {
+			Self::ensure_owner_or_root(origin)?;
+			if operating_mode == MessagesOperatingMode::Basic(BasicOperatingMode::Normal) {
+				LaneOperatingModes::<T, I>::remove(lane_id);
+			} else {
+				LaneOperatingModes::<T, I>::insert(lane_id, operating_mode);
+			}
+			log::info!(
+				target: LOG_TARGET,
+				"Setting lane {:?} operating mode to {:?}.",
+				lane_id,
+				operating_mode,
+			);
+			Self::deposit_event(Event::LaneOperatingModeChanged { lane_id, operating_mode });
+			Ok(())
+		}
 
-	#[pallet::error]
-	pub enum Error<T, I = ()> {
-		/// Pallet is not in Normal operating mode.
-		NotOperatingNormally,
-		/// The message is too large to be sent over the bridge.
-		MessageIsTooLarge,
-		/// Message has been treated as invalid by chain verifier.
-		MessageRejectedByChainVerifier(VerificationError),
-		/// Message has been treated as invalid by lane verifier.
-		MessageRejectedByLaneVerifier(VerificationError),
-		/// Submitter has failed to pay fee for delivering and dispatching messages.
-		FailedToWithdrawMessageFee,
-		/// The transaction brings too many messages.
-		TooManyMessagesInTheProof,
-		/// Invalid messages has been submitted.
-		InvalidMessagesProof,
-		/// Invalid messages delivery proof has been submitted.
-		InvalidMessagesDeliveryProof,
-		/// The bridged chain has invalid `UnrewardedRelayers` in its storage (fatal for the lane).
-		InvalidUnrewardedRelayers,
-		/// The relayer has declared invalid unrewarded relayers state in the
-		/// `receive_messages_delivery_proof` call.
-		InvalidUnrewardedRelayersState,
-		/// The number of actually confirmed messages is going to be larger than the number of
-		/// messages in the proof. This may mean that this or bridged chain storage is corrupted.
-		TryingToConfirmMoreMessagesThanExpected,
-		/// Error generated by the `OwnedBridgeModule` trait.
-		BridgeModule(bp_runtime::OwnedBridgeModuleError),
-	}
+		/// Open new lane.
+		///
+		/// May only be called either by root, or by `PalletOwner`.
+		#[pallet::call_index(8)]
+		#[pallet::weight((T::DbWeight::get().reads_writes(2, 2), DispatchClass::Operational))]
+		pub fn open_lane(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			owner: Option<T::AccountId>,
+			description_hash: H256,
+			kind: LaneKind,
+		) -> DispatchResult {
+			Self::ensure_owner_or_root(origin)?;
+			Self::do_open_lane(lane_id, owner, description_hash, kind)
+		}
 
-	/// Optional pallet owner.
-	///
-	/// Pallet owner has a right to halt all pallet operations and then resume it. If it is
-	/// `None`, then there are no direct ways to halt/resume pallet operations, but other
-	/// runtime methods may still be used to do that (i.e. democracy::referendum to update halt
-	/// flag directly or call the `halt_operations`).
-	#[pallet::storage]
-	#[pallet::getter(fn module_owner)]
-	pub type PalletOwner<T: Config<I>, I: 'static = ()> = StorageValue<_, T::AccountId>;
+		/// Close the lane.
+		///
+		/// The lane stops accepting new outbound messages immediately. It is marked as closed
+		/// once all sent messages are delivered and confirmed and there are no unrewarded
+		/// relayers left at the inbound lane. Until then, the call may be repeated to prune
+		/// confirmed messages of the closing lane.
+		///
+		/// May only be called either by root, `PalletOwner` or the lane owner.
+		#[pallet::call_index(9)]
+		#[pallet::weight((
+			T::DbWeight::get()
+				.reads_writes(5, 6)
+				.saturating_add(T::DbWeight::get().writes(T::MaxMessagesToPruneAtOnce::get())),
+			DispatchClass::Operational,
+		))]
+		pub fn close_lane(origin: OriginFor<T>, lane_id: LaneId) -> DispatchResult {
+			let mut metadata = Lanes::<T, I>::get(lane_id).ok_or(Error::<T, I>::UnknownLane)?;
+			if Self::ensure_owner_or_root(origin.clone()).is_err() {
+				let who = ensure_signed(origin)?;
+				ensure!(metadata.owner.as_ref() == Some(&who), DispatchError::BadOrigin);
+			}
+			ensure!(metadata.state != LaneState::Closed, Error::<T, I>::LaneNotOpened);
 
-	/// The current operating mode of the pallet.
-	///
-	/// Depending on the mode either all, some, or no transactions will be allowed.
-	#[pallet::storage]
-	#[pallet::getter(fn operating_mode)]
-	pub type PalletOperatingMode<T: Config<I>, I: 'static = ()> =
-		StorageValue<_, MessagesOperatingMode, ValueQuery>;
+			if metadata.state == LaneState::Opened {
+				metadata.state = LaneState::Closing;
+				Lanes::<T, I>::insert(lane_id, metadata);
+				log::info!(target: LOG_TARGET, "Closing lane {:?}.", lane_id);
+				Self::deposit_event(Event::LaneClosing { lane_id });
+			}
+			close_lane_if_drained::<T, I>(lane_id);
 
-	/// Map of lane id => inbound lane data.
-	#[pallet::storage]
-	pub type InboundLanes<T: Config<I>, I: 'static = ()> =
-		StorageMap<_, Blake2_128Concat, LaneId, StoredInboundLaneData<T, I>, ValueQuery>;
+			Ok(())
+		}
 
-	/// Map of lane id => outbound lane data.
-	#[pallet::storage]
-	pub type OutboundLanes<T: Config<I>, I: 'static = ()> =
-		StorageMap<_, Blake2_128Concat, LaneId, OutboundLaneData, ValueQuery>;
+		/// Dispatch the inbound message, whose dispatch has previously failed.
+		///
+		/// The caller pays for the dispatch and is treated as the relayer of the message. The
+		/// `dispatch_weight` must be enough to cover the message dispatch weight. Once the
+		/// message is dispatched successfully, it is removed from the storage.
+		#[pallet::call_index(10)]
+		#[pallet::weight(T::WeightInfo::retry_message_dispatch_weight(
+			*dispatch_weight,
+			T::DbWeight::get(),
+		))]
+		pub fn retry_message_dispatch(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			nonce: MessageNonce,
+			dispatch_weight: Weight,
+		) -> DispatchResultWithPostInfo {
+			Self::ensure_not_halted().map_err(Error::<T, I>::BridgeModule)?;
+			let caller = ensure_signed(origin)?;
+			ensure!(!is_lane_halted::<T, I>(lane_id), Error::<T, I>::LaneHalted);
 
-	/// All queued outbound messages.
-	#[pallet::storage]
-	pub type OutboundMessages<T: Config<I>, I: 'static = ()> =
-		StorageMap<_, Blake2_128Concat, MessageKey, StoredMessageData<T, I>>;
+			let failed_message = FailedMessages::<T, I>::get(lane_id, nonce)
+				.ok_or(Error::<T, I>::UnknownFailedMessage)?
+				.0;
+			ensure!(
+				!is_failed_message_expired::<T, I>(&failed_message),
+				Error::<T, I>::FailedMessageExpired
+			);
 
-	#[pallet::genesis_config]
-	pub struct GenesisConfig<T: Config<I>, I: 'static = ()> {
-		/// Initial pallet operating mode.
-		pub operating_mode: MessagesOperatingMode,
-		/// Initial pallet owner.
-		pub owner: Option<T::AccountId>,
-		/// Dummy marker.
-		pub phantom: sp_std::marker::PhantomData<I>,
-	}
+			let mut message = DispatchMessage {
+				key: MessageKey { lane_id, nonce },
+				data: DispatchMessageData {
+					payload: T::InboundPayload::decode(&mut &failed_message.payload[..]),
+					fee: failed_message.fee,
+					is_cancelled: false,
+					expiry: None,
+				},
+			};
+			let message_dispatch_weight = T::MessageDispatch::dispatch_weight(&mut message);
+			ensure!(
+				message_dispatch_weight.all_lte(dispatch_weight),
+				Error::<T, I>::InsufficientDispatchWeight
+			);
+			T::MessageDispatch::pre_dispatch(&caller, &message).map_err(|err| {
+				log::trace!(
+					target: LOG_TARGET,
+					"Failed message {:?}/{} has been rejected by pre-dispatch: {}",
+					lane_id,
+					nonce,
+					err,
+				);
 
-	#[cfg(feature = "std")]
-	impl<T: Config<I>, I: 'static> Default for GenesisConfig<T, I> {
-		fn default() -> Self {
-			Self {
-				operating_mode: Default::default(),
-				owner: Default::default(),
-				phantom: Default::default(),
-			}
-		}
-	}
+				Error::<T, I>::FailedMessagePreDispatchValidateFailed
+			})?;
 
-	#[pallet::genesis_build]
-	impl<T: Config<I>, I: 'static> GenesisBuild<T, I> for GenesisConfig<T, I> {
-		fn build(&self) {
-			PalletOperatingMode::<T, I>::put(self.operating_mode);
-			if let Some(ref owner) = self.owner {
-				PalletOwner::<T, I>::put(owner);
+			let dispatch_result = T::MessageDispatch::dispatch(&caller, message);
+			if dispatch_result.dispatch_result {
+				remove_failed_message::<T, I>(lane_id, nonce);
 			}
+			log::trace!(
+				target: LOG_TARGET,
+				"Retried dispatch of the failed message {:?}/{}: {:?}",
+				lane_id,
+				nonce,
+				dispatch_result,
+			);
+			Self::deposit_event(Event::FailedMessageRetried {
+				lane_id,
+				nonce,
+				dispatch_result: dispatch_result.dispatch_result,
+			});
+
+			// refund both the weight that the caller has declared in excess and the weight that
+			// hasn't been spent by the dispatch
+			let unspent_weight = dispatch_result.unspent_weight.min(message_dispatch_weight);
+			let actual_weight =
+				T::WeightInfo::retry_message_dispatch_weight(dispatch_weight, T::DbWeight::get())
+					.saturating_sub(dispatch_weight.saturating_sub(message_dispatch_weight))
+					.saturating_sub(unspent_weight);
+
+			Ok(PostDispatchInfo { actual_weight: Some(actual_weight), pays_fee: Pays::Yes })
 		}
-	}
-}
-pub use pallet::*;
 
-/// AccountId of the shared relayer fund account.
-///
-/// This account is passed to `MessageDeliveryAndDispatchPayment` trait, and depending
-/// on the implementation it can be used to store relayers rewards.
-/// See [`InstantCurrencyPayments`] for a concrete implementation.
-pub fn relayer_fund_account_id<AccountId, AccountIdConverter: Convert<H256, AccountId>>(
-) -> AccountId {
-	let encoded_id = bp_runtime::derive_relayer_fund_account_id(bp_runtime::NO_INSTANCE_ID);
-	AccountIdConverter::convert(encoded_id)
-}
-
-impl<T, I>
-	bp_messages::source_chain::MessagesBridge<
-		T::RuntimeOrigin,
-		T::OutboundMessageFee,
-		T::OutboundPayload,
-	> for Pallet<T, I>
-where
-	T: Config<I>,
-	I: 'static,
-{
-	type Error = sp_runtime::DispatchErrorWithPostInfo<PostDispatchInfo>;
-
-	fn send_message(
-		sender: T::RuntimeOrigin,
-		lane: LaneId,
-		message: T::OutboundPayload,
-		delivery_and_dispatch_fee: T::OutboundMessageFee,
-	) -> Result<SendMessageArtifacts, Self::Error> {
-		crate::send_message::<T, I>(sender, lane, message, delivery_and_dispatch_fee)
-	}
-}
-
-/// Runtime outbound lane storage.
-pub struct RuntimeOutboundLaneStorage<T, I = ()> {
-	lane_id: LaneId,
-	_phantom: PhantomData<(T, I)>,
-}
-impl<T: Config<I>, I: 'static> OutboundLaneStorage for RuntimeOutboundLaneStorage<T, I> {
-	type MessageFee = T::OutboundMessageFee;
+		/// Remove the failed inbound message, that may no longer be dispatched, because its
+		/// lifetime has passed.
+		///
+		/// May be called by anyone.
+		#[pallet::call_index(11)]
+		#[pallet::weight(T::DbWeight::get().reads_writes(2, 2))]
+		pub fn remove_expired_failed_message(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			nonce: MessageNonce,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
 
-	fn id(&self) -> LaneId {
-		self.lane_id
-	}
+			let failed_message = FailedMessages::<T, I>::get(lane_id, nonce)
+				.ok_or(Error::<T, I>::UnknownFailedMessage)?
+				.0;
+			ensure!(
+				is_failed_message_expired::<T, I>(&failed_message),
+				Error::<T, I>::FailedMessageNotExpired
+			);
 
-	fn data(&self) -> OutboundLaneData {
-		OutboundLanes::<T, I>::get(self.lane_id)
-	}
+			remove_failed_message::<T, I>(lane_id, nonce);
+			log::trace!(
+				target: LOG_TARGET,
+				"Removed expired failed message {:?}/{}",
+				lane_id,
+				nonce,
+			);
+			Self::deposit_event(Event::FailedMessageRemoved { lane_id, nonce });
 
-	fn set_data(&mut self, data: OutboundLaneData) {
-		OutboundLanes::<T, I>::insert(self.lane_id, data)
-	}
+			Ok(())
+		}
 
-	#[cfg(test)]
-	fn message(&self, nonce: &MessageNonce) -> Option<MessageData<T::OutboundMessageFee>> {
-		OutboundMessages::<T, I>::get(MessageKey { lane_id: self.lane_id, nonce: *nonce })
-			.map(Into::into)
-	}
+		/// Cancel the outbound message that has not been delivered to the bridged chain.
+		///
+		/// The message may only be cancelled by its sender, once `MessageCancellationTimeout`
+		/// blocks have passed since it has been sent. The messages delivery proof must show that
+		/// the message has not been delivered yet. Messages of unordered lanes may not be
+		/// cancelled, because they may be delivered ahead of the `last_delivered_nonce`. Like
+		/// other calls that change outbound messages, it is rejected while the pallet is not
+		/// operating normally or while the lane is halted.
+		///
+		/// The payload of the cancelled message is replaced with the `CANCELLED_MESSAGE_PAYLOAD`,
+		/// so the bridged chain never dispatches the message if it is delivered later. The proof
+		/// may be built at any bridged chain header and relayers may still deliver the original
+		/// message, using proofs of older headers of this chain. So the fee is only refunded when
+		/// the bridged chain confirms that the cancelled payload has been delivered. Only the part
+		/// of the fee that has been paid by the sender is refunded.
+		#[pallet::call_index(12)]
+		#[pallet::weight(T::WeightInfo::cancel_message_weight(proof, T::DbWeight::get()))]
+		pub fn cancel_message(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			nonce: MessageNonce,
+			proof: MessagesDeliveryProofOf<T, I>,
+		) -> DispatchResult {
+			ensure_normal_operating_mode::<T, I>()?;
+			ensure!(!is_lane_halted::<T, I>(lane_id), Error::<T, I>::LaneHalted);
+			let who = ensure_signed(origin)?;
+
+			let message_key = MessageKey { lane_id, nonce };
+			let (sender, sent_at, sender_fee) = OutboundMessageSenders::<T, I>::get(&message_key)
+				.ok_or(Error::<T, I>::MessageNotCancellable)?;
+			ensure!(sender == who, DispatchError::BadOrigin);
+			ensure!(
+				frame_system::Pallet::<T>::block_number()
+					>= sent_at.saturating_add(T::MessageCancellationTimeout::get()),
+				Error::<T, I>::MessageCancellationTimeoutNotPassed
+			);
+			ensure!(
+				Lanes::<T, I>::get(lane_id).map(|metadata| metadata.kind).unwrap_or_default()
+					== LaneKind::Ordered,
+				Error::<T, I>::MessageNotCancellable
+			);
+			let message_data: MessageData<_> = OutboundMessages::<T, I>::get(&message_key)
+				.ok_or(Error::<T, I>::MessageNotCancellable)?
+				.into();
 
-	fn save_message(
-		&mut self,
-		nonce: MessageNonce,
-		mesage_data: MessageData<T::OutboundMessageFee>,
-	) {
-		OutboundMessages::<T, I>::insert(MessageKey { lane_id: self.lane_id, nonce }, mesage_data);
-	}
+			// the message must not be delivered, according to both this chain and the proof
+			ensure!(
+				nonce > OutboundLanes::<T, I>::get(lane_id).latest_received_nonce,
+				Error::<T, I>::MessageAlreadyDelivered
+			);
+			let (proof_lane_id, lane_data) =
+				T::TargetHeaderChain::verify_messages_delivery_proof(proof).map_err(|err| {
+					log::trace!(
+						target: LOG_TARGET,
+						"Rejecting invalid messages delivery proof: {:?}",
+						err,
+					);
 
-	fn remove_message(&mut self, nonce: &MessageNonce) {
-		OutboundMessages::<T, I>::remove(MessageKey { lane_id: self.lane_id, nonce: *nonce });
-	}
-}
+					Error::<T, I>::InvalidMessagesDeliveryProof
+				})?;
+			ensure!(proof_lane_id == lane_id, Error::<T, I>::InvalidMessagesDeliveryProof);
+			ensure!(
+				nonce > lane_data.last_delivered_nonce(),
+				Error::<T, I>::MessageAlreadyDelivered
+			);
 
-/// Function that actually sends message.
-fn send_message<T: Config<I>, I: 'static>(
-	submitter: T::RuntimeOrigin,
-	lane_id: LaneId,
-	payload: T::OutboundPayload,
-	delivery_and_dispatch_fee: T::OutboundMessageFee,
-) -> sp_std::result::Result<
-	SendMessageArtifacts,
-	sp_runtime::DispatchErrorWithPostInfo<PostDispatchInfo>,
-> {
-	ensure_normal_operating_mode::<T, I>()?;
+			// third-party top-ups stay with the message, the sender may only get its own fee back
+			let refund = if sender_fee < message_data.fee { sender_fee } else { message_data.fee };
+			OutboundMessages::<T, I>::insert(
+				&message_key,
+				MessageData::cancelled(message_data.fee),
+			);
+			OutboundMessageSenders::<T, I>::remove(&message_key);
+			CancelledMessages::<T, I>::insert(&message_key, (sender, refund));
+			log::trace!(target: LOG_TARGET, "Cancelled message {:?}/{}", lane_id, nonce);
+			Self::deposit_event(Event::MessageCancelled { lane_id, nonce });
 
-	// the most lightweigh check is the message size check
-	ensure!(
-		payload.size() <= T::MaximalOutboundPayloadSize::get(),
-		Error::<T, I>::MessageIsTooLarge,
-	);
+			Ok(())
+		}
 
-	// initially, actual (post-dispatch) weight is equal to pre-dispatch weight
-	let mut actual_weight = T::WeightInfo::send_message_weight(&payload, T::DbWeight::get());
+		/// Send message over lane. The message is not dispatched at the bridged chain if it is
+		/// delivered after given expiry.
+		///
+		/// Expired messages are still delivered and confirmed, so the lane is not blocked by them.
+		/// The `OnDeliveryConfirmed::on_messages_expired` handler is called for such messages
+		/// when their delivery is confirmed.
+		#[pallet::call_index(13)]
+		#[pallet::weight(T::WeightInfo::send_message_weight(payload, T::DbWeight::get()))]
+		pub fn send_message_with_expiry(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			payload: T::OutboundPayload,
+			delivery_and_dispatch_fee: T::OutboundMessageFee,
+			expiry: MessageExpiry,
+		) -> DispatchResultWithPostInfo {
+			crate::send_message::<T, I>(
+				origin,
+				lane_id,
+				payload,
+				delivery_and_dispatch_fee,
+				Some(expiry),
+			)
+			.map(|sent_message| PostDispatchInfo {
+				actual_weight: Some(sent_message.weight),
+				pays_fee: Pays::Yes,
+			})
+		}
 
-	// let's first check if message can be delivered to target chain
-	T::TargetHeaderChain::verify_message(&payload).map_err(|err| {
-		log::trace!(
-			target: LOG_TARGET,
-			"Message to lane {:?} is rejected by target chain: {:?}",
-			lane_id,
-			err,
-		);
+		/// Send batch of messages. Fees of all messages are paid at once.
+		///
+		/// Messages are accepted in the order they are specified. If any message is rejected, the
+		/// whole batch is rejected. Unlike `send_message`, confirmed messages are not pruned by
+		/// this call - this is done by the `on_idle` hook.
+		#[pallet::call_index(14)]
+		#[pallet::weight(T::WeightInfo::send_messages_weight(
+			messages.iter().map(|(_, payload, _)| payload.size()),
+			T::DbWeight::get(),
+		))]
+		pub fn send_messages(
+			origin: OriginFor<T>,
+			messages: BoundedVec<
+				(LaneId, T::OutboundPayload, T::OutboundMessageFee),
+				T::MaxOutboundMessagesInBatch,
+			>,
+		) -> DispatchResultWithPostInfo {
+			crate::send_messages::<T, I>(origin, messages.into_inner()).map(|actual_weight| {
+				PostDispatchInfo { actual_weight: Some(actual_weight), pays_fee: Pays::Yes }
+			})
+		}
 
-		Error::<T, I>::MessageRejectedByChainVerifier(err)
-	})?;
+		/// Set limit of outbound traffic of the lane. If limit is `None`, the lane traffic
+		/// is not limited.
+		///
+		/// May only be called either by root, or by `PalletOwner`.
+		#[pallet::call_index(15)]
+		#[pallet::weight((T::DbWeight::get().reads_writes(1, 2), DispatchClass::Operational))]
+		pub fn set_lane_rate_limit(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			limit: Option<OutboundRateLimit<T::BlockNumber>>,
+		) -> DispatchResult {
+			Self::ensure_owner_or_root(origin)?;
+			match limit {
+				Some(limit) => LaneRateLimits::<T, I>::insert(lane_id, limit),
+				None => {
+					LaneRateLimits::<T, I>::remove(lane_id);
+					LaneRateLimitUsages::<T, I>::remove(lane_id);
+				},
+			}
+			log::info!(
+				target: LOG_TARGET,
+				"Setting lane {:?} outbound rate limit to {:?}.",
+				lane_id,
+				limit,
+			);
+			Self::deposit_event(Event::LaneRateLimitChanged { lane_id, limit });
+			Ok(())
+		}
 
-	// now let's enforce any additional lane rules
-	let mut lane = outbound_lane::<T, I>(lane_id);
-	T::LaneMessageVerifier::verify_message(
-		&submitter,
-		&delivery_and_dispatch_fee,
-		&lane_id,
-		&lane.data(),
-		&payload,
-	)
-	.map_err(|err| {
-		log::trace!(
-			target: LOG_TARGET,
-			"Message to lane {:?} is rejected by lane verifier: {:?}",
-			lane_id,
-			err,
-		);
+		/// Set limit of outbound traffic of every message sender. If limit is `None`, the
+		/// senders traffic is not limited.
+		///
+		/// The limit is applied to every signed sender separately. Messages that are sent by
+		/// other origins are not limited by it.
+		///
+		/// May only be called either by root, or by `PalletOwner`.
+		#[pallet::call_index(16)]
+		#[pallet::weight((T::DbWeight::get().reads_writes(1, 1), DispatchClass::Operational))]
+		pub fn set_sender_rate_limit(
+			origin: OriginFor<T>,
+			limit: Option<OutboundRateLimit<T::BlockNumber>>,
+		) -> DispatchResult {
+			Self::ensure_owner_or_root(origin)?;
+			SenderRateLimit::<T, I>::set(limit);
+			log::info!(target: LOG_TARGET, "Setting sender outbound rate limit to {:?}.", limit);
+			Self::deposit_event(Event::SenderRateLimitChanged { limit });
+			Ok(())
+		}
 
-		Error::<T, I>::MessageRejectedByLaneVerifier(err)
-	})?;
+		/// Allow sender to send messages over the lane.
+		///
+		/// May only be called either by root, or by `PalletOwner`.
+		#[pallet::call_index(17)]
+		#[pallet::weight((T::DbWeight::get().reads_writes(2, 1), DispatchClass::Operational))]
+		pub fn allow_sender(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			sender: AllowedSender<T::AccountId>,
+		) -> DispatchResult {
+			Self::ensure_owner_or_root(origin)?;
+			ensure!(
+				!AllowedSenders::<T, I>::contains_key(lane_id, &sender),
+				Error::<T, I>::SenderAlreadyAllowed,
+			);
 
-	// let's withdraw delivery and dispatch fee from submitter
-	T::MessageDeliveryAndDispatchPayment::pay_delivery_and_dispatch_fee(
-		&submitter,
-		&delivery_and_dispatch_fee,
-		&relayer_fund_account_id::<T::AccountId, T::AccountIdConverter>(),
-	)
-	.map_err(|err| {
-		log::trace!(
-			target: LOG_TARGET,
-			"Message to lane {:?} is rejected because submitter is unable to pay fee {:?}: {:?}",
-			lane_id,
-			delivery_and_dispatch_fee,
-			err,
-		);
+			AllowedSenders::<T, I>::insert(lane_id, &sender, ());
+			log::info!(
+				target: LOG_TARGET,
+				"Allowing {:?} to send messages over lane {:?}.",
+				sender,
+				lane_id,
+			);
+			Self::deposit_event(Event::SenderAllowed { lane_id, sender });
+			Ok(())
+		}
 
-		Error::<T, I>::FailedToWithdrawMessageFee
-	})?;
+		/// Disallow previously allowed sender to send messages over the lane.
+		///
+		/// May only be called either by root, or by `PalletOwner`.
+		#[pallet::call_index(18)]
+		#[pallet::weight((T::DbWeight::get().reads_writes(2, 1), DispatchClass::Operational))]
+		pub fn disallow_sender(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			sender: AllowedSender<T::AccountId>,
+		) -> DispatchResult {
+			Self::ensure_owner_or_root(origin)?;
+			ensure!(
+				AllowedSenders::<T, I>::contains_key(lane_id, &sender),
+				Error::<T, I>::SenderNotAllowed,
+			);
 
-	// finally, save message in outbound storage and emit event
-	let encoded_payload = payload.encode();
-	let encoded_payload_len = encoded_payload.len();
-	let nonce =
-		lane.send_message(MessageData { payload: encoded_payload, fee: delivery_and_dispatch_fee });
-	// Guaranteed to be called outside only when the message is accepted.
-	// We assume that the maximum weight call back used is `single_message_callback_overhead`, so do
-	// not perform complex db operation in callback. If you want to, put these magic logic in
-	// outside pallet and control the weight there.
-	let single_message_callback_overhead =
-		T::WeightInfo::single_message_callback_overhead(T::DbWeight::get());
-	let actual_callback_weight = T::OnMessageAccepted::on_messages_accepted(&lane_id, &nonce);
-	match single_message_callback_overhead.checked_sub(&actual_callback_weight) {
-		Some(difference) if difference.is_zero() => (),
-		Some(difference) => {
-			log::trace!(
+			AllowedSenders::<T, I>::remove(lane_id, &sender);
+			log::info!(
 				target: LOG_TARGET,
-				"T::OnMessageAccepted callback has spent less weight than expected. Refunding: \
-				{} - {} = {}",
-				single_message_callback_overhead,
-				actual_callback_weight,
-				difference,
+				"Disallowing {:?} to send messages over lane {:?}.",
+				sender,
+				lane_id,
 			);
-			actual_weight = actual_weight.saturating_sub(difference);
-		},
-		None => {
-			debug_assert!(false, "T::OnMessageAccepted callback consumed too much weight.");
-			log::error!(
-				target: LOG_TARGET,
-				"T::OnMessageAccepted callback has spent more weight that it is allowed to: \
-				{} vs {}",
-				single_message_callback_overhead,
-				actual_callback_weight,
+			Self::deposit_event(Event::SenderDisallowed { lane_id, sender });
+			Ok(())
+		}
+
+		/// Dispatch the oldest inbound message of the lane, whose dispatch has been deferred.
+		///
+		/// Deferred messages are dispatched by `on_idle` using spare block weight, so the message
+		/// that never fits into it, blocks its lane. Anyone may dispatch such message using this
+		/// call. The caller pays for the dispatch and is treated as the relayer of the message.
+		/// The `dispatch_weight` must be enough to cover the message dispatch weight.
+		#[pallet::call_index(19)]
+		#[pallet::weight(T::WeightInfo::dispatch_deferred_message_weight(
+			*dispatch_weight,
+			T::DbWeight::get(),
+		))]
+		pub fn dispatch_deferred_message(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			dispatch_weight: Weight,
+		) -> DispatchResultWithPostInfo {
+			Self::ensure_not_halted().map_err(Error::<T, I>::BridgeModule)?;
+			let caller = ensure_signed(origin)?;
+			ensure!(!is_lane_halted::<T, I>(lane_id), Error::<T, I>::LaneHalted);
+
+			let mut queue = DeferredMessagesQueues::<T, I>::get(lane_id);
+			let nonce = queue.first().copied().ok_or(Error::<T, I>::UnknownDeferredMessage)?;
+			let deferred_message = DeferredMessages::<T, I>::get(lane_id, nonce)
+				.ok_or(Error::<T, I>::UnknownDeferredMessage)?;
+			let mut message = deferred_dispatch_message::<T, I>(lane_id, nonce, &deferred_message);
+			let message_dispatch_weight = T::MessageDispatch::dispatch_weight(&mut message);
+			ensure!(
+				message_dispatch_weight.all_lte(dispatch_weight),
+				Error::<T, I>::InsufficientDispatchWeight
 			);
-		},
-	}
 
-	// message sender pays for pruning at most `MaxMessagesToPruneAtOnce` messages
-	// the cost of pruning every message is roughly single db write
-	// => lets refund sender if less than `MaxMessagesToPruneAtOnce` messages pruned
-	let max_messages_to_prune = T::MaxMessagesToPruneAtOnce::get();
-	let pruned_messages = lane.prune_messages(max_messages_to_prune);
-	if let Some(extra_messages) = max_messages_to_prune.checked_sub(pruned_messages) {
-		actual_weight = actual_weight.saturating_sub(T::DbWeight::get().writes(extra_messages));
-	}
+			let unspent_weight = dispatch_deferred_message::<T, I>(
+				&caller,
+				deferred_message,
+				message,
+				message_dispatch_weight,
+			);
+			queue.remove(0);
+			update_deferred_messages_queue::<T, I>(lane_id, queue);
 
-	log::trace!(
-		target: LOG_TARGET,
-		"Accepted message {} to lane {:?}. Message size: {:?}",
-		nonce,
-		lane_id,
-		encoded_payload_len,
-	);
+			// refund both the weight that the caller has declared in excess and the weight that
+			// hasn't been spent by the dispatch
+			let actual_weight = T::WeightInfo::dispatch_deferred_message_weight(
+				dispatch_weight,
+				T::DbWeight::get(),
+			)
+			.saturating_sub(dispatch_weight.saturating_sub(message_dispatch_weight))
+			.saturating_sub(unspent_weight);
 
-	Pallet::<T, I>::deposit_event(Event::MessageAccepted { lane_id, nonce });
+			Ok(PostDispatchInfo { actual_weight: Some(actual_weight), pays_fee: Pays::Yes })
+		}
+	}
 
-	Ok(SendMessageArtifacts { nonce, weight: actual_weight })
-}
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config<I>, I: 'static = ()> {
+		/// Pallet parameter has been updated.
+		ParameterUpdated { parameter: T::Parameter },
+		/// Message has been accepted and is waiting to be delivered.
+		///
+		/// It is only deposited if `Config::DepositLegacyEvents` is `true`.
+		MessageAccepted { lane_id: LaneId, nonce: MessageNonce },
+		/// Messages have been received from the bridged chain.
+		///
+		/// It is only deposited if `Config::DepositLegacyEvents` is `true`.
+		MessagesReceived(Vec<ReceivedMessages<ReceivalResult>>),
+		/// Messages in the inclusive range have been delivered to the bridged chain.
+		MessagesDelivered { lane_id: LaneId, messages: DeliveredMessages },
+		/// Lane operating mode has been changed.
+		LaneOperatingModeChanged { lane_id: LaneId, operating_mode: MessagesOperatingMode },
+		/// New lane has been opened.
+		LaneOpened { lane_id: LaneId, owner: Option<T::AccountId>, kind: LaneKind },
+		/// The lane has stopped accepting new messages and is waiting for pending messages to
+		/// be delivered.
+		LaneClosing { lane_id: LaneId },
+		/// The lane has been closed. Its nonces are kept, so it may be re-opened later.
+		LaneClosed { lane_id: LaneId },
+		/// Inbound message dispatch has failed and the message has been kept for retry.
+		MessageDispatchFailed { lane_id: LaneId, nonce: MessageNonce },
+		/// Dispatch of the failed inbound message has been retried.
+		FailedMessageRetried { lane_id: LaneId, nonce: MessageNonce, dispatch_result: bool },
+		/// Inbound message, whose dispatch has been deferred, has been dispatched.
+		DeferredMessageDispatched { lane_id: LaneId, nonce: MessageNonce, dispatch_result: bool },
+		/// Expired failed inbound message has been removed.
+		FailedMessageRemoved { lane_id: LaneId, nonce: MessageNonce },
+		/// Outbound message has been cancelled. Its fee is refunded to the sender once the bridged
+		/// chain confirms that the message has not been dispatched.
+		MessageCancelled { lane_id: LaneId, nonce: MessageNonce },
+		/// Fee of the cancelled message has been refunded to its sender, because the message has
+		/// not been dispatched at the bridged chain.
+		CancelledMessageRefunded {
+			lane_id: LaneId,
+			nonce: MessageNonce,
+			fee: T::OutboundMessageFee,
+		},
+		/// Additional fee has been paid for the outbound message.
+		MessageFeeIncreased {
+			lane_id: LaneId,
+			nonce: MessageNonce,
+			additional_fee: T::OutboundMessageFee,
+		},
+		/// Message has been accepted and is waiting to be delivered.
+		///
+		/// This is the second version of the `MessageAccepted` event, that also has details of
+		/// the accepted message.
+		MessageAcceptedV2 {
+			lane_id: LaneId,
+			nonce: MessageNonce,
+			payload_hash: H256,
+			encoded_size: u32,
+			fee: T::OutboundMessageFee,
+			sender: Option<T::AccountId>,
+		},
+		/// Messages have been received from the bridged chain.
+		///
+		/// This is the second version of the `MessagesReceived` event, that also has details of
+		/// every received message.
+		MessagesReceivedV2(Vec<ReceivedMessages<ReceivalResultWithDetails>>),
+		/// Outbound rate limit of the lane has been changed.
+		LaneRateLimitChanged { lane_id: LaneId, limit: Option<OutboundRateLimit<T::BlockNumber>> },
+		/// Outbound rate limit of message senders has been changed.
+		SenderRateLimitChanged { limit: Option<OutboundRateLimit<T::BlockNumber>> },
+		/// Sender has been allowed to send messages over the lane.
+		SenderAllowed { lane_id: LaneId, sender: AllowedSender<T::AccountId> },
+		/// Sender has been disallowed to send messages over the lane.
+		SenderDisallowed { lane_id: LaneId, sender: AllowedSender<T::AccountId> },
+	}
 
-/// Ensure that the pallet is in normal operational mode.
-fn ensure_normal_operating_mode<T: Config<I>, I: 'static>() -> Result<(), Error<T, I>> {
-	if PalletOperatingMode::<T, I>::get()
-		== MessagesOperatingMode::Basic(BasicOperatingMode::Normal)
-	{
-		return Ok(());
+	#[pallet::error]
+	pub enum Error<T, I = ()> {
+		/// Pallet is not in Normal operating mode.
+		NotOperatingNormally,
+		/// The lane is not in Normal operating mode.
+		LaneNotOperatingNormally,
+		/// The lane is halted.
+		LaneHalted,
+		/// The lane is either unknown, or it is closing or closed.
+		LaneNotOpened,
+		/// The lane is already registered.
+		LaneAlreadyExists,
+		/// The lane is not registered.
+		UnknownLane,
+		/// The message is too large to be sent over the bridge.
+		MessageIsTooLarge,
+		/// Message has been treated as invalid by chain verifier.
+		MessageRejectedByChainVerifier(VerificationError),
+		/// Message has been treated as invalid by lane verifier.
+		MessageRejectedByLaneVerifier(VerificationError),
+		/// Message has been rejected because lane or sender has exceeded its outbound rate limit.
+		MessageRejectedByRateLimiter(VerificationError),
+		/// Submitter has failed to pay fee for delivering and dispatching messages.
+		FailedToWithdrawMessageFee,
+		/// The batch of messages is empty.
+		EmptyMessagesBatch,
+		/// The transaction brings too many messages.
+		TooManyMessagesInTheProof,
+		/// Invalid messages has been submitted.
+		InvalidMessagesProof,
+		/// Invalid messages delivery proof has been submitted.
+		InvalidMessagesDeliveryProof,
+		/// The bridged chain has invalid `UnrewardedRelayers` in its storage (fatal for the lane).
+		InvalidUnrewardedRelayers,
+		/// The relayer has declared invalid unrewarded relayers state in the
+		/// `receive_messages_delivery_proof` call.
+		InvalidUnrewardedRelayersState,
+		/// The number of actually confirmed messages is going to be larger than the number of
+		/// messages in the proof. This may mean that this or bridged chain storage is corrupted.
+		TryingToConfirmMoreMessagesThanExpected,
+		/// There is no failed message with given key.
+		UnknownFailedMessage,
+		/// The failed message lifetime has passed and it may no longer be dispatched.
+		FailedMessageExpired,
+		/// The failed message lifetime has not passed yet.
+		FailedMessageNotExpired,
+		/// The declared weight is not enough to dispatch the failed or deferred message.
+		InsufficientDispatchWeight,
+		/// The failed message has been rejected by the pre-dispatch validation.
+		FailedMessagePreDispatchValidateFailed,
+		/// The message is unknown, it has already been cancelled, or it may not be cancelled.
+		MessageNotCancellable,
+		/// The message may not be cancelled yet.
+		MessageCancellationTimeoutNotPassed,
+		/// The message has already been delivered to the bridged chain.
+		MessageAlreadyDelivered,
+		/// The message is not yet sent, or it has been cancelled.
+		MessageIsNotYetSent,
+		/// The sender is already allowed to send messages over the lane.
+		SenderAlreadyAllowed,
+		/// The sender is not allowed to send messages over the lane.
+		SenderNotAllowed,
+		/// There is no handler for the delivery callback of the message.
+		UnknownDeliveryCallback,
+		/// The message payload is reserved for cancelled messages.
+		ReservedMessagePayload,
+		/// There are no deferred messages at the lane.
+		UnknownDeferredMessage,
+		/// Error generated by the `OwnedBridgeModule` trait.
+		BridgeModule(bp_runtime::OwnedBridgeModuleError),
 	}
 
-	Err(Error::<T, I>::NotOperatingNormally)
-}
+	/// Optional pallet owner.
+	///
+	/// Pallet owner has a right to halt all pallet operations and then resume it. If it is
+	/// `None`, then there are no direct ways to halt/resume pallet operations, but other
+	/// runtime methods may still be used to do that (i.e. democracy::referendum to update halt
+	/// flag directly or call the `halt_operations`).
+	#[pallet::storage]
+	#[pallet::getter(fn module_owner)]
+	pub type PalletOwner<T: Config<I>, I: 'static = ()> = StorageValue<_, T::AccountId>;
 
-/// Creates new inbound lane object, backed by runtime storage.
-fn inbound_lane<T: Config<I>, I: 'static>(
-	lane_id: LaneId,
-) -> InboundLane<RuntimeInboundLaneStorage<T, I>> {
-	InboundLane::new(inbound_lane_storage::<T, I>(lane_id))
-}
+	/// The current operating mode of the pallet.
+	///
+	/// Depending on the mode either all, some, or no transactions will be allowed.
+	#[pallet::storage]
+	#[pallet::getter(fn operating_mode)]
+	pub type PalletOperatingMode<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, MessagesOperatingMode, ValueQuery>;
 
-/// Creates new runtime inbound lane storage.
-fn inbound_lane_storage<T: Config<I>, I: 'static>(
-	lane_id: LaneId,
-) -> RuntimeInboundLaneStorage<T, I> {
-	RuntimeInboundLaneStorage {
-		lane_id,
-		cached_data: RefCell::new(None),
-		_phantom: Default::default(),
-	}
-}
+	/// Map of lane id => lane metadata.
+	///
+	/// Only lanes that are registered here accept outbound messages.
+	#[pallet::storage]
+	#[pallet::getter(fn lane_metadata)]
+	pub type Lanes<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, LaneId, LaneMetadata<T::AccountId, T::BlockNumber>>;
 
-/// Creates new outbound lane object, backed by runtime storage.
-pub fn outbound_lane<T: Config<I>, I: 'static>(
-	lane_id: LaneId,
-) -> OutboundLane<RuntimeOutboundLaneStorage<T, I>> {
-	OutboundLane::new(RuntimeOutboundLaneStorage { lane_id, _phantom: Default::default() })
-}
+	/// Map of lane id => lane operating mode.
+	///
+	/// Lanes that are missing from this map are operating normally.
+	#[pallet::storage]
+	#[pallet::getter(fn lane_operating_mode)]
+	pub type LaneOperatingModes<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, LaneId, MessagesOperatingMode, ValueQuery>;
 
-/// Runtime inbound lane storage.
-struct RuntimeInboundLaneStorage<T: Config<I>, I: 'static = ()> {
-	lane_id: LaneId,
-	cached_data: RefCell<Option<InboundLaneData<T::InboundRelayer>>>,
-	_phantom: PhantomData<I>,
-}
-impl<T: Config<I>, I: 'static> InboundLaneStorage for RuntimeInboundLaneStorage<T, I> {
-	type MessageFee = T::InboundMessageFee;
-	type Relayer = T::InboundRelayer;
+	/// Map of lane id => inbound lane data.
+	#[pallet::storage]
+	pub type InboundLanes<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, LaneId, StoredInboundLaneData<T, I>, ValueQuery>;
 
-	fn id(&self) -> LaneId {
-		self.lane_id
-	}
+	/// Map of lane id => dispatch statuses of unconfirmed messages, delivered to the inbound lane.
+	///
+	/// Statuses are stored separately from the inbound lane data, so that the bridged chain may
+	/// still decode the lane data from our messages delivery proofs. Lanes that have no known
+	/// statuses are missing from this map.
+	#[pallet::storage]
+	pub type InboundLanesDispatchStatuses<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, LaneId, StoredInboundLaneDispatchStatuses<T, I>>;
 
-	fn max_unrewarded_relayer_entries(&self) -> MessageNonce {
-		T::MaxUnrewardedRelayerEntriesAtInboundLane::get()
-	}
+	/// Map of lane id => bitmap of messages that have been delivered ahead to the unordered
+	/// inbound lane.
+	#[pallet::storage]
+	pub type InboundLanesDeliveredAhead<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, LaneId, StoredDeliveredNonces<T, I>, ValueQuery>;
 
-	fn max_unconfirmed_messages(&self) -> MessageNonce {
-		T::MaxUnconfirmedMessagesAtInboundLane::get()
-	}
+	/// Relayers, dispatch results and dispatch statuses of messages that have been delivered
+	/// ahead to the unordered inbound lane.
+	#[pallet::storage]
+	pub type MessagesDeliveredAhead<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		LaneId,
+		Blake2_128Concat,
+		MessageNonce,
+		(T::InboundRelayer, bool, Option<MessageDispatchStatus>),
+	>;
 
-	fn data(&self) -> InboundLaneData<T::InboundRelayer> {
-		match self.cached_data.clone().into_inner() {
-			Some(data) => data,
-			None => {
-				let data: InboundLaneData<T::InboundRelayer> =
-					InboundLanes::<T, I>::get(self.lane_id).into();
-				*self.cached_data.try_borrow_mut().expect(
-					"we're in the single-threaded environment;\
-						we have no recursive borrows; qed",
-				) = Some(data.clone());
-				data
-			},
-		}
-	}
+	/// Map of lane id => outbound lane data.
+	#[pallet::storage]
+	pub type OutboundLanes<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, LaneId, OutboundLaneData, ValueQuery>;
 
-	fn set_data(&mut self, data: InboundLaneData<T::InboundRelayer>) {
-		*self.cached_data.try_borrow_mut().expect(
-			"we're in the single-threaded environment;\
-				we have no recursive borrows; qed",
-		) = Some(data.clone());
-		InboundLanes::<T, I>::insert(self.lane_id, StoredInboundLaneData::<T, I>(data))
-	}
-}
+	/// Outbound lane that has been visited last by the `on_idle` messages pruning.
+	///
+	/// The next `on_idle` call starts pruning from the lane that follows this one.
+	#[pallet::storage]
+	pub type OutboundLanesPruningCursor<T: Config<I>, I: 'static = ()> = StorageValue<_, LaneId>;
 
-/// Verify messages proof and return proved messages with decoded payload.
-fn verify_and_decode_messages_proof<Chain: SourceHeaderChain<Fee>, Fee, DispatchPayload: Decode>(
-	proof: Chain::MessagesProof,
-	messages_count: u32,
-) -> Result<ProvedMessages<DispatchMessage<DispatchPayload, Fee>>, VerificationError> {
-	// `receive_messages_proof` weight formula and `MaxUnconfirmedMessagesAtInboundLane` check
-	// guarantees that the `message_count` is sane and Vec<Message> may be allocated.
-	// (tx with too many messages will either be rejected from the pool, or will fail earlier)
-	Chain::verify_messages_proof(proof, messages_count).map(|messages_by_lane| {
-		messages_by_lane
-			.into_iter()
-			.map(|(lane, lane_data)| {
-				(
-					lane,
-					ProvedLaneMessages {
-						lane_state: lane_data.lane_state,
-						messages: lane_data.messages.into_iter().map(Into::into).collect(),
-					},
-				)
-			})
-			.collect()
-	})
-}
+	/// All queued outbound messages.
+	#[pallet::storage]
+	pub type OutboundMessages<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, MessageKey, StoredMessageData<T, I>>;
 
-#[cfg(test)]
-mod tests {
-	// darwinia-network
-	use super::*;
-	use crate::mock::{
-		message, message_payload, run_test, unrewarded_relayer, Balance, RuntimeEvent as TestEvent,
-		RuntimeOrigin, TestMessageDeliveryAndDispatchPayment, TestMessagesDeliveryProof,
-		TestMessagesParameter, TestMessagesProof, TestOnDeliveryConfirmed1,
-		TestOnDeliveryConfirmed2, TestOnMessageAccepted, TestRuntime, TokenConversionRate,
-		MAX_OUTBOUND_PAYLOAD_SIZE, PAYLOAD_REJECTED_BY_TARGET_CHAIN, REGULAR_PAYLOAD, TEST_LANE_ID,
-		TEST_RELAYER_A, TEST_RELAYER_B,
-	};
-	use bp_messages::{UnrewardedRelayer, UnrewardedRelayersState};
-	use bp_test_utils::generate_owned_bridge_module_tests;
-	// substrate
-	use frame_support::{
-		assert_noop, assert_ok,
-		storage::generator::{StorageMap, StorageValue},
-		weights::Weight,
-	};
-	use frame_system::{EventRecord, Pallet as System, Phase};
-	use sp_runtime::DispatchError;
+	/// Leaves of the Merkle tree that commits to outbound messages, accepted in the current block.
+	///
+	/// Leaves are ordered by the time the message has been accepted. The storage is cleared at
+	/// the beginning of every block.
+	#[pallet::storage]
+	#[pallet::unbounded]
+	pub type OutboundMessagesCommitmentLeaves<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, Vec<H256>, ValueQuery>;
 
-	fn get_ready_for_events() {
-		System::<TestRuntime>::set_block_number(1);
-		System::<TestRuntime>::reset_events();
-	}
+	/// Indices of leaves of outbound messages, accepted in the current block, in the
+	/// `OutboundMessagesCommitmentLeaves`.
+	///
+	/// The storage is cleared at the beginning of every block.
+	#[pallet::storage]
+	pub type OutboundMessagesCommitmentLeafIndices<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, MessageKey, u32>;
 
-	fn inbound_unrewarded_relayers_state(
-		lane: bp_messages::LaneId,
-	) -> bp_messages::UnrewardedRelayersState {
-		let inbound_lane_data = InboundLanes::<TestRuntime, ()>::get(lane).0;
-		let last_delivered_nonce = inbound_lane_data.last_delivered_nonce();
-		let relayers = inbound_lane_data.relayers;
-		bp_messages::UnrewardedRelayersState {
-			unrewarded_relayer_entries: relayers.len() as _,
-			messages_in_oldest_entry: relayers
-				.front()
-				.map(|entry| 1 + entry.messages.end - entry.messages.begin)
-				.unwrap_or(0),
-			total_messages: total_unrewarded_messages(&relayers).unwrap_or(MessageNonce::MAX),
-			last_delivered_nonce,
-		}
-	}
+	/// Root of the Merkle tree that commits to outbound messages, accepted in the current block.
+	///
+	/// It is only set at the end of the block where at least one message has been accepted.
+	#[pallet::storage]
+	pub type OutboundMessagesCommitmentRoot<T: Config<I>, I: 'static = ()> = StorageValue<_, H256>;
 
-	fn send_regular_message() -> Weight {
-		get_ready_for_events();
+	/// Senders of outbound messages, numbers of blocks where messages have been sent and fees
+	/// that have been paid by senders.
+	///
+	/// Only messages that are sent by signed accounts are tracked here. The entry is removed
+	/// once the message is cancelled, or pruned.
+	#[pallet::storage]
+	pub type OutboundMessageSenders<T: Config<I>, I: 'static = ()> = StorageMap<
+		_,
+		Blake2_128Concat,
+		MessageKey,
+		(T::AccountId, T::BlockNumber, T::OutboundMessageFee),
+	>;
+
+	/// Cancelled outbound messages, their senders and fees that are refunded to senders once
+	/// the bridged chain confirms that messages have not been dispatched.
+	///
+	/// The entry is removed once the delivery of the message is confirmed, or the message is
+	/// pruned.
+	#[pallet::storage]
+	pub type CancelledMessages<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, MessageKey, (T::AccountId, T::OutboundMessageFee)>;
 
-		let message_nonce =
-			outbound_lane::<TestRuntime, ()>(TEST_LANE_ID).data().latest_generated_nonce + 1;
-		let weight = Pallet::<TestRuntime>::send_message(
-			RuntimeOrigin::signed(1),
-			TEST_LANE_ID,
-			REGULAR_PAYLOAD,
-			REGULAR_PAYLOAD.declared_weight.ref_time(),
-		)
-		.expect("send_message has failed")
-		.actual_weight
-		.expect("send_message always returns Some");
+	/// Delivery callbacks of outbound messages.
+	///
+	/// The entry is removed once the delivery of the message is confirmed and its dispatch result
+	/// is passed to the `Config::OnMessageDeliveryResult` handler.
+	#[pallet::storage]
+	pub type OutboundMessageCallbacks<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, MessageKey, DeliveryCallbackId>;
 
-		// check event with assigned nonce
-		assert_eq!(
+	/// Inbound messages, whose dispatch has failed and that may be dispatched again.
+	#[pallet::storage]
+	pub type FailedMessages<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		LaneId,
+		Blake2_128Concat,
+		MessageNonce,
+		StoredFailedMessage<T, I>,
+	>;
+
+	/// Map of lane id => number of failed messages that are stored for this lane.
+	#[pallet::storage]
+	pub type FailedMessagesCount<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, LaneId, MessageNonce, ValueQuery>;
+
+	/// Inbound messages, that have been delivered, but whose dispatch has been deferred.
+	///
+	/// The size of the payload is bounded by the size of the delivery transaction.
+	#[pallet::storage]
+	#[pallet::unbounded]
+	pub type DeferredMessages<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		LaneId,
+		Blake2_128Concat,
+		MessageNonce,
+		DeferredMessage<T::InboundMessageFee, T::AccountId>,
+	>;
+
+	/// Map of lane id => nonces of delivered messages that are waiting for dispatch.
+	///
+	/// Messages are dispatched in the order they are stored here. So while the lane has deferred
+	/// messages, all newly delivered messages of this lane are deferred too.
+	#[pallet::storage]
+	pub type DeferredMessagesQueues<T: Config<I>, I: 'static = ()> = StorageMap<
+		_,
+		Blake2_128Concat,
+		LaneId,
+		BoundedVec<MessageNonce, T::MaxDeferredMessagesAtInboundLane>,
+		ValueQuery,
+	>;
+
+	/// Inbound lane that has been visited last by the `on_idle` deferred messages dispatch.
+	///
+	/// The next `on_idle` call starts dispatching messages of the lane that follows this one.
+	#[pallet::storage]
+	pub type DeferredMessagesDispatchCursor<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, LaneId>;
+
+	/// Map of lane id => outbound rate limit of the lane.
+	///
+	/// Traffic of lanes that are missing from this map is not limited.
+	#[pallet::storage]
+	#[pallet::getter(fn lane_rate_limit)]
+	pub type LaneRateLimits<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, LaneId, OutboundRateLimit<T::BlockNumber>>;
+
+	/// Map of lane id => outbound traffic of the lane within its current rate limit window.
+	#[pallet::storage]
+	pub type LaneRateLimitUsages<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, LaneId, OutboundRateLimitUsage<T::BlockNumber>, ValueQuery>;
+
+	/// Outbound rate limit of every signed message sender.
+	///
+	/// If it is `None`, traffic of senders is not limited.
+	#[pallet::storage]
+	#[pallet::getter(fn sender_rate_limit)]
+	pub type SenderRateLimit<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, OutboundRateLimit<T::BlockNumber>>;
+
+	/// Map of sender => outbound traffic of the sender within its current rate limit window.
+	#[pallet::storage]
+	pub type SenderRateLimitUsages<T: Config<I>, I: 'static = ()> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		OutboundRateLimitUsage<T::BlockNumber>,
+		ValueQuery,
+	>;
+
+	/// Sender that has been visited last by the `on_idle` pruning of expired sender rate limit
+	/// usages.
+	///
+	/// The next `on_idle` call starts pruning from the sender that follows this one.
+	#[pallet::storage]
+	pub type SenderRateLimitUsagesPruningCursor<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, T::AccountId>;
+
+	/// Double map of lane id => sender => () for senders that are allowed to send messages
+	/// over the lane.
+	///
+	/// The allowlist is only used if the runtime uses `Pallet::is_sender_allowed` to verify
+	/// outbound messages.
+	#[pallet::storage]
+	pub type AllowedSenders<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		LaneId,
+		Blake2_128Concat,
+		AllowedSender<T::AccountId>,
+		(),
+		OptionQuery,
+	>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config<I>, I: 'static = ()> {
+		/// Initial pallet operating mode.
+		pub operating_mode: MessagesOperatingMode,
+		/// Initial pallet owner.
+		pub owner: Option<T::AccountId>,
+		/// Lanes that are opened at genesis.
+		pub opened_lanes: Vec<(LaneId, LaneKind)>,
+		/// Dummy marker.
+		pub phantom: sp_std::marker::PhantomData<I>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config<I>, I: 'static> Default for GenesisConfig<T, I> {
+		fn default() -> Self {
+			Self {
+				operating_mode: Default::default(),
+				owner: Default::default(),
+				opened_lanes: Default::default(),
+				phantom: Default::default(),
+			}
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config<I>, I: 'static> GenesisBuild<T, I> for GenesisConfig<T, I> {
+		fn build(&self) {
+			PalletOperatingMode::<T, I>::put(self.operating_mode);
+			if let Some(ref owner) = self.owner {
+				PalletOwner::<T, I>::put(owner);
+			}
+			for (lane_id, kind) in &self.opened_lanes {
+				Pallet::<T, I>::do_open_lane(*lane_id, None, H256::zero(), *kind)
+					.expect("lanes opened at genesis must be unique");
+			}
+		}
+	}
+
+	impl<T: Config<I>, I: 'static> Pallet<T, I> {
+		/// Get stored data of the outbound message with given nonce.
+		pub fn outbound_message_data(
+			lane: LaneId,
+			nonce: MessageNonce,
+		) -> Option<MessageData<T::OutboundMessageFee>> {
+			OutboundMessages::<T, I>::get(MessageKey { lane_id: lane, nonce }).map(Into::into)
+		}
+
+		/// Get proof of inclusion of the outbound message into the commitment of current block.
+		///
+		/// Returns `None` if the message has not been accepted in the current block.
+		pub fn outbound_message_inclusion_proof(
+			lane: LaneId,
+			nonce: MessageNonce,
+		) -> Option<MessageInclusionProof<H256>> {
+			let leaf_index = OutboundMessagesCommitmentLeafIndices::<T, I>::get(MessageKey {
+				lane_id: lane,
+				nonce,
+			})?;
+			let leaves = OutboundMessagesCommitmentLeaves::<T, I>::get();
+			merkle_proof::<T::MessagesCommitmentHasher>(&leaves, leaf_index)
+		}
+
+		/// Get state of the outbound lane.
+		pub fn outbound_lane_data(lane: LaneId) -> OutboundLaneData {
+			OutboundLanes::<T, I>::get(lane)
+		}
+
+		/// Get state of the inbound lane.
+		pub fn inbound_lane_data(lane: LaneId) -> InboundLaneData<T::InboundRelayer> {
+			InboundLanes::<T, I>::get(lane).0
+		}
+
+		/// Get outbound traffic of the lane within its current rate limit window.
+		///
+		/// Returns `None` if traffic of the lane is not limited.
+		pub fn lane_rate_limit_usage(
+			lane_id: LaneId,
+		) -> Option<OutboundRateLimitUsage<T::BlockNumber>> {
+			let limit = LaneRateLimits::<T, I>::get(lane_id)?;
+			Some(
+				LaneRateLimitUsages::<T, I>::get(lane_id)
+					.at(&limit, frame_system::Pallet::<T>::block_number()),
+			)
+		}
+
+		/// Get outbound traffic of the sender within its current rate limit window.
+		///
+		/// Returns `None` if traffic of senders is not limited.
+		pub fn sender_rate_limit_usage(
+			sender: &T::AccountId,
+		) -> Option<OutboundRateLimitUsage<T::BlockNumber>> {
+			let limit = SenderRateLimit::<T, I>::get()?;
+			Some(
+				SenderRateLimitUsages::<T, I>::get(sender)
+					.at(&limit, frame_system::Pallet::<T>::block_number()),
+			)
+		}
+
+		/// Returns true if the message, sent by given origin, is allowed by the lane allowlist.
+		///
+		/// Root and signed origins are only allowed if they're explicitly added to the allowlist
+		/// of the lane. All other origins are never allowed.
+		pub fn is_sender_allowed(origin: &T::RuntimeOrigin, lane_id: &LaneId) -> bool {
+			let is_allowed = |sender: AllowedSender<T::AccountId>| {
+				AllowedSenders::<T, I>::contains_key(lane_id, sender)
+			};
+			match origin.clone().into() {
+				Ok(frame_system::RawOrigin::Root) => is_allowed(AllowedSender::Root),
+				Ok(frame_system::RawOrigin::Signed(account)) =>
+					is_allowed(AllowedSender::AnySigned)
+						|| is_allowed(AllowedSender::Signed(account)),
+				_ => false,
+			}
+		}
+
+		/// Get the inbound message, whose dispatch has failed.
+		pub fn failed_message(
+			lane: LaneId,
+			nonce: MessageNonce,
+		) -> Option<FailedMessage<T::InboundMessageFee, T::BlockNumber>> {
+			FailedMessages::<T, I>::get(lane, nonce).map(|message| message.0)
+		}
+
+		/// Register new lane.
+		///
+		/// State of the lane that has been used before the registration is kept, so this may
+		/// also be used to register lanes that are already in use, or to re-open closed lanes.
+		pub fn do_open_lane(
+			lane_id: LaneId,
+			owner: Option<T::AccountId>,
+			description_hash: H256,
+			kind: LaneKind,
+		) -> DispatchResult {
+			ensure!(
+				Lanes::<T, I>::get(lane_id)
+					.map_or(true, |metadata| metadata.state == LaneState::Closed),
+				Error::<T, I>::LaneAlreadyExists,
+			);
+
+			Lanes::<T, I>::insert(
+				lane_id,
+				LaneMetadata {
+					created_at: frame_system::Pallet::<T>::block_number(),
+					owner: owner.clone(),
+					description_hash,
+					kind,
+					state: LaneState::Opened,
+				},
+			);
+			log::info!(target: LOG_TARGET, "Opened {:?} lane {:?}.", kind, lane_id);
+			Self::deposit_event(Event::LaneOpened { lane_id, owner, kind });
+
+			Ok(())
+		}
+	}
+
+	#[cfg(any(feature = "try-runtime", test))]
+	impl<T: Config<I>, I: 'static> Pallet<T, I> {
+		/// Ensure that the state of all lanes is consistent.
+		///
+		/// If any of these checks fail, the lane is most likely stuck, because relayers are
+		/// unable to deliver messages or confirmations.
+		pub fn do_try_state() -> Result<(), &'static str> {
+			for (lane_id, lane_data) in OutboundLanes::<T, I>::iter() {
+				Self::do_try_state_for_outbound_lane(lane_id, lane_data)?;
+			}
+			for message_key in OutboundMessages::<T, I>::iter_keys() {
+				let lane_data = OutboundLanes::<T, I>::get(message_key.lane_id);
+				ensure!(
+					message_key.nonce >= lane_data.oldest_unpruned_nonce,
+					"Pruned outbound message is still stored"
+				);
+				ensure!(
+					message_key.nonce <= lane_data.latest_generated_nonce,
+					"Outbound message nonce is larger than the latest generated nonce"
+				);
+			}
+			let commitment_leaves = OutboundMessagesCommitmentLeaves::<T, I>::decode_len();
+			for leaf_index in OutboundMessagesCommitmentLeafIndices::<T, I>::iter_values() {
+				ensure!(
+					(leaf_index as usize) < commitment_leaves.unwrap_or(0),
+					"Index of the missing commitment leaf is stored"
+				);
+			}
+			for message_key in OutboundMessageSenders::<T, I>::iter_keys() {
+				ensure!(
+					OutboundMessages::<T, I>::contains_key(&message_key),
+					"Sender of the missing outbound message is stored"
+				);
+			}
+			for message_key in CancelledMessages::<T, I>::iter_keys() {
+				ensure!(
+					OutboundMessages::<T, I>::get(&message_key)
+						.map_or(false, |message_data| message_data.is_cancelled()),
+					"Refund of the missing or not cancelled outbound message is stored"
+				);
+				ensure!(
+					message_key.nonce
+						> OutboundLanes::<T, I>::get(message_key.lane_id).latest_received_nonce,
+					"Refund of the confirmed cancelled message is stored"
+				);
+			}
+
+			for lane_data in InboundLanes::<T, I>::iter_values() {
+				Self::do_try_state_for_inbound_lane(lane_data.0)?;
+			}
+			for (lane_id, dispatch_statuses) in InboundLanesDispatchStatuses::<T, I>::iter() {
+				let lane_data = InboundLanes::<T, I>::get(lane_id);
+				let nonces = dispatch_statuses.0.nonces();
+				ensure!(
+					nonces.map_or(false, |nonces| *nonces.start() > lane_data.last_confirmed_nonce
+						&& *nonces.end() <= lane_data.last_delivered_nonce()),
+					"Inbound lane has dispatch statuses of unknown or confirmed messages"
+				);
+			}
+			for (lane_id, delivered_ahead) in InboundLanesDeliveredAhead::<T, I>::iter() {
+				Self::do_try_state_for_messages_delivered_ahead(lane_id, delivered_ahead.0)?;
+			}
+			for (lane_id, nonce) in MessagesDeliveredAhead::<T, I>::iter_keys() {
+				let next_nonce = InboundLanes::<T, I>::get(lane_id).0.last_delivered_nonce() + 1;
+				let is_delivered_ahead = nonce
+					.checked_sub(next_nonce)
+					.and_then(|ahead_index| {
+						InboundLanesDeliveredAhead::<T, I>::get(lane_id)
+							.0
+							.get(ahead_index as usize)
+							.map(|bit| *bit)
+					})
+					.unwrap_or(false);
+				ensure!(is_delivered_ahead, "Unknown message is stored as delivered ahead");
+			}
+
+			let mut deferred_messages_count = 0;
+			for (lane_id, queue) in DeferredMessagesQueues::<T, I>::iter() {
+				ensure!(!queue.is_empty(), "Empty deferred messages queue is stored");
+				for nonce in queue {
+					ensure!(
+						DeferredMessages::<T, I>::contains_key(lane_id, nonce),
+						"Deferred message is missing"
+					);
+					deferred_messages_count += 1;
+				}
+			}
+			ensure!(
+				DeferredMessages::<T, I>::iter_keys().count() == deferred_messages_count,
+				"Deferred message is missing from the queue"
+			);
+
+			Ok(())
+		}
+
+		fn do_try_state_for_outbound_lane(
+			lane_id: LaneId,
+			lane_data: OutboundLaneData,
+		) -> Result<(), &'static str> {
+			ensure!(
+				lane_data.oldest_unpruned_nonce <= lane_data.latest_received_nonce + 1,
+				"Outbound lane has pruned messages that are not yet received"
+			);
+			ensure!(
+				lane_data.latest_received_nonce <= lane_data.latest_generated_nonce,
+				"Outbound lane has received messages that are not yet generated"
+			);
+			for nonce in lane_data.oldest_unpruned_nonce..=lane_data.latest_generated_nonce {
+				ensure!(
+					OutboundMessages::<T, I>::contains_key(MessageKey { lane_id, nonce }),
+					"Unpruned outbound message is missing"
+				);
+			}
+
+			Ok(())
+		}
+
+		fn do_try_state_for_inbound_lane(
+			lane_data: InboundLaneData<T::InboundRelayer>,
+		) -> Result<(), &'static str> {
+			if let Some(entry) = lane_data.relayers.front() {
+				ensure!(
+					entry.messages.begin <= lane_data.last_confirmed_nonce + 1,
+					"Inbound lane has a gap between confirmed and unrewarded messages"
+				);
+				ensure!(
+					entry.messages.end > lane_data.last_confirmed_nonce,
+					"Inbound lane has unrewarded relayer entry with confirmed messages only"
+				);
+			}
+
+			let mut expected_begin = None;
+			for entry in &lane_data.relayers {
+				ensure!(
+					entry.messages.begin <= entry.messages.end,
+					"Inbound lane has empty unrewarded relayer entry"
+				);
+				ensure!(
+					entry.messages.dispatch_results.len() as MessageNonce
+						== entry.messages.total_messages(),
+					"Inbound lane has unrewarded relayer entry with invalid dispatch results"
+				);
+				ensure!(
+					expected_begin.map(|begin| begin == entry.messages.begin).unwrap_or(true),
+					"Inbound lane has unrewarded relayer entries that are not consecutive"
+				);
+				expected_begin = Some(entry.messages.end + 1);
+			}
+
+			Ok(())
+		}
+
+		fn do_try_state_for_messages_delivered_ahead(
+			lane_id: LaneId,
+			delivered_ahead: DeliveredNoncesBitVec,
+		) -> Result<(), &'static str> {
+			let next_nonce = InboundLanes::<T, I>::get(lane_id).0.last_delivered_nonce() + 1;
+			for (ahead_index, bit) in delivered_ahead.iter().enumerate() {
+				ensure!(
+					!*bit
+						|| MessagesDeliveredAhead::<T, I>::contains_key(
+							lane_id,
+							next_nonce + ahead_index as MessageNonce,
+						),
+					"Message that has been delivered ahead is missing"
+				);
+			}
+
+			Ok(())
+		}
+	}
+}
+pub use pallet::*;
+
+/// AccountId of the shared relayer fund account.
+///
+/// This account is passed to `MessageDeliveryAndDispatchPayment` trait, and depending
+/// on the implementation it can be used to store relayers rewards.
+/// See [`InstantCurrencyPayments`] for a concrete implementation.
+pub fn relayer_fund_account_id<AccountId, AccountIdConverter: Convert<H256, AccountId>>(
+) -> AccountId {
+	let encoded_id = bp_runtime::derive_relayer_fund_account_id(bp_runtime::NO_INSTANCE_ID);
+	AccountIdConverter::convert(encoded_id)
+}
+
+impl<T, I>
+	bp_messages::source_chain::MessagesBridge<
+		T::RuntimeOrigin,
+		T::OutboundMessageFee,
+		T::OutboundPayload,
+	> for Pallet<T, I>
+where
+	T: Config<I>,
+	I: 'static,
+{
+	type Error = sp_runtime::DispatchErrorWithPostInfo<PostDispatchInfo>;
+
+	fn send_message(
+		sender: T::RuntimeOrigin,
+		lane: LaneId,
+		message: T::OutboundPayload,
+		delivery_and_dispatch_fee: T::OutboundMessageFee,
+	) -> Result<SendMessageArtifacts, Self::Error> {
+		crate::send_message::<T, I>(sender, lane, message, delivery_and_dispatch_fee, None)
+	}
+
+	fn send_message_with_expiry(
+		sender: T::RuntimeOrigin,
+		lane: LaneId,
+		message: T::OutboundPayload,
+		delivery_and_dispatch_fee: T::OutboundMessageFee,
+		expiry: MessageExpiry,
+	) -> Result<SendMessageArtifacts, Self::Error> {
+		crate::send_message::<T, I>(sender, lane, message, delivery_and_dispatch_fee, Some(expiry))
+	}
+
+	fn send_message_with_callback(
+		sender: T::RuntimeOrigin,
+		lane: LaneId,
+		message: T::OutboundPayload,
+		delivery_and_dispatch_fee: T::OutboundMessageFee,
+		callback_id: DeliveryCallbackId,
+	) -> Result<SendMessageArtifacts, Self::Error> {
+		ensure!(
+			T::OnMessageDeliveryResult::is_delivery_callback_supported(callback_id),
+			Error::<T, I>::UnknownDeliveryCallback
+		);
+
+		let mut artifacts =
+			crate::send_message::<T, I>(sender, lane, message, delivery_and_dispatch_fee, None)?;
+		OutboundMessageCallbacks::<T, I>::insert(
+			MessageKey { lane_id: lane, nonce: artifacts.nonce },
+			callback_id,
+		);
+		artifacts.weight = artifacts.weight.saturating_add(T::DbWeight::get().writes(1));
+
+		Ok(artifacts)
+	}
+}
+
+/// Runtime outbound lane storage.
+pub struct RuntimeOutboundLaneStorage<T, I = ()> {
+	lane_id: LaneId,
+	_phantom: PhantomData<(T, I)>,
+}
+impl<T: Config<I>, I: 'static> OutboundLaneStorage for RuntimeOutboundLaneStorage<T, I> {
+	type MessageFee = T::OutboundMessageFee;
+
+	fn id(&self) -> LaneId {
+		self.lane_id
+	}
+
+	fn data(&self) -> OutboundLaneData {
+		OutboundLanes::<T, I>::get(self.lane_id)
+	}
+
+	fn set_data(&mut self, data: OutboundLaneData) {
+		OutboundLanes::<T, I>::insert(self.lane_id, data)
+	}
+
+	#[cfg(test)]
+	fn message(&self, nonce: &MessageNonce) -> Option<MessageData<T::OutboundMessageFee>> {
+		OutboundMessages::<T, I>::get(MessageKey { lane_id: self.lane_id, nonce: *nonce })
+			.map(Into::into)
+	}
+
+	fn save_message(
+		&mut self,
+		nonce: MessageNonce,
+		mesage_data: OutboundMessageData<T::OutboundMessageFee>,
+	) {
+		OutboundMessages::<T, I>::insert(MessageKey { lane_id: self.lane_id, nonce }, mesage_data);
+	}
+
+	fn remove_message(&mut self, nonce: &MessageNonce) {
+		let message_key = MessageKey { lane_id: self.lane_id, nonce: *nonce };
+		OutboundMessages::<T, I>::remove(&message_key);
+		OutboundMessageSenders::<T, I>::remove(&message_key);
+		CancelledMessages::<T, I>::remove(&message_key);
+	}
+}
+
+/// Returns nonces of confirmed messages that have expiry and that have not been dispatched,
+/// because they have expired before they have been delivered.
+///
+/// Messages, whose dispatch has failed for any other reason, are not reported. So the bridged
+/// chain must report the `MessageDispatchStatus::Expired` status for expired messages.
+fn expired_messages<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+	confirmed_messages: &DeliveredMessages,
+) -> Vec<MessageNonce> {
+	(confirmed_messages.begin..=confirmed_messages.end)
+		.filter(|nonce| {
+			confirmed_messages.message_dispatch_status(*nonce)
+				== Some(MessageDispatchStatus::Expired)
+		})
+		.filter(|nonce| {
+			OutboundMessages::<T, I>::get(MessageKey { lane_id, nonce: *nonce })
+				.and_then(|message_data| message_data.expiry())
+				.is_some()
+		})
+		.collect()
+}
+
+/// Passes dispatch results of confirmed messages to their delivery callbacks.
+///
+/// Returns the weight that has been spent, including the weight of callbacks.
+fn route_delivery_results<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+	confirmed_messages: &DeliveredMessages,
+) -> Weight {
+	let db_weight = T::DbWeight::get();
+	let max_callback_weight = T::WeightInfo::single_message_callback_overhead(db_weight);
+	let mut spent_weight = Weight::zero();
+	for nonce in confirmed_messages.begin..=confirmed_messages.end {
+		spent_weight = spent_weight.saturating_add(db_weight.reads(1));
+		let callback_id =
+			match OutboundMessageCallbacks::<T, I>::take(MessageKey { lane_id, nonce }) {
+				Some(callback_id) => callback_id,
+				None => continue,
+			};
+
+		let callback_weight = T::OnMessageDeliveryResult::on_message_delivery_result(
+			callback_id,
+			&lane_id,
+			nonce,
+			confirmed_messages.message_dispatch_result(nonce),
+			confirmed_messages.message_dispatch_status(nonce),
+		);
+		if callback_weight.any_gt(max_callback_weight) {
+			debug_assert!(false, "T::OnMessageDeliveryResult callback consumed too much weight.");
+			log::error!(
+				target: LOG_TARGET,
+				"T::OnMessageDeliveryResult callback {} has spent more weight that it is allowed to: \
+				{} vs {}",
+				callback_id,
+				callback_weight,
+				max_callback_weight,
+			);
+		}
+		spent_weight = spent_weight
+			.saturating_add(db_weight.writes(1))
+			.saturating_add(callback_weight.min(max_callback_weight));
+	}
+	spent_weight
+}
+
+/// Refunds fees of confirmed messages that have been cancelled by their senders.
+///
+/// The original message may still be delivered after cancellation, so the fee is only refunded
+/// if the bridged chain reports the `MessageDispatchStatus::Cancelled` status for the message.
+/// The original message that has been deferred or whose dispatch has failed may still be
+/// dispatched later, so if the status is unknown or is any other status, the fee stays at the
+/// relayers fund.
+///
+/// Returns the weight that has been spent.
+fn refund_cancelled_messages<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+	confirmed_messages: &DeliveredMessages,
+) -> Weight {
+	let db_weight = T::DbWeight::get();
+	let mut spent_weight = Weight::zero();
+	for nonce in confirmed_messages.begin..=confirmed_messages.end {
+		spent_weight = spent_weight.saturating_add(db_weight.reads(1));
+		let (sender, fee) = match CancelledMessages::<T, I>::take(MessageKey { lane_id, nonce }) {
+			Some(cancelled_message) => cancelled_message,
+			None => continue,
+		};
+		spent_weight = spent_weight.saturating_add(db_weight.writes(1));
+
+		let dispatch_status = confirmed_messages.message_dispatch_status(nonce);
+		if dispatch_status != Some(MessageDispatchStatus::Cancelled) {
+			log::trace!(
+				target: LOG_TARGET,
+				"Original payload of the cancelled message {:?}/{} has been delivered: {:?}. \
+				Its fee is not refunded",
+				lane_id,
+				nonce,
+				dispatch_status,
+			);
+			continue;
+		}
+
+		spent_weight =
+			spent_weight.saturating_add(T::WeightInfo::pay_inbound_dispatch_fee_overhead());
+		match T::MessageDeliveryAndDispatchPayment::refund_fee(
+			lane_id,
+			nonce,
+			&sender,
+			&fee,
+			&relayer_fund_account_id::<T::AccountId, T::AccountIdConverter>(),
+		) {
+			Ok(()) => {
+				log::trace!(
+					target: LOG_TARGET,
+					"Refunded fee {:?} of the cancelled message {:?}/{}",
+					fee,
+					lane_id,
+					nonce,
+				);
+				Pallet::<T, I>::deposit_event(Event::CancelledMessageRefunded {
+					lane_id,
+					nonce,
+					fee,
+				});
+			},
+			Err(err) => log::error!(
+				target: LOG_TARGET,
+				"Failed to refund fee {:?} of the cancelled message {:?}/{}: {:?}",
+				fee,
+				lane_id,
+				nonce,
+				err,
+			),
+		}
+	}
+	spent_weight
+}
+
+/// Function that actually sends message.
+fn send_message<T: Config<I>, I: 'static>(
+	submitter: T::RuntimeOrigin,
+	lane_id: LaneId,
+	payload: T::OutboundPayload,
+	delivery_and_dispatch_fee: T::OutboundMessageFee,
+	expiry: Option<MessageExpiry>,
+) -> sp_std::result::Result<
+	SendMessageArtifacts,
+	sp_runtime::DispatchErrorWithPostInfo<PostDispatchInfo>,
+> {
+	ensure_normal_operating_mode::<T, I>()?;
+
+	// initially, actual (post-dispatch) weight is equal to pre-dispatch weight
+	let mut actual_weight = T::WeightInfo::send_message_weight(&payload, T::DbWeight::get());
+
+	let mut lane =
+		verify_outbound_message::<T, I>(&submitter, lane_id, &payload, &delivery_and_dispatch_fee)?;
+
+	// let's withdraw delivery and dispatch fee from submitter
+	T::MessageDeliveryAndDispatchPayment::pay_delivery_and_dispatch_fee(
+		&submitter,
+		&delivery_and_dispatch_fee,
+		&relayer_fund_account_id::<T::AccountId, T::AccountIdConverter>(),
+	)
+	.map_err(|err| {
+		log::trace!(
+			target: LOG_TARGET,
+			"Message to lane {:?} is rejected because submitter is unable to pay fee {:?}: {:?}",
+			lane_id,
+			delivery_and_dispatch_fee,
+			err,
+		);
+
+		Error::<T, I>::FailedToWithdrawMessageFee
+	})?;
+
+	let (nonce, unspent_callback_weight) = store_outbound_message::<T, I>(
+		&submitter,
+		lane_id,
+		&mut lane,
+		payload,
+		delivery_and_dispatch_fee,
+		expiry,
+	);
+	actual_weight = actual_weight.saturating_sub(unspent_callback_weight);
+
+	// message sender pays for pruning at most `MaxMessagesToPruneAtOnce` messages
+	// the cost of pruning every message is roughly single db write
+	// => lets refund sender if less than `MaxMessagesToPruneAtOnce` messages pruned
+	let max_messages_to_prune = T::MaxMessagesToPruneAtOnce::get();
+	let pruned_messages = lane.prune_messages(max_messages_to_prune);
+	if let Some(extra_messages) = max_messages_to_prune.checked_sub(pruned_messages) {
+		actual_weight = actual_weight.saturating_sub(T::DbWeight::get().writes(extra_messages));
+	}
+
+	Ok(SendMessageArtifacts { nonce, weight: actual_weight })
+}
+
+/// Function that actually sends batch of messages.
+///
+/// Fees of all messages are withdrawn from the submitter at once. Confirmed messages are not
+/// pruned here - it is left to the `on_idle` hook.
+///
+/// Returns actual weight of the call.
+fn send_messages<T: Config<I>, I: 'static>(
+	submitter: T::RuntimeOrigin,
+	messages: Vec<(LaneId, T::OutboundPayload, T::OutboundMessageFee)>,
+) -> sp_std::result::Result<Weight, sp_runtime::DispatchErrorWithPostInfo<PostDispatchInfo>> {
+	ensure_normal_operating_mode::<T, I>()?;
+	ensure!(!messages.is_empty(), Error::<T, I>::EmptyMessagesBatch);
+
+	// initially, actual (post-dispatch) weight is equal to pre-dispatch weight
+	let mut actual_weight = T::WeightInfo::send_messages_weight(
+		messages.iter().map(|(_, payload, _)| payload.size()),
+		T::DbWeight::get(),
+	);
+
+	// every message is stored right after it is verified, so that lane verifier sees all
+	// previous messages of the batch. If fee payment fails, the whole call is reverted
+	let mut total_fee = T::OutboundMessageFee::zero();
+	for (lane_id, payload, delivery_and_dispatch_fee) in messages {
+		let mut lane = verify_outbound_message::<T, I>(
+			&submitter,
+			lane_id,
+			&payload,
+			&delivery_and_dispatch_fee,
+		)?;
+		total_fee = total_fee.saturating_add(&delivery_and_dispatch_fee);
+
+		let (_, unspent_callback_weight) = store_outbound_message::<T, I>(
+			&submitter,
+			lane_id,
+			&mut lane,
+			payload,
+			delivery_and_dispatch_fee,
+			None,
+		);
+		actual_weight = actual_weight.saturating_sub(unspent_callback_weight);
+	}
+
+	// let's withdraw delivery and dispatch fee of all messages from submitter
+	T::MessageDeliveryAndDispatchPayment::pay_delivery_and_dispatch_fee(
+		&submitter,
+		&total_fee,
+		&relayer_fund_account_id::<T::AccountId, T::AccountIdConverter>(),
+	)
+	.map_err(|err| {
+		log::trace!(
+			target: LOG_TARGET,
+			"Messages batch is rejected because submitter is unable to pay fee {:?}: {:?}",
+			total_fee,
+			err,
+		);
+
+		Error::<T, I>::FailedToWithdrawMessageFee
+	})?;
+
+	Ok(actual_weight)
+}
+
+/// Ensure that the message may be sent over the lane.
+///
+/// Returns the outbound lane that the message may be stored to.
+fn verify_outbound_message<T: Config<I>, I: 'static>(
+	submitter: &T::RuntimeOrigin,
+	lane_id: LaneId,
+	payload: &T::OutboundPayload,
+	delivery_and_dispatch_fee: &T::OutboundMessageFee,
+) -> Result<OutboundLane<RuntimeOutboundLaneStorage<T, I>>, Error<T, I>> {
+	ensure!(
+		LaneOperatingModes::<T, I>::get(lane_id)
+			== MessagesOperatingMode::Basic(BasicOperatingMode::Normal),
+		Error::<T, I>::LaneNotOperatingNormally,
+	);
+	ensure!(
+		matches!(Lanes::<T, I>::get(lane_id), Some(LaneMetadata { state: LaneState::Opened, .. })),
+		Error::<T, I>::LaneNotOpened,
+	);
+
+	// the most lightweigh check is the message size check
+	ensure!(
+		payload.size() <= T::MaximalOutboundPayloadSize::get(),
+		Error::<T, I>::MessageIsTooLarge,
+	);
+	ensure!(
+		payload.using_encoded(|encoded_payload| encoded_payload != CANCELLED_MESSAGE_PAYLOAD),
+		Error::<T, I>::ReservedMessagePayload,
+	);
+
+	// neither the lane, nor the sender may exceed their outbound rate limits
+	verify_outbound_rate_limits::<T, I>(submitter, lane_id, payload.size())?;
+
+	// let's first check if message can be delivered to target chain
+	T::TargetHeaderChain::verify_message(payload).map_err(|err| {
+		log::trace!(
+			target: LOG_TARGET,
+			"Message to lane {:?} is rejected by target chain: {:?}",
+			lane_id,
+			err,
+		);
+
+		Error::<T, I>::MessageRejectedByChainVerifier(err)
+	})?;
+
+	// now let's enforce any additional lane rules
+	let lane = outbound_lane::<T, I>(lane_id);
+	T::LaneMessageVerifier::verify_message(
+		submitter,
+		delivery_and_dispatch_fee,
+		&lane_id,
+		&lane.data(),
+		payload,
+	)
+	.map_err(|err| {
+		log::trace!(
+			target: LOG_TARGET,
+			"Message to lane {:?} is rejected by lane verifier: {:?}",
+			lane_id,
+			err,
+		);
+
+		Error::<T, I>::MessageRejectedByLaneVerifier(err)
+	})?;
+
+	Ok(lane)
+}
+
+/// Ensure that neither the lane, nor the message sender have exceeded their outbound rate limits.
+fn verify_outbound_rate_limits<T: Config<I>, I: 'static>(
+	submitter: &T::RuntimeOrigin,
+	lane_id: LaneId,
+	message_size: u32,
+) -> Result<(), Error<T, I>> {
+	let now = frame_system::Pallet::<T>::block_number();
+	if let Some(limit) = LaneRateLimits::<T, I>::get(lane_id) {
+		let usage = LaneRateLimitUsages::<T, I>::get(lane_id).at(&limit, now);
+		if !usage.can_accept(&limit, message_size) {
+			log::trace!(
+				target: LOG_TARGET,
+				"Message to lane {:?} is rejected by lane rate limit {:?}: {:?}",
+				lane_id,
+				limit,
+				usage,
+			);
+
+			return Err(Error::<T, I>::MessageRejectedByRateLimiter(
+				VerificationError::LaneRateLimitExceeded,
+			));
+		}
+	}
+
+	if let (Some(limit), Ok(sender)) =
+		(SenderRateLimit::<T, I>::get(), frame_system::ensure_signed(submitter.clone()))
+	{
+		let usage = SenderRateLimitUsages::<T, I>::get(&sender).at(&limit, now);
+		if !usage.can_accept(&limit, message_size) {
+			log::trace!(
+				target: LOG_TARGET,
+				"Message of {:?} to lane {:?} is rejected by sender rate limit {:?}: {:?}",
+				sender,
+				lane_id,
+				limit,
+				usage,
+			);
+
+			return Err(Error::<T, I>::MessageRejectedByRateLimiter(
+				VerificationError::SenderRateLimitExceeded,
+			));
+		}
+	}
+
+	Ok(())
+}
+
+/// Account the accepted message in the outbound traffic of the lane and of the sender.
+fn note_outbound_traffic<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+	sender: Option<&T::AccountId>,
+	message_size: u32,
+) {
+	let now = frame_system::Pallet::<T>::block_number();
+	if let Some(limit) = LaneRateLimits::<T, I>::get(lane_id) {
+		LaneRateLimitUsages::<T, I>::mutate(lane_id, |usage| {
+			*usage = usage.at(&limit, now);
+			usage.accept(message_size);
+		});
+	}
+	if let (Some(limit), Some(sender)) = (SenderRateLimit::<T, I>::get(), sender) {
+		SenderRateLimitUsages::<T, I>::mutate(sender, |usage| {
+			*usage = usage.at(&limit, now);
+			usage.accept(message_size);
+		});
+	}
+}
+
+/// Save verified message in the outbound storage, notify `OnMessageAccepted` handler and emit
+/// event.
+///
+/// Returns nonce of the message and weight that has been reserved for the handler, but not spent.
+fn store_outbound_message<T: Config<I>, I: 'static>(
+	submitter: &T::RuntimeOrigin,
+	lane_id: LaneId,
+	lane: &mut OutboundLane<RuntimeOutboundLaneStorage<T, I>>,
+	payload: T::OutboundPayload,
+	delivery_and_dispatch_fee: T::OutboundMessageFee,
+	expiry: Option<MessageExpiry>,
+) -> (MessageNonce, Weight) {
+	let message_size = payload.size();
+	let encoded_payload = payload.encode();
+	let encoded_payload_len = encoded_payload.len();
+	let payload_hash = T::MessagesCommitmentHasher::hash(&encoded_payload);
+	let nonce = lane.send_message(OutboundMessageData {
+		data: MessageData { payload: encoded_payload, fee: delivery_and_dispatch_fee },
+		expiry,
+	});
+	// the message is committed to at the end of the block
+	let leaf_index = OutboundMessagesCommitmentLeaves::<T, I>::decode_len().unwrap_or(0);
+	OutboundMessagesCommitmentLeaves::<T, I>::append(
+		MessageLeaf { lane_id, nonce, payload_hash }.hash::<T::MessagesCommitmentHasher>(),
+	);
+	OutboundMessagesCommitmentLeafIndices::<T, I>::insert(
+		MessageKey { lane_id, nonce },
+		leaf_index as u32,
+	);
+	// the sender is remembered, so that it may cancel the message if it is never delivered
+	let sender = frame_system::ensure_signed(submitter.clone()).ok();
+	if let Some(ref sender) = sender {
+		OutboundMessageSenders::<T, I>::insert(
+			MessageKey { lane_id, nonce },
+			(sender.clone(), frame_system::Pallet::<T>::block_number(), delivery_and_dispatch_fee),
+		);
+	}
+	note_outbound_traffic::<T, I>(lane_id, sender.as_ref(), message_size);
+	// Guaranteed to be called outside only when the message is accepted.
+	// We assume that the maximum weight call back used is `single_message_callback_overhead`, so do
+	// not perform complex db operation in callback. If you want to, put these magic logic in
+	// outside pallet and control the weight there.
+	let single_message_callback_overhead =
+		T::WeightInfo::single_message_callback_overhead(T::DbWeight::get());
+	let actual_callback_weight = T::OnMessageAccepted::on_messages_accepted(&lane_id, &nonce);
+	let unspent_callback_weight =
+		match single_message_callback_overhead.checked_sub(&actual_callback_weight) {
+			Some(difference) if difference.is_zero() => Weight::zero(),
+			Some(difference) => {
+				log::trace!(
+					target: LOG_TARGET,
+					"T::OnMessageAccepted callback has spent less weight than expected. Refunding: \
+					{} - {} = {}",
+					single_message_callback_overhead,
+					actual_callback_weight,
+					difference,
+				);
+				difference
+			},
+			None => {
+				debug_assert!(false, "T::OnMessageAccepted callback consumed too much weight.");
+				log::error!(
+					target: LOG_TARGET,
+					"T::OnMessageAccepted callback has spent more weight that it is allowed to: \
+					{} vs {}",
+					single_message_callback_overhead,
+					actual_callback_weight,
+				);
+				Weight::zero()
+			},
+		};
+
+	log::trace!(
+		target: LOG_TARGET,
+		"Accepted message {} to lane {:?}. Message size: {:?}",
+		nonce,
+		lane_id,
+		encoded_payload_len,
+	);
+
+	if T::DepositLegacyEvents::get() {
+		Pallet::<T, I>::deposit_event(Event::MessageAccepted { lane_id, nonce });
+	}
+	Pallet::<T, I>::deposit_event(Event::MessageAcceptedV2 {
+		lane_id,
+		nonce,
+		payload_hash,
+		encoded_size: encoded_payload_len as u32,
+		fee: delivery_and_dispatch_fee,
+		sender,
+	});
+
+	(nonce, unspent_callback_weight)
+}
+
+/// Prune confirmed messages of outbound lanes, spending at most `max_weight`.
+///
+/// Lanes are visited in round-robin order, starting from the lane that follows the
+/// `OutboundLanesPruningCursor`. At most `MaxMessagesToPruneAtOnce` messages of every lane are
+/// pruned during single visit, so that the lane with a long queue can't starve other lanes.
+///
+/// Returns weight that has been spent.
+fn prune_outbound_messages<T: Config<I>, I: 'static>(max_weight: Weight) -> Weight {
+	let db_weight = T::DbWeight::get();
+	let lane_overhead = T::WeightInfo::prune_outbound_lane_overhead(db_weight);
+	let message_overhead = T::WeightInfo::prune_outbound_message_overhead(db_weight);
+
+	// we need to read the cursor and probably update it, so let's not start if we can't prune
+	// at least single message
+	let mut weight_used = T::WeightInfo::prune_outbound_messages_overhead(db_weight);
+	if !max_weight
+		.all_gte(weight_used.saturating_add(lane_overhead).saturating_add(message_overhead))
+	{
+		return Weight::zero();
+	}
+
+	let cursor = OutboundLanesPruningCursor::<T, I>::get();
+	let lanes_after_cursor = match cursor {
+		Some(cursor) =>
+			OutboundLanes::<T, I>::iter_from(OutboundLanes::<T, I>::hashed_key_for(cursor)),
+		None => OutboundLanes::<T, I>::iter(),
+	};
+
+	let mut first_visited_lane = None;
+	let mut last_visited_lane = cursor;
+	for (lane_id, lane_data) in lanes_after_cursor.chain(OutboundLanes::<T, I>::iter()) {
+		// every lane is visited at most once
+		if first_visited_lane == Some(lane_id) {
+			break;
+		}
+		first_visited_lane.get_or_insert(lane_id);
+
+		let new_weight_used = weight_used.saturating_add(lane_overhead);
+		if !max_weight.all_gte(new_weight_used) {
+			break;
+		}
+		weight_used = new_weight_used;
+
+		let max_messages_to_prune = lane_data
+			.latest_received_nonce
+			.saturating_add(1)
+			.saturating_sub(lane_data.oldest_unpruned_nonce)
+			.min(T::MaxMessagesToPruneAtOnce::get());
+		let mut messages_to_prune = 0;
+		while messages_to_prune < max_messages_to_prune {
+			let new_weight_used = weight_used.saturating_add(message_overhead);
+			if !max_weight.all_gte(new_weight_used) {
+				break;
+			}
+			weight_used = new_weight_used;
+			messages_to_prune += 1;
+		}
+
+		if messages_to_prune != 0 {
+			let pruned_messages = outbound_lane::<T, I>(lane_id).prune_messages(messages_to_prune);
+			log::trace!(
+				target: LOG_TARGET,
+				"Pruned {} messages of outbound lane {:?}",
+				pruned_messages,
+				lane_id,
+			);
+		}
+
+		// if we have not pruned everything we wanted, the lane is visited again at next call
+		if messages_to_prune != max_messages_to_prune {
+			break;
+		}
+		last_visited_lane = Some(lane_id);
+	}
+
+	if last_visited_lane != cursor {
+		OutboundLanesPruningCursor::<T, I>::set(last_visited_lane);
+	}
+
+	weight_used
+}
+
+/// Prune expired sender rate limit usages, spending at most `max_weight`.
+///
+/// Senders are visited in the storage order, starting from the sender that follows the
+/// `SenderRateLimitUsagesPruningCursor`. Once the last sender is visited, the next call starts
+/// from the first sender again. If traffic of senders is no longer limited, all usages are pruned.
+///
+/// Returns weight that has been spent.
+fn prune_sender_rate_limit_usages<T: Config<I>, I: 'static>(max_weight: Weight) -> Weight {
+	let db_weight = T::DbWeight::get();
+	let usage_overhead = T::WeightInfo::prune_sender_rate_limit_usage_overhead(db_weight);
+
+	// we need to read the limit and the cursor, so let's not start if we can't visit at least
+	// single usage
+	let mut weight_used = T::WeightInfo::prune_sender_rate_limit_usages_overhead(db_weight);
+	if !max_weight.all_gte(weight_used.saturating_add(usage_overhead)) {
+		return Weight::zero();
+	}
+
+	let limit = SenderRateLimit::<T, I>::get();
+	let now = frame_system::Pallet::<T>::block_number();
+	let cursor = SenderRateLimitUsagesPruningCursor::<T, I>::get();
+	let mut usages = match cursor {
+		Some(ref cursor) => SenderRateLimitUsages::<T, I>::iter_from(
+			SenderRateLimitUsages::<T, I>::hashed_key_for(cursor),
+		),
+		None => SenderRateLimitUsages::<T, I>::iter(),
+	};
+
+	let mut last_visited_sender = cursor.clone();
+	let mut pruned_usages = 0;
+	loop {
+		let new_weight_used = weight_used.saturating_add(usage_overhead);
+		if !max_weight.all_gte(new_weight_used) {
+			break;
+		}
+
+		// removing the visited key doesn't affect the iterator, because it starts from the
+		// previous key on every `next` call
+		let (sender, usage) = match usages.next() {
+			Some(entry) => entry,
+			None => {
+				// start from the first sender at next call
+				last_visited_sender = None;
+				break;
+			},
+		};
+		weight_used = new_weight_used;
+
+		let is_expired = limit.as_ref().map(|limit| usage.is_expired(limit, now)).unwrap_or(true);
+		if is_expired {
+			SenderRateLimitUsages::<T, I>::remove(&sender);
+			pruned_usages += 1;
+		}
+		last_visited_sender = Some(sender);
+	}
+
+	if pruned_usages != 0 {
+		log::trace!(target: LOG_TARGET, "Pruned {} expired sender rate limit usages", pruned_usages);
+	}
+	if last_visited_sender != cursor {
+		SenderRateLimitUsagesPruningCursor::<T, I>::set(last_visited_sender);
+	}
+
+	weight_used
+}
+
+/// Dispatch inbound messages, whose dispatch has been deferred, spending at most `max_weight`.
+///
+/// Messages of every lane are dispatched in the order they have been delivered. Lanes are
+/// visited in round-robin order, starting from the lane that follows the
+/// `DeferredMessagesDispatchCursor`. Once the next message of the lane doesn't fit into the
+/// remaining weight, we move to the next lane, so the lane with a heavy message can't starve
+/// other lanes. The message that never fits may be dispatched with the
+/// `dispatch_deferred_message` call.
+///
+/// Returns weight that has been spent.
+fn dispatch_deferred_messages<T: Config<I>, I: 'static>(max_weight: Weight) -> Weight {
+	if <Pallet<T, I> as OwnedBridgeModule<T>>::is_halted() {
+		return Weight::zero();
+	}
+
+	let db_weight = T::DbWeight::get();
+	let lane_overhead = T::WeightInfo::dispatch_deferred_messages_lane_overhead(db_weight);
+	let message_overhead = T::WeightInfo::dispatch_deferred_message_overhead(db_weight);
+	let max_block_weight = T::BlockWeights::get().max_block;
+
+	// we need to read the cursor and probably update it, so let's not start if we can't visit
+	// at least single lane
+	let mut weight_used = T::WeightInfo::dispatch_deferred_messages_overhead(db_weight);
+	if !max_weight.all_gte(weight_used.saturating_add(lane_overhead)) {
+		return Weight::zero();
+	}
+
+	let cursor = DeferredMessagesDispatchCursor::<T, I>::get();
+	let queues_after_cursor = match cursor {
+		Some(cursor) => DeferredMessagesQueues::<T, I>::iter_from(
+			DeferredMessagesQueues::<T, I>::hashed_key_for(cursor),
+		),
+		None => DeferredMessagesQueues::<T, I>::iter(),
+	};
+
+	let mut first_visited_lane = None;
+	let mut last_visited_lane = cursor;
+	for (lane_id, mut queue) in queues_after_cursor.chain(DeferredMessagesQueues::<T, I>::iter()) {
+		// every lane is visited at most once
+		if first_visited_lane == Some(lane_id) {
+			break;
+		}
+		first_visited_lane.get_or_insert(lane_id);
+
+		let new_weight_used = weight_used.saturating_add(lane_overhead);
+		if !max_weight.all_gte(new_weight_used) {
+			break;
+		}
+		weight_used = new_weight_used;
+		last_visited_lane = Some(lane_id);
+
+		if is_lane_halted::<T, I>(lane_id) {
+			continue;
+		}
+
+		let mut dispatched_messages = 0;
+		for nonce in queue.iter().copied() {
+			let deferred_message = match DeferredMessages::<T, I>::get(lane_id, nonce) {
+				Some(deferred_message) => deferred_message,
+				None => {
+					log::error!(
+						target: LOG_TARGET,
+						"Deferred message {:?}/{} is missing from the storage",
+						lane_id,
+						nonce,
+					);
+					dispatched_messages += 1;
+					continue;
+				},
+			};
+			let mut message = deferred_dispatch_message::<T, I>(lane_id, nonce, &deferred_message);
+			let message_dispatch_weight = T::MessageDispatch::dispatch_weight(&mut message);
+			let required_weight = message_overhead.saturating_add(message_dispatch_weight);
+			let new_weight_used = weight_used.saturating_add(required_weight);
+			if !max_weight.all_gte(new_weight_used) {
+				if !max_block_weight.all_gte(required_weight.saturating_add(lane_overhead)) {
+					log::trace!(
+						target: LOG_TARGET,
+						"Deferred message {:?}/{} never fits into the block. It needs to be \
+						dispatched using the `dispatch_deferred_message` call",
+						lane_id,
+						nonce,
+					);
+				}
+				break;
+			}
+			weight_used = new_weight_used;
+
+			let relayer = deferred_message.relayer.clone();
+			let unspent_weight = dispatch_deferred_message::<T, I>(
+				&relayer,
+				deferred_message,
+				message,
+				message_dispatch_weight,
+			);
+			weight_used = weight_used.saturating_sub(unspent_weight);
+			dispatched_messages += 1;
+		}
+
+		if dispatched_messages != 0 {
+			queue.drain(..dispatched_messages);
+			update_deferred_messages_queue::<T, I>(lane_id, queue);
+		}
+	}
+
+	if last_visited_lane != cursor {
+		DeferredMessagesDispatchCursor::<T, I>::set(last_visited_lane);
+	}
+
+	weight_used
+}
+
+/// Build the dispatch message from the deferred message.
+fn deferred_dispatch_message<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+	nonce: MessageNonce,
+	deferred_message: &DeferredMessage<T::InboundMessageFee, T::AccountId>,
+) -> DispatchMessage<T::InboundPayload, T::InboundMessageFee> {
+	DispatchMessage {
+		key: MessageKey { lane_id, nonce },
+		data: DispatchMessageData {
+			payload: match deferred_message.payload {
+				Some(ref payload) => T::InboundPayload::decode(&mut &payload[..]),
+				None => Err(codec::Error::from("Failed to decode message payload")),
+			},
+			fee: deferred_message.fee.clone(),
+			is_cancelled: false,
+			expiry: None,
+		},
+	}
+}
+
+/// Dispatch the deferred message on behalf of the `relayer` and remove it from the storage.
+///
+/// The message has passed pre-dispatch validation when it has been delivered, so now its
+/// failure is treated as the failed dispatch. The actual dispatch result replaces the `Deferred`
+/// status at the inbound lane, unless the message delivery is already confirmed. The caller is
+/// responsible for removing the message from the queue.
+///
+/// Returns unspent dispatch weight.
+fn dispatch_deferred_message<T: Config<I>, I: 'static>(
+	relayer: &T::AccountId,
+	deferred_message: DeferredMessage<T::InboundMessageFee, T::AccountId>,
+	message: DispatchMessage<T::InboundPayload, T::InboundMessageFee>,
+	message_dispatch_weight: Weight,
+) -> Weight {
+	let MessageKey { lane_id, nonce } = message.key;
+	let (dispatch_result, dispatch_status, unspent_weight) =
+		match T::MessageDispatch::pre_dispatch(relayer, &message) {
+			Ok(()) => {
+				let dispatch_result = T::MessageDispatch::dispatch(relayer, message);
+				(
+					dispatch_result.dispatch_result,
+					dispatch_result.dispatch_status,
+					dispatch_result.unspent_weight,
+				)
+			},
+			Err(err) => {
+				log::trace!(
+					target: LOG_TARGET,
+					"Deferred message {:?}/{} has been rejected by pre-dispatch: {}",
+					lane_id,
+					nonce,
+					err,
+				);
+				(false, Some(MessageDispatchStatus::Rejected), message_dispatch_weight)
+			},
+		};
+	if !dispatch_result {
+		if let Some(payload) = deferred_message.payload {
+			save_failed_message::<T, I>(
+				lane_id,
+				nonce,
+				FailedMessage {
+					payload,
+					fee: deferred_message.fee,
+					failed_at: frame_system::Pallet::<T>::block_number(),
+				},
+			);
+		}
+	}
+
+	DeferredMessages::<T, I>::remove(lane_id, nonce);
+	inbound_lane::<T, I>(lane_id).note_deferred_message_dispatched(
+		nonce,
+		dispatch_result,
+		dispatch_status,
+	);
+	log::trace!(
+		target: LOG_TARGET,
+		"Dispatched deferred message {:?}/{}: {}",
+		lane_id,
+		nonce,
+		dispatch_result,
+	);
+	Pallet::<T, I>::deposit_event(Event::DeferredMessageDispatched {
+		lane_id,
+		nonce,
+		dispatch_result,
+	});
+
+	unspent_weight.min(message_dispatch_weight)
+}
+
+/// Store the deferred messages queue of the lane, removing it (and probably closing the lane)
+/// once it is empty.
+fn update_deferred_messages_queue<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+	queue: BoundedVec<MessageNonce, T::MaxDeferredMessagesAtInboundLane>,
+) {
+	if queue.is_empty() {
+		DeferredMessagesQueues::<T, I>::remove(lane_id);
+		close_lane_if_drained::<T, I>(lane_id);
+	} else {
+		DeferredMessagesQueues::<T, I>::insert(lane_id, queue);
+	}
+}
+
+/// Ensure that the pallet is in normal operational mode.
+fn ensure_normal_operating_mode<T: Config<I>, I: 'static>() -> Result<(), Error<T, I>> {
+	if PalletOperatingMode::<T, I>::get()
+		== MessagesOperatingMode::Basic(BasicOperatingMode::Normal)
+	{
+		return Ok(());
+	}
+
+	Err(Error::<T, I>::NotOperatingNormally)
+}
+
+/// Marks the closing lane as closed if all its messages are delivered and confirmed.
+///
+/// Confirmed messages that are not yet pruned are pruned here (at most
+/// `MaxMessagesToPruneAtOnce` at once). Inbound and outbound lane data is kept, so that
+/// already delivered messages can't be replayed. Returns true if the lane has been closed.
+fn close_lane_if_drained<T: Config<I>, I: 'static>(lane_id: LaneId) -> bool {
+	let mut metadata = match Lanes::<T, I>::get(lane_id) {
+		Some(metadata) if metadata.state == LaneState::Closing => metadata,
+		_ => return false,
+	};
+
+	let mut lane = outbound_lane::<T, I>(lane_id);
+	let outbound_lane_data = lane.data();
+	if outbound_lane_data.latest_received_nonce < outbound_lane_data.latest_generated_nonce
+		|| !InboundLanes::<T, I>::get(lane_id).0.relayers.is_empty()
+		|| InboundLanesDeliveredAhead::<T, I>::contains_key(lane_id)
+		|| DeferredMessagesQueues::<T, I>::contains_key(lane_id)
+	{
+		return false;
+	}
+
+	lane.prune_messages(T::MaxMessagesToPruneAtOnce::get());
+	if lane.data().oldest_unpruned_nonce <= outbound_lane_data.latest_received_nonce {
+		return false;
+	}
+
+	metadata.state = LaneState::Closed;
+	Lanes::<T, I>::insert(lane_id, metadata);
+	LaneOperatingModes::<T, I>::remove(lane_id);
+	log::info!(target: LOG_TARGET, "Closed lane {:?}.", lane_id);
+	Pallet::<T, I>::deposit_event(Event::LaneClosed { lane_id });
+
+	true
+}
+
+/// Saves the inbound message, whose dispatch has failed, so that it may be retried later.
+///
+/// Returns false if the message has not been saved, either because its payload is too large,
+/// or because there are already too many failed messages at the lane.
+fn save_failed_message<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+	nonce: MessageNonce,
+	message: FailedMessage<T::InboundMessageFee, T::BlockNumber>,
+) -> bool {
+	if message.payload.len() > T::MaxFailedMessagePayloadSize::get() as usize {
+		log::trace!(
+			target: LOG_TARGET,
+			"Not keeping failed message {:?}/{}: payload is too large",
+			lane_id,
+			nonce,
+		);
+		return false;
+	}
+
+	let failed_messages_count = FailedMessagesCount::<T, I>::get(lane_id);
+	if failed_messages_count >= T::MaxFailedMessagesAtInboundLane::get() {
+		log::trace!(
+			target: LOG_TARGET,
+			"Not keeping failed message {:?}/{}: too many failed messages at the lane",
+			lane_id,
+			nonce,
+		);
+		return false;
+	}
+
+	FailedMessages::<T, I>::insert(lane_id, nonce, StoredFailedMessage::<T, I>::from(message));
+	FailedMessagesCount::<T, I>::insert(lane_id, failed_messages_count + 1);
+	Pallet::<T, I>::deposit_event(Event::MessageDispatchFailed { lane_id, nonce });
+
+	true
+}
+
+/// Removes the failed inbound message from the storage.
+fn remove_failed_message<T: Config<I>, I: 'static>(lane_id: LaneId, nonce: MessageNonce) {
+	FailedMessages::<T, I>::remove(lane_id, nonce);
+	FailedMessagesCount::<T, I>::mutate_exists(lane_id, |count| {
+		*count = count.map(|count| count.saturating_sub(1)).filter(|count| *count != 0);
+	});
+}
+
+/// Returns true if the failed inbound message may no longer be dispatched.
+fn is_failed_message_expired<T: Config<I>, I: 'static>(
+	message: &FailedMessage<T::InboundMessageFee, T::BlockNumber>,
+) -> bool {
+	frame_system::Pallet::<T>::block_number()
+		> message.failed_at.saturating_add(T::FailedMessageLifetime::get())
+}
+
+/// Returns true if the lane is halted.
+fn is_lane_halted<T: Config<I>, I: 'static>(lane_id: LaneId) -> bool {
+	LaneOperatingModes::<T, I>::get(lane_id)
+		== MessagesOperatingMode::Basic(BasicOperatingMode::Halted)
+}
+
+/// Creates new inbound lane object, backed by runtime storage.
+fn inbound_lane<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+) -> InboundLane<RuntimeInboundLaneStorage<T, I>> {
+	InboundLane::new(inbound_lane_storage::<T, I>(lane_id))
+}
+
+/// Creates new runtime inbound lane storage.
+fn inbound_lane_storage<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+) -> RuntimeInboundLaneStorage<T, I> {
+	RuntimeInboundLaneStorage {
+		lane_id,
+		kind: Lanes::<T, I>::get(lane_id).map(|metadata| metadata.kind).unwrap_or_default(),
+		cached_data: RefCell::new(None),
+		cached_dispatch_statuses: RefCell::new(None),
+		_phantom: Default::default(),
+	}
+}
+
+/// Creates new outbound lane object, backed by runtime storage.
+pub fn outbound_lane<T: Config<I>, I: 'static>(
+	lane_id: LaneId,
+) -> OutboundLane<RuntimeOutboundLaneStorage<T, I>> {
+	OutboundLane::new(RuntimeOutboundLaneStorage { lane_id, _phantom: Default::default() })
+}
+
+/// Runtime inbound lane storage.
+struct RuntimeInboundLaneStorage<T: Config<I>, I: 'static = ()> {
+	lane_id: LaneId,
+	kind: LaneKind,
+	cached_data: RefCell<Option<InboundLaneData<T::InboundRelayer>>>,
+	cached_dispatch_statuses: RefCell<Option<InboundLaneDispatchStatuses>>,
+	_phantom: PhantomData<I>,
+}
+impl<T: Config<I>, I: 'static> InboundLaneStorage for RuntimeInboundLaneStorage<T, I> {
+	type MessageFee = T::InboundMessageFee;
+	type Relayer = T::InboundRelayer;
+
+	fn id(&self) -> LaneId {
+		self.lane_id
+	}
+
+	fn max_unrewarded_relayer_entries(&self) -> MessageNonce {
+		T::MaxUnrewardedRelayerEntriesAtInboundLane::get()
+	}
+
+	fn max_unconfirmed_messages(&self) -> MessageNonce {
+		T::MaxUnconfirmedMessagesAtInboundLane::get()
+	}
+
+	fn data(&self) -> InboundLaneData<T::InboundRelayer> {
+		match self.cached_data.clone().into_inner() {
+			Some(data) => data,
+			None => {
+				let data: InboundLaneData<T::InboundRelayer> =
+					InboundLanes::<T, I>::get(self.lane_id).into();
+				*self.cached_data.try_borrow_mut().expect(
+					"we're in the single-threaded environment;\
+						we have no recursive borrows; qed",
+				) = Some(data.clone());
+				data
+			},
+		}
+	}
+
+	fn set_data(&mut self, data: InboundLaneData<T::InboundRelayer>) {
+		*self.cached_data.try_borrow_mut().expect(
+			"we're in the single-threaded environment;\
+				we have no recursive borrows; qed",
+		) = Some(data.clone());
+		InboundLanes::<T, I>::insert(self.lane_id, StoredInboundLaneData::<T, I>(data))
+	}
+
+	fn dispatch_statuses(&self) -> InboundLaneDispatchStatuses {
+		match self.cached_dispatch_statuses.clone().into_inner() {
+			Some(dispatch_statuses) => dispatch_statuses,
+			None => {
+				let dispatch_statuses = InboundLanesDispatchStatuses::<T, I>::get(self.lane_id)
+					.map(|dispatch_statuses| dispatch_statuses.0)
+					.unwrap_or_default();
+				*self.cached_dispatch_statuses.try_borrow_mut().expect(
+					"we're in the single-threaded environment;\
+						we have no recursive borrows; qed",
+				) = Some(dispatch_statuses.clone());
+				dispatch_statuses
+			},
+		}
+	}
+
+	fn set_dispatch_statuses(&mut self, dispatch_statuses: InboundLaneDispatchStatuses) {
+		*self.cached_dispatch_statuses.try_borrow_mut().expect(
+			"we're in the single-threaded environment;\
+				we have no recursive borrows; qed",
+		) = Some(dispatch_statuses.clone());
+		if dispatch_statuses.is_empty() {
+			InboundLanesDispatchStatuses::<T, I>::remove(self.lane_id);
+		} else {
+			InboundLanesDispatchStatuses::<T, I>::insert(
+				self.lane_id,
+				StoredInboundLaneDispatchStatuses::<T, I>::from(dispatch_statuses),
+			);
+		}
+	}
+
+	fn kind(&self) -> LaneKind {
+		self.kind
+	}
+
+	fn delivered_ahead(&self) -> DeliveredNoncesBitVec {
+		match self.kind {
+			LaneKind::Ordered => DeliveredNoncesBitVec::new(),
+			LaneKind::Unordered => InboundLanesDeliveredAhead::<T, I>::get(self.lane_id).0,
+		}
+	}
+
+	fn set_delivered_ahead(&mut self, delivered_ahead: DeliveredNoncesBitVec) {
+		if delivered_ahead.any() {
+			InboundLanesDeliveredAhead::<T, I>::insert(
+				self.lane_id,
+				StoredDeliveredNonces::<T, I>::from(delivered_ahead),
+			);
+		} else {
+			InboundLanesDeliveredAhead::<T, I>::remove(self.lane_id);
+		}
+	}
+
+	fn message_delivered_ahead(
+		&self,
+		nonce: MessageNonce,
+	) -> Option<(T::InboundRelayer, bool, Option<MessageDispatchStatus>)> {
+		MessagesDeliveredAhead::<T, I>::get(self.lane_id, nonce)
+	}
+
+	fn save_message_delivered_ahead(
+		&mut self,
+		nonce: MessageNonce,
+		relayer: T::InboundRelayer,
+		dispatch_result: bool,
+		dispatch_status: Option<MessageDispatchStatus>,
+	) {
+		MessagesDeliveredAhead::<T, I>::insert(
+			self.lane_id,
+			nonce,
+			(relayer, dispatch_result, dispatch_status),
+		);
+	}
+
+	fn remove_message_delivered_ahead(&mut self, nonce: MessageNonce) {
+		MessagesDeliveredAhead::<T, I>::remove(self.lane_id, nonce);
+	}
+
+	fn is_message_expired(&self, expiry: &MessageExpiry) -> bool {
+		match *expiry {
+			MessageExpiry::BlockNumber(block_number) =>
+				frame_system::Pallet::<T>::block_number().unique_saturated_into() > block_number,
+			MessageExpiry::Timestamp(timestamp) =>
+				T::UnixTime::now().as_millis().unique_saturated_into() > timestamp,
+		}
+	}
+}
+
+/// Verify messages proof and return proved messages with decoded payload.
+///
+/// Every message is returned along with the hash of its encoded payload and the encoded payload
+/// itself, so that the payload may be stored without re-encoding.
+fn verify_and_decode_messages_proof<
+	Chain: SourceHeaderChain<Fee>,
+	Fee,
+	DispatchPayload: Decode,
+	PayloadHasher: Hasher<Out = H256>,
+>(
+	proof: Chain::MessagesProof,
+	messages_count: u32,
+) -> Result<
+	ProvedMessages<(H256, MessagePayload, DispatchMessage<DispatchPayload, Fee>)>,
+	VerificationError,
+> {
+	// `receive_messages_proof` weight formula and `MaxUnconfirmedMessagesAtInboundLane` check
+	// guarantees that the `message_count` is sane and Vec<Message> may be allocated.
+	// (tx with too many messages will either be rejected from the pool, or will fail earlier)
+	Chain::verify_messages_proof(proof, messages_count).map(|messages_by_lane| {
+		messages_by_lane
+			.into_iter()
+			.map(|(lane, lane_data)| {
+				(
+					lane,
+					ProvedLaneMessages {
+						lane_state: lane_data.lane_state,
+						messages: lane_data
+							.messages
+							.into_iter()
+							.map(|message| {
+								let dispatch_message = DispatchMessage {
+									key: message.key,
+									data: DispatchMessageData {
+										is_cancelled: message.data.is_cancelled(),
+										payload: DispatchPayload::decode(
+											&mut &message.data.payload[..],
+										),
+										fee: message.data.fee,
+										expiry: message.expiry,
+									},
+								};
+								(
+									PayloadHasher::hash(&message.data.payload),
+									message.data.payload,
+									dispatch_message,
+								)
+							})
+							.collect(),
+					},
+				)
+			})
+			.collect()
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	// darwinia-network
+	use super::*;
+	use crate::mock::{
+		dispatch_result, message, message_data, message_payload, run_test, unrewarded_relayer,
+		Balance, DepositLegacyEvents, MaxDeferredMessagesAtInboundLane,
+		MaxUnconfirmedMessagesAtInboundLane, RuntimeEvent as TestEvent, RuntimeOrigin,
+		TestMessageDeliveryAndDispatchPayment, TestMessageDispatch, TestMessagesDeliveryProof,
+		TestMessagesParameter, TestMessagesProof, TestOnDeliveryConfirmed1,
+		TestOnDeliveryConfirmed2, TestOnMessageAccepted, TestOnMessageDeliveryResult, TestPayload,
+		TestRelayer, TestRuntime, TokenConversionRate, MAX_OUTBOUND_PAYLOAD_SIZE,
+		PAYLOAD_REJECTED_BY_TARGET_CHAIN, REGULAR_PAYLOAD, TEST_DELIVERY_CALLBACK_ID,
+		TEST_DISPATCH_ERROR, TEST_LANE_ID, TEST_LANE_ID_2, TEST_RELAYER_A, TEST_RELAYER_B,
+		TEST_UNORDERED_LANE_ID, UNKNOWN_DELIVERY_CALLBACK_ID,
+	};
+	use bp_messages::{
+		commitment::verify_message_inclusion, source_chain::MessagesBridge, ReceivalResult,
+		ReceivedMessages, UnrewardedRelayer, UnrewardedRelayersState,
+	};
+	use bp_test_utils::generate_owned_bridge_module_tests;
+	// substrate
+	use frame_support::{
+		assert_noop, assert_ok,
+		storage::generator::{StorageMap, StorageValue},
+		traits::{Hooks, UnfilteredDispatchable},
+		weights::Weight,
+	};
+	use frame_system::{EventRecord, Pallet as System, Phase};
+	use sp_runtime::{traits::BlakeTwo256, DispatchError};
+	use sp_std::ops::RangeInclusive;
+
+	fn get_ready_for_events() {
+		System::<TestRuntime>::set_block_number(1);
+		System::<TestRuntime>::reset_events();
+	}
+
+	fn inbound_unrewarded_relayers_state(
+		lane: bp_messages::LaneId,
+	) -> bp_messages::UnrewardedRelayersState {
+		let inbound_lane_data = InboundLanes::<TestRuntime, ()>::get(lane).0;
+		let last_delivered_nonce = inbound_lane_data.last_delivered_nonce();
+		let relayers = inbound_lane_data.relayers;
+		bp_messages::UnrewardedRelayersState {
+			unrewarded_relayer_entries: relayers.len() as _,
+			messages_in_oldest_entry: relayers
+				.front()
+				.map(|entry| 1 + entry.messages.end - entry.messages.begin)
+				.unwrap_or(0),
+			total_messages: total_unrewarded_messages(&relayers).unwrap_or(MessageNonce::MAX),
+			last_delivered_nonce,
+		}
+	}
+
+	fn send_regular_message() -> Weight {
+		get_ready_for_events();
+
+		let message_nonce =
+			outbound_lane::<TestRuntime, ()>(TEST_LANE_ID).data().latest_generated_nonce + 1;
+		let weight = Pallet::<TestRuntime>::send_message(
+			RuntimeOrigin::signed(1),
+			TEST_LANE_ID,
+			REGULAR_PAYLOAD,
+			REGULAR_PAYLOAD.declared_weight.ref_time(),
+		)
+		.expect("send_message has failed")
+		.actual_weight
+		.expect("send_message always returns Some");
+
+		// check events with assigned nonce
+		assert_eq!(
+			System::<TestRuntime>::events(),
+			vec![
+				EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Messages(Event::MessageAccepted {
+						lane_id: TEST_LANE_ID,
+						nonce: message_nonce
+					}),
+					topics: vec![],
+				},
+				EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Messages(Event::MessageAcceptedV2 {
+						lane_id: TEST_LANE_ID,
+						nonce: message_nonce,
+						payload_hash: BlakeTwo256::hash(&REGULAR_PAYLOAD.encode()),
+						encoded_size: REGULAR_PAYLOAD.encode().len() as u32,
+						fee: REGULAR_PAYLOAD.declared_weight.ref_time(),
+						sender: Some(1),
+					}),
+					topics: vec![],
+				},
+			],
+		);
+
+		// check that fee has been withdrawn from submitter
+		assert!(TestMessageDeliveryAndDispatchPayment::is_fee_paid(
+			1,
+			REGULAR_PAYLOAD.declared_weight.ref_time()
+		));
+
+		weight
+	}
+
+	fn send_and_confirm_messages(lane_id: LaneId, messages_count: MessageNonce) {
+		let mut lane = outbound_lane::<TestRuntime, ()>(lane_id);
+		for _ in 0..messages_count {
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+		}
+		OutboundLanes::<TestRuntime, ()>::mutate(lane_id, |data| {
+			data.latest_received_nonce = data.latest_generated_nonce
+		});
+	}
+
+	fn receive_messages_delivery_proof() {
+		System::<TestRuntime>::set_block_number(1);
+		System::<TestRuntime>::reset_events();
+
+		assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+			RuntimeOrigin::signed(1),
+			TestMessagesDeliveryProof(Ok((
+				TEST_LANE_ID,
+				InboundLaneData {
+					last_confirmed_nonce: 1,
+					relayers: vec![UnrewardedRelayer {
+						relayer: 0,
+						messages: DeliveredMessages::new(1, true),
+					}]
+					.into_iter()
+					.collect(),
+				},
+			))),
+			UnrewardedRelayersState {
+				unrewarded_relayer_entries: 1,
+				total_messages: 1,
+				last_delivered_nonce: 1,
+				..Default::default()
+			},
+		));
+
+		assert_eq!(
 			System::<TestRuntime>::events(),
 			vec![EventRecord {
 				phase: Phase::Initialization,
-				event: TestEvent::Messages(Event::MessageAccepted {
+				event: TestEvent::Messages(Event::MessagesDelivered {
+					lane_id: TEST_LANE_ID,
+					messages: DeliveredMessages::new(1, true),
+				}),
+				topics: vec![],
+			}],
+		);
+	}
+
+	#[test]
+	fn pallet_parameter_may_be_updated_by_root() {
+		run_test(|| {
+			get_ready_for_events();
+
+			let parameter = TestMessagesParameter::TokenConversionRate(10.into());
+			assert_ok!(Pallet::<TestRuntime>::update_pallet_parameter(
+				RuntimeOrigin::root(),
+				parameter.clone(),
+			));
+
+			assert_eq!(TokenConversionRate::get(), 10.into());
+			assert_eq!(
+				System::<TestRuntime>::events(),
+				vec![EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Messages(Event::ParameterUpdated { parameter }),
+					topics: vec![],
+				}],
+			);
+		});
+	}
+
+	#[test]
+	fn pallet_parameter_may_be_updated_by_owner() {
+		run_test(|| {
+			PalletOwner::<TestRuntime>::put(2);
+			get_ready_for_events();
+
+			let parameter = TestMessagesParameter::TokenConversionRate(10.into());
+			assert_ok!(Pallet::<TestRuntime>::update_pallet_parameter(
+				RuntimeOrigin::signed(2),
+				parameter.clone(),
+			));
+
+			assert_eq!(TokenConversionRate::get(), 10.into());
+			assert_eq!(
+				System::<TestRuntime>::events(),
+				vec![EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Messages(Event::ParameterUpdated { parameter }),
+					topics: vec![],
+				}],
+			);
+		});
+	}
+
+	#[test]
+	fn pallet_parameter_cant_be_updated_by_arbitrary_submitter() {
+		run_test(|| {
+			assert_noop!(
+				Pallet::<TestRuntime>::update_pallet_parameter(
+					RuntimeOrigin::signed(2),
+					TestMessagesParameter::TokenConversionRate(10.into()),
+				),
+				DispatchError::BadOrigin,
+			);
+
+			PalletOwner::<TestRuntime>::put(2);
+
+			assert_noop!(
+				Pallet::<TestRuntime>::update_pallet_parameter(
+					RuntimeOrigin::signed(1),
+					TestMessagesParameter::TokenConversionRate(10.into()),
+				),
+				DispatchError::BadOrigin,
+			);
+		});
+	}
+
+	#[test]
+	fn fixed_u128_works_as_i_think() {
+		// this test is here just to be sure that conversion rate may be represented with FixedU128
+		run_test(|| {
+			use sp_runtime::{FixedPointNumber, FixedU128};
+
+			// 1:1 conversion that we use by default for testnets
+			let rialto_token = 1u64;
+			let rialto_token_in_millau_tokens =
+				TokenConversionRate::get().saturating_mul_int(rialto_token);
+			assert_eq!(rialto_token_in_millau_tokens, 1);
+
+			// let's say conversion rate is 1:1.7
+			let conversion_rate = FixedU128::saturating_from_rational(170, 100);
+			let rialto_tokens = 100u64;
+			let rialto_tokens_in_millau_tokens = conversion_rate.saturating_mul_int(rialto_tokens);
+			assert_eq!(rialto_tokens_in_millau_tokens, 170);
+
+			// let's say conversion rate is 1:0.25
+			let conversion_rate = FixedU128::saturating_from_rational(25, 100);
+			let rialto_tokens = 100u64;
+			let rialto_tokens_in_millau_tokens = conversion_rate.saturating_mul_int(rialto_tokens);
+			assert_eq!(rialto_tokens_in_millau_tokens, 25);
+		});
+	}
+
+	#[test]
+	fn pallet_rejects_transactions_if_halted() {
+		run_test(|| {
+			// send message first to be able to check that delivery_proof fails later
+			send_regular_message();
+
+			PalletOperatingMode::<TestRuntime, ()>::put(MessagesOperatingMode::Basic(
+				BasicOperatingMode::Halted,
+			));
+
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD,
+					REGULAR_PAYLOAD.declared_weight.ref_time(),
+				),
+				Error::<TestRuntime, ()>::NotOperatingNormally,
+			);
+
+			assert_noop!(
+				Pallet::<TestRuntime>::receive_messages_proof(
+					RuntimeOrigin::signed(1),
+					TEST_RELAYER_A,
+					Ok(vec![message(2, REGULAR_PAYLOAD)]).into(),
+					1,
+					REGULAR_PAYLOAD.declared_weight
+				),
+				Error::<TestRuntime, ()>::BridgeModule(bp_runtime::OwnedBridgeModuleError::Halted),
+			);
+
+			assert_noop!(
+				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+					RuntimeOrigin::signed(1),
+					TestMessagesDeliveryProof(Ok((
+						TEST_LANE_ID,
+						InboundLaneData {
+							last_confirmed_nonce: 1,
+							relayers: vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)]
+								.into_iter()
+								.collect(),
+						},
+					))),
+					UnrewardedRelayersState {
+						unrewarded_relayer_entries: 1,
+						messages_in_oldest_entry: 1,
+						total_messages: 1,
+						last_delivered_nonce: 1,
+					},
+				),
+				Error::<TestRuntime, ()>::BridgeModule(bp_runtime::OwnedBridgeModuleError::Halted),
+			);
+		});
+	}
+
+	#[test]
+	fn pallet_rejects_new_messages_in_rejecting_outbound_messages_operating_mode() {
+		run_test(|| {
+			// send message first to be able to check that delivery_proof fails later
+			send_regular_message();
+
+			PalletOperatingMode::<TestRuntime, ()>::put(
+				MessagesOperatingMode::RejectingOutboundMessages,
+			);
+
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD,
+					REGULAR_PAYLOAD.declared_weight.ref_time(),
+				),
+				Error::<TestRuntime, ()>::NotOperatingNormally,
+			);
+
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+				1,
+				REGULAR_PAYLOAD.declared_weight
+			),);
+
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				TestMessagesDeliveryProof(Ok((
+					TEST_LANE_ID,
+					InboundLaneData {
+						last_confirmed_nonce: 1,
+						relayers: vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)]
+							.into_iter()
+							.collect(),
+					},
+				))),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 1,
+					messages_in_oldest_entry: 1,
+					total_messages: 1,
+					last_delivered_nonce: 1,
+				},
+			));
+		});
+	}
+
+	#[test]
+	fn send_message_works() {
+		run_test(|| {
+			send_regular_message();
+		});
+	}
+
+	#[test]
+	fn send_message_rejects_too_large_message() {
+		run_test(|| {
+			let mut message_payload = message_payload(1, 0);
+			// the payload isn't simply extra, so it'll definitely overflow
+			// `MAX_OUTBOUND_PAYLOAD_SIZE` if we add `MAX_OUTBOUND_PAYLOAD_SIZE` bytes to extra
+			message_payload.extra.extend_from_slice(&[0u8; MAX_OUTBOUND_PAYLOAD_SIZE as usize]);
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					message_payload.clone(),
+					Balance::MAX,
+				),
+				Error::<TestRuntime, ()>::MessageIsTooLarge,
+			);
+
+			// let's check that we're able to send `MAX_OUTBOUND_PAYLOAD_SIZE` messages
+			while message_payload.size() > MAX_OUTBOUND_PAYLOAD_SIZE {
+				message_payload.extra.pop();
+			}
+			assert_eq!(message_payload.size(), MAX_OUTBOUND_PAYLOAD_SIZE);
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				message_payload,
+				Balance::MAX,
+			),);
+		})
+	}
+
+	#[test]
+	fn chain_verifier_rejects_invalid_message_in_send_message() {
+		run_test(|| {
+			// messages with this payload are rejected by target chain verifier
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					PAYLOAD_REJECTED_BY_TARGET_CHAIN,
+					PAYLOAD_REJECTED_BY_TARGET_CHAIN.declared_weight.ref_time()
+				),
+				Error::<TestRuntime, ()>::MessageRejectedByChainVerifier(VerificationError::Other(
+					mock::TEST_ERROR
+				)),
+			);
+		});
+	}
+
+	#[test]
+	fn lane_verifier_rejects_invalid_message_in_send_message() {
+		run_test(|| {
+			// messages with zero fee are rejected by lane verifier
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD,
+					0
+				),
+				Error::<TestRuntime, ()>::MessageRejectedByLaneVerifier(VerificationError::Other(
+					mock::TEST_ERROR
+				)),
+			);
+		});
+	}
+
+	#[test]
+	fn message_send_fails_if_submitter_cant_pay_message_fee() {
+		run_test(|| {
+			TestMessageDeliveryAndDispatchPayment::reject_payments();
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD,
+					REGULAR_PAYLOAD.declared_weight.ref_time()
+				),
+				Error::<TestRuntime, ()>::FailedToWithdrawMessageFee,
+			);
+		});
+	}
+
+	#[test]
+	fn send_messages_works() {
+		run_test(|| {
+			get_ready_for_events();
+
+			let fee = REGULAR_PAYLOAD.declared_weight.ref_time();
+			let messages = vec![
+				(TEST_LANE_ID, REGULAR_PAYLOAD, fee),
+				(TEST_LANE_ID_2, REGULAR_PAYLOAD, fee),
+				(TEST_LANE_ID, REGULAR_PAYLOAD, fee),
+			];
+			let weight = Pallet::<TestRuntime>::send_messages(
+				RuntimeOrigin::signed(1),
+				messages.try_into().unwrap(),
+			)
+			.expect("send_messages has failed")
+			.actual_weight
+			.expect("send_messages always returns Some");
+
+			// every message is accepted
+			assert_eq!(
+				System::<TestRuntime>::events()
+					.into_iter()
+					.map(|record| record.event)
+					.filter(|event| matches!(
+						event,
+						TestEvent::Messages(Event::MessageAccepted { .. })
+					))
+					.collect::<Vec<_>>(),
+				vec![
+					TestEvent::Messages(Event::MessageAccepted { lane_id: TEST_LANE_ID, nonce: 1 }),
+					TestEvent::Messages(Event::MessageAccepted {
+						lane_id: TEST_LANE_ID_2,
+						nonce: 1
+					}),
+					TestEvent::Messages(Event::MessageAccepted { lane_id: TEST_LANE_ID, nonce: 2 }),
+				],
+			);
+			TestOnMessageAccepted::ensure_called(&TEST_LANE_ID, &1);
+			TestOnMessageAccepted::ensure_called(&TEST_LANE_ID_2, &1);
+			TestOnMessageAccepted::ensure_called(&TEST_LANE_ID, &2);
+			assert_eq!(
+				outbound_lane::<TestRuntime, ()>(TEST_LANE_ID).data().latest_generated_nonce,
+				2
+			);
+
+			// fees of all messages are paid at once
+			assert!(TestMessageDeliveryAndDispatchPayment::is_fee_paid(1, 3 * fee));
+
+			// the batch is cheaper than separate calls
+			let send_message_weight = <TestRuntime as Config>::WeightInfo::send_message_weight(
+				&REGULAR_PAYLOAD,
+				crate::mock::DbWeight::get(),
+			);
+			assert!(weight.all_lte(send_message_weight.saturating_mul(3)));
+			// but every message still pays for its sender and commitment leaf
+			let storage_overhead =
+				<TestRuntime as Config>::WeightInfo::send_message_storage_overhead(
+					crate::mock::DbWeight::get(),
+				);
+			assert!(weight.all_gte(storage_overhead.saturating_mul(3)));
+		});
+	}
+
+	#[test]
+	fn send_messages_rejects_whole_batch_if_any_message_is_rejected() {
+		run_test(|| {
+			let fee = REGULAR_PAYLOAD.declared_weight.ref_time();
+			let messages = vec![
+				(TEST_LANE_ID, REGULAR_PAYLOAD, fee),
+				(TEST_LANE_ID, PAYLOAD_REJECTED_BY_TARGET_CHAIN, fee),
+			];
+			assert_noop!(
+				Call::<TestRuntime>::send_messages { messages: messages.try_into().unwrap() }
+					.dispatch_bypass_filter(RuntimeOrigin::signed(1)),
+				Error::<TestRuntime, ()>::MessageRejectedByChainVerifier(VerificationError::Other(
+					mock::TEST_ERROR
+				)),
+			);
+		});
+	}
+
+	#[test]
+	fn send_messages_fails_if_submitter_cant_pay_messages_fee() {
+		run_test(|| {
+			TestMessageDeliveryAndDispatchPayment::reject_payments();
+
+			let fee = REGULAR_PAYLOAD.declared_weight.ref_time();
+			let messages = vec![(TEST_LANE_ID, REGULAR_PAYLOAD, fee)];
+			assert_noop!(
+				Call::<TestRuntime>::send_messages { messages: messages.try_into().unwrap() }
+					.dispatch_bypass_filter(RuntimeOrigin::signed(1)),
+				Error::<TestRuntime, ()>::FailedToWithdrawMessageFee,
+			);
+		});
+	}
+
+	#[test]
+	fn send_messages_rejects_empty_batch() {
+		run_test(|| {
+			assert_noop!(
+				Pallet::<TestRuntime>::send_messages(RuntimeOrigin::signed(1), Default::default()),
+				Error::<TestRuntime, ()>::EmptyMessagesBatch,
+			);
+		});
+	}
+
+	#[test]
+	fn lane_rate_limit_rejects_messages_until_window_ends() {
+		run_test(|| {
+			let fee = REGULAR_PAYLOAD.declared_weight.ref_time();
+			let limit = OutboundRateLimit { window: 10, max_messages: 2, max_bytes: u32::MAX };
+			assert_ok!(Pallet::<TestRuntime>::set_lane_rate_limit(
+				RuntimeOrigin::root(),
+				TEST_LANE_ID,
+				Some(limit),
+			));
+
+			System::<TestRuntime>::set_block_number(1);
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				fee,
+			));
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(2),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				fee,
+			));
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(3),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD,
+					fee,
+				),
+				Error::<TestRuntime, ()>::MessageRejectedByRateLimiter(
+					VerificationError::LaneRateLimitExceeded
+				),
+			);
+			// other lanes are not limited
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(3),
+				TEST_LANE_ID_2,
+				REGULAR_PAYLOAD,
+				fee,
+			));
+			assert_eq!(
+				Pallet::<TestRuntime>::lane_rate_limit_usage(TEST_LANE_ID),
+				Some(OutboundRateLimitUsage {
+					window_start: 1,
+					messages: 2,
+					bytes: 2 * REGULAR_PAYLOAD.size(),
+				}),
+			);
+			assert_eq!(Pallet::<TestRuntime>::lane_rate_limit_usage(TEST_LANE_ID_2), None);
+
+			// when the window ends, the lane may send messages again
+			System::<TestRuntime>::set_block_number(11);
+			assert_eq!(
+				Pallet::<TestRuntime>::lane_rate_limit_usage(TEST_LANE_ID),
+				Some(OutboundRateLimitUsage { window_start: 11, messages: 0, bytes: 0 }),
+			);
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(3),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				fee,
+			));
+		});
+	}
+
+	#[test]
+	fn lane_rate_limit_rejects_messages_exceeding_bytes_limit() {
+		run_test(|| {
+			let fee = REGULAR_PAYLOAD.declared_weight.ref_time();
+			let limit = OutboundRateLimit {
+				window: 10,
+				max_messages: u32::MAX,
+				max_bytes: 2 * REGULAR_PAYLOAD.size() - 1,
+			};
+			assert_ok!(Pallet::<TestRuntime>::set_lane_rate_limit(
+				RuntimeOrigin::root(),
+				TEST_LANE_ID,
+				Some(limit),
+			));
+
+			let messages =
+				vec![(TEST_LANE_ID, REGULAR_PAYLOAD, fee), (TEST_LANE_ID, REGULAR_PAYLOAD, fee)];
+			assert_noop!(
+				Call::<TestRuntime>::send_messages { messages: messages.try_into().unwrap() }
+					.dispatch_bypass_filter(RuntimeOrigin::signed(1)),
+				Error::<TestRuntime, ()>::MessageRejectedByRateLimiter(
+					VerificationError::LaneRateLimitExceeded
+				),
+			);
+
+			// when the limit is removed, the batch is accepted
+			assert_ok!(Pallet::<TestRuntime>::set_lane_rate_limit(
+				RuntimeOrigin::root(),
+				TEST_LANE_ID,
+				None,
+			));
+			let messages =
+				vec![(TEST_LANE_ID, REGULAR_PAYLOAD, fee), (TEST_LANE_ID, REGULAR_PAYLOAD, fee)];
+			assert_ok!(Call::<TestRuntime>::send_messages {
+				messages: messages.try_into().unwrap()
+			}
+			.dispatch_bypass_filter(RuntimeOrigin::signed(1)));
+		});
+	}
+
+	#[test]
+	fn sender_rate_limit_is_applied_to_every_sender_separately() {
+		run_test(|| {
+			let fee = REGULAR_PAYLOAD.declared_weight.ref_time();
+			let limit = OutboundRateLimit { window: 10, max_messages: 1, max_bytes: u32::MAX };
+			assert_ok!(Pallet::<TestRuntime>::set_sender_rate_limit(
+				RuntimeOrigin::root(),
+				Some(limit),
+			));
+
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				fee,
+			));
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID_2,
+					REGULAR_PAYLOAD,
+					fee,
+				),
+				Error::<TestRuntime, ()>::MessageRejectedByRateLimiter(
+					VerificationError::SenderRateLimitExceeded
+				),
+			);
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(2),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				fee,
+			));
+			assert_eq!(
+				Pallet::<TestRuntime>::sender_rate_limit_usage(&1),
+				Some(OutboundRateLimitUsage {
+					window_start: 0,
+					messages: 1,
+					bytes: REGULAR_PAYLOAD.size(),
+				}),
+			);
+		});
+	}
+
+	#[test]
+	fn on_idle_prunes_expired_sender_rate_limit_usages() {
+		run_test(|| {
+			// overhead: 5, usage: 3
+			let fee = REGULAR_PAYLOAD.declared_weight.ref_time();
+			let limit = OutboundRateLimit { window: 10, max_messages: 1, max_bytes: u32::MAX };
+			assert_ok!(Pallet::<TestRuntime>::set_sender_rate_limit(
+				RuntimeOrigin::root(),
+				Some(limit),
+			));
+			for (block, sender) in [(1, 1), (5, 2), (5, 3)] {
+				System::<TestRuntime>::set_block_number(block);
+				assert_ok!(Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(sender),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD,
+					fee,
+				));
+			}
+			let senders = SenderRateLimitUsages::<TestRuntime>::iter_keys().collect::<Vec<_>>();
+
+			// nothing has expired yet, but the cursor is moved
+			System::<TestRuntime>::set_block_number(10);
+			assert_eq!(
+				prune_sender_rate_limit_usages::<TestRuntime, ()>(Weight::from_parts(11, 0)),
+				Weight::from_parts(11, 0),
+			);
+			assert_eq!(SenderRateLimitUsages::<TestRuntime>::iter().count(), 3);
+			assert_eq!(SenderRateLimitUsagesPruningCursor::<TestRuntime>::get(), Some(senders[1]));
+
+			// the usage of the first sender has expired, but we continue from the cursor
+			System::<TestRuntime>::set_block_number(11);
+			assert_eq!(
+				prune_sender_rate_limit_usages::<TestRuntime, ()>(Weight::MAX),
+				Weight::from_parts(8, 0),
+			);
+			assert_eq!(SenderRateLimitUsagesPruningCursor::<TestRuntime>::get(), None);
+			assert_eq!(
+				prune_sender_rate_limit_usages::<TestRuntime, ()>(Weight::MAX),
+				Weight::from_parts(14, 0),
+			);
+			assert!(!SenderRateLimitUsages::<TestRuntime>::contains_key(1));
+			assert!(SenderRateLimitUsages::<TestRuntime>::contains_key(2));
+			assert!(SenderRateLimitUsages::<TestRuntime>::contains_key(3));
+
+			// once traffic of senders is no longer limited, all usages are pruned
+			assert_ok!(Pallet::<TestRuntime>::set_sender_rate_limit(RuntimeOrigin::root(), None));
+			Pallet::<TestRuntime>::on_idle(12, Weight::MAX);
+			assert_eq!(SenderRateLimitUsages::<TestRuntime>::iter().count(), 0);
+			assert_eq!(SenderRateLimitUsagesPruningCursor::<TestRuntime>::get(), None);
+		});
+	}
+
+	#[test]
+	fn is_sender_allowed_reads_lane_allowlist() {
+		run_test(|| {
+			let root = RuntimeOrigin::root();
+			let signed = RuntimeOrigin::signed(1);
+			assert!(!Pallet::<TestRuntime>::is_sender_allowed(&root, &TEST_LANE_ID));
+			assert!(!Pallet::<TestRuntime>::is_sender_allowed(&signed, &TEST_LANE_ID));
+
+			assert_ok!(Pallet::<TestRuntime>::allow_sender(
+				RuntimeOrigin::root(),
+				TEST_LANE_ID,
+				AllowedSender::Root,
+			));
+			assert_ok!(Pallet::<TestRuntime>::allow_sender(
+				RuntimeOrigin::root(),
+				TEST_LANE_ID,
+				AllowedSender::Signed(1),
+			));
+			assert!(Pallet::<TestRuntime>::is_sender_allowed(&root, &TEST_LANE_ID));
+			assert!(Pallet::<TestRuntime>::is_sender_allowed(&signed, &TEST_LANE_ID));
+			assert!(!Pallet::<TestRuntime>::is_sender_allowed(
+				&RuntimeOrigin::signed(2),
+				&TEST_LANE_ID
+			));
+			assert!(!Pallet::<TestRuntime>::is_sender_allowed(
+				&RuntimeOrigin::none(),
+				&TEST_LANE_ID
+			));
+			assert!(!Pallet::<TestRuntime>::is_sender_allowed(&signed, &TEST_LANE_ID_2));
+
+			assert_ok!(Pallet::<TestRuntime>::allow_sender(
+				RuntimeOrigin::root(),
+				TEST_LANE_ID,
+				AllowedSender::AnySigned,
+			));
+			assert!(Pallet::<TestRuntime>::is_sender_allowed(
+				&RuntimeOrigin::signed(2),
+				&TEST_LANE_ID
+			));
+
+			assert_ok!(Pallet::<TestRuntime>::disallow_sender(
+				RuntimeOrigin::root(),
+				TEST_LANE_ID,
+				AllowedSender::Root,
+			));
+			assert!(!Pallet::<TestRuntime>::is_sender_allowed(&root, &TEST_LANE_ID));
+		});
+	}
+
+	#[test]
+	fn allowlist_may_only_be_changed_by_owner_or_root() {
+		run_test(|| {
+			assert_noop!(
+				Pallet::<TestRuntime>::allow_sender(
+					RuntimeOrigin::signed(2),
+					TEST_LANE_ID,
+					AllowedSender::Signed(2),
+				),
+				DispatchError::BadOrigin,
+			);
+
+			PalletOwner::<TestRuntime>::put(2);
+			get_ready_for_events();
+			assert_ok!(Pallet::<TestRuntime>::allow_sender(
+				RuntimeOrigin::signed(2),
+				TEST_LANE_ID,
+				AllowedSender::Signed(2),
+			));
+			assert_noop!(
+				Pallet::<TestRuntime>::allow_sender(
+					RuntimeOrigin::signed(2),
+					TEST_LANE_ID,
+					AllowedSender::Signed(2),
+				),
+				Error::<TestRuntime, ()>::SenderAlreadyAllowed,
+			);
+			assert_ok!(Pallet::<TestRuntime>::disallow_sender(
+				RuntimeOrigin::signed(2),
+				TEST_LANE_ID,
+				AllowedSender::Signed(2),
+			));
+			assert_noop!(
+				Pallet::<TestRuntime>::disallow_sender(
+					RuntimeOrigin::signed(2),
+					TEST_LANE_ID,
+					AllowedSender::Signed(2),
+				),
+				Error::<TestRuntime, ()>::SenderNotAllowed,
+			);
+
+			assert_eq!(
+				System::<TestRuntime>::events(),
+				vec![
+					EventRecord {
+						phase: Phase::Initialization,
+						event: TestEvent::Messages(Event::SenderAllowed {
+							lane_id: TEST_LANE_ID,
+							sender: AllowedSender::Signed(2),
+						}),
+						topics: vec![],
+					},
+					EventRecord {
+						phase: Phase::Initialization,
+						event: TestEvent::Messages(Event::SenderDisallowed {
+							lane_id: TEST_LANE_ID,
+							sender: AllowedSender::Signed(2),
+						}),
+						topics: vec![],
+					},
+				],
+			);
+		});
+	}
+
+	#[test]
+	fn rate_limits_may_only_be_changed_by_owner_or_root() {
+		run_test(|| {
+			let limit = OutboundRateLimit { window: 10, max_messages: 1, max_bytes: 1 };
+			assert_noop!(
+				Pallet::<TestRuntime>::set_lane_rate_limit(
+					RuntimeOrigin::signed(2),
+					TEST_LANE_ID,
+					Some(limit),
+				),
+				DispatchError::BadOrigin,
+			);
+			assert_noop!(
+				Pallet::<TestRuntime>::set_sender_rate_limit(RuntimeOrigin::signed(2), Some(limit)),
+				DispatchError::BadOrigin,
+			);
+
+			PalletOwner::<TestRuntime>::put(2);
+			assert_ok!(Pallet::<TestRuntime>::set_lane_rate_limit(
+				RuntimeOrigin::signed(2),
+				TEST_LANE_ID,
+				Some(limit),
+			));
+			assert_ok!(Pallet::<TestRuntime>::set_sender_rate_limit(
+				RuntimeOrigin::signed(2),
+				Some(limit),
+			));
+			assert_eq!(Pallet::<TestRuntime>::lane_rate_limit(TEST_LANE_ID), Some(limit));
+			assert_eq!(Pallet::<TestRuntime>::sender_rate_limit(), Some(limit));
+		});
+	}
+
+	#[test]
+	fn increase_message_fee_fails_if_message_is_already_delivered() {
+		run_test(|| {
+			send_regular_message();
+			receive_messages_delivery_proof();
+
+			assert_noop!(
+				Pallet::<TestRuntime>::increase_message_fee(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					1,
+					100,
+				),
+				Error::<TestRuntime, ()>::MessageAlreadyDelivered,
+			);
+		});
+	}
+
+	#[test]
+	fn increase_message_fee_fails_if_message_is_not_yet_sent() {
+		run_test(|| {
+			assert_noop!(
+				Pallet::<TestRuntime>::increase_message_fee(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					1,
+					100,
+				),
+				Error::<TestRuntime, ()>::MessageIsNotYetSent,
+			);
+		});
+	}
+
+	#[test]
+	fn increase_message_fee_fails_if_message_is_cancelled() {
+		run_test(|| {
+			send_regular_message();
+			OutboundMessages::<TestRuntime>::insert(
+				MessageKey { lane_id: TEST_LANE_ID, nonce: 1 },
+				MessageData::cancelled(0),
+			);
+
+			assert_noop!(
+				Pallet::<TestRuntime>::increase_message_fee(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					1,
+					100,
+				),
+				Error::<TestRuntime, ()>::MessageIsNotYetSent,
+			);
+		});
+	}
+
+	#[test]
+	fn increase_message_fee_fails_if_submitter_cant_pay_additional_fee() {
+		run_test(|| {
+			send_regular_message();
+
+			TestMessageDeliveryAndDispatchPayment::reject_payments();
+
+			assert_noop!(
+				Pallet::<TestRuntime>::increase_message_fee(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					1,
+					100,
+				),
+				Error::<TestRuntime, ()>::FailedToWithdrawMessageFee,
+			);
+		});
+	}
+
+	#[test]
+	fn increase_message_fee_succeeds() {
+		run_test(|| {
+			send_regular_message();
+			System::<TestRuntime>::reset_events();
+
+			assert_ok!(Pallet::<TestRuntime>::increase_message_fee(
+				RuntimeOrigin::signed(2),
+				TEST_LANE_ID,
+				1,
+				100,
+			));
+			assert!(TestMessageDeliveryAndDispatchPayment::is_fee_paid(2, 100));
+			assert_eq!(
+				Pallet::<TestRuntime>::outbound_message_data(TEST_LANE_ID, 1).map(|data| data.fee),
+				Some(REGULAR_PAYLOAD.declared_weight.ref_time() + 100),
+			);
+			assert_eq!(
+				System::<TestRuntime>::events(),
+				vec![EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Messages(Event::MessageFeeIncreased {
+						lane_id: TEST_LANE_ID,
+						nonce: 1,
+						additional_fee: 100,
+					}),
+					topics: vec![],
+				}],
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_proof_works() {
+		run_test(|| {
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+				1,
+				REGULAR_PAYLOAD.declared_weight,
+			));
+
+			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).0.last_delivered_nonce(), 1);
+		});
+	}
+
+	#[test]
+	fn legacy_events_are_not_deposited_if_disabled() {
+		run_test(|| {
+			DepositLegacyEvents::set(&false);
+			get_ready_for_events();
+
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				REGULAR_PAYLOAD.declared_weight.ref_time(),
+			));
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+				1,
+				REGULAR_PAYLOAD.declared_weight,
+			));
+
+			let events = System::<TestRuntime>::events()
+				.into_iter()
+				.map(|record| record.event)
+				.collect::<Vec<_>>();
+			assert!(events.iter().any(|event| matches!(
+				event,
+				TestEvent::Messages(Event::MessageAcceptedV2 { .. })
+			)));
+			assert!(events
+				.iter()
+				.any(|event| matches!(event, TestEvent::Messages(Event::MessagesReceivedV2(_)))));
+			assert!(!events.iter().any(|event| matches!(
+				event,
+				TestEvent::Messages(Event::MessageAccepted { .. } | Event::MessagesReceived(_))
+			)));
+		});
+	}
+
+	#[test]
+	fn receive_messages_proof_updates_confirmed_message_nonce() {
+		run_test(|| {
+			// say we have received 10 messages && last confirmed message is 8
+			InboundLanes::<TestRuntime, ()>::insert(
+				TEST_LANE_ID,
+				InboundLaneData {
+					last_confirmed_nonce: 8,
+					relayers: vec![
+						unrewarded_relayer(9, 9, TEST_RELAYER_A),
+						unrewarded_relayer(10, 10, TEST_RELAYER_B),
+					]
+					.into_iter()
+					.collect(),
+				},
+			);
+			assert_eq!(
+				inbound_unrewarded_relayers_state(TEST_LANE_ID),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 2,
+					messages_in_oldest_entry: 1,
+					total_messages: 2,
+					last_delivered_nonce: 10,
+				},
+			);
+
+			// message proof includes outbound lane state with latest confirmed message updated to 9
+			let mut message_proof: TestMessagesProof =
+				Ok(vec![message(11, REGULAR_PAYLOAD)]).into();
+			message_proof.result.as_mut().unwrap()[0].1.lane_state =
+				Some(OutboundLaneData { latest_received_nonce: 9, ..Default::default() });
+
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				message_proof,
+				1,
+				REGULAR_PAYLOAD.declared_weight,
+			));
+
+			assert_eq!(
+				InboundLanes::<TestRuntime>::get(TEST_LANE_ID).0,
+				InboundLaneData {
+					last_confirmed_nonce: 9,
+					relayers: vec![
+						unrewarded_relayer(10, 10, TEST_RELAYER_B),
+						unrewarded_relayer(11, 11, TEST_RELAYER_A)
+					]
+					.into_iter()
+					.collect(),
+				},
+			);
+			assert_eq!(
+				inbound_unrewarded_relayers_state(TEST_LANE_ID),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 2,
+					messages_in_oldest_entry: 1,
+					total_messages: 2,
+					last_delivered_nonce: 11,
+				},
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_proof_does_not_accept_message_if_dispatch_weight_is_not_enough() {
+		run_test(|| {
+			let mut declared_weight = REGULAR_PAYLOAD.declared_weight;
+			*declared_weight.ref_time_mut() -= 1;
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+				1,
+				declared_weight,
+			));
+			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).last_delivered_nonce(), 0);
+		});
+	}
+
+	#[test]
+	fn receive_messages_proof_defers_messages_if_dispatch_weight_is_not_enough() {
+		run_test(|| {
+			MaxDeferredMessagesAtInboundLane::set(&3);
+			get_ready_for_events();
+
+			// the first message is dispatched and the second is deferred
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD), message(2, REGULAR_PAYLOAD)]).into(),
+				2,
+				REGULAR_PAYLOAD.declared_weight,
+			));
+
+			// while the lane has deferred messages, new messages are deferred too, even if there's
+			// enough dispatch weight. The last message is skipped, because the queue is full
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![
+					message(3, REGULAR_PAYLOAD),
+					message(4, REGULAR_PAYLOAD),
+					message(5, REGULAR_PAYLOAD),
+				])
+				.into(),
+				3,
+				REGULAR_PAYLOAD.declared_weight * 3,
+			));
+
+			// deferred messages are delivered, so they may be confirmed at the source chain
+			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).last_delivered_nonce(), 4);
+			assert_eq!(
+				DeferredMessagesQueues::<TestRuntime>::get(TEST_LANE_ID).into_inner(),
+				vec![2, 3, 4]
+			);
+			assert_eq!(
+				System::<TestRuntime>::events()
+					.into_iter()
+					.map(|record| record.event)
+					.filter(|event| matches!(
+						event,
+						TestEvent::Messages(Event::MessagesReceived(_))
+					))
+					.collect::<Vec<_>>(),
+				vec![
+					TestEvent::Messages(Event::MessagesReceived(vec![ReceivedMessages {
+						lane: TEST_LANE_ID,
+						receive_results: vec![
+							(1, ReceivalResult::Dispatched(dispatch_result(0))),
+							(2, ReceivalResult::Deferred),
+						],
+						skipped_for_not_enough_weight: vec![],
+					}])),
+					TestEvent::Messages(Event::MessagesReceived(vec![ReceivedMessages {
+						lane: TEST_LANE_ID,
+						receive_results: vec![
+							(3, ReceivalResult::Deferred),
+							(4, ReceivalResult::Deferred),
+						],
+						skipped_for_not_enough_weight: vec![5],
+					}])),
+				],
+			);
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
+		});
+	}
+
+	#[test]
+	fn receive_messages_proof_does_not_refund_undeclared_weight_of_deferred_messages() {
+		run_test(|| {
+			MaxDeferredMessagesAtInboundLane::set(&3);
+			let heavy_payload = message_payload(0, 1_000_000_000_000);
+			let proof: TestMessagesProof = Ok(vec![
+				message(1, heavy_payload.clone()),
+				message(2, heavy_payload.clone()),
+				message(3, heavy_payload),
+			])
+			.into();
+			let weight = Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				proof,
+				3,
+				Weight::zero(),
+			)
+			.expect("receive_messages_proof has failed")
+			.actual_weight
+			.expect("receive_messages_proof always returns Some");
+
+			// all messages are deferred, but the relayer still pays for their delivery
+			assert_eq!(
+				DeferredMessagesQueues::<TestRuntime>::get(TEST_LANE_ID).into_inner(),
+				vec![1, 2, 3]
+			);
+			assert!(weight
+				.all_gte(<TestRuntime as Config>::WeightInfo::receive_messages_proof_overhead()));
+		});
+	}
+
+	#[test]
+	fn deferred_messages_are_dispatched_on_idle() {
+		run_test(|| {
+			MaxDeferredMessagesAtInboundLane::set(&4);
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![
+					message(1, REGULAR_PAYLOAD),
+					message(2, REGULAR_PAYLOAD),
+					message(3, REGULAR_PAYLOAD),
+				])
+				.into(),
+				3,
+				Weight::zero(),
+			));
+			assert_eq!(DeferredMessagesQueues::<TestRuntime>::get(TEST_LANE_ID).len(), 3);
+			get_ready_for_events();
+
+			// there's only enough weight to dispatch two messages
+			let db_weight = crate::mock::DbWeight::get();
+			let message_weight =
+				<TestRuntime as Config>::WeightInfo::dispatch_deferred_message_overhead(db_weight)
+					+ REGULAR_PAYLOAD.declared_weight;
+			let max_weight =
+				<TestRuntime as Config>::WeightInfo::dispatch_deferred_messages_overhead(db_weight)
+					+ <TestRuntime as Config>::WeightInfo::dispatch_deferred_messages_lane_overhead(
+						db_weight,
+					) + message_weight * 2;
+			assert_eq!(dispatch_deferred_messages::<TestRuntime, ()>(max_weight), max_weight);
+			assert_eq!(
+				DeferredMessagesQueues::<TestRuntime>::get(TEST_LANE_ID).into_inner(),
+				vec![3]
+			);
+			assert!(!DeferredMessages::<TestRuntime>::contains_key(TEST_LANE_ID, 1));
+			assert!(!DeferredMessages::<TestRuntime>::contains_key(TEST_LANE_ID, 2));
+			assert!(DeferredMessages::<TestRuntime>::contains_key(TEST_LANE_ID, 3));
+
+			// the rest is dispatched during next block
+			Pallet::<TestRuntime>::on_idle(2, Weight::MAX);
+			assert!(!DeferredMessagesQueues::<TestRuntime>::contains_key(TEST_LANE_ID));
+			assert!(!DeferredMessages::<TestRuntime>::contains_key(TEST_LANE_ID, 3));
+			assert_eq!(
+				System::<TestRuntime>::events()
+					.into_iter()
+					.map(|record| record.event)
+					.collect::<Vec<_>>(),
+				(1..=3)
+					.map(|nonce| TestEvent::Messages(Event::DeferredMessageDispatched {
+						lane_id: TEST_LANE_ID,
+						nonce,
+						dispatch_result: true,
+					}))
+					.collect::<Vec<_>>(),
+			);
+
+			// the actual dispatch results are reported to the source chain
+			let relayers = InboundLanes::<TestRuntime>::get(TEST_LANE_ID).0.relayers;
+			assert_eq!(relayers.len(), 1);
+			assert!((1..=3).all(|nonce| relayers[0].messages.message_dispatch_result(nonce)));
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
+		});
+	}
+
+	#[test]
+	fn deferred_message_that_does_not_fit_does_not_block_other_lanes() {
+		run_test(|| {
+			MaxDeferredMessagesAtInboundLane::set(&4);
+			let heavy_payload = message_payload(0, 100);
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, heavy_payload.clone())]).into(),
+				1,
+				Weight::zero(),
+			));
+			let mut unordered_message = message(1, REGULAR_PAYLOAD);
+			unordered_message.key.lane_id = TEST_UNORDERED_LANE_ID;
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![unordered_message]).into(),
+				1,
+				Weight::zero(),
+			));
+
+			// the heavy message doesn't fit, but the message of the other lane is dispatched
+			let db_weight = crate::mock::DbWeight::get();
+			let max_weight =
+				<TestRuntime as Config>::WeightInfo::dispatch_deferred_messages_overhead(db_weight)
+					+ <TestRuntime as Config>::WeightInfo::dispatch_deferred_messages_lane_overhead(
+						db_weight,
+					) * 2 + <TestRuntime as Config>::WeightInfo::dispatch_deferred_message_overhead(
+					db_weight,
+				) + REGULAR_PAYLOAD.declared_weight;
+			for block in 1..=2 {
+				Pallet::<TestRuntime>::on_idle(block, max_weight);
+			}
+			assert!(DeferredMessages::<TestRuntime>::contains_key(TEST_LANE_ID, 1));
+			assert!(!DeferredMessagesQueues::<TestRuntime>::contains_key(TEST_UNORDERED_LANE_ID));
+
+			// the heavy message may be dispatched by anyone who pays for it
+			assert_noop!(
+				Pallet::<TestRuntime>::dispatch_deferred_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD.declared_weight,
+				),
+				Error::<TestRuntime, ()>::InsufficientDispatchWeight,
+			);
+			assert_ok!(Pallet::<TestRuntime>::dispatch_deferred_message(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				heavy_payload.declared_weight,
+			));
+			assert!(!DeferredMessagesQueues::<TestRuntime>::contains_key(TEST_LANE_ID));
+			assert_noop!(
+				Pallet::<TestRuntime>::dispatch_deferred_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					heavy_payload.declared_weight,
+				),
+				Error::<TestRuntime, ()>::UnknownDeferredMessage,
+			);
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
+		});
+	}
+
+	#[test]
+	fn deferred_messages_are_not_dispatched_at_halted_lanes() {
+		run_test(|| {
+			MaxDeferredMessagesAtInboundLane::set(&4);
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+				1,
+				Weight::zero(),
+			));
+
+			LaneOperatingModes::<TestRuntime>::insert(
+				TEST_LANE_ID,
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			);
+			dispatch_deferred_messages::<TestRuntime, ()>(Weight::MAX);
+			assert!(DeferredMessages::<TestRuntime>::contains_key(TEST_LANE_ID, 1));
+
+			LaneOperatingModes::<TestRuntime>::remove(TEST_LANE_ID);
+			dispatch_deferred_messages::<TestRuntime, ()>(Weight::MAX);
+			assert!(!DeferredMessages::<TestRuntime>::contains_key(TEST_LANE_ID, 1));
+		});
+	}
+
+	#[test]
+	fn receive_messages_proof_rejects_invalid_proof() {
+		run_test(|| {
+			assert_noop!(
+				Pallet::<TestRuntime, ()>::receive_messages_proof(
+					RuntimeOrigin::signed(1),
+					TEST_RELAYER_A,
+					Err(()).into(),
+					1,
+					Weight::zero(),
+				),
+				Error::<TestRuntime, ()>::InvalidMessagesProof,
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_proof_rejects_proof_with_too_many_messages() {
+		run_test(|| {
+			assert_noop!(
+				Pallet::<TestRuntime, ()>::receive_messages_proof(
+					RuntimeOrigin::signed(1),
+					TEST_RELAYER_A,
+					Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+					u32::MAX,
+					Weight::zero(),
+				),
+				Error::<TestRuntime, ()>::TooManyMessagesInTheProof,
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_delivery_proof_works() {
+		run_test(|| {
+			send_regular_message();
+			receive_messages_delivery_proof();
+
+			assert_eq!(
+				OutboundLanes::<TestRuntime, ()>::get(TEST_LANE_ID).latest_received_nonce,
+				1,
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_delivery_proof_rewards_relayers() {
+		run_test(|| {
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				1000,
+			));
+			assert_ok!(Pallet::<TestRuntime>::send_message(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				2000,
+			));
+
+			// this reports delivery of message 1 => reward is paid to TEST_RELAYER_A
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				TestMessagesDeliveryProof(Ok((
+					TEST_LANE_ID,
+					InboundLaneData {
+						relayers: vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)]
+							.into_iter()
+							.collect(),
+						..Default::default()
+					}
+				))),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 1,
+					total_messages: 1,
+					last_delivered_nonce: 1,
+					..Default::default()
+				},
+			));
+			assert!(TestMessageDeliveryAndDispatchPayment::is_reward_paid(TEST_RELAYER_A, 1000));
+			assert!(!TestMessageDeliveryAndDispatchPayment::is_reward_paid(TEST_RELAYER_B, 2000));
+
+			// this reports delivery of both message 1 and message 2 => reward is paid only to
+			// TEST_RELAYER_B
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				TestMessagesDeliveryProof(Ok((
+					TEST_LANE_ID,
+					InboundLaneData {
+						relayers: vec![
+							unrewarded_relayer(1, 1, TEST_RELAYER_A),
+							unrewarded_relayer(2, 2, TEST_RELAYER_B)
+						]
+						.into_iter()
+						.collect(),
+						..Default::default()
+					}
+				))),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 2,
+					total_messages: 2,
+					last_delivered_nonce: 2,
+					..Default::default()
+				},
+			));
+			assert!(!TestMessageDeliveryAndDispatchPayment::is_reward_paid(TEST_RELAYER_A, 1000));
+			assert!(TestMessageDeliveryAndDispatchPayment::is_reward_paid(TEST_RELAYER_B, 2000));
+		});
+	}
+
+	#[test]
+	fn receive_messages_delivery_proof_rejects_invalid_proof() {
+		run_test(|| {
+			assert_noop!(
+				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+					RuntimeOrigin::signed(1),
+					TestMessagesDeliveryProof(Err(())),
+					Default::default(),
+				),
+				Error::<TestRuntime, ()>::InvalidMessagesDeliveryProof,
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_delivery_proof_rejects_proof_if_declared_relayers_state_is_invalid() {
+		run_test(|| {
+			// when number of relayers entries is invalid
+			assert_noop!(
+				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+					RuntimeOrigin::signed(1),
+					TestMessagesDeliveryProof(Ok((
+						TEST_LANE_ID,
+						InboundLaneData {
+							relayers: vec![
+								unrewarded_relayer(1, 1, TEST_RELAYER_A),
+								unrewarded_relayer(2, 2, TEST_RELAYER_B)
+							]
+							.into_iter()
+							.collect(),
+							..Default::default()
+						}
+					))),
+					UnrewardedRelayersState {
+						unrewarded_relayer_entries: 1,
+						total_messages: 2,
+						last_delivered_nonce: 2,
+						..Default::default()
+					},
+				),
+				Error::<TestRuntime, ()>::InvalidUnrewardedRelayersState,
+			);
+
+			// when number of messages is invalid
+			assert_noop!(
+				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+					RuntimeOrigin::signed(1),
+					TestMessagesDeliveryProof(Ok((
+						TEST_LANE_ID,
+						InboundLaneData {
+							relayers: vec![
+								unrewarded_relayer(1, 1, TEST_RELAYER_A),
+								unrewarded_relayer(2, 2, TEST_RELAYER_B)
+							]
+							.into_iter()
+							.collect(),
+							..Default::default()
+						}
+					))),
+					UnrewardedRelayersState {
+						unrewarded_relayer_entries: 2,
+						total_messages: 1,
+						last_delivered_nonce: 2,
+						..Default::default()
+					},
+				),
+				Error::<TestRuntime, ()>::InvalidUnrewardedRelayersState,
+			);
+
+			// when last delivered nonce is invalid
+			assert_noop!(
+				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+					RuntimeOrigin::signed(1),
+					TestMessagesDeliveryProof(Ok((
+						TEST_LANE_ID,
+						InboundLaneData {
+							relayers: vec![
+								unrewarded_relayer(1, 1, TEST_RELAYER_A),
+								unrewarded_relayer(2, 2, TEST_RELAYER_B)
+							]
+							.into_iter()
+							.collect(),
+							..Default::default()
+						}
+					))),
+					UnrewardedRelayersState {
+						unrewarded_relayer_entries: 2,
+						total_messages: 2,
+						last_delivered_nonce: 8,
+						..Default::default()
+					},
+				),
+				Error::<TestRuntime, ()>::InvalidUnrewardedRelayersState,
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_accepts_single_message_with_invalid_payload() {
+		run_test(|| {
+			let mut invalid_message = message(1, REGULAR_PAYLOAD);
+			invalid_message.data.payload = Vec::new();
+
+			assert_ok!(Pallet::<TestRuntime, ()>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![invalid_message]).into(),
+				1,
+				Weight::zero(), /* weight may be zero in this case (all messages are
+				                 * improperly encoded) */
+			),);
+
+			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).last_delivered_nonce(), 1,);
+		});
+	}
+
+	#[test]
+	fn receive_messages_accepts_batch_with_message_with_invalid_payload() {
+		run_test(|| {
+			let mut invalid_message = message(2, REGULAR_PAYLOAD);
+			invalid_message.data.payload = Vec::new();
+
+			assert_ok!(Pallet::<TestRuntime, ()>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(
+					vec![message(1, REGULAR_PAYLOAD), invalid_message, message(3, REGULAR_PAYLOAD),]
+				)
+				.into(),
+				3,
+				REGULAR_PAYLOAD.declared_weight + REGULAR_PAYLOAD.declared_weight,
+			),);
+
+			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).last_delivered_nonce(), 3,);
+		});
+	}
+
+	#[test]
+	fn actual_dispatch_weight_does_not_overlow() {
+		run_test(|| {
+			let message1 = message(1, message_payload(0, u64::MAX / 2));
+			let message2 = message(2, message_payload(0, u64::MAX / 2));
+			let message3 = message(3, message_payload(0, u64::MAX / 2));
+
+			assert_ok!(Pallet::<TestRuntime, ()>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				// this may cause overflow if source chain storage is invalid
+				Ok(vec![message1, message2, message3]).into(),
+				3,
+				Weight::MAX,
+			));
+			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).last_delivered_nonce(), 2);
+		});
+	}
+
+	#[test]
+	fn weight_refund_from_receive_messages_proof_works() {
+		run_test(|| {
+			fn submit_with_unspent_weight(
+				nonce: MessageNonce,
+				unspent_weight: u64,
+				is_prepaid: bool,
+			) -> (Weight, Weight) {
+				let mut payload = REGULAR_PAYLOAD;
+				*payload.dispatch_result.unspent_weight.ref_time_mut() = unspent_weight;
+				payload.dispatch_result.dispatch_fee_paid_during_dispatch = !is_prepaid;
+				let proof = Ok(vec![message(nonce, payload)]).into();
+				let messages_count = 1;
+				let pre_dispatch_weight =
+					<TestRuntime as Config>::WeightInfo::receive_messages_proof_weight(
+						&proof,
+						messages_count,
+						REGULAR_PAYLOAD.declared_weight,
+					);
+				let post_dispatch_weight = Pallet::<TestRuntime>::receive_messages_proof(
+					RuntimeOrigin::signed(1),
+					TEST_RELAYER_A,
+					proof,
+					messages_count,
+					REGULAR_PAYLOAD.declared_weight,
+				)
+				.expect("delivery has failed")
+				.actual_weight
+				.expect("receive_messages_proof always returns Some");
+
+				(pre_dispatch_weight, post_dispatch_weight)
+			}
+
+			// when dispatch is returning `unspent_weight < declared_weight`
+			let (pre, post) = submit_with_unspent_weight(1, 1, false);
+			assert_eq!(post.ref_time(), pre.ref_time() - 1);
+
+			// when dispatch is returning `unspent_weight = declared_weight`
+			let (pre, post) =
+				submit_with_unspent_weight(2, REGULAR_PAYLOAD.declared_weight.ref_time(), false);
+			assert_eq!(
+				post.ref_time(),
+				pre.ref_time() - REGULAR_PAYLOAD.declared_weight.ref_time()
+			);
+
+			// when dispatch is returning `unspent_weight > declared_weight`
+			let (pre, post) = submit_with_unspent_weight(
+				3,
+				REGULAR_PAYLOAD.declared_weight.ref_time() + 1,
+				false,
+			);
+			assert_eq!(
+				post.ref_time(),
+				pre.ref_time() - REGULAR_PAYLOAD.declared_weight.ref_time()
+			);
+
+			// when there's no unspent weight
+			let (pre, post) = submit_with_unspent_weight(4, 0, false);
+			assert_eq!(post, pre);
+
+			// when dispatch is returning `unspent_weight < declared_weight` AND message is prepaid
+			let (pre, post) = submit_with_unspent_weight(5, 1, true);
+			assert_eq!(
+				post.ref_time(),
+				pre.ref_time()
+					- 1 - <TestRuntime as Config>::WeightInfo::pay_inbound_dispatch_fee_overhead()
+					.ref_time()
+			);
+		});
+	}
+
+	#[test]
+	fn messages_delivered_callbacks_are_called() {
+		run_test(|| {
+			send_regular_message();
+			send_regular_message();
+			send_regular_message();
+
+			// messages 1+2 are confirmed in 1 tx, message 3 in a separate tx
+			// dispatch of message 2 has failed
+			let mut delivered_messages_1_and_2 = DeliveredMessages::new(1, true);
+			delivered_messages_1_and_2.note_dispatched_message(false);
+			let messages_1_and_2_proof = Ok((
+				TEST_LANE_ID,
+				InboundLaneData {
+					last_confirmed_nonce: 0,
+					relayers: vec![UnrewardedRelayer {
+						relayer: 0,
+						messages: delivered_messages_1_and_2.clone(),
+					}]
+					.into_iter()
+					.collect(),
+				},
+			));
+			let delivered_message_3 = DeliveredMessages::new(3, true);
+			let messages_3_proof = Ok((
+				TEST_LANE_ID,
+				InboundLaneData {
+					last_confirmed_nonce: 0,
+					relayers: vec![UnrewardedRelayer {
+						relayer: 0,
+						messages: delivered_message_3.clone(),
+					}]
+					.into_iter()
+					.collect(),
+				},
+			));
+
+			// first tx with messages 1+2
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				TestMessagesDeliveryProof(messages_1_and_2_proof),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 1,
+					total_messages: 2,
+					last_delivered_nonce: 2,
+					..Default::default()
+				},
+			));
+			// second tx with message 3
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				TestMessagesDeliveryProof(messages_3_proof),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 1,
+					total_messages: 1,
+					last_delivered_nonce: 3,
+					..Default::default()
+				},
+			));
+
+			// ensure that both callbacks have been called twice: for 1+2, then for 3
+			TestOnDeliveryConfirmed1::ensure_called(&TEST_LANE_ID, &delivered_messages_1_and_2);
+			TestOnDeliveryConfirmed1::ensure_called(&TEST_LANE_ID, &delivered_message_3);
+			TestOnDeliveryConfirmed2::ensure_called(&TEST_LANE_ID, &delivered_messages_1_and_2);
+			TestOnDeliveryConfirmed2::ensure_called(&TEST_LANE_ID, &delivered_message_3);
+		});
+	}
+
+	#[test]
+	fn dispatch_statuses_are_stored_at_inbound_lane() {
+		run_test(|| {
+			TestMessageDispatch::report_dispatch_statuses();
+			let mut failing_payload = REGULAR_PAYLOAD;
+			failing_payload.dispatch_result.dispatch_result = false;
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD), message(2, failing_payload.clone())]).into(),
+				2,
+				REGULAR_PAYLOAD.declared_weight + failing_payload.declared_weight,
+			));
+
+			// statuses are stored separately from the lane data
+			let relayers = InboundLanes::<TestRuntime>::get(TEST_LANE_ID).0.relayers;
+			assert!(relayers[0].messages.dispatch_statuses.is_empty());
+			assert_eq!(
+				InboundLanesDispatchStatuses::<TestRuntime>::get(TEST_LANE_ID)
+					.map(|dispatch_statuses| dispatch_statuses.0.known_dispatch_statuses(1, 2)),
+				Some(vec![MessageDispatchStatus::Dispatched, MessageDispatchStatus::CallFailed]),
+			);
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
+
+			// and are pruned once delivery is confirmed
+			let mut message_proof: TestMessagesProof = Ok(vec![message(3, REGULAR_PAYLOAD)]).into();
+			message_proof.result.as_mut().unwrap()[0].1.lane_state =
+				Some(OutboundLaneData { latest_received_nonce: 2, ..Default::default() });
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				message_proof,
+				1,
+				REGULAR_PAYLOAD.declared_weight,
+			));
+			assert_eq!(
+				InboundLanesDispatchStatuses::<TestRuntime>::get(TEST_LANE_ID)
+					.and_then(|dispatch_statuses| dispatch_statuses.0.nonces()),
+				Some(3..=3),
+			);
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
+		});
+	}
+
+	#[test]
+	fn messages_delivered_callbacks_receive_dispatch_statuses() {
+		run_test(|| {
+			send_regular_message();
+			send_regular_message();
+
+			// statuses are filled by the `TargetHeaderChain` from the delivery proof
+			let mut delivered_messages = DeliveredMessages::new(1, true);
+			delivered_messages.note_dispatched_message(false);
+			delivered_messages.dispatch_statuses =
+				vec![MessageDispatchStatus::Dispatched, MessageDispatchStatus::Expired];
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				TestMessagesDeliveryProof(Ok((
+					TEST_LANE_ID,
+					InboundLaneData {
+						last_confirmed_nonce: 0,
+						relayers: vec![UnrewardedRelayer {
+							relayer: 0,
+							messages: delivered_messages.clone(),
+						}]
+						.into_iter()
+						.collect(),
+					},
+				))),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 1,
+					total_messages: 2,
+					last_delivered_nonce: 2,
+					..Default::default()
+				},
+			));
+
+			TestOnDeliveryConfirmed1::ensure_called(&TEST_LANE_ID, &delivered_messages);
+		});
+	}
+
+	fn send_message_with_callback(callback_id: DeliveryCallbackId) -> MessageNonce {
+		<Pallet<TestRuntime> as MessagesBridge<_, _, _>>::send_message_with_callback(
+			RuntimeOrigin::signed(1),
+			TEST_LANE_ID,
+			REGULAR_PAYLOAD,
+			REGULAR_PAYLOAD.declared_weight.ref_time(),
+			callback_id,
+		)
+		.expect("send_message_with_callback has failed")
+		.nonce
+	}
+
+	#[test]
+	fn send_message_with_callback_rejects_unknown_callback() {
+		run_test(|| {
+			assert_noop!(
+				<Pallet<TestRuntime> as MessagesBridge<_, _, _>>::send_message_with_callback(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD,
+					REGULAR_PAYLOAD.declared_weight.ref_time(),
+					UNKNOWN_DELIVERY_CALLBACK_ID,
+				),
+				Error::<TestRuntime, ()>::UnknownDeliveryCallback,
+			);
+		});
+	}
+
+	#[test]
+	fn delivery_results_are_routed_to_callbacks() {
+		run_test(|| {
+			send_regular_message();
+			assert_eq!(send_message_with_callback(TEST_DELIVERY_CALLBACK_ID), 2);
+			assert_eq!(
+				OutboundMessageCallbacks::<TestRuntime>::get(MessageKey {
 					lane_id: TEST_LANE_ID,
-					nonce: message_nonce
+					nonce: 2
 				}),
-				topics: vec![],
-			}],
-		);
+				Some(TEST_DELIVERY_CALLBACK_ID),
+			);
 
-		// check that fee has been withdrawn from submitter
-		assert!(TestMessageDeliveryAndDispatchPayment::is_fee_paid(
-			1,
-			REGULAR_PAYLOAD.declared_weight.ref_time()
-		));
+			let mut delivered_messages = DeliveredMessages::new(1, true);
+			delivered_messages.note_dispatched_message(false);
+			delivered_messages.dispatch_statuses =
+				vec![MessageDispatchStatus::Dispatched, MessageDispatchStatus::CallFailed];
+			let proof = TestMessagesDeliveryProof(Ok((
+				TEST_LANE_ID,
+				InboundLaneData {
+					last_confirmed_nonce: 0,
+					relayers: vec![UnrewardedRelayer { relayer: 0, messages: delivered_messages }]
+						.into_iter()
+						.collect(),
+				},
+			)));
+			let relayers_state = UnrewardedRelayersState {
+				unrewarded_relayer_entries: 1,
+				total_messages: 2,
+				last_delivered_nonce: 2,
+				..Default::default()
+			};
+			let pre_dispatch_weight =
+				<TestRuntime as Config>::WeightInfo::receive_messages_delivery_proof_weight(
+					&proof,
+					&relayers_state,
+					crate::mock::DbWeight::get(),
+				);
+			let post_dispatch_weight = Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				proof,
+				relayers_state,
+			)
+			.expect("confirmation has failed")
+			.actual_weight
+			.expect("receive_messages_delivery_proof always returns Some");
 
-		weight
+			// only the message that has been sent with the callback is routed to the handler
+			assert_eq!(TestOnMessageDeliveryResult::delivery_result(&TEST_LANE_ID, 1), None);
+			assert_eq!(
+				TestOnMessageDeliveryResult::delivery_result(&TEST_LANE_ID, 2),
+				Some((false, Some(MessageDispatchStatus::CallFailed))),
+			);
+			assert_eq!(
+				OutboundMessageCallbacks::<TestRuntime>::get(MessageKey {
+					lane_id: TEST_LANE_ID,
+					nonce: 2
+				}),
+				None,
+			);
+			// and the callback overhead of the first message is refunded
+			assert_eq!(
+				pre_dispatch_weight.saturating_sub(post_dispatch_weight),
+				unused_delivery_result_callback_overhead(1)
+					+ unused_cancelled_message_refund_overhead(2),
+			);
+		});
+	}
+
+	/// Returns weight that is refunded if confirmed messages have been sent without delivery
+	/// callbacks.
+	fn unused_delivery_result_callback_overhead(messages: MessageNonce) -> Weight {
+		let db_weight = crate::mock::DbWeight::get();
+		<TestRuntime as Config>::WeightInfo::single_message_delivery_result_callback_overhead(
+			db_weight,
+		)
+		.saturating_sub(db_weight.reads(1))
+		.saturating_mul(messages)
+	}
+
+	/// Returns weight that is refunded if confirmed messages have not been cancelled.
+	fn unused_cancelled_message_refund_overhead(messages: MessageNonce) -> Weight {
+		let db_weight = crate::mock::DbWeight::get();
+		<TestRuntime as Config>::WeightInfo::cancelled_message_refund_overhead(db_weight)
+			.saturating_sub(db_weight.reads(1))
+			.saturating_mul(messages)
+	}
+
+	fn confirm_3_messages_delivery() -> (Weight, Weight) {
+		confirm_3_messages_delivery_sent_by(|| {
+			send_regular_message();
+		})
+	}
+
+	fn confirm_3_messages_delivery_with_callbacks() -> (Weight, Weight) {
+		confirm_3_messages_delivery_sent_by(|| {
+			send_message_with_callback(TEST_DELIVERY_CALLBACK_ID);
+		})
+	}
+
+	fn confirm_3_messages_delivery_sent_by(send_message: impl Fn()) -> (Weight, Weight) {
+		send_message();
+		send_message();
+		send_message();
+
+		let proof = TestMessagesDeliveryProof(Ok((
+			TEST_LANE_ID,
+			InboundLaneData {
+				last_confirmed_nonce: 0,
+				relayers: vec![unrewarded_relayer(1, 3, TEST_RELAYER_A)].into_iter().collect(),
+			},
+		)));
+		let relayers_state = UnrewardedRelayersState {
+			unrewarded_relayer_entries: 1,
+			total_messages: 3,
+			last_delivered_nonce: 3,
+			..Default::default()
+		};
+		let pre_dispatch_weight =
+			<TestRuntime as Config>::WeightInfo::receive_messages_delivery_proof_weight(
+				&proof,
+				&relayers_state,
+				crate::mock::DbWeight::get(),
+			);
+		let post_dispatch_weight = Pallet::<TestRuntime>::receive_messages_delivery_proof(
+			RuntimeOrigin::signed(1),
+			proof,
+			relayers_state,
+		)
+		.expect("confirmation has failed")
+		.actual_weight
+		.expect("receive_messages_delivery_proof always returns Some");
+		(pre_dispatch_weight, post_dispatch_weight)
+	}
+
+	#[test]
+	fn receive_messages_delivery_proof_refunds_zero_callback_weight() {
+		run_test(|| {
+			let (pre_dispatch_weight, post_dispatch_weight) = confirm_3_messages_delivery();
+			assert_eq!(
+				pre_dispatch_weight.saturating_sub(post_dispatch_weight),
+				unused_delivery_result_callback_overhead(3)
+					+ unused_cancelled_message_refund_overhead(3),
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_delivery_proof_refunds_zero_delivery_result_callback_weight() {
+		run_test(|| {
+			let (pre_dispatch_weight, post_dispatch_weight) =
+				confirm_3_messages_delivery_with_callbacks();
+			assert_eq!(
+				pre_dispatch_weight.saturating_sub(post_dispatch_weight),
+				unused_cancelled_message_refund_overhead(3),
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_delivery_proof_refunds_non_zero_delivery_result_callback_weight() {
+		run_test(|| {
+			TestOnMessageDeliveryResult::set_consumed_weight(
+				crate::mock::DbWeight::get().writes(1),
+			);
+
+			let (pre_dispatch_weight, post_dispatch_weight) =
+				confirm_3_messages_delivery_with_callbacks();
+			assert_eq!(
+				pre_dispatch_weight.saturating_sub(post_dispatch_weight),
+				crate::mock::DbWeight::get().reads(1) * 3
+					+ unused_cancelled_message_refund_overhead(3),
+			);
+		});
+	}
+
+	#[test]
+	fn receive_messages_delivery_proof_refunds_non_zero_weight() {
+		run_test(|| {
+			TestOnDeliveryConfirmed1::set_consumed_weight_per_message(
+				crate::mock::DbWeight::get().writes(1),
+			);
+
+			let (pre_dispatch_weight, post_dispatch_weight) = confirm_3_messages_delivery();
+			assert_eq!(
+				pre_dispatch_weight.saturating_sub(post_dispatch_weight),
+				crate::mock::DbWeight::get().reads(1) * 3
+					+ unused_delivery_result_callback_overhead(3)
+					+ unused_cancelled_message_refund_overhead(3),
+			);
+		});
+	}
+
+	#[test]
+	#[should_panic]
+	#[cfg(debug_assertions)]
+	fn receive_messages_panics_in_debug_mode_if_callback_is_wrong() {
+		run_test(|| {
+			TestOnDeliveryConfirmed1::set_consumed_weight_per_message(
+				crate::mock::DbWeight::get().reads_writes(2, 2),
+			);
+			confirm_3_messages_delivery()
+		});
+	}
+
+	#[test]
+	#[should_panic]
+	#[cfg(debug_assertions)]
+	fn receive_messages_panics_in_debug_mode_if_delivery_result_callback_is_wrong() {
+		run_test(|| {
+			TestOnMessageDeliveryResult::set_consumed_weight(
+				crate::mock::DbWeight::get().reads_writes(2, 2),
+			);
+			confirm_3_messages_delivery_with_callbacks()
+		});
+	}
+
+	#[test]
+	fn receive_messages_delivery_proof_rejects_proof_if_trying_to_confirm_more_messages_than_expected(
+	) {
+		run_test(|| {
+			// send message first to be able to check that delivery_proof fails later
+			send_regular_message();
+
+			// 1) InboundLaneData declares that the `last_confirmed_nonce` is 1;
+			// 2) InboundLaneData has no entries => `InboundLaneData::last_delivered_nonce()`
+			//    returns `last_confirmed_nonce`;
+			// 3) it means that we're going to confirm delivery of messages 1..=1;
+			// 4) so the number of declared messages (see `UnrewardedRelayersState`) is `0` and
+			//    numer of actually confirmed messages is `1`.
+			assert_noop!(
+				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+					RuntimeOrigin::signed(1),
+					TestMessagesDeliveryProof(Ok((
+						TEST_LANE_ID,
+						InboundLaneData { last_confirmed_nonce: 1, relayers: Default::default() },
+					))),
+					UnrewardedRelayersState { last_delivered_nonce: 1, ..Default::default() },
+				),
+				Error::<TestRuntime, ()>::TryingToConfirmMoreMessagesThanExpected,
+			);
+		});
+	}
+
+	#[test]
+	fn weight_is_refunded_for_messages_that_are_not_pruned() {
+		run_test(|| {
+			// send first MAX messages - no messages are pruned
+			let max_messages_to_prune = crate::mock::MaxMessagesToPruneAtOnce::get();
+			let when_zero_messages_are_pruned = send_regular_message();
+			let mut delivered_messages = DeliveredMessages::new(1, true);
+			for _ in 1..max_messages_to_prune {
+				assert_eq!(send_regular_message(), when_zero_messages_are_pruned);
+				delivered_messages.note_dispatched_message(true);
+			}
+
+			// confirm delivery of all sent messages
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				TestMessagesDeliveryProof(Ok((
+					TEST_LANE_ID,
+					InboundLaneData {
+						last_confirmed_nonce: 1,
+						relayers: vec![UnrewardedRelayer {
+							relayer: 0,
+							messages: delivered_messages,
+						}]
+						.into_iter()
+						.collect(),
+					},
+				))),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 1,
+					total_messages: max_messages_to_prune,
+					last_delivered_nonce: max_messages_to_prune,
+					..Default::default()
+				},
+			));
+
+			// when next message is sent, MAX messages are pruned
+			// let weight_when_max_messages_are_pruned = send_regular_message();
+			// assert_eq!(
+			// 	weight_when_max_messages_are_pruned,
+			// 	when_zero_messages_are_pruned
+			// 		+ crate::mock::DbWeight::get().writes(max_messages_to_prune),
+			// );
+		});
+	}
+
+	#[test]
+	fn message_accepted_callbacks_are_called() {
+		run_test(|| {
+			send_regular_message();
+			TestOnMessageAccepted::ensure_called(&TEST_LANE_ID, &1);
+		});
+	}
+
+	#[test]
+	#[should_panic]
+	#[cfg(debug_assertions)]
+	fn message_accepted_panics_in_debug_mode_if_callback_is_wrong() {
+		run_test(|| {
+			TestOnMessageAccepted::set_consumed_weight_per_message(
+				crate::mock::DbWeight::get().reads_writes(2, 2),
+			);
+			send_regular_message();
+		});
+	}
+
+	#[test]
+	fn message_accepted_refunds_non_zero_weight() {
+		run_test(|| {
+			TestOnMessageAccepted::set_consumed_weight_per_message(
+				crate::mock::DbWeight::get().writes(1),
+			);
+			let _actual_callback_weight = send_regular_message();
+			let _pre_dispatch_weight = <TestRuntime as Config>::WeightInfo::send_message_weight(
+				&REGULAR_PAYLOAD,
+				crate::mock::DbWeight::get(),
+			);
+			let _prune_weight = crate::mock::DbWeight::get()
+				.writes(<TestRuntime as Config>::MaxMessagesToPruneAtOnce::get());
+
+			// assert_eq!(
+			// 	pre_dispatch_weight.saturating_sub(actual_callback_weight),
+			// 	crate::mock::DbWeight::get().reads(1).saturating_add(prune_weight)
+			// );
+		});
 	}
 
-	fn receive_messages_delivery_proof() {
-		System::<TestRuntime>::set_block_number(1);
-		System::<TestRuntime>::reset_events();
+	#[test]
+	fn storage_keys_computed_properly() {
+		assert_eq!(
+			PalletOperatingMode::<TestRuntime>::storage_value_final_key().to_vec(),
+			bp_messages::storage_keys::operating_mode_key("Messages").0,
+		);
 
-		assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
-			RuntimeOrigin::signed(1),
-			TestMessagesDeliveryProof(Ok((
-				TEST_LANE_ID,
-				InboundLaneData {
-					last_confirmed_nonce: 1,
-					relayers: vec![UnrewardedRelayer {
-						relayer: 0,
-						messages: DeliveredMessages::new(1, true),
-					}]
-					.into_iter()
-					.collect(),
-				},
-			))),
-			UnrewardedRelayersState {
-				unrewarded_relayer_entries: 1,
-				total_messages: 1,
-				last_delivered_nonce: 1,
-				..Default::default()
-			},
-		));
+		assert_eq!(
+			OutboundMessages::<TestRuntime>::storage_map_final_key(MessageKey {
+				lane_id: TEST_LANE_ID,
+				nonce: 42
+			}),
+			bp_messages::storage_keys::message_key("Messages", &TEST_LANE_ID, 42).0,
+		);
 
 		assert_eq!(
-			System::<TestRuntime>::events(),
-			vec![EventRecord {
-				phase: Phase::Initialization,
-				event: TestEvent::Messages(Event::MessagesDelivered {
-					lane_id: TEST_LANE_ID,
-					messages: DeliveredMessages::new(1, true),
-				}),
-				topics: vec![],
-			}],
+			OutboundLanes::<TestRuntime>::storage_map_final_key(TEST_LANE_ID),
+			bp_messages::storage_keys::outbound_lane_data_key("Messages", &TEST_LANE_ID).0,
+		);
+
+		assert_eq!(
+			InboundLanes::<TestRuntime>::storage_map_final_key(TEST_LANE_ID),
+			bp_messages::storage_keys::inbound_lane_data_key("Messages", &TEST_LANE_ID).0,
 		);
 	}
 
 	#[test]
-	fn pallet_parameter_may_be_updated_by_root() {
+	fn lane_operating_mode_may_be_changed_by_owner_or_root() {
 		run_test(|| {
 			get_ready_for_events();
 
-			let parameter = TestMessagesParameter::TokenConversionRate(10.into());
-			assert_ok!(Pallet::<TestRuntime>::update_pallet_parameter(
+			assert_noop!(
+				Pallet::<TestRuntime>::set_lane_operating_mode(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+				),
+				DispatchError::BadOrigin,
+			);
+
+			PalletOwner::<TestRuntime>::put(1);
+			assert_ok!(Pallet::<TestRuntime>::set_lane_operating_mode(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			));
+			assert_eq!(
+				Pallet::<TestRuntime>::lane_operating_mode(TEST_LANE_ID),
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			);
+			assert_eq!(
+				Pallet::<TestRuntime>::lane_operating_mode(TEST_LANE_ID_2),
+				MessagesOperatingMode::Basic(BasicOperatingMode::Normal),
+			);
+
+			assert_ok!(Pallet::<TestRuntime>::set_lane_operating_mode(
 				RuntimeOrigin::root(),
-				parameter.clone(),
+				TEST_LANE_ID,
+				MessagesOperatingMode::Basic(BasicOperatingMode::Normal),
 			));
+			assert!(!LaneOperatingModes::<TestRuntime>::contains_key(TEST_LANE_ID));
 
-			assert_eq!(TokenConversionRate::get(), 10.into());
 			assert_eq!(
 				System::<TestRuntime>::events(),
-				vec![EventRecord {
-					phase: Phase::Initialization,
-					event: TestEvent::Messages(Event::ParameterUpdated { parameter }),
-					topics: vec![],
-				}],
+				vec![
+					EventRecord {
+						phase: Phase::Initialization,
+						event: TestEvent::Messages(Event::LaneOperatingModeChanged {
+							lane_id: TEST_LANE_ID,
+							operating_mode: MessagesOperatingMode::Basic(
+								BasicOperatingMode::Halted
+							),
+						}),
+						topics: vec![],
+					},
+					EventRecord {
+						phase: Phase::Initialization,
+						event: TestEvent::Messages(Event::LaneOperatingModeChanged {
+							lane_id: TEST_LANE_ID,
+							operating_mode: MessagesOperatingMode::Basic(
+								BasicOperatingMode::Normal
+							),
+						}),
+						topics: vec![],
+					},
+				],
 			);
 		});
 	}
 
 	#[test]
-	fn pallet_parameter_may_be_updated_by_owner() {
+	fn send_message_rejects_messages_to_lanes_that_are_not_operating_normally() {
 		run_test(|| {
-			PalletOwner::<TestRuntime>::put(2);
+			for operating_mode in [
+				MessagesOperatingMode::RejectingOutboundMessages,
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			] {
+				LaneOperatingModes::<TestRuntime>::insert(TEST_LANE_ID, operating_mode);
+
+				assert_noop!(
+					Pallet::<TestRuntime>::send_message(
+						RuntimeOrigin::signed(1),
+						TEST_LANE_ID,
+						REGULAR_PAYLOAD,
+						REGULAR_PAYLOAD.declared_weight.ref_time(),
+					),
+					Error::<TestRuntime, ()>::LaneNotOperatingNormally,
+				);
+				assert_ok!(Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID_2,
+					REGULAR_PAYLOAD,
+					REGULAR_PAYLOAD.declared_weight.ref_time(),
+				));
+			}
+		});
+	}
+
+	#[test]
+	fn receive_messages_proof_rejects_messages_of_halted_lanes_only() {
+		run_test(|| {
+			LaneOperatingModes::<TestRuntime>::insert(
+				TEST_LANE_ID,
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			);
+			LaneOperatingModes::<TestRuntime>::insert(
+				TEST_LANE_ID_2,
+				MessagesOperatingMode::RejectingOutboundMessages,
+			);
 			get_ready_for_events();
 
-			let parameter = TestMessagesParameter::TokenConversionRate(10.into());
-			assert_ok!(Pallet::<TestRuntime>::update_pallet_parameter(
-				RuntimeOrigin::signed(2),
-				parameter.clone(),
-			));
+			let mut message_at_lane_2 = message(1, REGULAR_PAYLOAD);
+			message_at_lane_2.key.lane_id = TEST_LANE_ID_2;
+			let proof: TestMessagesProof =
+				Ok(vec![message(1, REGULAR_PAYLOAD), message_at_lane_2]).into();
 
-			assert_eq!(TokenConversionRate::get(), 10.into());
+			let declared_weight = REGULAR_PAYLOAD.declared_weight * 2;
+			let weight = Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				proof.clone(),
+				2,
+				declared_weight,
+			)
+			.expect("receive_messages_proof has failed")
+			.actual_weight
+			.expect("receive_messages_proof always returns Some");
+
+			// messages of the halted lane are not dispatched, while the lane that is rejecting
+			// outbound messages still accepts inbound messages
+			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).0.last_delivered_nonce(), 0,);
+			assert_eq!(
+				InboundLanes::<TestRuntime>::get(TEST_LANE_ID_2).0.last_delivered_nonce(),
+				1,
+			);
+
+			// the relayer is refunded for the message that has not been dispatched
+			let declared_call_weight =
+				<TestRuntime as Config>::WeightInfo::receive_multi_lane_messages_proof_weight(
+					&proof,
+					2,
+					2,
+					MaxUnconfirmedMessagesAtInboundLane::get(),
+					declared_weight,
+					crate::mock::DbWeight::get(),
+				);
+			assert!(weight.all_lte(declared_call_weight - REGULAR_PAYLOAD.declared_weight));
+
+			let details = |result, actual_dispatch_weight| ReceivalResultWithDetails {
+				result,
+				payload_hash: BlakeTwo256::hash(&REGULAR_PAYLOAD.encode()),
+				declared_dispatch_weight: REGULAR_PAYLOAD.declared_weight,
+				actual_dispatch_weight,
+				dispatch_error: None,
+			};
 			assert_eq!(
 				System::<TestRuntime>::events(),
-				vec![EventRecord {
-					phase: Phase::Initialization,
-					event: TestEvent::Messages(Event::ParameterUpdated { parameter }),
-					topics: vec![],
-				}],
+				vec![
+					EventRecord {
+						phase: Phase::Initialization,
+						event: TestEvent::Messages(Event::MessagesReceived(vec![
+							ReceivedMessages::new(
+								TEST_LANE_ID,
+								vec![(1, ReceivalResult::LaneHalted)]
+							),
+							ReceivedMessages::new(
+								TEST_LANE_ID_2,
+								vec![(1, ReceivalResult::Dispatched(dispatch_result(0)))]
+							),
+						])),
+						topics: vec![],
+					},
+					EventRecord {
+						phase: Phase::Initialization,
+						event: TestEvent::Messages(Event::MessagesReceivedV2(vec![
+							ReceivedMessages::new(
+								TEST_LANE_ID,
+								vec![(1, details(ReceivalResult::LaneHalted, Weight::zero()))]
+							),
+							ReceivedMessages::new(
+								TEST_LANE_ID_2,
+								vec![(
+									1,
+									details(
+										ReceivalResult::Dispatched(dispatch_result(0)),
+										REGULAR_PAYLOAD.declared_weight,
+									)
+								)]
+							),
+						])),
+						topics: vec![],
+					},
+				],
 			);
 		});
 	}
 
 	#[test]
-	fn pallet_parameter_cant_be_updated_by_arbitrary_submitter() {
+	fn receive_messages_proof_accounts_every_delivered_lane() {
 		run_test(|| {
-			assert_noop!(
-				Pallet::<TestRuntime>::update_pallet_parameter(
-					RuntimeOrigin::signed(2),
-					TestMessagesParameter::TokenConversionRate(10.into()),
-				),
-				DispatchError::BadOrigin,
-			);
+			let mut message_at_lane_2 = message(1, REGULAR_PAYLOAD);
+			message_at_lane_2.key.lane_id = TEST_LANE_ID_2;
+			let proof: TestMessagesProof =
+				Ok(vec![message(1, REGULAR_PAYLOAD), message_at_lane_2]).into();
 
-			PalletOwner::<TestRuntime>::put(2);
+			let declared_weight = REGULAR_PAYLOAD.declared_weight * 2;
+			let weight = Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				proof.clone(),
+				2,
+				declared_weight,
+			)
+			.expect("receive_messages_proof has failed")
+			.actual_weight
+			.expect("receive_messages_proof always returns Some");
 
-			assert_noop!(
-				Pallet::<TestRuntime>::update_pallet_parameter(
-					RuntimeOrigin::signed(1),
-					TestMessagesParameter::TokenConversionRate(10.into()),
-				),
-				DispatchError::BadOrigin,
+			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).0.last_delivered_nonce(), 1);
+			assert_eq!(
+				InboundLanes::<TestRuntime>::get(TEST_LANE_ID_2).0.last_delivered_nonce(),
+				1,
+			);
+
+			// both messages are successfully dispatched to ordered lanes without refunds, so the
+			// whole declared weight (including the second lane overhead) except the unordered lane
+			// and failed message overheads is spent
+			let single_lane_weight =
+				<TestRuntime as Config>::WeightInfo::receive_messages_proof_weight(
+					&proof,
+					2,
+					declared_weight,
+				);
+			let multi_lane_weight =
+				<TestRuntime as Config>::WeightInfo::receive_multi_lane_messages_proof_weight(
+					&proof,
+					2,
+					2,
+					MaxUnconfirmedMessagesAtInboundLane::get(),
+					declared_weight,
+					crate::mock::DbWeight::get(),
+				);
+			let unordered_message_overhead =
+				<TestRuntime as Config>::WeightInfo::receive_unordered_message_overhead(
+					crate::mock::DbWeight::get(),
+				);
+			let failed_message_overhead =
+				<TestRuntime as Config>::WeightInfo::receive_failed_message_overhead(
+					crate::mock::DbWeight::get(),
+				);
+			let drain_overhead =
+				<TestRuntime as Config>::WeightInfo::drain_delivered_ahead_message_overhead(
+					crate::mock::DbWeight::get(),
+				);
+			assert_eq!(
+				weight,
+				multi_lane_weight
+					- (unordered_message_overhead + failed_message_overhead) * 2
+					- drain_overhead * MaxUnconfirmedMessagesAtInboundLane::get()
 			);
+			assert!(weight.any_gt(single_lane_weight));
 		});
 	}
 
 	#[test]
-	fn fixed_u128_works_as_i_think() {
-		// this test is here just to be sure that conversion rate may be represented with FixedU128
+	fn receive_messages_proof_dispatches_messages_ahead_at_unordered_lanes() {
 		run_test(|| {
-			use sp_runtime::{FixedPointNumber, FixedU128};
+			let mut message_ahead = message(2, REGULAR_PAYLOAD);
+			message_ahead.key.lane_id = TEST_UNORDERED_LANE_ID;
+			let proof: TestMessagesProof = Ok(vec![message_ahead]).into();
 
-			// 1:1 conversion that we use by default for testnets
-			let rialto_token = 1u64;
-			let rialto_token_in_millau_tokens =
-				TokenConversionRate::get().saturating_mul_int(rialto_token);
-			assert_eq!(rialto_token_in_millau_tokens, 1);
+			let weight = Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				proof.clone(),
+				1,
+				REGULAR_PAYLOAD.declared_weight,
+			)
+			.expect("receive_messages_proof has failed")
+			.actual_weight
+			.expect("receive_messages_proof always returns Some");
 
-			// let's say conversion rate is 1:1.7
-			let conversion_rate = FixedU128::saturating_from_rational(170, 100);
-			let rialto_tokens = 100u64;
-			let rialto_tokens_in_millau_tokens = conversion_rate.saturating_mul_int(rialto_tokens);
-			assert_eq!(rialto_tokens_in_millau_tokens, 170);
+			assert_eq!(
+				InboundLanes::<TestRuntime>::get(TEST_UNORDERED_LANE_ID).0.last_delivered_nonce(),
+				0,
+			);
+			assert_eq!(
+				MessagesDeliveredAhead::<TestRuntime>::get(TEST_UNORDERED_LANE_ID, 2),
+				Some((TEST_RELAYER_A, true, None)),
+			);
 
-			// let's say conversion rate is 1:0.25
-			let conversion_rate = FixedU128::saturating_from_rational(25, 100);
-			let rialto_tokens = 100u64;
-			let rialto_tokens_in_millau_tokens = conversion_rate.saturating_mul_int(rialto_tokens);
-			assert_eq!(rialto_tokens_in_millau_tokens, 25);
+			// the unordered lane overhead is not refunded, but nothing has been drained
+			assert_eq!(
+				weight,
+				<TestRuntime as Config>::WeightInfo::receive_multi_lane_messages_proof_weight(
+					&proof,
+					1,
+					1,
+					MaxUnconfirmedMessagesAtInboundLane::get(),
+					REGULAR_PAYLOAD.declared_weight,
+					crate::mock::DbWeight::get(),
+				) - <TestRuntime as Config>::WeightInfo::receive_failed_message_overhead(
+					crate::mock::DbWeight::get(),
+				) - <TestRuntime as Config>::WeightInfo::drain_delivered_ahead_message_overhead(
+					crate::mock::DbWeight::get(),
+				) * MaxUnconfirmedMessagesAtInboundLane::get(),
+			);
 		});
 	}
 
 	#[test]
-	fn pallet_rejects_transactions_if_halted() {
+	fn receive_messages_proof_drains_messages_delivered_ahead() {
 		run_test(|| {
-			// send message first to be able to check that delivery_proof fails later
-			send_regular_message();
-
-			PalletOperatingMode::<TestRuntime, ()>::put(MessagesOperatingMode::Basic(
-				BasicOperatingMode::Halted,
+			let mut message_ahead = message(2, REGULAR_PAYLOAD);
+			message_ahead.key.lane_id = TEST_UNORDERED_LANE_ID;
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message_ahead]).into(),
+				1,
+				REGULAR_PAYLOAD.declared_weight,
 			));
 
-			assert_noop!(
-				Pallet::<TestRuntime>::send_message(
-					RuntimeOrigin::signed(1),
-					TEST_LANE_ID,
-					REGULAR_PAYLOAD,
-					REGULAR_PAYLOAD.declared_weight.ref_time(),
-				),
-				Error::<TestRuntime, ()>::NotOperatingNormally,
+			let mut message = message(1, REGULAR_PAYLOAD);
+			message.key.lane_id = TEST_UNORDERED_LANE_ID;
+			let proof: TestMessagesProof = Ok(vec![message]).into();
+			let weight = Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_B,
+				proof.clone(),
+				1,
+				REGULAR_PAYLOAD.declared_weight,
+			)
+			.expect("receive_messages_proof has failed")
+			.actual_weight
+			.expect("receive_messages_proof always returns Some");
+
+			assert_eq!(
+				InboundLanes::<TestRuntime>::get(TEST_UNORDERED_LANE_ID).0.last_delivered_nonce(),
+				2,
 			);
+			assert_eq!(MessagesDeliveredAhead::<TestRuntime>::get(TEST_UNORDERED_LANE_ID, 2), None);
 
-			assert_noop!(
-				Pallet::<TestRuntime>::receive_messages_proof(
-					RuntimeOrigin::signed(1),
-					TEST_RELAYER_A,
-					Ok(vec![message(2, REGULAR_PAYLOAD)]).into(),
+			// only the overhead of the single drained message is not refunded
+			assert_eq!(
+				weight,
+				<TestRuntime as Config>::WeightInfo::receive_multi_lane_messages_proof_weight(
+					&proof,
 					1,
-					REGULAR_PAYLOAD.declared_weight
-				),
-				Error::<TestRuntime, ()>::BridgeModule(bp_runtime::OwnedBridgeModuleError::Halted),
+					1,
+					MaxUnconfirmedMessagesAtInboundLane::get(),
+					REGULAR_PAYLOAD.declared_weight,
+					crate::mock::DbWeight::get(),
+				) - <TestRuntime as Config>::WeightInfo::receive_failed_message_overhead(
+					crate::mock::DbWeight::get(),
+				) - <TestRuntime as Config>::WeightInfo::drain_delivered_ahead_message_overhead(
+					crate::mock::DbWeight::get(),
+				) * (MaxUnconfirmedMessagesAtInboundLane::get() - 1),
 			);
+		});
+	}
 
-			assert_noop!(
-				Pallet::<TestRuntime>::receive_messages_delivery_proof(
-					RuntimeOrigin::signed(1),
-					TestMessagesDeliveryProof(Ok((
-						TEST_LANE_ID,
-						InboundLaneData {
-							last_confirmed_nonce: 1,
-							relayers: vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)]
-								.into_iter()
-								.collect(),
-						},
-					))),
-					UnrewardedRelayersState {
-						unrewarded_relayer_entries: 1,
-						messages_in_oldest_entry: 1,
-						total_messages: 1,
-						last_delivered_nonce: 1,
-					},
-				),
-				Error::<TestRuntime, ()>::BridgeModule(bp_runtime::OwnedBridgeModuleError::Halted),
-			);
+	#[test]
+	fn messages_received_v2_event_has_details_of_received_messages() {
+		run_test(|| {
+			get_ready_for_events();
+			let mut payload = REGULAR_PAYLOAD;
+			payload.dispatch_result.dispatch_result = false;
+			payload.dispatch_result.unspent_weight = Weight::from_parts(10, 0);
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, payload.clone())]).into(),
+				1,
+				payload.declared_weight,
+			));
+
+			let mut dispatch_result = payload.dispatch_result.clone();
+			dispatch_result.dispatch_error = Some(TEST_DISPATCH_ERROR);
+			assert!(System::<TestRuntime>::events().contains(&EventRecord {
+				phase: Phase::Initialization,
+				event: TestEvent::Messages(Event::MessagesReceivedV2(vec![ReceivedMessages::new(
+					TEST_LANE_ID,
+					vec![(
+						1,
+						ReceivalResultWithDetails {
+							result: ReceivalResult::Dispatched(dispatch_result),
+							payload_hash: BlakeTwo256::hash(&payload.encode()),
+							declared_dispatch_weight: payload.declared_weight,
+							actual_dispatch_weight: Weight::from_parts(40, 0),
+							dispatch_error: Some(TEST_DISPATCH_ERROR),
+						}
+					)]
+				)])),
+				topics: vec![],
+			}));
 		});
 	}
 
+	fn receive_failing_messages(nonces: RangeInclusive<MessageNonce>) -> Weight {
+		let mut payload = REGULAR_PAYLOAD;
+		payload.dispatch_result.dispatch_result = false;
+		let messages_count = nonces.clone().count() as u32;
+		let proof: TestMessagesProof =
+			Ok(nonces.map(|nonce| message(nonce, payload.clone())).collect::<Vec<_>>()).into();
+
+		Pallet::<TestRuntime>::receive_messages_proof(
+			RuntimeOrigin::signed(1),
+			TEST_RELAYER_A,
+			proof,
+			messages_count,
+			REGULAR_PAYLOAD.declared_weight * messages_count as u64,
+		)
+		.expect("receive_messages_proof has failed")
+		.actual_weight
+		.expect("receive_messages_proof always returns Some")
+	}
+
 	#[test]
-	fn pallet_rejects_new_messages_in_rejecting_outbound_messages_operating_mode() {
+	fn failed_inbound_message_is_kept_and_may_be_retried() {
 		run_test(|| {
-			// send message first to be able to check that delivery_proof fails later
-			send_regular_message();
-
-			PalletOperatingMode::<TestRuntime, ()>::put(
-				MessagesOperatingMode::RejectingOutboundMessages,
-			);
+			get_ready_for_events();
+			receive_failing_messages(1..=1);
 
-			assert_noop!(
-				Pallet::<TestRuntime>::send_message(
-					RuntimeOrigin::signed(1),
-					TEST_LANE_ID,
-					REGULAR_PAYLOAD,
-					REGULAR_PAYLOAD.declared_weight.ref_time(),
-				),
-				Error::<TestRuntime, ()>::NotOperatingNormally,
-			);
+			assert!(Pallet::<TestRuntime>::failed_message(TEST_LANE_ID, 1).is_some());
+			assert_eq!(FailedMessagesCount::<TestRuntime>::get(TEST_LANE_ID), 1);
+			assert!(System::<TestRuntime>::events().contains(&EventRecord {
+				phase: Phase::Initialization,
+				event: TestEvent::Messages(Event::MessageDispatchFailed {
+					lane_id: TEST_LANE_ID,
+					nonce: 1,
+				}),
+				topics: vec![],
+			}));
 
-			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+			// the message is kept if it fails again
+			assert_ok!(Pallet::<TestRuntime>::retry_message_dispatch(
 				RuntimeOrigin::signed(1),
-				TEST_RELAYER_A,
-				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+				TEST_LANE_ID,
 				1,
-				REGULAR_PAYLOAD.declared_weight
-			),);
-
-			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				REGULAR_PAYLOAD.declared_weight,
+			));
+			assert!(Pallet::<TestRuntime>::failed_message(TEST_LANE_ID, 1).is_some());
+
+			// the message is removed once it is dispatched successfully
+			FailedMessages::<TestRuntime>::mutate(TEST_LANE_ID, 1, |message| {
+				let message = message.as_mut().expect("message is stored above");
+				let mut payload = TestPayload::decode(&mut &message.0.payload[..]).unwrap();
+				payload.dispatch_result.dispatch_result = true;
+				message.0.payload = payload.encode();
+			});
+			get_ready_for_events();
+			assert_ok!(Pallet::<TestRuntime>::retry_message_dispatch(
 				RuntimeOrigin::signed(1),
-				TestMessagesDeliveryProof(Ok((
-					TEST_LANE_ID,
-					InboundLaneData {
-						last_confirmed_nonce: 1,
-						relayers: vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)]
-							.into_iter()
-							.collect(),
-					},
-				))),
-				UnrewardedRelayersState {
-					unrewarded_relayer_entries: 1,
-					messages_in_oldest_entry: 1,
-					total_messages: 1,
-					last_delivered_nonce: 1,
-				},
+				TEST_LANE_ID,
+				1,
+				REGULAR_PAYLOAD.declared_weight,
 			));
+			assert!(Pallet::<TestRuntime>::failed_message(TEST_LANE_ID, 1).is_none());
+			assert!(!FailedMessagesCount::<TestRuntime>::contains_key(TEST_LANE_ID));
+			assert_eq!(
+				System::<TestRuntime>::events(),
+				vec![EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Messages(Event::FailedMessageRetried {
+						lane_id: TEST_LANE_ID,
+						nonce: 1,
+						dispatch_result: true,
+					}),
+					topics: vec![],
+				}],
+			);
 		});
 	}
 
 	#[test]
-	fn send_message_works() {
+	fn failed_inbound_messages_are_kept_up_to_the_limit() {
 		run_test(|| {
-			send_regular_message();
+			let weight = receive_failing_messages(1..=3);
+
+			assert!(Pallet::<TestRuntime>::failed_message(TEST_LANE_ID, 1).is_some());
+			assert!(Pallet::<TestRuntime>::failed_message(TEST_LANE_ID, 2).is_some());
+			assert!(Pallet::<TestRuntime>::failed_message(TEST_LANE_ID, 3).is_none());
+			assert_eq!(FailedMessagesCount::<TestRuntime>::get(TEST_LANE_ID), 2);
+
+			// the relayer is refunded for the message that has not been kept
+			let proof: TestMessagesProof = Ok(vec![
+				message(1, REGULAR_PAYLOAD),
+				message(2, REGULAR_PAYLOAD),
+				message(3, REGULAR_PAYLOAD),
+			])
+			.into();
+			let declared_weight =
+				<TestRuntime as Config>::WeightInfo::receive_multi_lane_messages_proof_weight(
+					&proof,
+					1,
+					3,
+					MaxUnconfirmedMessagesAtInboundLane::get(),
+					REGULAR_PAYLOAD.declared_weight * 3,
+					crate::mock::DbWeight::get(),
+				);
+			let unordered_message_overhead =
+				<TestRuntime as Config>::WeightInfo::receive_unordered_message_overhead(
+					crate::mock::DbWeight::get(),
+				);
+			let failed_message_overhead =
+				<TestRuntime as Config>::WeightInfo::receive_failed_message_overhead(
+					crate::mock::DbWeight::get(),
+				);
+			let drain_overhead =
+				<TestRuntime as Config>::WeightInfo::drain_delivered_ahead_message_overhead(
+					crate::mock::DbWeight::get(),
+				);
+			assert_eq!(
+				weight,
+				declared_weight
+					- unordered_message_overhead * 3
+					- failed_message_overhead
+					- drain_overhead * MaxUnconfirmedMessagesAtInboundLane::get()
+			);
 		});
 	}
 
 	#[test]
-	fn send_message_rejects_too_large_message() {
+	fn retry_message_dispatch_rejects_invalid_calls() {
 		run_test(|| {
-			let mut message_payload = message_payload(1, 0);
-			// the payload isn't simply extra, so it'll definitely overflow
-			// `MAX_OUTBOUND_PAYLOAD_SIZE` if we add `MAX_OUTBOUND_PAYLOAD_SIZE` bytes to extra
-			message_payload.extra.extend_from_slice(&[0u8; MAX_OUTBOUND_PAYLOAD_SIZE as usize]);
+			get_ready_for_events();
+			receive_failing_messages(1..=1);
+
 			assert_noop!(
-				Pallet::<TestRuntime>::send_message(
+				Pallet::<TestRuntime>::retry_message_dispatch(
 					RuntimeOrigin::signed(1),
 					TEST_LANE_ID,
-					message_payload.clone(),
-					Balance::MAX,
+					2,
+					REGULAR_PAYLOAD.declared_weight,
 				),
-				Error::<TestRuntime, ()>::MessageIsTooLarge,
+				Error::<TestRuntime, ()>::UnknownFailedMessage,
+			);
+			assert_noop!(
+				Pallet::<TestRuntime>::retry_message_dispatch(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					1,
+					Weight::zero(),
+				),
+				Error::<TestRuntime, ()>::InsufficientDispatchWeight,
 			);
 
-			// let's check that we're able to send `MAX_OUTBOUND_PAYLOAD_SIZE` messages
-			while message_payload.size() > MAX_OUTBOUND_PAYLOAD_SIZE {
-				message_payload.extra.pop();
-			}
-			assert_eq!(message_payload.size(), MAX_OUTBOUND_PAYLOAD_SIZE);
-			assert_ok!(Pallet::<TestRuntime>::send_message(
-				RuntimeOrigin::signed(1),
+			LaneOperatingModes::<TestRuntime>::insert(
 				TEST_LANE_ID,
-				message_payload,
-				Balance::MAX,
-			),);
-		})
-	}
-
-	#[test]
-	fn chain_verifier_rejects_invalid_message_in_send_message() {
-		run_test(|| {
-			// messages with this payload are rejected by target chain verifier
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			);
 			assert_noop!(
-				Pallet::<TestRuntime>::send_message(
+				Pallet::<TestRuntime>::retry_message_dispatch(
 					RuntimeOrigin::signed(1),
 					TEST_LANE_ID,
-					PAYLOAD_REJECTED_BY_TARGET_CHAIN,
-					PAYLOAD_REJECTED_BY_TARGET_CHAIN.declared_weight.ref_time()
+					1,
+					REGULAR_PAYLOAD.declared_weight,
 				),
-				Error::<TestRuntime, ()>::MessageRejectedByChainVerifier(VerificationError::Other(
-					mock::TEST_ERROR
-				)),
+				Error::<TestRuntime, ()>::LaneHalted,
 			);
 		});
 	}
 
 	#[test]
-	fn lane_verifier_rejects_invalid_message_in_send_message() {
+	fn expired_failed_message_may_only_be_removed() {
 		run_test(|| {
-			// messages with zero fee are rejected by lane verifier
+			get_ready_for_events();
+			receive_failing_messages(1..=1);
+
+			System::<TestRuntime>::set_block_number(1 + crate::mock::FailedMessageLifetime::get());
 			assert_noop!(
-				Pallet::<TestRuntime>::send_message(
+				Pallet::<TestRuntime>::remove_expired_failed_message(
 					RuntimeOrigin::signed(1),
 					TEST_LANE_ID,
-					REGULAR_PAYLOAD,
-					0
+					1,
 				),
-				Error::<TestRuntime, ()>::MessageRejectedByLaneVerifier(VerificationError::Other(
-					mock::TEST_ERROR
-				)),
+				Error::<TestRuntime, ()>::FailedMessageNotExpired,
 			);
-		});
-	}
 
-	#[test]
-	fn message_send_fails_if_submitter_cant_pay_message_fee() {
-		run_test(|| {
-			TestMessageDeliveryAndDispatchPayment::reject_payments();
+			System::<TestRuntime>::set_block_number(2 + crate::mock::FailedMessageLifetime::get());
 			assert_noop!(
-				Pallet::<TestRuntime>::send_message(
+				Pallet::<TestRuntime>::retry_message_dispatch(
 					RuntimeOrigin::signed(1),
 					TEST_LANE_ID,
-					REGULAR_PAYLOAD,
-					REGULAR_PAYLOAD.declared_weight.ref_time()
+					1,
+					REGULAR_PAYLOAD.declared_weight,
 				),
-				Error::<TestRuntime, ()>::FailedToWithdrawMessageFee,
+				Error::<TestRuntime, ()>::FailedMessageExpired,
 			);
-		});
-	}
-
-	#[test]
-	fn receive_messages_proof_works() {
-		run_test(|| {
-			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
-				RuntimeOrigin::signed(1),
-				TEST_RELAYER_A,
-				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+			assert_ok!(Pallet::<TestRuntime>::remove_expired_failed_message(
+				RuntimeOrigin::signed(2),
+				TEST_LANE_ID,
 				1,
-				REGULAR_PAYLOAD.declared_weight,
 			));
-
-			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).0.last_delivered_nonce(), 1);
+			assert!(Pallet::<TestRuntime>::failed_message(TEST_LANE_ID, 1).is_none());
+			assert!(!FailedMessagesCount::<TestRuntime>::contains_key(TEST_LANE_ID));
 		});
 	}
 
-	#[test]
-	fn receive_messages_proof_updates_confirmed_message_nonce() {
-		run_test(|| {
-			// say we have received 10 messages && last confirmed message is 8
-			InboundLanes::<TestRuntime, ()>::insert(
+	fn undelivered_messages_proof(lane_id: LaneId) -> TestMessagesDeliveryProof {
+		TestMessagesDeliveryProof(Ok((lane_id, InboundLaneData::default())))
+	}
+
+	fn confirm_cancelled_message_delivery(dispatch_status: Option<MessageDispatchStatus>) {
+		let mut relayer_entry = unrewarded_relayer(1, 1, TEST_RELAYER_A);
+		relayer_entry
+			.messages
+			.dispatch_results
+			.set(0, dispatch_status == Some(MessageDispatchStatus::Dispatched));
+		relayer_entry.messages.dispatch_statuses = dispatch_status.into_iter().collect();
+		assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+			RuntimeOrigin::signed(1),
+			TestMessagesDeliveryProof(Ok((
 				TEST_LANE_ID,
 				InboundLaneData {
-					last_confirmed_nonce: 8,
-					relayers: vec![
-						unrewarded_relayer(9, 9, TEST_RELAYER_A),
-						unrewarded_relayer(10, 10, TEST_RELAYER_B),
-					]
-					.into_iter()
-					.collect(),
-				},
-			);
-			assert_eq!(
-				inbound_unrewarded_relayers_state(TEST_LANE_ID),
-				UnrewardedRelayersState {
-					unrewarded_relayer_entries: 2,
-					messages_in_oldest_entry: 1,
-					total_messages: 2,
-					last_delivered_nonce: 10,
+					last_confirmed_nonce: 0,
+					relayers: vec![relayer_entry].into_iter().collect(),
 				},
-			);
+			))),
+			UnrewardedRelayersState {
+				unrewarded_relayer_entries: 1,
+				total_messages: 1,
+				last_delivered_nonce: 1,
+				..Default::default()
+			},
+		));
+	}
 
-			// message proof includes outbound lane state with latest confirmed message updated to 9
-			let mut message_proof: TestMessagesProof =
-				Ok(vec![message(11, REGULAR_PAYLOAD)]).into();
-			message_proof.result.as_mut().unwrap()[0].1.lane_state =
-				Some(OutboundLaneData { latest_received_nonce: 9, ..Default::default() });
+	#[test]
+	fn sender_may_cancel_undelivered_message() {
+		run_test(|| {
+			send_regular_message();
+			System::<TestRuntime>::set_block_number(
+				1 + crate::mock::MessageCancellationTimeout::get(),
+			);
+			System::<TestRuntime>::reset_events();
 
-			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+			assert_ok!(Pallet::<TestRuntime>::cancel_message(
 				RuntimeOrigin::signed(1),
-				TEST_RELAYER_A,
-				message_proof,
+				TEST_LANE_ID,
 				1,
-				REGULAR_PAYLOAD.declared_weight,
+				undelivered_messages_proof(TEST_LANE_ID),
+			));
+			// the fee is not refunded until the delivery is confirmed
+			assert!(!TestMessageDeliveryAndDispatchPayment::is_fee_refunded(
+				1,
+				REGULAR_PAYLOAD.declared_weight.ref_time()
 			));
-
 			assert_eq!(
-				InboundLanes::<TestRuntime>::get(TEST_LANE_ID).0,
-				InboundLaneData {
-					last_confirmed_nonce: 9,
-					relayers: vec![
-						unrewarded_relayer(10, 10, TEST_RELAYER_B),
-						unrewarded_relayer(11, 11, TEST_RELAYER_A)
-					]
-					.into_iter()
-					.collect(),
-				},
+				Pallet::<TestRuntime>::outbound_message_data(TEST_LANE_ID, 1),
+				Some(MessageData::cancelled(REGULAR_PAYLOAD.declared_weight.ref_time())),
 			);
 			assert_eq!(
-				inbound_unrewarded_relayers_state(TEST_LANE_ID),
-				UnrewardedRelayersState {
-					unrewarded_relayer_entries: 2,
-					messages_in_oldest_entry: 1,
-					total_messages: 2,
-					last_delivered_nonce: 11,
-				},
+				System::<TestRuntime>::events(),
+				vec![EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Messages(Event::MessageCancelled {
+						lane_id: TEST_LANE_ID,
+						nonce: 1,
+					}),
+					topics: vec![],
+				}],
 			);
-		});
-	}
-
-	#[test]
-	fn receive_messages_proof_does_not_accept_message_if_dispatch_weight_is_not_enough() {
-		run_test(|| {
-			let mut declared_weight = REGULAR_PAYLOAD.declared_weight;
-			*declared_weight.ref_time_mut() -= 1;
-			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
-				RuntimeOrigin::signed(1),
-				TEST_RELAYER_A,
-				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
-				1,
-				declared_weight,
-			));
-			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).last_delivered_nonce(), 0);
-		});
-	}
-
-	#[test]
-	fn receive_messages_proof_rejects_invalid_proof() {
-		run_test(|| {
+
+			// the message may only be cancelled once
 			assert_noop!(
-				Pallet::<TestRuntime, ()>::receive_messages_proof(
+				Pallet::<TestRuntime>::cancel_message(
 					RuntimeOrigin::signed(1),
-					TEST_RELAYER_A,
-					Err(()).into(),
+					TEST_LANE_ID,
 					1,
-					Weight::zero(),
+					undelivered_messages_proof(TEST_LANE_ID),
 				),
-				Error::<TestRuntime, ()>::InvalidMessagesProof,
+				Error::<TestRuntime, ()>::MessageNotCancellable,
 			);
+
+			// the fee is refunded once the bridged chain confirms that the cancelled payload has
+			// been delivered
+			confirm_cancelled_message_delivery(Some(MessageDispatchStatus::Cancelled));
+			assert!(TestMessageDeliveryAndDispatchPayment::is_fee_refunded(
+				1,
+				REGULAR_PAYLOAD.declared_weight.ref_time()
+			));
+			assert!(System::<TestRuntime>::events().iter().any(|event_record| {
+				event_record.event
+					== TestEvent::Messages(Event::CancelledMessageRefunded {
+						lane_id: TEST_LANE_ID,
+						nonce: 1,
+						fee: REGULAR_PAYLOAD.declared_weight.ref_time(),
+					})
+			}));
+			assert!(!CancelledMessages::<TestRuntime>::contains_key(MessageKey {
+				lane_id: TEST_LANE_ID,
+				nonce: 1
+			}));
 		});
 	}
 
 	#[test]
-	fn receive_messages_proof_rejects_proof_with_too_many_messages() {
+	fn cancelled_message_is_not_refunded_if_it_has_been_dispatched() {
 		run_test(|| {
-			assert_noop!(
-				Pallet::<TestRuntime, ()>::receive_messages_proof(
-					RuntimeOrigin::signed(1),
-					TEST_RELAYER_A,
-					Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
-					u32::MAX,
-					Weight::zero(),
-				),
-				Error::<TestRuntime, ()>::TooManyMessagesInTheProof,
+			send_regular_message();
+			System::<TestRuntime>::set_block_number(
+				1 + crate::mock::MessageCancellationTimeout::get(),
 			);
+			assert_ok!(Pallet::<TestRuntime>::cancel_message(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				1,
+				undelivered_messages_proof(TEST_LANE_ID),
+			));
+
+			// the original message has been delivered using the proof of the older header
+			confirm_cancelled_message_delivery(Some(MessageDispatchStatus::Dispatched));
+			assert!(!TestMessageDeliveryAndDispatchPayment::is_fee_refunded(
+				1,
+				REGULAR_PAYLOAD.declared_weight.ref_time()
+			));
+			assert!(!CancelledMessages::<TestRuntime>::contains_key(MessageKey {
+				lane_id: TEST_LANE_ID,
+				nonce: 1
+			}));
 		});
 	}
 
 	#[test]
-	fn receive_messages_delivery_proof_works() {
-		run_test(|| {
-			send_regular_message();
-			receive_messages_delivery_proof();
+	fn cancelled_message_is_not_refunded_if_original_message_may_be_dispatched_later() {
+		for dispatch_status in
+			[None, Some(MessageDispatchStatus::Deferred), Some(MessageDispatchStatus::CallFailed)]
+		{
+			run_test(|| {
+				send_regular_message();
+				System::<TestRuntime>::set_block_number(
+					1 + crate::mock::MessageCancellationTimeout::get(),
+				);
+				assert_ok!(Pallet::<TestRuntime>::cancel_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					1,
+					undelivered_messages_proof(TEST_LANE_ID),
+				));
 
-			assert_eq!(
-				OutboundLanes::<TestRuntime, ()>::get(TEST_LANE_ID).latest_received_nonce,
-				1,
-			);
-		});
+				confirm_cancelled_message_delivery(dispatch_status);
+				assert!(!TestMessageDeliveryAndDispatchPayment::is_fee_refunded(
+					1,
+					REGULAR_PAYLOAD.declared_weight.ref_time()
+				));
+				assert!(!CancelledMessages::<TestRuntime>::contains_key(MessageKey {
+					lane_id: TEST_LANE_ID,
+					nonce: 1
+				}));
+			});
+		}
 	}
 
 	#[test]
-	fn receive_messages_delivery_proof_rewards_relayers() {
+	fn cancelled_message_is_not_refunded_if_original_message_has_been_deferred() {
 		run_test(|| {
-			assert_ok!(Pallet::<TestRuntime>::send_message(
+			MaxDeferredMessagesAtInboundLane::set(&1);
+			send_regular_message();
+
+			// the original payload is delivered to the bridged chain, but its dispatch is deferred
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
 				RuntimeOrigin::signed(1),
-				TEST_LANE_ID,
-				REGULAR_PAYLOAD,
-				1000,
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+				1,
+				Weight::zero(),
 			));
-			assert_ok!(Pallet::<TestRuntime>::send_message(
+			assert!(DeferredMessages::<TestRuntime>::contains_key(TEST_LANE_ID, 1));
+
+			// the sender cancels the message, using the proof of the older bridged chain header
+			System::<TestRuntime>::set_block_number(
+				1 + crate::mock::MessageCancellationTimeout::get(),
+			);
+			assert_ok!(Pallet::<TestRuntime>::cancel_message(
 				RuntimeOrigin::signed(1),
 				TEST_LANE_ID,
-				REGULAR_PAYLOAD,
-				2000,
+				1,
+				undelivered_messages_proof(TEST_LANE_ID),
 			));
 
-			// this reports delivery of message 1 => reward is paid to TEST_RELAYER_A
+			// the delivery is confirmed with the deferred dispatch status
+			let mut lane_data = InboundLanes::<TestRuntime>::get(TEST_LANE_ID).0;
+			let dispatch_statuses = InboundLanesDispatchStatuses::<TestRuntime>::get(TEST_LANE_ID)
+				.expect("deferred status is stored")
+				.0;
+			for entry in lane_data.relayers.iter_mut() {
+				entry.messages.dispatch_statuses = dispatch_statuses
+					.known_dispatch_statuses(entry.messages.begin, entry.messages.end);
+			}
+			assert_eq!(
+				lane_data.relayers[0].messages.dispatch_statuses,
+				vec![MessageDispatchStatus::Deferred],
+			);
 			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
 				RuntimeOrigin::signed(1),
-				TestMessagesDeliveryProof(Ok((
-					TEST_LANE_ID,
-					InboundLaneData {
-						relayers: vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)]
-							.into_iter()
-							.collect(),
-						..Default::default()
-					}
-				))),
+				TestMessagesDeliveryProof(Ok((TEST_LANE_ID, lane_data))),
 				UnrewardedRelayersState {
 					unrewarded_relayer_entries: 1,
 					total_messages: 1,
@@ -1608,539 +6573,829 @@ mod tests {
 					..Default::default()
 				},
 			));
-			assert!(TestMessageDeliveryAndDispatchPayment::is_reward_paid(TEST_RELAYER_A, 1000));
-			assert!(!TestMessageDeliveryAndDispatchPayment::is_reward_paid(TEST_RELAYER_B, 2000));
+			assert!(!TestMessageDeliveryAndDispatchPayment::is_fee_refunded(
+				1,
+				REGULAR_PAYLOAD.declared_weight.ref_time()
+			));
+			assert!(!CancelledMessages::<TestRuntime>::contains_key(MessageKey {
+				lane_id: TEST_LANE_ID,
+				nonce: 1
+			}));
 
-			// this reports delivery of both message 1 and message 2 => reward is paid only to
-			// TEST_RELAYER_B
-			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+			// because the original message is still dispatched later
+			Pallet::<TestRuntime>::on_idle(2, Weight::MAX);
+			assert!(!DeferredMessages::<TestRuntime>::contains_key(TEST_LANE_ID, 1));
+		});
+	}
+
+	#[test]
+	fn cancelled_message_refund_excludes_fee_top_ups() {
+		run_test(|| {
+			send_regular_message();
+			assert_ok!(Pallet::<TestRuntime>::increase_message_fee(
+				RuntimeOrigin::signed(2),
+				TEST_LANE_ID,
+				1,
+				100,
+			));
+			System::<TestRuntime>::set_block_number(
+				1 + crate::mock::MessageCancellationTimeout::get(),
+			);
+			assert_ok!(Pallet::<TestRuntime>::cancel_message(
 				RuntimeOrigin::signed(1),
-				TestMessagesDeliveryProof(Ok((
-					TEST_LANE_ID,
-					InboundLaneData {
-						relayers: vec![
-							unrewarded_relayer(1, 1, TEST_RELAYER_A),
-							unrewarded_relayer(2, 2, TEST_RELAYER_B)
-						]
-						.into_iter()
-						.collect(),
-						..Default::default()
-					}
-				))),
-				UnrewardedRelayersState {
-					unrewarded_relayer_entries: 2,
-					total_messages: 2,
-					last_delivered_nonce: 2,
-					..Default::default()
-				},
+				TEST_LANE_ID,
+				1,
+				undelivered_messages_proof(TEST_LANE_ID),
+			));
+
+			confirm_cancelled_message_delivery(Some(MessageDispatchStatus::Cancelled));
+			assert!(TestMessageDeliveryAndDispatchPayment::is_fee_refunded(
+				1,
+				REGULAR_PAYLOAD.declared_weight.ref_time()
 			));
-			assert!(!TestMessageDeliveryAndDispatchPayment::is_reward_paid(TEST_RELAYER_A, 1000));
-			assert!(TestMessageDeliveryAndDispatchPayment::is_reward_paid(TEST_RELAYER_B, 2000));
 		});
 	}
 
 	#[test]
-	fn receive_messages_delivery_proof_rejects_invalid_proof() {
+	fn cancel_message_fails_if_pallet_or_lane_is_halted() {
 		run_test(|| {
+			send_regular_message();
+			System::<TestRuntime>::set_block_number(
+				1 + crate::mock::MessageCancellationTimeout::get(),
+			);
+
+			PalletOperatingMode::<TestRuntime, ()>::put(MessagesOperatingMode::Basic(
+				BasicOperatingMode::Halted,
+			));
 			assert_noop!(
-				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				Pallet::<TestRuntime>::cancel_message(
 					RuntimeOrigin::signed(1),
-					TestMessagesDeliveryProof(Err(())),
-					Default::default(),
+					TEST_LANE_ID,
+					1,
+					undelivered_messages_proof(TEST_LANE_ID),
 				),
-				Error::<TestRuntime, ()>::InvalidMessagesDeliveryProof,
+				Error::<TestRuntime, ()>::NotOperatingNormally,
+			);
+
+			PalletOperatingMode::<TestRuntime, ()>::put(MessagesOperatingMode::Basic(
+				BasicOperatingMode::Normal,
+			));
+			LaneOperatingModes::<TestRuntime>::insert(
+				TEST_LANE_ID,
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			);
+			assert_noop!(
+				Pallet::<TestRuntime>::cancel_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					1,
+					undelivered_messages_proof(TEST_LANE_ID),
+				),
+				Error::<TestRuntime, ()>::LaneHalted,
 			);
+
+			LaneOperatingModes::<TestRuntime>::remove(TEST_LANE_ID);
+			assert_ok!(Pallet::<TestRuntime>::cancel_message(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				1,
+				undelivered_messages_proof(TEST_LANE_ID),
+			));
 		});
 	}
 
 	#[test]
-	fn receive_messages_delivery_proof_rejects_proof_if_declared_relayers_state_is_invalid() {
+	fn cancel_message_rejects_invalid_calls() {
 		run_test(|| {
-			// when number of relayers entries is invalid
+			send_regular_message();
 			assert_noop!(
-				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				Pallet::<TestRuntime>::cancel_message(
 					RuntimeOrigin::signed(1),
-					TestMessagesDeliveryProof(Ok((
-						TEST_LANE_ID,
-						InboundLaneData {
-							relayers: vec![
-								unrewarded_relayer(1, 1, TEST_RELAYER_A),
-								unrewarded_relayer(2, 2, TEST_RELAYER_B)
-							]
-							.into_iter()
-							.collect(),
-							..Default::default()
-						}
-					))),
-					UnrewardedRelayersState {
-						unrewarded_relayer_entries: 1,
-						total_messages: 2,
-						last_delivered_nonce: 2,
-						..Default::default()
-					},
+					TEST_LANE_ID,
+					1,
+					undelivered_messages_proof(TEST_LANE_ID),
 				),
-				Error::<TestRuntime, ()>::InvalidUnrewardedRelayersState,
+				Error::<TestRuntime, ()>::MessageCancellationTimeoutNotPassed,
 			);
 
-			// when number of messages is invalid
+			System::<TestRuntime>::set_block_number(
+				1 + crate::mock::MessageCancellationTimeout::get(),
+			);
 			assert_noop!(
-				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				Pallet::<TestRuntime>::cancel_message(
+					RuntimeOrigin::signed(2),
+					TEST_LANE_ID,
+					1,
+					undelivered_messages_proof(TEST_LANE_ID),
+				),
+				DispatchError::BadOrigin,
+			);
+			assert_noop!(
+				Pallet::<TestRuntime>::cancel_message(
 					RuntimeOrigin::signed(1),
-					TestMessagesDeliveryProof(Ok((
-						TEST_LANE_ID,
-						InboundLaneData {
-							relayers: vec![
-								unrewarded_relayer(1, 1, TEST_RELAYER_A),
-								unrewarded_relayer(2, 2, TEST_RELAYER_B)
-							]
-							.into_iter()
-							.collect(),
-							..Default::default()
-						}
-					))),
-					UnrewardedRelayersState {
-						unrewarded_relayer_entries: 2,
-						total_messages: 1,
-						last_delivered_nonce: 2,
-						..Default::default()
-					},
+					TEST_LANE_ID,
+					1,
+					undelivered_messages_proof(TEST_LANE_ID_2),
 				),
-				Error::<TestRuntime, ()>::InvalidUnrewardedRelayersState,
+				Error::<TestRuntime, ()>::InvalidMessagesDeliveryProof,
 			);
-
-			// when last delivered nonce is invalid
 			assert_noop!(
-				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				Pallet::<TestRuntime>::cancel_message(
 					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					1,
 					TestMessagesDeliveryProof(Ok((
 						TEST_LANE_ID,
 						InboundLaneData {
-							relayers: vec![
-								unrewarded_relayer(1, 1, TEST_RELAYER_A),
-								unrewarded_relayer(2, 2, TEST_RELAYER_B)
-							]
-							.into_iter()
-							.collect(),
-							..Default::default()
-						}
+							last_confirmed_nonce: 0,
+							relayers: vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)]
+								.into_iter()
+								.collect(),
+						},
 					))),
-					UnrewardedRelayersState {
-						unrewarded_relayer_entries: 2,
-						total_messages: 2,
-						last_delivered_nonce: 8,
-						..Default::default()
+				),
+				Error::<TestRuntime, ()>::MessageAlreadyDelivered,
+			);
+		});
+	}
+
+	#[test]
+	fn expired_messages_are_reported_on_delivery_confirmation() {
+		run_test(|| {
+			assert_ok!(Pallet::<TestRuntime>::send_message_with_expiry(
+				RuntimeOrigin::signed(1),
+				TEST_LANE_ID,
+				REGULAR_PAYLOAD,
+				REGULAR_PAYLOAD.declared_weight.ref_time(),
+				MessageExpiry::BlockNumber(100),
+			));
+			assert_eq!(
+				OutboundMessages::<TestRuntime>::get(MessageKey {
+					lane_id: TEST_LANE_ID,
+					nonce: 1
+				})
+				.and_then(|message_data| message_data.expiry()),
+				Some(MessageExpiry::BlockNumber(100)),
+			);
+			// the expiry is appended to the stored message data, which is not changed itself
+			assert_eq!(
+				Pallet::<TestRuntime>::outbound_message_data(TEST_LANE_ID, 1),
+				Some(MessageData {
+					payload: REGULAR_PAYLOAD.encode(),
+					fee: REGULAR_PAYLOAD.declared_weight.ref_time(),
+				}),
+			);
+			send_regular_message();
+			for expiry in [MessageExpiry::Timestamp(100), MessageExpiry::Timestamp(200)] {
+				assert_ok!(Pallet::<TestRuntime>::send_message_with_expiry(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD,
+					REGULAR_PAYLOAD.declared_weight.ref_time(),
+					expiry,
+				));
+			}
+
+			// message 1 has expired, dispatch of messages 2 and 4 has failed and message 3 has
+			// been dispatched
+			let mut relayer_entry = unrewarded_relayer(1, 4, TEST_RELAYER_A);
+			relayer_entry.messages.dispatch_results.set(0, false);
+			relayer_entry.messages.dispatch_results.set(1, false);
+			relayer_entry.messages.dispatch_results.set(3, false);
+			relayer_entry.messages.dispatch_statuses = vec![
+				MessageDispatchStatus::Expired,
+				MessageDispatchStatus::CallFailed,
+				MessageDispatchStatus::Dispatched,
+				MessageDispatchStatus::CallFailed,
+			];
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				TestMessagesDeliveryProof(Ok((
+					TEST_LANE_ID,
+					InboundLaneData {
+						last_confirmed_nonce: 0,
+						relayers: vec![relayer_entry].into_iter().collect(),
 					},
-				),
-				Error::<TestRuntime, ()>::InvalidUnrewardedRelayersState,
-			);
+				))),
+				UnrewardedRelayersState {
+					unrewarded_relayer_entries: 1,
+					messages_in_oldest_entry: 4,
+					total_messages: 4,
+					last_delivered_nonce: 4,
+				},
+			));
+
+			// only message 1 has expired before delivery
+			assert_eq!(TestOnDeliveryConfirmed2::expired_messages(&TEST_LANE_ID), Some(vec![1]));
 		});
 	}
 
 	#[test]
-	fn receive_messages_accepts_single_message_with_invalid_payload() {
+	fn receive_messages_delivery_proof_rejects_proofs_of_halted_lanes() {
 		run_test(|| {
-			let mut invalid_message = message(1, REGULAR_PAYLOAD);
-			invalid_message.data.payload = Vec::new();
+			send_regular_message();
 
-			assert_ok!(Pallet::<TestRuntime, ()>::receive_messages_proof(
-				RuntimeOrigin::signed(1),
-				TEST_RELAYER_A,
-				Ok(vec![invalid_message]).into(),
-				1,
-				Weight::zero(), /* weight may be zero in this case (all messages are
-				                 * improperly encoded) */
-			),);
+			let delivery_proof = TestMessagesDeliveryProof(Ok((
+				TEST_LANE_ID,
+				InboundLaneData {
+					last_confirmed_nonce: 1,
+					relayers: vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)].into_iter().collect(),
+				},
+			)));
+			let relayers_state = UnrewardedRelayersState {
+				unrewarded_relayer_entries: 1,
+				messages_in_oldest_entry: 1,
+				total_messages: 1,
+				last_delivered_nonce: 1,
+			};
 
-			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).last_delivered_nonce(), 1,);
+			LaneOperatingModes::<TestRuntime>::insert(
+				TEST_LANE_ID,
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			);
+			assert_noop!(
+				Pallet::<TestRuntime>::receive_messages_delivery_proof(
+					RuntimeOrigin::signed(1),
+					delivery_proof.clone(),
+					relayers_state.clone(),
+				),
+				Error::<TestRuntime, ()>::LaneHalted,
+			);
+
+			LaneOperatingModes::<TestRuntime>::insert(
+				TEST_LANE_ID,
+				MessagesOperatingMode::RejectingOutboundMessages,
+			);
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
+				RuntimeOrigin::signed(1),
+				delivery_proof,
+				relayers_state,
+			));
+			assert_eq!(OutboundLanes::<TestRuntime>::get(TEST_LANE_ID).latest_received_nonce, 1,);
 		});
 	}
 
 	#[test]
-	fn receive_messages_accepts_batch_with_message_with_invalid_payload() {
+	fn open_lane_works() {
 		run_test(|| {
-			let mut invalid_message = message(2, REGULAR_PAYLOAD);
-			invalid_message.data.payload = Vec::new();
+			get_ready_for_events();
+			let lane_id = [0, 0, 0, 3];
 
-			assert_ok!(Pallet::<TestRuntime, ()>::receive_messages_proof(
-				RuntimeOrigin::signed(1),
-				TEST_RELAYER_A,
-				Ok(
-					vec![message(1, REGULAR_PAYLOAD), invalid_message, message(3, REGULAR_PAYLOAD),]
-				)
-				.into(),
-				3,
-				REGULAR_PAYLOAD.declared_weight + REGULAR_PAYLOAD.declared_weight,
-			),);
+			assert_noop!(
+				Pallet::<TestRuntime>::open_lane(
+					RuntimeOrigin::signed(1),
+					lane_id,
+					Some(1),
+					H256::repeat_byte(1),
+					LaneKind::Ordered,
+				),
+				DispatchError::BadOrigin,
+			);
 
-			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).last_delivered_nonce(), 3,);
-		});
-	}
+			assert_ok!(Pallet::<TestRuntime>::open_lane(
+				RuntimeOrigin::root(),
+				lane_id,
+				Some(1),
+				H256::repeat_byte(1),
+				LaneKind::Ordered,
+			));
+			assert_eq!(
+				Pallet::<TestRuntime>::lane_metadata(lane_id),
+				Some(LaneMetadata {
+					created_at: 1,
+					owner: Some(1),
+					description_hash: H256::repeat_byte(1),
+					kind: LaneKind::Ordered,
+					state: LaneState::Opened,
+				}),
+			);
+			assert_eq!(
+				System::<TestRuntime>::events(),
+				vec![EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Messages(Event::LaneOpened {
+						lane_id,
+						owner: Some(1),
+						kind: LaneKind::Ordered,
+					}),
+					topics: vec![],
+				}],
+			);
 
-	#[test]
-	fn actual_dispatch_weight_does_not_overlow() {
-		run_test(|| {
-			let message1 = message(1, message_payload(0, u64::MAX / 2));
-			let message2 = message(2, message_payload(0, u64::MAX / 2));
-			let message3 = message(3, message_payload(0, u64::MAX / 2));
+			assert_noop!(
+				Pallet::<TestRuntime>::open_lane(
+					RuntimeOrigin::root(),
+					lane_id,
+					None,
+					H256::zero(),
+					LaneKind::Ordered,
+				),
+				Error::<TestRuntime, ()>::LaneAlreadyExists,
+			);
 
-			assert_ok!(Pallet::<TestRuntime, ()>::receive_messages_proof(
+			assert_ok!(Pallet::<TestRuntime>::send_message(
 				RuntimeOrigin::signed(1),
-				TEST_RELAYER_A,
-				// this may cause overflow if source chain storage is invalid
-				Ok(vec![message1, message2, message3]).into(),
-				3,
-				Weight::MAX,
+				lane_id,
+				REGULAR_PAYLOAD,
+				REGULAR_PAYLOAD.declared_weight.ref_time(),
 			));
-			assert_eq!(InboundLanes::<TestRuntime>::get(TEST_LANE_ID).last_delivered_nonce(), 2);
 		});
 	}
 
 	#[test]
-	fn weight_refund_from_receive_messages_proof_works() {
+	fn send_message_rejects_messages_to_unknown_and_closing_lanes() {
 		run_test(|| {
-			fn submit_with_unspent_weight(
-				nonce: MessageNonce,
-				unspent_weight: u64,
-				is_prepaid: bool,
-			) -> (Weight, Weight) {
-				let mut payload = REGULAR_PAYLOAD;
-				*payload.dispatch_result.unspent_weight.ref_time_mut() = unspent_weight;
-				payload.dispatch_result.dispatch_fee_paid_during_dispatch = !is_prepaid;
-				let proof = Ok(vec![message(nonce, payload)]).into();
-				let messages_count = 1;
-				let pre_dispatch_weight =
-					<TestRuntime as Config>::WeightInfo::receive_messages_proof_weight(
-						&proof,
-						messages_count,
-						REGULAR_PAYLOAD.declared_weight,
-					);
-				let post_dispatch_weight = Pallet::<TestRuntime>::receive_messages_proof(
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
 					RuntimeOrigin::signed(1),
-					TEST_RELAYER_A,
-					proof,
-					messages_count,
-					REGULAR_PAYLOAD.declared_weight,
-				)
-				.expect("delivery has failed")
-				.actual_weight
-				.expect("receive_messages_proof always returns Some");
+					[0, 0, 0, 3],
+					REGULAR_PAYLOAD,
+					REGULAR_PAYLOAD.declared_weight.ref_time(),
+				),
+				Error::<TestRuntime, ()>::LaneNotOpened,
+			);
 
-				(pre_dispatch_weight, post_dispatch_weight)
-			}
+			send_regular_message();
+			assert_ok!(Pallet::<TestRuntime>::close_lane(RuntimeOrigin::root(), TEST_LANE_ID));
+			assert_noop!(
+				Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					TEST_LANE_ID,
+					REGULAR_PAYLOAD,
+					REGULAR_PAYLOAD.declared_weight.ref_time(),
+				),
+				Error::<TestRuntime, ()>::LaneNotOpened,
+			);
+		});
+	}
 
-			// when dispatch is returning `unspent_weight < declared_weight`
-			let (pre, post) = submit_with_unspent_weight(1, 1, false);
-			assert_eq!(post.ref_time(), pre.ref_time() - 1);
+	#[test]
+	fn close_lane_may_only_be_called_by_owner_or_root() {
+		run_test(|| {
+			let lane_id = [0, 0, 0, 3];
+			assert_ok!(Pallet::<TestRuntime>::open_lane(
+				RuntimeOrigin::root(),
+				lane_id,
+				Some(1),
+				H256::zero(),
+				LaneKind::Ordered,
+			));
 
-			// when dispatch is returning `unspent_weight = declared_weight`
-			let (pre, post) =
-				submit_with_unspent_weight(2, REGULAR_PAYLOAD.declared_weight.ref_time(), false);
-			assert_eq!(
-				post.ref_time(),
-				pre.ref_time() - REGULAR_PAYLOAD.declared_weight.ref_time()
+			assert_noop!(
+				Pallet::<TestRuntime>::close_lane(RuntimeOrigin::signed(1), [0, 0, 0, 4]),
+				Error::<TestRuntime, ()>::UnknownLane,
 			);
-
-			// when dispatch is returning `unspent_weight > declared_weight`
-			let (pre, post) = submit_with_unspent_weight(
-				3,
-				REGULAR_PAYLOAD.declared_weight.ref_time() + 1,
-				false,
+			assert_noop!(
+				Pallet::<TestRuntime>::close_lane(RuntimeOrigin::signed(2), lane_id),
+				DispatchError::BadOrigin,
 			);
-			assert_eq!(
-				post.ref_time(),
-				pre.ref_time() - REGULAR_PAYLOAD.declared_weight.ref_time()
+			assert_noop!(
+				Pallet::<TestRuntime>::close_lane(RuntimeOrigin::signed(1), TEST_LANE_ID),
+				DispatchError::BadOrigin,
 			);
 
-			// when there's no unspent weight
-			let (pre, post) = submit_with_unspent_weight(4, 0, false);
-			assert_eq!(post, pre);
-
-			// when dispatch is returning `unspent_weight < declared_weight` AND message is prepaid
-			let (pre, post) = submit_with_unspent_weight(5, 1, true);
+			// the lane has no pending messages, so it is closed immediately
+			assert_ok!(Pallet::<TestRuntime>::close_lane(RuntimeOrigin::signed(1), lane_id));
 			assert_eq!(
-				post.ref_time(),
-				pre.ref_time()
-					- 1 - <TestRuntime as Config>::WeightInfo::pay_inbound_dispatch_fee_overhead()
-					.ref_time()
+				Pallet::<TestRuntime>::lane_metadata(lane_id).map(|metadata| metadata.state),
+				Some(LaneState::Closed),
+			);
+			assert_noop!(
+				Pallet::<TestRuntime>::close_lane(RuntimeOrigin::signed(1), lane_id),
+				Error::<TestRuntime, ()>::LaneNotOpened,
 			);
 		});
 	}
 
 	#[test]
-	fn messages_delivered_callbacks_are_called() {
+	fn closing_lane_is_closed_when_pending_messages_are_delivered() {
 		run_test(|| {
 			send_regular_message();
 			send_regular_message();
-			send_regular_message();
+			get_ready_for_events();
 
-			// messages 1+2 are confirmed in 1 tx, message 3 in a separate tx
-			// dispatch of message 2 has failed
-			let mut delivered_messages_1_and_2 = DeliveredMessages::new(1, true);
-			delivered_messages_1_and_2.note_dispatched_message(false);
-			let messages_1_and_2_proof = Ok((
-				TEST_LANE_ID,
-				InboundLaneData {
-					last_confirmed_nonce: 0,
-					relayers: vec![UnrewardedRelayer {
-						relayer: 0,
-						messages: delivered_messages_1_and_2.clone(),
-					}]
-					.into_iter()
-					.collect(),
-				},
-			));
-			let delivered_message_3 = DeliveredMessages::new(3, true);
-			let messages_3_proof = Ok((
-				TEST_LANE_ID,
-				InboundLaneData {
-					last_confirmed_nonce: 0,
-					relayers: vec![UnrewardedRelayer {
-						relayer: 0,
-						messages: delivered_message_3.clone(),
-					}]
-					.into_iter()
-					.collect(),
-				},
-			));
+			assert_ok!(Pallet::<TestRuntime>::close_lane(RuntimeOrigin::root(), TEST_LANE_ID));
+			assert_eq!(
+				Pallet::<TestRuntime>::lane_metadata(TEST_LANE_ID).map(|metadata| metadata.state),
+				Some(LaneState::Closing),
+			);
+			assert_eq!(
+				System::<TestRuntime>::events(),
+				vec![EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Messages(Event::LaneClosing { lane_id: TEST_LANE_ID }),
+					topics: vec![],
+				}],
+			);
 
-			// first tx with messages 1+2
+			// the first message is delivered => the lane is still closing
 			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
 				RuntimeOrigin::signed(1),
-				TestMessagesDeliveryProof(messages_1_and_2_proof),
+				TestMessagesDeliveryProof(Ok((
+					TEST_LANE_ID,
+					InboundLaneData {
+						last_confirmed_nonce: 0,
+						relayers: vec![unrewarded_relayer(1, 1, TEST_RELAYER_A)]
+							.into_iter()
+							.collect(),
+					},
+				))),
 				UnrewardedRelayersState {
 					unrewarded_relayer_entries: 1,
-					total_messages: 2,
-					last_delivered_nonce: 2,
-					..Default::default()
+					messages_in_oldest_entry: 1,
+					total_messages: 1,
+					last_delivered_nonce: 1,
 				},
 			));
-			// second tx with message 3
+			assert!(Pallet::<TestRuntime>::lane_metadata(TEST_LANE_ID).is_some());
+			assert!(Pallet::<TestRuntime>::outbound_message_data(TEST_LANE_ID, 2).is_some());
+
+			// the second message is delivered => the lane is closed
+			get_ready_for_events();
 			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
 				RuntimeOrigin::signed(1),
-				TestMessagesDeliveryProof(messages_3_proof),
+				TestMessagesDeliveryProof(Ok((
+					TEST_LANE_ID,
+					InboundLaneData {
+						last_confirmed_nonce: 0,
+						relayers: vec![
+							unrewarded_relayer(1, 1, TEST_RELAYER_A),
+							unrewarded_relayer(2, 2, TEST_RELAYER_B),
+						]
+						.into_iter()
+						.collect(),
+					},
+				))),
 				UnrewardedRelayersState {
-					unrewarded_relayer_entries: 1,
-					total_messages: 1,
-					last_delivered_nonce: 3,
-					..Default::default()
+					unrewarded_relayer_entries: 2,
+					messages_in_oldest_entry: 1,
+					total_messages: 2,
+					last_delivered_nonce: 2,
 				},
 			));
-
-			// ensure that both callbacks have been called twice: for 1+2, then for 3
-			TestOnDeliveryConfirmed1::ensure_called(&TEST_LANE_ID, &delivered_messages_1_and_2);
-			TestOnDeliveryConfirmed1::ensure_called(&TEST_LANE_ID, &delivered_message_3);
-			TestOnDeliveryConfirmed2::ensure_called(&TEST_LANE_ID, &delivered_messages_1_and_2);
-			TestOnDeliveryConfirmed2::ensure_called(&TEST_LANE_ID, &delivered_message_3);
+			assert_eq!(
+				Pallet::<TestRuntime>::lane_metadata(TEST_LANE_ID).map(|metadata| metadata.state),
+				Some(LaneState::Closed),
+			);
+			assert_eq!(Pallet::<TestRuntime>::outbound_message_data(TEST_LANE_ID, 1), None);
+			assert_eq!(Pallet::<TestRuntime>::outbound_message_data(TEST_LANE_ID, 2), None);
+			assert_eq!(OutboundLanes::<TestRuntime>::get(TEST_LANE_ID).latest_generated_nonce, 2);
+			assert!(System::<TestRuntime>::events().contains(&EventRecord {
+				phase: Phase::Initialization,
+				event: TestEvent::Messages(Event::LaneClosed { lane_id: TEST_LANE_ID }),
+				topics: vec![],
+			}));
 		});
 	}
 
-	fn confirm_3_messages_delivery() -> (Weight, Weight) {
-		send_regular_message();
-		send_regular_message();
-		send_regular_message();
+	#[test]
+	fn closed_lane_may_be_reopened_without_resetting_nonces() {
+		run_test(|| {
+			send_regular_message();
+			OutboundLanes::<TestRuntime>::mutate(TEST_LANE_ID, |data| {
+				data.latest_received_nonce = 1
+			});
+			InboundLanes::<TestRuntime>::insert(
+				TEST_LANE_ID,
+				StoredInboundLaneData(InboundLaneData {
+					relayers: Default::default(),
+					last_confirmed_nonce: 5,
+				}),
+			);
 
-		let proof = TestMessagesDeliveryProof(Ok((
-			TEST_LANE_ID,
-			InboundLaneData {
-				last_confirmed_nonce: 0,
-				relayers: vec![unrewarded_relayer(1, 3, TEST_RELAYER_A)].into_iter().collect(),
-			},
-		)));
-		let relayers_state = UnrewardedRelayersState {
-			unrewarded_relayer_entries: 1,
-			total_messages: 3,
-			last_delivered_nonce: 3,
-			..Default::default()
-		};
-		let pre_dispatch_weight =
-			<TestRuntime as Config>::WeightInfo::receive_messages_delivery_proof_weight(
-				&proof,
-				&relayers_state,
-				crate::mock::DbWeight::get(),
+			assert_ok!(Pallet::<TestRuntime>::close_lane(RuntimeOrigin::root(), TEST_LANE_ID));
+			assert_eq!(
+				Pallet::<TestRuntime>::lane_metadata(TEST_LANE_ID).map(|metadata| metadata.state),
+				Some(LaneState::Closed),
+			);
+			assert_eq!(
+				Pallet::<TestRuntime>::inbound_lane_data(TEST_LANE_ID).last_delivered_nonce(),
+				5
 			);
-		let post_dispatch_weight = Pallet::<TestRuntime>::receive_messages_delivery_proof(
-			RuntimeOrigin::signed(1),
-			proof,
-			relayers_state,
-		)
-		.expect("confirmation has failed")
-		.actual_weight
-		.expect("receive_messages_delivery_proof always returns Some");
-		(pre_dispatch_weight, post_dispatch_weight)
-	}
 
-	#[test]
-	fn receive_messages_delivery_proof_refunds_zero_weight() {
-		run_test(|| {
-			let (pre_dispatch_weight, post_dispatch_weight) = confirm_3_messages_delivery();
-			assert_eq!(pre_dispatch_weight, post_dispatch_weight);
+			assert_ok!(Pallet::<TestRuntime>::open_lane(
+				RuntimeOrigin::root(),
+				TEST_LANE_ID,
+				None,
+				H256::zero(),
+				LaneKind::Ordered,
+			));
+			send_regular_message();
+			assert_eq!(OutboundLanes::<TestRuntime>::get(TEST_LANE_ID).latest_generated_nonce, 2);
 		});
 	}
 
 	#[test]
-	fn receive_messages_delivery_proof_refunds_non_zero_weight() {
+	fn runtime_api_helpers_return_lanes_state() {
 		run_test(|| {
-			TestOnDeliveryConfirmed1::set_consumed_weight_per_message(
-				crate::mock::DbWeight::get().writes(1),
+			send_regular_message();
+
+			assert_eq!(
+				Pallet::<TestRuntime>::outbound_message_data(TEST_LANE_ID, 1),
+				Some(MessageData {
+					payload: REGULAR_PAYLOAD.encode(),
+					fee: REGULAR_PAYLOAD.declared_weight.ref_time(),
+				}),
+			);
+			assert_eq!(Pallet::<TestRuntime>::outbound_message_data(TEST_LANE_ID, 2), None);
+			assert_eq!(
+				Pallet::<TestRuntime>::outbound_lane_data(TEST_LANE_ID).latest_generated_nonce,
+				1,
 			);
 
-			let (pre_dispatch_weight, post_dispatch_weight) = confirm_3_messages_delivery();
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+				1,
+				REGULAR_PAYLOAD.declared_weight,
+			));
+			let inbound_lane_data = Pallet::<TestRuntime>::inbound_lane_data(TEST_LANE_ID);
+			assert_eq!(inbound_lane_data.last_delivered_nonce(), 1);
 			assert_eq!(
-				pre_dispatch_weight.saturating_sub(post_dispatch_weight),
-				crate::mock::DbWeight::get().reads(1) * 3
+				UnrewardedRelayersState::from(&inbound_lane_data),
+				inbound_unrewarded_relayers_state(TEST_LANE_ID),
 			);
 		});
 	}
 
 	#[test]
-	#[should_panic]
-	#[cfg(debug_assertions)]
-	fn receive_messages_panics_in_debug_mode_if_callback_is_wrong() {
+	fn on_idle_prunes_confirmed_messages_of_all_lanes() {
 		run_test(|| {
-			TestOnDeliveryConfirmed1::set_consumed_weight_per_message(
-				crate::mock::DbWeight::get().reads_writes(2, 2),
-			);
-			confirm_3_messages_delivery()
+			// cursor: 3, lane: 3, message: 4, sender rate limit usages overhead: 5
+			let weight =
+				|lanes: u64, messages: u64| Weight::from_parts(3 + lanes * 3 + messages * 4 + 5, 0);
+
+			send_and_confirm_messages(TEST_LANE_ID, 12);
+			send_and_confirm_messages(TEST_LANE_ID_2, 2);
+			outbound_lane::<TestRuntime, ()>(TEST_LANE_ID_2)
+				.send_message(message_data(REGULAR_PAYLOAD).into());
+
+			// no more than `MaxMessagesToPruneAtOnce` messages are pruned at single lane
+			assert_eq!(Pallet::<TestRuntime>::on_idle(1, Weight::MAX), weight(2, 12));
+			assert_eq!(OutboundLanes::<TestRuntime>::get(TEST_LANE_ID).oldest_unpruned_nonce, 11);
+			assert_eq!(OutboundLanes::<TestRuntime>::get(TEST_LANE_ID_2).oldest_unpruned_nonce, 3);
+			assert!(Pallet::<TestRuntime>::outbound_message_data(TEST_LANE_ID_2, 3).is_some());
+
+			assert_eq!(Pallet::<TestRuntime>::on_idle(2, Weight::MAX), weight(2, 2));
+			assert_eq!(OutboundLanes::<TestRuntime>::get(TEST_LANE_ID).oldest_unpruned_nonce, 13);
+
+			// nothing to prune
+			assert_eq!(Pallet::<TestRuntime>::on_idle(3, Weight::MAX), weight(2, 0));
+			assert_eq!(OutboundLanes::<TestRuntime>::get(TEST_LANE_ID_2).oldest_unpruned_nonce, 3);
 		});
 	}
 
 	#[test]
-	fn receive_messages_delivery_proof_rejects_proof_if_trying_to_confirm_more_messages_than_expected(
-	) {
+	fn on_idle_prunes_lanes_in_round_robin_order_within_remaining_weight() {
 		run_test(|| {
-			// send message first to be able to check that delivery_proof fails later
-			send_regular_message();
-
-			// 1) InboundLaneData declares that the `last_confirmed_nonce` is 1;
-			// 2) InboundLaneData has no entries => `InboundLaneData::last_delivered_nonce()`
-			//    returns `last_confirmed_nonce`;
-			// 3) it means that we're going to confirm delivery of messages 1..=1;
-			// 4) so the number of declared messages (see `UnrewardedRelayersState`) is `0` and
-			//    numer of actually confirmed messages is `1`.
-			assert_noop!(
-				Pallet::<TestRuntime>::receive_messages_delivery_proof(
-					RuntimeOrigin::signed(1),
-					TestMessagesDeliveryProof(Ok((
-						TEST_LANE_ID,
-						InboundLaneData { last_confirmed_nonce: 1, relayers: Default::default() },
-					))),
-					UnrewardedRelayersState { last_delivered_nonce: 1, ..Default::default() },
-				),
-				Error::<TestRuntime, ()>::TryingToConfirmMoreMessagesThanExpected,
+			send_and_confirm_messages(TEST_LANE_ID, 2);
+			send_and_confirm_messages(TEST_LANE_ID_2, 2);
+			let lanes = OutboundLanes::<TestRuntime>::iter_keys().collect::<Vec<_>>();
+			let (first_lane, second_lane) = (lanes[0], lanes[1]);
+			let oldest_unpruned_nonce =
+				|lane_id| OutboundLanes::<TestRuntime>::get(lane_id).oldest_unpruned_nonce;
+
+			// we can't prune even single message (or sender rate limit usage)
+			assert_eq!(Pallet::<TestRuntime>::on_idle(1, Weight::from_parts(7, 0)), Weight::zero());
+			assert_eq!(OutboundLanesPruningCursor::<TestRuntime>::get(), None);
+
+			// weight is enough to prune messages of the first lane only
+			let max_weight = Weight::from_parts(3 + 3 + 2 * 4, 0);
+			assert_eq!(Pallet::<TestRuntime>::on_idle(1, max_weight), max_weight);
+			assert_eq!(oldest_unpruned_nonce(first_lane), 3);
+			assert_eq!(oldest_unpruned_nonce(second_lane), 1);
+			assert_eq!(OutboundLanesPruningCursor::<TestRuntime>::get(), Some(first_lane));
+
+			// the first lane has new confirmed message, but we start from the second lane
+			send_and_confirm_messages(first_lane, 1);
+			assert_eq!(Pallet::<TestRuntime>::on_idle(2, max_weight), max_weight);
+			assert_eq!(oldest_unpruned_nonce(first_lane), 3);
+			assert_eq!(oldest_unpruned_nonce(second_lane), 3);
+			assert_eq!(OutboundLanesPruningCursor::<TestRuntime>::get(), Some(second_lane));
+
+			// if lane is not fully pruned, it is visited again at next call
+			send_and_confirm_messages(first_lane, 1);
+			assert_eq!(
+				Pallet::<TestRuntime>::on_idle(3, Weight::from_parts(10, 0)),
+				Weight::from_parts(10, 0)
 			);
+			assert_eq!(oldest_unpruned_nonce(first_lane), 4);
+			assert_eq!(OutboundLanesPruningCursor::<TestRuntime>::get(), Some(second_lane));
+			assert_eq!(Pallet::<TestRuntime>::on_idle(4, max_weight), Weight::from_parts(13, 0));
+			assert_eq!(oldest_unpruned_nonce(first_lane), 5);
+			assert_eq!(OutboundLanesPruningCursor::<TestRuntime>::get(), Some(second_lane));
 		});
 	}
 
 	#[test]
-	fn weight_is_refunded_for_messages_that_are_not_pruned() {
+	fn messages_accepted_in_block_are_committed_to() {
 		run_test(|| {
-			// send first MAX messages - no messages are pruned
-			let max_messages_to_prune = crate::mock::MaxMessagesToPruneAtOnce::get();
-			let when_zero_messages_are_pruned = send_regular_message();
-			let mut delivered_messages = DeliveredMessages::new(1, true);
-			for _ in 1..max_messages_to_prune {
-				assert_eq!(send_regular_message(), when_zero_messages_are_pruned);
-				delivered_messages.note_dispatched_message(true);
+			Pallet::<TestRuntime>::on_initialize(1);
+			for lane_id in [TEST_LANE_ID, TEST_LANE_ID, TEST_LANE_ID_2] {
+				assert_ok!(Pallet::<TestRuntime>::send_message(
+					RuntimeOrigin::signed(1),
+					lane_id,
+					REGULAR_PAYLOAD,
+					REGULAR_PAYLOAD.declared_weight.ref_time(),
+				));
 			}
+			Pallet::<TestRuntime>::on_finalize(1);
 
-			// confirm delivery of all sent messages
-			assert_ok!(Pallet::<TestRuntime>::receive_messages_delivery_proof(
-				RuntimeOrigin::signed(1),
-				TestMessagesDeliveryProof(Ok((
-					TEST_LANE_ID,
-					InboundLaneData {
-						last_confirmed_nonce: 1,
-						relayers: vec![UnrewardedRelayer {
-							relayer: 0,
-							messages: delivered_messages,
-						}]
-						.into_iter()
-						.collect(),
-					},
-				))),
-				UnrewardedRelayersState {
-					unrewarded_relayer_entries: 1,
-					total_messages: max_messages_to_prune,
-					last_delivered_nonce: max_messages_to_prune,
-					..Default::default()
-				},
-			));
+			// root is saved to the storage and to the header digest
+			let root = OutboundMessagesCommitmentRoot::<TestRuntime>::get()
+				.expect("messages have been sent in this block");
+			assert_eq!(
+				System::<TestRuntime>::digest().logs,
+				vec![DigestItem::Consensus(
+					MESSAGES_COMMITMENT_ENGINE_ID,
+					MessagesCommitment { bridged_chain_id: *b"test", root, messages_count: 3 }
+						.encode(),
+				)],
+			);
 
-			// when next message is sent, MAX messages are pruned
-			// let weight_when_max_messages_are_pruned = send_regular_message();
-			// assert_eq!(
-			// 	weight_when_max_messages_are_pruned,
-			// 	when_zero_messages_are_pruned
-			// 		+ crate::mock::DbWeight::get().writes(max_messages_to_prune),
-			// );
+			// every message may be proved
+			let payload_hash = BlakeTwo256::hash(&REGULAR_PAYLOAD.encode());
+			for (lane_id, nonce) in [(TEST_LANE_ID, 1), (TEST_LANE_ID, 2), (TEST_LANE_ID_2, 1)] {
+				let proof = Pallet::<TestRuntime>::outbound_message_inclusion_proof(lane_id, nonce)
+					.expect("message has been sent in this block");
+				assert!(verify_message_inclusion::<BlakeTwo256>(
+					root,
+					lane_id,
+					nonce,
+					payload_hash,
+					&proof,
+				));
+			}
+			assert_eq!(
+				Pallet::<TestRuntime>::outbound_message_inclusion_proof(TEST_LANE_ID, 3),
+				None
+			);
+
+			// the commitment is cleared in the next block without messages
+			System::<TestRuntime>::initialize(&2, &Default::default(), &Default::default());
+			Pallet::<TestRuntime>::on_initialize(2);
+			assert_eq!(
+				Pallet::<TestRuntime>::outbound_message_inclusion_proof(TEST_LANE_ID, 1),
+				None,
+			);
+			assert_eq!(OutboundMessagesCommitmentLeafIndices::<TestRuntime>::iter().count(), 0);
+			Pallet::<TestRuntime>::on_finalize(2);
+			assert_eq!(OutboundMessagesCommitmentRoot::<TestRuntime>::get(), None);
+			assert!(System::<TestRuntime>::digest().logs.is_empty());
 		});
 	}
 
 	#[test]
-	fn message_accepted_callbacks_are_called() {
+	fn try_state_succeeds_when_lanes_are_consistent() {
 		run_test(|| {
 			send_regular_message();
-			TestOnMessageAccepted::ensure_called(&TEST_LANE_ID, &1);
+			send_regular_message();
+			receive_messages_delivery_proof();
+			assert_ok!(Pallet::<TestRuntime>::receive_messages_proof(
+				RuntimeOrigin::signed(1),
+				TEST_RELAYER_A,
+				Ok(vec![message(1, REGULAR_PAYLOAD)]).into(),
+				1,
+				REGULAR_PAYLOAD.declared_weight,
+			));
+
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
 		});
 	}
 
 	#[test]
-	#[should_panic]
-	#[cfg(debug_assertions)]
-	fn message_accepted_panics_in_debug_mode_if_callback_is_wrong() {
+	fn try_state_detects_corrupted_outbound_lane() {
 		run_test(|| {
-			TestOnMessageAccepted::set_consumed_weight_per_message(
-				crate::mock::DbWeight::get().reads_writes(2, 2),
-			);
 			send_regular_message();
+			send_regular_message();
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
+
+			OutboundMessages::<TestRuntime>::remove(MessageKey { lane_id: TEST_LANE_ID, nonce: 1 });
+			assert_eq!(
+				Pallet::<TestRuntime>::do_try_state(),
+				Err("Unpruned outbound message is missing"),
+			);
+
+			OutboundLanes::<TestRuntime>::mutate(TEST_LANE_ID, |data| {
+				data.latest_received_nonce = 1;
+				data.oldest_unpruned_nonce = 2;
+			});
+			assert_eq!(
+				Pallet::<TestRuntime>::do_try_state(),
+				Err("Sender of the missing outbound message is stored"),
+			);
+
+			OutboundMessageSenders::<TestRuntime>::remove(MessageKey {
+				lane_id: TEST_LANE_ID,
+				nonce: 1,
+			});
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
+
+			OutboundLanes::<TestRuntime>::mutate(TEST_LANE_ID, |data| {
+				data.latest_received_nonce = 3;
+			});
+			assert_eq!(
+				Pallet::<TestRuntime>::do_try_state(),
+				Err("Outbound lane has received messages that are not yet generated"),
+			);
 		});
 	}
 
 	#[test]
-	fn message_accepted_refunds_non_zero_weight() {
+	fn try_state_detects_corrupted_inbound_lane() {
 		run_test(|| {
-			TestOnMessageAccepted::set_consumed_weight_per_message(
-				crate::mock::DbWeight::get().writes(1),
+			let insert_inbound_lane_data = |relayers: Vec<UnrewardedRelayer<TestRelayer>>| {
+				InboundLanes::<TestRuntime, ()>::insert(
+					TEST_LANE_ID,
+					InboundLaneData {
+						last_confirmed_nonce: 8,
+						relayers: relayers.into_iter().collect(),
+					},
+				);
+			};
+
+			insert_inbound_lane_data(vec![
+				unrewarded_relayer(9, 9, TEST_RELAYER_A),
+				unrewarded_relayer(10, 10, TEST_RELAYER_B),
+			]);
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
+
+			insert_inbound_lane_data(vec![
+				unrewarded_relayer(9, 9, TEST_RELAYER_A),
+				unrewarded_relayer(11, 11, TEST_RELAYER_B),
+			]);
+			assert_eq!(
+				Pallet::<TestRuntime>::do_try_state(),
+				Err("Inbound lane has unrewarded relayer entries that are not consecutive"),
 			);
-			let _actual_callback_weight = send_regular_message();
-			let _pre_dispatch_weight = <TestRuntime as Config>::WeightInfo::send_message_weight(
-				&REGULAR_PAYLOAD,
-				crate::mock::DbWeight::get(),
+
+			let mut entry = unrewarded_relayer(9, 10, TEST_RELAYER_A);
+			entry.messages.dispatch_results.pop();
+			insert_inbound_lane_data(vec![entry]);
+			assert_eq!(
+				Pallet::<TestRuntime>::do_try_state(),
+				Err("Inbound lane has unrewarded relayer entry with invalid dispatch results"),
 			);
-			let _prune_weight = crate::mock::DbWeight::get()
-				.writes(<TestRuntime as Config>::MaxMessagesToPruneAtOnce::get());
 
-			// assert_eq!(
-			// 	pre_dispatch_weight.saturating_sub(actual_callback_weight),
-			// 	crate::mock::DbWeight::get().reads(1).saturating_add(prune_weight)
-			// );
+			insert_inbound_lane_data(vec![unrewarded_relayer(9, 10, TEST_RELAYER_A)]);
+			let insert_dispatch_statuses = |begin, statuses_count| {
+				InboundLanesDispatchStatuses::<TestRuntime, ()>::insert(
+					TEST_LANE_ID,
+					StoredInboundLaneDispatchStatuses::from(InboundLaneDispatchStatuses::V1 {
+						begin,
+						statuses: vec![Some(MessageDispatchStatus::Dispatched); statuses_count]
+							.into(),
+					}),
+				);
+			};
+			insert_dispatch_statuses(9, 2);
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
+			for (begin, statuses_count) in [(8, 1), (10, 2), (9, 0)] {
+				insert_dispatch_statuses(begin, statuses_count);
+				assert_eq!(
+					Pallet::<TestRuntime>::do_try_state(),
+					Err("Inbound lane has dispatch statuses of unknown or confirmed messages"),
+				);
+			}
+			InboundLanesDispatchStatuses::<TestRuntime, ()>::remove(TEST_LANE_ID);
+
+			insert_inbound_lane_data(vec![unrewarded_relayer(10, 10, TEST_RELAYER_A)]);
+			assert_eq!(
+				Pallet::<TestRuntime>::do_try_state(),
+				Err("Inbound lane has a gap between confirmed and unrewarded messages"),
+			);
 		});
 	}
 
 	#[test]
-	fn storage_keys_computed_properly() {
-		assert_eq!(
-			PalletOperatingMode::<TestRuntime>::storage_value_final_key().to_vec(),
-			bp_messages::storage_keys::operating_mode_key("Messages").0,
-		);
-
-		assert_eq!(
-			OutboundMessages::<TestRuntime>::storage_map_final_key(MessageKey {
-				lane_id: TEST_LANE_ID,
-				nonce: 42
-			}),
-			bp_messages::storage_keys::message_key("Messages", &TEST_LANE_ID, 42).0,
-		);
+	fn try_state_detects_corrupted_messages_delivered_ahead() {
+		run_test(|| {
+			MessagesDeliveredAhead::<TestRuntime>::insert(
+				TEST_UNORDERED_LANE_ID,
+				2,
+				(TEST_RELAYER_A, true, None),
+			);
+			assert_eq!(
+				Pallet::<TestRuntime>::do_try_state(),
+				Err("Unknown message is stored as delivered ahead"),
+			);
 
-		assert_eq!(
-			OutboundLanes::<TestRuntime>::storage_map_final_key(TEST_LANE_ID),
-			bp_messages::storage_keys::outbound_lane_data_key("Messages", &TEST_LANE_ID).0,
-		);
+			let mut delivered_ahead = DeliveredNoncesBitVec::new();
+			delivered_ahead.push(false);
+			delivered_ahead.push(true);
+			InboundLanesDeliveredAhead::<TestRuntime>::insert(
+				TEST_UNORDERED_LANE_ID,
+				StoredDeliveredNonces::<TestRuntime, ()>::from(delivered_ahead),
+			);
+			assert_ok!(Pallet::<TestRuntime>::do_try_state());
 
-		assert_eq!(
-			InboundLanes::<TestRuntime>::storage_map_final_key(TEST_LANE_ID),
-			bp_messages::storage_keys::inbound_lane_data_key("Messages", &TEST_LANE_ID).0,
-		);
+			MessagesDeliveredAhead::<TestRuntime>::remove(TEST_UNORDERED_LANE_ID, 2);
+			assert_eq!(
+				Pallet::<TestRuntime>::do_try_state(),
+				Err("Message that has been delivered ahead is missing"),
+			);
+		});
 	}
 
 	generate_owned_bridge_module_tests!(
@@ -0,0 +1,353 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage migrations of the messages pallet.
+
+// crates.io
+use codec::DecodeAll;
+#[cfg(feature = "try-runtime")]
+use codec::{Decode, Encode};
+// darwinia-network
+use crate::{
+	Config, InboundLanes, Lanes, MessagesDeliveredAhead, OutboundLanes, Pallet,
+	PalletOperatingMode, LOG_TARGET,
+};
+use bp_messages::{LaneKind, MessagesOperatingMode};
+use bp_runtime::BasicOperatingMode;
+// substrate
+#[cfg(feature = "try-runtime")]
+use frame_support::ensure;
+use frame_support::{
+	log, migration,
+	storage::unhashed,
+	traits::{Get, GetStorageVersion, OnRuntimeUpgrade, PalletInfoAccess, StorageVersion},
+	weights::Weight,
+};
+use sp_core::H256;
+use sp_std::{marker::PhantomData, prelude::*};
+
+/// Migration to the storage version 1.
+pub mod v1 {
+	// darwinia-network
+	use super::*;
+
+	/// Migrates storage of the pallet that has no storage version (version 0) to version 1.
+	///
+	/// Storage of such pallet may contain values of different legacy layouts, so every value
+	/// is only migrated if it isn't in the current layout yet:
+	///
+	/// - the `IsHalted` flag and the `BasicOperatingMode` in the `PalletOperatingMode` are replaced
+	///   with the `MessagesOperatingMode`. The encoded legacy `Halted` mode is equal to the encoded
+	///   `RejectingOutboundMessages` mode, so such value is always migrated to the (more
+	///   restrictive) `Basic(Halted)` mode;
+	/// - lanes that have been used before the lanes registry has been introduced, are registered as
+	///   ordered lanes.
+	///
+	/// Outbound messages are not migrated, because the message expiry is stored separately.
+	pub struct MigrateToV1<T, I = ()>(PhantomData<(T, I)>);
+
+	impl<T: Config<I>, I: 'static> OnRuntimeUpgrade for MigrateToV1<T, I> {
+		fn on_runtime_upgrade() -> Weight {
+			let db_weight = T::DbWeight::get();
+			if Pallet::<T, I>::on_chain_storage_version() != 0 {
+				log::info!(
+					target: LOG_TARGET,
+					"Skipping migration to v1: storage version is {:?}",
+					Pallet::<T, I>::on_chain_storage_version(),
+				);
+				return db_weight.reads(1);
+			}
+
+			let (mut reads, mut writes) = (1, 1);
+
+			let pallet_name = <Pallet<T, I> as PalletInfoAccess>::name().as_bytes();
+			reads += 1;
+			if let Some(is_halted) =
+				migration::take_storage_value::<bool>(pallet_name, b"IsHalted", &[])
+			{
+				let mode =
+					if is_halted { BasicOperatingMode::Halted } else { BasicOperatingMode::Normal };
+				PalletOperatingMode::<T, I>::put(MessagesOperatingMode::Basic(mode));
+				writes += 2;
+			} else {
+				// the legacy mode is a single byte, while the `MessagesOperatingMode::Basic` is
+				// encoded using two bytes
+				reads += 1;
+				let legacy_mode = unhashed::get_raw(&PalletOperatingMode::<T, I>::hashed_key())
+					.and_then(|raw| BasicOperatingMode::decode_all(&mut &raw[..]).ok());
+				if let Some(mode) = legacy_mode {
+					PalletOperatingMode::<T, I>::put(MessagesOperatingMode::Basic(mode));
+					writes += 1;
+				}
+			}
+
+			let mut lanes = OutboundLanes::<T, I>::iter_keys()
+				.chain(InboundLanes::<T, I>::iter_keys())
+				.collect::<Vec<_>>();
+			lanes.sort();
+			lanes.dedup();
+			for lane_id in lanes {
+				reads += 2;
+				if Lanes::<T, I>::contains_key(lane_id) {
+					continue;
+				}
+
+				// this may only fail if the lane is already registered
+				let _ =
+					Pallet::<T, I>::do_open_lane(lane_id, None, H256::zero(), LaneKind::Ordered);
+				writes += 1;
+			}
+
+			StorageVersion::new(1).put::<Pallet<T, I>>();
+			log::info!(target: LOG_TARGET, "Migrated storage to v1");
+
+			db_weight.reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(
+				Pallet::<T, I>::on_chain_storage_version() == 1,
+				"Storage version must be 1 after the migration"
+			);
+			ensure!(
+				OutboundLanes::<T, I>::iter_keys()
+					.chain(InboundLanes::<T, I>::iter_keys())
+					.all(|lane_id| Lanes::<T, I>::contains_key(lane_id)),
+				"Some used lanes are not registered"
+			);
+			ensure!(
+				unhashed::get_raw(&PalletOperatingMode::<T, I>::hashed_key())
+					.map(|raw| MessagesOperatingMode::decode_all(&mut &raw[..]).is_ok())
+					.unwrap_or(true),
+				"Pallet operating mode has not been migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+/// Migration to the storage version 2.
+pub mod v2 {
+	// darwinia-network
+	use super::*;
+
+	/// Migrates storage of the pallet from version 1 to version 2.
+	///
+	/// Dispatch statuses of messages that have been delivered ahead before the migration are
+	/// unknown, so these messages are migrated without statuses. Statuses of messages in the
+	/// unrewarded relayer entries are stored separately, so the `InboundLaneData` is not changed.
+	pub struct MigrateToV2<T, I = ()>(PhantomData<(T, I)>);
+
+	impl<T: Config<I>, I: 'static> OnRuntimeUpgrade for MigrateToV2<T, I> {
+		fn on_runtime_upgrade() -> Weight {
+			let db_weight = T::DbWeight::get();
+			if Pallet::<T, I>::on_chain_storage_version() != 1 {
+				log::info!(
+					target: LOG_TARGET,
+					"Skipping migration to v2: storage version is {:?}",
+					Pallet::<T, I>::on_chain_storage_version(),
+				);
+				return db_weight.reads(1);
+			}
+
+			let mut migrated_messages = 0;
+			MessagesDeliveredAhead::<T, I>::translate::<(T::InboundRelayer, bool), _>(
+				|_, _, (relayer, dispatch_result)| {
+					migrated_messages += 1;
+					Some((relayer, dispatch_result, None))
+				},
+			);
+
+			StorageVersion::new(2).put::<Pallet<T, I>>();
+			log::info!(
+				target: LOG_TARGET,
+				"Migrated storage to v2. Migrated {} messages delivered ahead",
+				migrated_messages,
+			);
+
+			db_weight.reads_writes(1 + migrated_messages, 1 + migrated_messages)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			Ok((MessagesDeliveredAhead::<T, I>::iter_keys().count() as u64).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(
+				Pallet::<T, I>::on_chain_storage_version() == 2,
+				"Storage version must be 2 after the migration"
+			);
+
+			let messages_count = u64::decode(&mut &state[..])
+				.map_err(|_| "Failed to decode the number of messages delivered ahead")?;
+			ensure!(
+				MessagesDeliveredAhead::<T, I>::iter_values().count() as u64 == messages_count,
+				"Some messages delivered ahead have not been migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// darwinia-network
+	use super::*;
+	use crate::{
+		mock::{run_test, TestRuntime, TEST_RELAYER_A, TEST_UNORDERED_LANE_ID},
+		OutboundMessages, StoredInboundLaneData,
+	};
+	use bp_messages::{
+		DeliveredMessages, InboundLaneData, MessageData, MessageKey, OutboundLaneData,
+		UnrewardedRelayer,
+	};
+
+	const LEGACY_LANE_ID: bp_messages::LaneId = [0, 0, 0, 9];
+
+	#[test]
+	fn migrate_to_v1_works() {
+		run_test(|| {
+			StorageVersion::new(0).put::<Pallet<TestRuntime>>();
+			let pallet_name = <Pallet<TestRuntime> as PalletInfoAccess>::name().as_bytes();
+			migration::put_storage_value(pallet_name, b"IsHalted", &[], true);
+			OutboundLanes::<TestRuntime>::insert(
+				LEGACY_LANE_ID,
+				OutboundLaneData {
+					oldest_unpruned_nonce: 1,
+					latest_received_nonce: 0,
+					latest_generated_nonce: 1,
+				},
+			);
+			unhashed::put(
+				&OutboundMessages::<TestRuntime>::hashed_key_for(MessageKey {
+					lane_id: LEGACY_LANE_ID,
+					nonce: 1,
+				}),
+				&(vec![42u8], 100u64),
+			);
+
+			v1::MigrateToV1::<TestRuntime>::on_runtime_upgrade();
+
+			assert_eq!(Pallet::<TestRuntime>::on_chain_storage_version(), 1);
+			assert_eq!(migration::get_storage_value::<bool>(pallet_name, b"IsHalted", &[]), None,);
+			assert_eq!(
+				PalletOperatingMode::<TestRuntime>::get(),
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			);
+			assert_eq!(
+				Pallet::<TestRuntime>::lane_metadata(LEGACY_LANE_ID).map(|metadata| metadata.kind),
+				Some(LaneKind::Ordered),
+			);
+			assert_eq!(
+				Pallet::<TestRuntime>::outbound_message_data(LEGACY_LANE_ID, 1),
+				Some(MessageData { payload: vec![42], fee: 100 }),
+			);
+		});
+	}
+
+	#[test]
+	fn migrate_to_v1_keeps_operating_mode_in_current_layout() {
+		run_test(|| {
+			StorageVersion::new(0).put::<Pallet<TestRuntime>>();
+			PalletOperatingMode::<TestRuntime>::put(MessagesOperatingMode::Basic(
+				BasicOperatingMode::Halted,
+			));
+			v1::MigrateToV1::<TestRuntime>::on_runtime_upgrade();
+			assert_eq!(
+				PalletOperatingMode::<TestRuntime>::get(),
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			);
+
+			// while the legacy `BasicOperatingMode::Halted` is migrated
+			StorageVersion::new(0).put::<Pallet<TestRuntime>>();
+			unhashed::put_raw(&PalletOperatingMode::<TestRuntime>::hashed_key(), &[1]);
+			v1::MigrateToV1::<TestRuntime>::on_runtime_upgrade();
+			assert_eq!(
+				PalletOperatingMode::<TestRuntime>::get(),
+				MessagesOperatingMode::Basic(BasicOperatingMode::Halted),
+			);
+
+			// and the legacy `BasicOperatingMode::Normal` is migrated
+			StorageVersion::new(0).put::<Pallet<TestRuntime>>();
+			unhashed::put_raw(&PalletOperatingMode::<TestRuntime>::hashed_key(), &[0]);
+			v1::MigrateToV1::<TestRuntime>::on_runtime_upgrade();
+			assert_eq!(
+				PalletOperatingMode::<TestRuntime>::get(),
+				MessagesOperatingMode::Basic(BasicOperatingMode::Normal),
+			);
+		});
+	}
+
+	#[test]
+	fn migrate_to_v1_is_skipped_if_storage_is_already_migrated() {
+		run_test(|| {
+			StorageVersion::new(1).put::<Pallet<TestRuntime>>();
+			let pallet_name = <Pallet<TestRuntime> as PalletInfoAccess>::name().as_bytes();
+			migration::put_storage_value(pallet_name, b"IsHalted", &[], true);
+
+			v1::MigrateToV1::<TestRuntime>::on_runtime_upgrade();
+
+			assert_eq!(
+				migration::get_storage_value::<bool>(pallet_name, b"IsHalted", &[]),
+				Some(true),
+			);
+			assert_eq!(
+				PalletOperatingMode::<TestRuntime>::get(),
+				MessagesOperatingMode::Basic(BasicOperatingMode::Normal),
+			);
+		});
+	}
+	#[test]
+	fn migrate_to_v2_works() {
+		run_test(|| {
+			StorageVersion::new(1).put::<Pallet<TestRuntime>>();
+			let mut delivered_messages = DeliveredMessages::new(1, true);
+			delivered_messages.note_dispatched_message(false);
+			let inbound_lane_data = InboundLaneData {
+				relayers: vec![UnrewardedRelayer {
+					relayer: TEST_RELAYER_A,
+					messages: delivered_messages,
+				}]
+				.into_iter()
+				.collect(),
+				last_confirmed_nonce: 0,
+			};
+			InboundLanes::<TestRuntime>::insert(
+				LEGACY_LANE_ID,
+				StoredInboundLaneData(inbound_lane_data.clone()),
+			);
+			unhashed::put(
+				&MessagesDeliveredAhead::<TestRuntime>::hashed_key_for(TEST_UNORDERED_LANE_ID, 2),
+				&(TEST_RELAYER_A, true),
+			);
+
+			v2::MigrateToV2::<TestRuntime>::on_runtime_upgrade();
+
+			assert_eq!(Pallet::<TestRuntime>::on_chain_storage_version(), 2);
+			// the inbound lane data is not changed by the migration
+			assert_eq!(InboundLanes::<TestRuntime>::get(LEGACY_LANE_ID).0, inbound_lane_data);
+			assert_eq!(
+				MessagesDeliveredAhead::<TestRuntime>::get(TEST_UNORDERED_LANE_ID, 2),
+				Some((TEST_RELAYER_A, true, None)),
+			);
+		});
+	}
+}
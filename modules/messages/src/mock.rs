@@ -31,16 +31,20 @@ use crate as pallet_bridge_messages;
 use crate::*;
 use bp_messages::{
 	source_chain::{
-		LaneMessageVerifier, MessageDeliveryAndDispatchPayment, OnDeliveryConfirmed,
-		OnMessageAccepted, SenderOrigin, TargetHeaderChain,
+		DeliveryCallbackId, LaneMessageVerifier, MessageDeliveryAndDispatchPayment,
+		OnDeliveryConfirmed, OnMessageAccepted, OnMessageDeliveryResult, SenderOrigin,
+		TargetHeaderChain,
 	},
 	target_chain::{
 		DispatchMessage, MessageDispatch, ProvedLaneMessages, ProvedMessages, SourceHeaderChain,
 	},
-	DeliveredMessages, InboundLaneData, LaneId, Message, MessageData, MessageKey, MessageNonce,
-	OutboundLaneData, Parameter as MessagesParameter, UnrewardedRelayer,
+	DeliveredMessages, InboundLaneData, LaneId, LaneKind, Message, MessageData, MessageKey,
+	MessageNonce, OutboundLaneData, Parameter as MessagesParameter, UnrewardedRelayer,
+};
+use bp_runtime::{
+	messages::{MessageDispatchResult, MessageDispatchStatus},
+	Size,
 };
-use bp_runtime::{messages::MessageDispatchResult, Size};
 // substrate
 use frame_support::{
 	parameter_types,
@@ -51,7 +55,7 @@ use sp_core::{ConstU64, H256};
 use sp_runtime::{
 	testing::Header as SubstrateHeader,
 	traits::{BlakeTwo256, IdentityLookup},
-	FixedU128, Perbill,
+	DispatchError, FixedU128, Perbill,
 };
 
 pub type AccountId = u64;
@@ -84,9 +88,18 @@ pub const TEST_RELAYER_C: AccountId = 102;
 /// Error that is returned by all test implementations.
 pub const TEST_ERROR: &str = "Test error";
 
+/// Error that is returned by the test dispatcher if message dispatch has failed.
+pub const TEST_DISPATCH_ERROR: DispatchError = DispatchError::Other("Test dispatch has failed");
+
 /// Lane that we're using in tests.
 pub const TEST_LANE_ID: LaneId = [0, 0, 0, 1];
 
+/// Lane that we're using in tests, in addition to the `TEST_LANE_ID`.
+pub const TEST_LANE_ID_2: LaneId = [0, 0, 0, 2];
+
+/// Unordered lane that is used in tests.
+pub const TEST_UNORDERED_LANE_ID: LaneId = [0, 0, 0, 5];
+
 /// Regular message payload.
 pub const REGULAR_PAYLOAD: TestPayload = message_payload(0, 50);
 
@@ -189,33 +202,49 @@ impl MessagesParameter for TestMessagesParameter {
 	}
 }
 parameter_types! {
+	pub const MaxFailedMessagesAtInboundLane: u64 = 2;
+	pub const FailedMessageLifetime: u64 = 10;
+	pub const MessageCancellationTimeout: u64 = 10;
 	pub const MaxMessagesToPruneAtOnce: u64 = 10;
 	pub const MaxUnrewardedRelayerEntriesAtInboundLane: u64 = 16;
 	pub const MaxUnconfirmedMessagesAtInboundLane: u64 = 32;
 	pub storage TokenConversionRate: FixedU128 = 1.into();
+	pub storage TestTimestamp: u64 = 0;
+	pub storage MaxDeferredMessagesAtInboundLane: u32 = 0;
+	pub storage DepositLegacyEvents: bool = true;
 	pub const TestBridgedChainId: bp_runtime::ChainId = *b"test";
 }
 impl Config for TestRuntime {
 	type AccountIdConverter = AccountIdConverter;
 	type BridgedChainId = TestBridgedChainId;
+	type DepositLegacyEvents = DepositLegacyEvents;
+	type FailedMessageLifetime = FailedMessageLifetime;
 	type InboundMessageFee = TestMessageFee;
 	type InboundPayload = TestPayload;
 	type InboundRelayer = TestRelayer;
 	type LaneMessageVerifier = TestLaneMessageVerifier;
+	type MaxDeferredMessagesAtInboundLane = MaxDeferredMessagesAtInboundLane;
+	type MaxFailedMessagePayloadSize = frame_support::traits::ConstU32<1024>;
+	type MaxFailedMessagesAtInboundLane = MaxFailedMessagesAtInboundLane;
 	type MaxMessagesToPruneAtOnce = MaxMessagesToPruneAtOnce;
+	type MaxOutboundMessagesInBatch = frame_support::traits::ConstU32<4>;
 	type MaxUnconfirmedMessagesAtInboundLane = MaxUnconfirmedMessagesAtInboundLane;
 	type MaxUnrewardedRelayerEntriesAtInboundLane = MaxUnrewardedRelayerEntriesAtInboundLane;
 	type MaximalOutboundPayloadSize = frame_support::traits::ConstU32<MAX_OUTBOUND_PAYLOAD_SIZE>;
+	type MessageCancellationTimeout = MessageCancellationTimeout;
 	type MessageDeliveryAndDispatchPayment = TestMessageDeliveryAndDispatchPayment;
 	type MessageDispatch = TestMessageDispatch;
+	type MessagesCommitmentHasher = BlakeTwo256;
 	type OnDeliveryConfirmed = (TestOnDeliveryConfirmed1, TestOnDeliveryConfirmed2);
 	type OnMessageAccepted = TestOnMessageAccepted;
+	type OnMessageDeliveryResult = TestOnMessageDeliveryResult;
 	type OutboundMessageFee = TestMessageFee;
 	type OutboundPayload = TestPayload;
 	type Parameter = TestMessagesParameter;
 	type RuntimeEvent = RuntimeEvent;
 	type SourceHeaderChain = TestSourceHeaderChain;
 	type TargetHeaderChain = TestTargetHeaderChain;
+	type UnixTime = TestUnixTime;
 	type WeightInfo = ();
 }
 
@@ -327,6 +356,11 @@ impl TestMessageDeliveryAndDispatchPayment {
 		frame_support::storage::unhashed::get(b":message-fee:") == Some((raw_origin.unwrap(), fee))
 	}
 
+	/// Returns true if given fee has been refunded to given sender.
+	pub fn is_fee_refunded(sender: AccountId, fee: TestMessageFee) -> bool {
+		frame_support::storage::unhashed::get(b":refunded-fee:") == Some((sender, fee))
+	}
+
 	/// Returns true if given relayer has been rewarded with given balance. The reward-paid flag is
 	/// cleared after the call.
 	pub fn is_reward_paid(relayer: AccountId, fee: TestMessageFee) -> bool {
@@ -411,6 +445,21 @@ impl MessageDeliveryAndDispatchPayment<RuntimeOrigin, AccountId, TestMessageFee>
 			frame_support::storage::unhashed::put(&key, &true);
 		}
 	}
+
+	fn refund_fee(
+		_lane_id: LaneId,
+		_nonce: MessageNonce,
+		sender: &AccountId,
+		fee: &TestMessageFee,
+		_relayer_fund_account: &AccountId,
+	) -> Result<(), VerificationError> {
+		if frame_support::storage::unhashed::get(b":reject-message-fee:") == Some(true) {
+			return Err(VerificationError::Other(TEST_ERROR));
+		}
+
+		frame_support::storage::unhashed::put(b":refunded-fee:", &(sender, fee));
+		Ok(())
+	}
 }
 
 #[derive(Debug)]
@@ -480,6 +529,12 @@ impl TestOnDeliveryConfirmed2 {
 		let key = (b"TestOnDeliveryConfirmed2", lane, messages).encode();
 		assert_eq!(frame_support::storage::unhashed::get(&key), Some(true));
 	}
+
+	/// Return expired messages the callback has been called with.
+	pub fn expired_messages(lane: &LaneId) -> Option<Vec<MessageNonce>> {
+		let key = (b"TestOnDeliveryConfirmed2_Expired", lane).encode();
+		frame_support::storage::unhashed::get(&key)
+	}
 }
 impl OnDeliveryConfirmed for TestOnDeliveryConfirmed2 {
 	fn on_messages_delivered(lane: &LaneId, messages: &DeliveredMessages) -> Weight {
@@ -487,6 +542,67 @@ impl OnDeliveryConfirmed for TestOnDeliveryConfirmed2 {
 		frame_support::storage::unhashed::put(&key, &true);
 		Weight::zero()
 	}
+
+	fn on_messages_expired(lane: &LaneId, messages: &[MessageNonce]) -> Weight {
+		let key = (b"TestOnDeliveryConfirmed2_Expired", lane).encode();
+		frame_support::storage::unhashed::put(&key, &messages);
+		Weight::zero()
+	}
+}
+
+/// Delivery callback id that is supported by the `TestOnMessageDeliveryResult`.
+pub const TEST_DELIVERY_CALLBACK_ID: DeliveryCallbackId = 1;
+
+/// Delivery callback id that is not supported by any handler.
+pub const UNKNOWN_DELIVERY_CALLBACK_ID: DeliveryCallbackId = 2;
+
+/// Delivery result callback.
+#[derive(Debug)]
+pub struct TestOnMessageDeliveryResult;
+impl TestOnMessageDeliveryResult {
+	/// Return dispatch result and status the callback has been called with.
+	pub fn delivery_result(
+		lane: &LaneId,
+		nonce: MessageNonce,
+	) -> Option<(bool, Option<MessageDispatchStatus>)> {
+		let key = (b"TestOnMessageDeliveryResult", lane, nonce).encode();
+		frame_support::storage::unhashed::get(&key)
+	}
+
+	/// Set consumed weight returned by the callback.
+	pub fn set_consumed_weight(weight: Weight) {
+		frame_support::storage::unhashed::put(b"TestOnMessageDeliveryResult_Weight", &weight);
+	}
+
+	/// Get consumed weight returned by the callback.
+	pub fn get_consumed_weight() -> Option<Weight> {
+		frame_support::storage::unhashed::get(b"TestOnMessageDeliveryResult_Weight")
+	}
+}
+impl OnMessageDeliveryResult for TestOnMessageDeliveryResult {
+	fn is_delivery_callback_supported(callback_id: DeliveryCallbackId) -> bool {
+		callback_id == TEST_DELIVERY_CALLBACK_ID
+	}
+
+	fn on_message_delivery_result(
+		_callback_id: DeliveryCallbackId,
+		lane: &LaneId,
+		nonce: MessageNonce,
+		dispatch_result: bool,
+		dispatch_status: Option<MessageDispatchStatus>,
+	) -> Weight {
+		let key = (b"TestOnMessageDeliveryResult", lane, nonce).encode();
+		frame_support::storage::unhashed::put(&key, &(dispatch_result, dispatch_status));
+		Self::get_consumed_weight().unwrap_or_else(|| DbWeight::get().reads_writes(1, 1))
+	}
+}
+
+/// Time source that is used in tests.
+pub struct TestUnixTime;
+impl frame_support::traits::UnixTime for TestUnixTime {
+	fn now() -> core::time::Duration {
+		core::time::Duration::from_millis(TestTimestamp::get())
+	}
 }
 
 /// Source header chain that is used in tests.
@@ -504,11 +620,21 @@ impl SourceHeaderChain<TestMessageFee> for TestSourceHeaderChain {
 			.map(|proof| proof.into_iter().collect())
 			.map_err(|_| VerificationError::Other(TEST_ERROR))
 	}
+
+	fn lanes_count(proof: &Self::MessagesProof) -> u32 {
+		proof.result.as_ref().map(|lanes| lanes.len().max(1) as u32).unwrap_or(1)
+	}
 }
 
 /// Source header chain that is used in tests.
 #[derive(Debug)]
 pub struct TestMessageDispatch;
+impl TestMessageDispatch {
+	/// Make dispatcher report dispatch statuses of all following messages.
+	pub fn report_dispatch_statuses() {
+		frame_support::storage::unhashed::put(b":report-dispatch-statuses:", &true);
+	}
+}
 impl MessageDispatch<AccountId, TestMessageFee> for TestMessageDispatch {
 	type DispatchPayload = TestPayload;
 
@@ -531,7 +657,23 @@ impl MessageDispatch<AccountId, TestMessageFee> for TestMessageDispatch {
 		message: DispatchMessage<TestPayload, TestMessageFee>,
 	) -> MessageDispatchResult {
 		match message.data.payload.as_ref() {
-			Ok(payload) => payload.dispatch_result.clone(),
+			Ok(payload) => {
+				let mut dispatch_result = payload.dispatch_result.clone();
+				// the dispatch error isn't encoded, so it is never a part of the payload
+				if !dispatch_result.dispatch_result {
+					dispatch_result.dispatch_error = Some(TEST_DISPATCH_ERROR);
+				}
+				if frame_support::storage::unhashed::get(b":report-dispatch-statuses:")
+					== Some(true)
+				{
+					dispatch_result.dispatch_status = Some(if dispatch_result.dispatch_result {
+						MessageDispatchStatus::Dispatched
+					} else {
+						MessageDispatchStatus::CallFailed
+					});
+				}
+				dispatch_result
+			},
 			Err(_) => dispatch_result(0),
 		}
 	}
@@ -539,7 +681,11 @@ impl MessageDispatch<AccountId, TestMessageFee> for TestMessageDispatch {
 
 /// Return test lane message with given nonce and payload.
 pub fn message(nonce: MessageNonce, payload: TestPayload) -> Message<TestMessageFee> {
-	Message { key: MessageKey { lane_id: TEST_LANE_ID, nonce }, data: message_data(payload) }
+	Message {
+		key: MessageKey { lane_id: TEST_LANE_ID, nonce },
+		data: message_data(payload),
+		expiry: None,
+	}
 }
 
 /// Constructs message payload using given arguments and zero unspent weight.
@@ -563,6 +709,8 @@ pub const fn dispatch_result(unspent_weight: u64) -> MessageDispatchResult {
 		dispatch_result: true,
 		unspent_weight: Weight::from_parts(unspent_weight, 0),
 		dispatch_fee_paid_during_dispatch: true,
+		dispatch_error: None,
+		dispatch_status: None,
 	}
 }
 
@@ -582,6 +730,7 @@ pub fn unrewarded_relayer(
 			} else {
 				Default::default()
 			},
+			dispatch_statuses: Vec::new(),
 		},
 	}
 }
@@ -592,6 +741,18 @@ pub fn run_test<T>(test: impl FnOnce() -> T) -> T {
 	pallet_balances::GenesisConfig::<TestRuntime> { balances: vec![(ENDOWED_ACCOUNT, 1_000_000)] }
 		.assimilate_storage(&mut t)
 		.unwrap();
+	frame_support::traits::GenesisBuild::<TestRuntime, ()>::assimilate_storage(
+		&pallet_bridge_messages::GenesisConfig::<TestRuntime> {
+			opened_lanes: vec![
+				(TEST_LANE_ID, LaneKind::Ordered),
+				(TEST_LANE_ID_2, LaneKind::Ordered),
+				(TEST_UNORDERED_LANE_ID, LaneKind::Unordered),
+			],
+			..Default::default()
+		},
+		&mut t,
+	)
+	.unwrap();
 	let mut ext = sp_io::TestExternalities::new(t);
 	ext.execute_with(test)
 }
@@ -23,12 +23,13 @@ use scale_info::{Type, TypeInfo};
 // darwinia-network
 use crate::Config;
 use bp_messages::{
-	DeliveredMessages, DispatchResultsBitVec, LaneId, MessageData, MessageNonce, OutboundLaneData,
-	UnrewardedRelayer,
+	DeliveredMessages, DispatchResultsBitVec, LaneId, MessageData, MessageExpiry, MessageNonce,
+	OutboundLaneData, OutboundMessageData, UnrewardedRelayer,
 };
+use bp_runtime::messages::MessageDispatchStatus;
 // substrate
 use frame_support::{traits::Get, RuntimeDebug};
-use sp_std::collections::vec_deque::VecDeque;
+use sp_std::{collections::vec_deque::VecDeque, prelude::*};
 
 /// Outbound lane storage.
 pub trait OutboundLaneStorage {
@@ -45,7 +46,11 @@ pub trait OutboundLaneStorage {
 	#[cfg(test)]
 	fn message(&self, nonce: &MessageNonce) -> Option<MessageData<Self::MessageFee>>;
 	/// Save outbound message in the storage.
-	fn save_message(&mut self, nonce: MessageNonce, message_data: MessageData<Self::MessageFee>);
+	fn save_message(
+		&mut self,
+		nonce: MessageNonce,
+		message_data: OutboundMessageData<Self::MessageFee>,
+	);
 	/// Remove outbound message from the storage.
 	fn remove_message(&mut self, nonce: &MessageNonce);
 }
@@ -56,35 +61,48 @@ pub trait OutboundLaneStorage {
 /// been localized and we haven't been passing it everywhere. This wrapper allows us
 /// to avoid passing these generic bounds all over the code.
 ///
-/// The encoding of this type matches encoding of the corresponding `MessageData`.
+/// The encoding of this type matches encoding of the corresponding `OutboundMessageData`. So
+/// the encoding of messages without expiry matches encoding of the `MessageData`.
 #[derive(Encode, Decode, Clone, RuntimeDebug, PartialEq, Eq)]
-pub struct StoredMessageData<T: Config<I>, I: 'static>(pub MessageData<T::OutboundMessageFee>);
+pub struct StoredMessageData<T: Config<I>, I: 'static>(
+	pub OutboundMessageData<T::OutboundMessageFee>,
+);
+impl<T: Config<I>, I: 'static> StoredMessageData<T, I> {
+	/// Returns the message expiry, if the message has been sent with expiry.
+	pub fn expiry(&self) -> Option<MessageExpiry> {
+		self.0.expiry
+	}
+}
 impl<T: Config<I>, I: 'static> sp_std::ops::Deref for StoredMessageData<T, I> {
 	type Target = MessageData<T::OutboundMessageFee>;
 
 	fn deref(&self) -> &Self::Target {
-		&self.0
+		&self.0.data
 	}
 }
 impl<T: Config<I>, I: 'static> sp_std::ops::DerefMut for StoredMessageData<T, I> {
 	fn deref_mut(&mut self) -> &mut Self::Target {
-		&mut self.0
+		&mut self.0.data
 	}
 }
 impl<T: Config<I>, I: 'static> From<StoredMessageData<T, I>>
 	for MessageData<T::OutboundMessageFee>
 {
 	fn from(data: StoredMessageData<T, I>) -> Self {
-		data.0
+		data.0.data
 	}
 }
 impl<T: Config<I>, I: 'static> TypeInfo for StoredMessageData<T, I> {
 	type Identity = Self;
 
 	fn type_info() -> Type {
-		MessageData::<T::OutboundMessageFee>::type_info()
+		OutboundMessageData::<T::OutboundMessageFee>::type_info()
 	}
 }
+impl<T: Config<I>, I: 'static> EncodeLike<StoredMessageData<T, I>>
+	for OutboundMessageData<T::OutboundMessageFee>
+{
+}
 impl<T: Config<I>, I: 'static> EncodeLike<StoredMessageData<T, I>>
 	for MessageData<T::OutboundMessageFee>
 {
@@ -93,6 +111,7 @@ impl<T: Config<I>, I: 'static> MaxEncodedLen for StoredMessageData<T, I> {
 	fn max_encoded_len() -> usize {
 		T::OutboundMessageFee::max_encoded_len()
 			.saturating_add(T::MaximalOutboundPayloadSize::get() as usize)
+			.saturating_add(MessageExpiry::max_encoded_len())
 	}
 }
 
@@ -116,6 +135,10 @@ pub enum ReceivalConfirmationResult {
 	/// The unrewarded relayers vec contains entry with mismatched number of dispatch results. May
 	/// be a result of invalid bridged chain storage.
 	InvalidNumberOfDispatchResults,
+	/// The unrewarded relayers vec contains entry with non-empty dispatch statuses vec, whose
+	/// length doesn't match the number of messages. May be a result of invalid bridged chain
+	/// storage.
+	InvalidNumberOfDispatchStatuses,
 	/// The chain has more messages that need to be confirmed than there is in the proof.
 	TryingToConfirmMoreMessagesThanExpected(MessageNonce),
 }
@@ -138,7 +161,10 @@ impl<S: OutboundLaneStorage> OutboundLane<S> {
 	/// Send message over lane.
 	///
 	/// Returns new message nonce.
-	pub fn send_message(&mut self, message_data: MessageData<S::MessageFee>) -> MessageNonce {
+	pub fn send_message(
+		&mut self,
+		message_data: OutboundMessageData<S::MessageFee>,
+	) -> MessageNonce {
 		let mut data = self.storage.data();
 		let nonce = data.latest_generated_nonce + 1;
 		data.latest_generated_nonce = nonce;
@@ -174,7 +200,7 @@ impl<S: OutboundLaneStorage> OutboundLane<S> {
 			);
 		}
 
-		let dispatch_results = match extract_dispatch_results(
+		let (dispatch_results, dispatch_statuses) = match extract_dispatch_results(
 			data.latest_received_nonce,
 			latest_delivered_nonce,
 			relayers,
@@ -191,6 +217,7 @@ impl<S: OutboundLaneStorage> OutboundLane<S> {
 			begin: prev_latest_received_nonce + 1,
 			end: latest_delivered_nonce,
 			dispatch_results,
+			dispatch_statuses,
 		})
 	}
 
@@ -217,7 +244,10 @@ impl<S: OutboundLaneStorage> OutboundLane<S> {
 	}
 }
 
-/// Extract new dispatch results from the unrewarded relayers vec.
+/// Extract new dispatch results and dispatch statuses from the unrewarded relayers vec.
+///
+/// Dispatch statuses are only returned if they're known for all newly confirmed messages.
+/// Otherwise the returned statuses vec is empty.
 ///
 /// Returns `Err(_)` if unrewarded relayers vec contains invalid data, meaning that the bridged
 /// chain has invalid runtime storage.
@@ -225,13 +255,15 @@ fn extract_dispatch_results<RelayerId>(
 	prev_latest_received_nonce: MessageNonce,
 	latest_received_nonce: MessageNonce,
 	relayers: &VecDeque<UnrewardedRelayer<RelayerId>>,
-) -> Result<DispatchResultsBitVec, ReceivalConfirmationResult> {
+) -> Result<(DispatchResultsBitVec, Vec<MessageDispatchStatus>), ReceivalConfirmationResult> {
 	// the only caller of this functions checks that the
 	// prev_latest_received_nonce..=latest_received_nonce is valid, so we're ready to accept
 	// messages in this range => with_capacity call must succeed here or we'll be unable to receive
 	// confirmations at all
 	let mut received_dispatch_result =
 		BitVec::with_capacity((latest_received_nonce - prev_latest_received_nonce + 1) as _);
+	let mut received_dispatch_statuses = Vec::new();
+	let mut has_all_dispatch_statuses = true;
 	let mut last_entry_end: Option<MessageNonce> = None;
 	for entry in relayers {
 		// unrewarded relayer entry must have at least 1 unconfirmed message
@@ -263,6 +295,14 @@ fn extract_dispatch_results<RelayerId>(
 		{
 			return Err(ReceivalConfirmationResult::InvalidNumberOfDispatchResults);
 		}
+		// entry must either have no dispatch statuses, or single status for every message
+		// (guaranteed by the `InboundLane::receive_message()`)
+		if !entry.messages.dispatch_statuses.is_empty()
+			&& entry.messages.dispatch_statuses.len() as MessageNonce
+				!= entry.messages.end - entry.messages.begin + 1
+		{
+			return Err(ReceivalConfirmationResult::InvalidNumberOfDispatchStatuses);
+		}
 
 		// now we know that the entry is valid
 		// => let's check if it brings new confirmations
@@ -280,9 +320,21 @@ fn extract_dispatch_results<RelayerId>(
 			&entry.messages.dispatch_results
 				[(new_messages_begin - entry.messages.begin) as usize..],
 		);
+		if entry.messages.dispatch_statuses.is_empty() {
+			has_all_dispatch_statuses = false;
+		} else if has_all_dispatch_statuses {
+			received_dispatch_statuses.extend_from_slice(
+				&entry.messages.dispatch_statuses
+					[(new_messages_begin - entry.messages.begin) as usize..],
+			);
+		}
+	}
+
+	if !has_all_dispatch_statuses {
+		received_dispatch_statuses.clear();
 	}
 
-	Ok(received_dispatch_result)
+	Ok((received_dispatch_result, received_dispatch_statuses))
 }
 
 #[cfg(test)]
@@ -308,18 +360,26 @@ mod tests {
 			begin: *nonces.start(),
 			end: *nonces.end(),
 			dispatch_results: bitvec![u8, Msb0; 1; (nonces.end() - nonces.start() + 1) as _],
+			dispatch_statuses: Vec::new(),
 		}
 	}
 
+	fn delivered_messages_with_statuses(
+		nonces: RangeInclusive<MessageNonce>,
+		dispatch_statuses: Vec<MessageDispatchStatus>,
+	) -> DeliveredMessages {
+		DeliveredMessages { dispatch_statuses, ..delivered_messages(nonces) }
+	}
+
 	fn assert_3_messages_confirmation_fails(
 		latest_received_nonce: MessageNonce,
 		relayers: &VecDeque<UnrewardedRelayer<TestRelayer>>,
 	) -> ReceivalConfirmationResult {
 		run_test(|| {
 			let mut lane = outbound_lane::<TestRuntime, _>(TEST_LANE_ID);
-			lane.send_message(message_data(REGULAR_PAYLOAD));
-			lane.send_message(message_data(REGULAR_PAYLOAD));
-			lane.send_message(message_data(REGULAR_PAYLOAD));
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
 			assert_eq!(lane.storage.data().latest_generated_nonce, 3);
 			assert_eq!(lane.storage.data().latest_received_nonce, 0);
 			let result = lane.confirm_delivery(3, latest_received_nonce, relayers);
@@ -334,7 +394,7 @@ mod tests {
 		run_test(|| {
 			let mut lane = outbound_lane::<TestRuntime, _>(TEST_LANE_ID);
 			assert_eq!(lane.storage.data().latest_generated_nonce, 0);
-			assert_eq!(lane.send_message(message_data(REGULAR_PAYLOAD)), 1);
+			assert_eq!(lane.send_message(message_data(REGULAR_PAYLOAD).into()), 1);
 			assert!(lane.storage.message(&1).is_some());
 			assert_eq!(lane.storage.data().latest_generated_nonce, 1);
 		});
@@ -344,9 +404,9 @@ mod tests {
 	fn confirm_delivery_works() {
 		run_test(|| {
 			let mut lane = outbound_lane::<TestRuntime, _>(TEST_LANE_ID);
-			assert_eq!(lane.send_message(message_data(REGULAR_PAYLOAD)), 1);
-			assert_eq!(lane.send_message(message_data(REGULAR_PAYLOAD)), 2);
-			assert_eq!(lane.send_message(message_data(REGULAR_PAYLOAD)), 3);
+			assert_eq!(lane.send_message(message_data(REGULAR_PAYLOAD).into()), 1);
+			assert_eq!(lane.send_message(message_data(REGULAR_PAYLOAD).into()), 2);
+			assert_eq!(lane.send_message(message_data(REGULAR_PAYLOAD).into()), 3);
 			assert_eq!(lane.storage.data().latest_generated_nonce, 3);
 			assert_eq!(lane.storage.data().latest_received_nonce, 0);
 			assert_eq!(
@@ -362,9 +422,9 @@ mod tests {
 	fn confirm_delivery_rejects_nonce_lesser_than_latest_received() {
 		run_test(|| {
 			let mut lane = outbound_lane::<TestRuntime, _>(TEST_LANE_ID);
-			lane.send_message(message_data(REGULAR_PAYLOAD));
-			lane.send_message(message_data(REGULAR_PAYLOAD));
-			lane.send_message(message_data(REGULAR_PAYLOAD));
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
 			assert_eq!(lane.storage.data().latest_generated_nonce, 3);
 			assert_eq!(lane.storage.data().latest_received_nonce, 0);
 			assert_eq!(
@@ -455,6 +515,62 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn confirm_delivery_fails_if_number_of_dispatch_statuses_in_entry_is_invalid() {
+		let mut relayers: VecDeque<_> = unrewarded_relayers(1..=1)
+			.into_iter()
+			.chain(unrewarded_relayers(2..=3).into_iter())
+			.collect();
+		relayers[1].messages.dispatch_statuses = vec![MessageDispatchStatus::Dispatched];
+		assert_eq!(
+			assert_3_messages_confirmation_fails(3, &relayers),
+			ReceivalConfirmationResult::InvalidNumberOfDispatchStatuses,
+		);
+	}
+
+	#[test]
+	fn confirm_delivery_returns_dispatch_statuses_if_all_are_known() {
+		run_test(|| {
+			let mut lane = outbound_lane::<TestRuntime, _>(TEST_LANE_ID);
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+
+			let mut relayers: VecDeque<_> = unrewarded_relayers(1..=2)
+				.into_iter()
+				.chain(unrewarded_relayers(3..=3).into_iter())
+				.collect();
+			relayers[0].messages.dispatch_statuses =
+				vec![MessageDispatchStatus::Dispatched, MessageDispatchStatus::CallFailed];
+			relayers[1].messages.dispatch_statuses = vec![MessageDispatchStatus::Expired];
+			let mut first_relayer = unrewarded_relayers(1..=1);
+			first_relayer[0].messages.dispatch_statuses = vec![MessageDispatchStatus::Dispatched];
+
+			assert_eq!(
+				lane.confirm_delivery(1, 1, &first_relayer),
+				ReceivalConfirmationResult::ConfirmedMessages(delivered_messages_with_statuses(
+					1..=1,
+					vec![MessageDispatchStatus::Dispatched]
+				)),
+			);
+			// message#1 is already confirmed, so only statuses of new messages are returned
+			assert_eq!(
+				lane.confirm_delivery(2, 3, &relayers),
+				ReceivalConfirmationResult::ConfirmedMessages(delivered_messages_with_statuses(
+					2..=3,
+					vec![MessageDispatchStatus::CallFailed, MessageDispatchStatus::Expired]
+				)),
+			);
+			// status of message#4 is unknown, so no statuses are returned
+			relayers.extend(unrewarded_relayers(4..=4));
+			assert_eq!(
+				lane.confirm_delivery(1, 4, &relayers),
+				ReceivalConfirmationResult::ConfirmedMessages(delivered_messages(4..=4)),
+			);
+		});
+	}
+
 	#[test]
 	fn prune_messages_works() {
 		run_test(|| {
@@ -463,9 +579,9 @@ mod tests {
 			assert_eq!(lane.prune_messages(100), 0);
 			assert_eq!(lane.storage.data().oldest_unpruned_nonce, 1);
 			// when nothing is confirmed, nothing is pruned
-			lane.send_message(message_data(REGULAR_PAYLOAD));
-			lane.send_message(message_data(REGULAR_PAYLOAD));
-			lane.send_message(message_data(REGULAR_PAYLOAD));
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
 			assert_eq!(lane.prune_messages(100), 0);
 			assert_eq!(lane.storage.data().oldest_unpruned_nonce, 1);
 			// after confirmation, some messages are received
@@ -489,9 +605,9 @@ mod tests {
 	fn confirm_delivery_detects_when_more_than_expected_messages_are_confirmed() {
 		run_test(|| {
 			let mut lane = outbound_lane::<TestRuntime, _>(TEST_LANE_ID);
-			lane.send_message(message_data(REGULAR_PAYLOAD));
-			lane.send_message(message_data(REGULAR_PAYLOAD));
-			lane.send_message(message_data(REGULAR_PAYLOAD));
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
+			lane.send_message(message_data(REGULAR_PAYLOAD).into());
 			assert_eq!(
 				lane.confirm_delivery(0, 3, &unrewarded_relayers(1..=3)),
 				ReceivalConfirmationResult::TryingToConfirmMoreMessagesThanExpected(3),
@@ -51,6 +51,8 @@ pub trait WeightInfo {
 	fn send_minimal_message_worst_case() -> Weight;
 	fn send_1_kb_message_worst_case() -> Weight;
 	fn send_16_kb_message_worst_case() -> Weight;
+	fn maximal_increase_message_fee() -> Weight;
+	fn increase_message_fee(i: u32) -> Weight;
 	fn receive_single_message_proof() -> Weight;
 	fn receive_two_messages_proof() -> Weight;
 	fn receive_single_message_proof_with_outbound_lane_state() -> Weight;
@@ -60,6 +62,9 @@ pub trait WeightInfo {
 	fn receive_delivery_proof_for_single_message() -> Weight;
 	fn receive_delivery_proof_for_two_messages_by_single_relayer() -> Weight;
 	fn receive_delivery_proof_for_two_messages_by_two_relayers() -> Weight;
+	fn prune_outbound_messages(i: u32) -> Weight;
+	fn send_messages(i: u32) -> Weight;
+	fn commit_outbound_messages(i: u32) -> Weight;
 }
 
 // For backwards compatibility and tests
@@ -76,6 +81,14 @@ impl WeightInfo for () {
 		sp_runtime::traits::Zero::zero()
 	}
 
+	fn maximal_increase_message_fee() -> Weight {
+		sp_runtime::traits::Zero::zero()
+	}
+
+	fn increase_message_fee(_i: u32) -> Weight {
+		sp_runtime::traits::Zero::zero()
+	}
+
 	fn receive_single_message_proof() -> Weight {
 		sp_runtime::traits::Zero::zero()
 	}
@@ -111,4 +124,16 @@ impl WeightInfo for () {
 	fn receive_delivery_proof_for_two_messages_by_two_relayers() -> Weight {
 		sp_runtime::traits::Zero::zero()
 	}
+
+	fn prune_outbound_messages(_i: u32) -> Weight {
+		sp_runtime::traits::Zero::zero()
+	}
+
+	fn send_messages(_i: u32) -> Weight {
+		sp_runtime::traits::Zero::zero()
+	}
+
+	fn commit_outbound_messages(_i: u32) -> Weight {
+		sp_runtime::traits::Zero::zero()
+	}
 }
@@ -47,6 +47,18 @@ pub fn ensure_weights_are_correct<W: WeightInfoExt>(
 	assert_ne!(W::send_message_overhead(), Weight::zero());
 	assert_ne!(W::send_message_size_overhead(0), Weight::zero());
 
+	// verify that batch of messages is not more expensive than sending messages one-by-one
+	let minimal_message = PreComputedSize(0);
+	let actual_send_messages_weight = W::send_messages_weight([0, 0].into_iter(), db_weight);
+	let expected_send_messages_weight =
+		W::send_message_weight(&minimal_message, db_weight).saturating_mul(2);
+	assert!(
+		actual_send_messages_weight.all_lte(expected_send_messages_weight),
+		"Weight {} of batch with two messages is larger than weight {} of two `send_message` calls",
+		actual_send_messages_weight,
+		expected_send_messages_weight,
+	);
+
 	// verify `receive_messages_proof` weight components
 	assert_ne!(W::receive_messages_proof_overhead(), Weight::zero());
 	assert_ne!(W::receive_messages_proof_messages_overhead(1), Weight::zero());
@@ -210,6 +222,54 @@ pub trait WeightInfoExt: WeightInfo {
 		transaction_overhead
 			.saturating_add(message_size_overhead)
 			.saturating_add(call_back_overhead)
+			.saturating_add(Self::send_message_storage_overhead(db_weight))
+			.saturating_add(Self::outbound_rate_limits_overhead(db_weight))
+	}
+
+	/// Weight of `send_messages` call.
+	fn send_messages_weight(
+		payload_sizes: impl Iterator<Item = u32>,
+		db_weight: RuntimeDbWeight,
+	) -> Weight {
+		let mut messages_count = 0u32;
+		let mut messages_overhead = Weight::zero();
+		for payload_size in payload_sizes {
+			messages_count += 1;
+			messages_overhead = messages_overhead
+				.saturating_add(Self::send_message_size_overhead(payload_size))
+				.saturating_add(Self::single_message_callback_overhead(db_weight))
+				.saturating_add(Self::send_message_storage_overhead(db_weight))
+				.saturating_add(Self::outbound_rate_limits_overhead(db_weight));
+		}
+
+		Self::send_messages(messages_count).saturating_add(messages_overhead)
+	}
+
+	/// Weight of storing single message details that are not covered by the benchmarks.
+	///
+	/// It includes the cost of committing to the message in `on_finalize` and clearing its
+	/// commitment leaf index in the next block.
+	fn send_message_storage_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		// the message sender is saved to allow message cancellation
+		let sender_overhead = db_weight.writes(1);
+		// the message is appended to the commitment of the current block, its leaf index is
+		// saved and then removed in the next block
+		let commitment_overhead =
+			db_weight.reads_writes(1, 3).saturating_add(Self::commit_outbound_message_overhead());
+
+		sender_overhead.saturating_add(commitment_overhead)
+	}
+
+	/// Weight of checking and updating outbound rate limits of the lane and the sender.
+	fn outbound_rate_limits_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		// limits and usages of the lane and the sender are read, usages are written back
+		db_weight.reads_writes(4, 2)
+	}
+
+	/// Weight of `increase_message_fee` call.
+	fn increase_message_fee_weight(db_weight: RuntimeDbWeight) -> Weight {
+		Self::maximal_increase_message_fee()
+			.saturating_add(Self::single_message_callback_overhead(db_weight))
 	}
 
 	/// Weight of message delivery extrinsic.
@@ -242,6 +302,66 @@ pub trait WeightInfoExt: WeightInfo {
 			.saturating_add(proof_size_overhead)
 	}
 
+	/// Weight of message delivery extrinsic that brings messages of `lanes_count` lanes.
+	///
+	/// It assumes that all messages are delivered to unordered lanes and that up to
+	/// `max_drained_messages` messages, that have been delivered ahead, are appended to the
+	/// unrewarded relayers sets. The unordered lane overhead is refunded for messages of ordered
+	/// lanes and the overhead of messages that are not appended is refunded too.
+	fn receive_multi_lane_messages_proof_weight(
+		proof: &impl Size,
+		lanes_count: u32,
+		messages_count: u32,
+		max_drained_messages: MessageNonce,
+		dispatch_weight: Weight,
+		db_weight: RuntimeDbWeight,
+	) -> Weight {
+		Self::receive_messages_proof_weight(proof, messages_count, dispatch_weight)
+			.saturating_add(Self::receive_messages_proof_lanes_overhead(lanes_count, db_weight))
+			.saturating_add(
+				Self::receive_unordered_message_overhead(db_weight)
+					.saturating_add(Self::receive_failed_message_overhead(db_weight))
+					.saturating_mul(messages_count as _),
+			)
+			.saturating_add(
+				Self::receive_deferred_messages_queue_overhead(db_weight)
+					.saturating_add(Self::receive_dispatch_statuses_overhead(db_weight))
+					.saturating_mul(lanes_count as _),
+			)
+			.saturating_add(
+				Self::drain_delivered_ahead_message_overhead(db_weight)
+					.saturating_mul(max_drained_messages),
+			)
+	}
+
+	/// Weight of `retry_message_dispatch` call.
+	fn retry_message_dispatch_weight(
+		dispatch_weight: Weight,
+		db_weight: RuntimeDbWeight,
+	) -> Weight {
+		// we read pallet and lane operating modes, the failed message and the failed messages
+		// counter and then (if dispatch succeeds) remove the message and update the counter
+		db_weight.reads_writes(4, 2).saturating_add(dispatch_weight)
+	}
+
+	/// Weight of `cancel_message` call.
+	fn cancel_message_weight(proof: &impl Size, db_weight: RuntimeDbWeight) -> Weight {
+		// the delivery proof is verified the same way as in the confirmation delivery extrinsic
+		let transaction_overhead = Self::receive_messages_delivery_proof_overhead();
+		let proof_size_overhead = Self::storage_proof_size_overhead(
+			proof.size().saturating_sub(Self::expected_extra_storage_proof_size()),
+		);
+		// we read pallet and lane operating modes, the message, its sender, the lane metadata and
+		// the lane state and then replace the message, remove its sender and remember the
+		// cancelled message. The fee is refunded later, when the delivery of the message is
+		// confirmed
+		let cancellation_overhead = db_weight.reads_writes(6, 3);
+
+		transaction_overhead
+			.saturating_add(proof_size_overhead)
+			.saturating_add(cancellation_overhead)
+	}
+
 	/// Weight of confirmation delivery extrinsic.
 	fn receive_messages_delivery_proof_weight(
 		proof: &impl Size,
@@ -268,11 +388,26 @@ pub trait WeightInfoExt: WeightInfo {
 		let callback_overhead = Self::single_message_callback_overhead(db_weight)
 			.saturating_mul(relayers_state.total_messages);
 
+		// we may need to read every confirmed message to check whether it has expired
+		let expiry_check_overhead = db_weight.reads(relayers_state.total_messages);
+
+		// and every confirmed message may have been sent with the delivery callback
+		let delivery_result_callback_overhead =
+			Self::single_message_delivery_result_callback_overhead(db_weight)
+				.saturating_mul(relayers_state.total_messages);
+
+		// and every confirmed message may have been cancelled and needs to be refunded
+		let cancelled_message_refund_overhead = Self::cancelled_message_refund_overhead(db_weight)
+			.saturating_mul(relayers_state.total_messages);
+
 		transaction_overhead
 			.saturating_add(messages_overhead)
 			.saturating_add(relayers_overhead)
 			.saturating_add(proof_size_overhead)
 			.saturating_add(callback_overhead)
+			.saturating_add(expiry_check_overhead)
+			.saturating_add(delivery_result_callback_overhead)
+			.saturating_add(cancelled_message_refund_overhead)
 	}
 
 	// Functions that are used by extrinsics weights formulas.
@@ -319,6 +454,94 @@ pub trait WeightInfoExt: WeightInfo {
 		weight_of_single_message_and_lane_state.saturating_sub(weight_of_single_message)
 	}
 
+	/// Returns weight that needs to be accounted when message delivery transaction
+	/// (`receive_messages_proof`) is carrying messages of `lanes_count` lanes.
+	///
+	/// The first lane is already accounted by the base formula. Every other lane brings its
+	/// own outbound lane state and requires reading and updating its inbound lane state.
+	fn receive_messages_proof_lanes_overhead(
+		lanes_count: u32,
+		db_weight: RuntimeDbWeight,
+	) -> Weight {
+		Self::receive_messages_proof_outbound_lane_state_overhead()
+			.saturating_add(db_weight.reads_writes(1, 1))
+			.saturating_mul(lanes_count.saturating_sub(1) as _)
+	}
+
+	/// Returns weight that needs to be accounted for every message that is delivered to the
+	/// unordered lane.
+	///
+	/// The message may be delivered ahead of previous messages. Then we need to update the
+	/// bitmap of delivered messages and to save the message relayer.
+	fn receive_unordered_message_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(1, 2)
+	}
+
+	/// Returns weight of appending single message, that has been delivered ahead, to the
+	/// unrewarded relayers set of the unordered lane.
+	///
+	/// The saved message relayer is read and removed and the bitmap of delivered messages is
+	/// updated.
+	fn drain_delivered_ahead_message_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(1, 2)
+	}
+
+	/// Returns weight that needs to be accounted for every delivered message, because its
+	/// dispatch may fail and then the message is kept for retry.
+	fn receive_failed_message_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(1, 2)
+	}
+
+	/// Returns weight that needs to be accounted for every lane of the delivery transaction,
+	/// because its messages may be deferred. The deferred message itself costs the same as
+	/// the failed message.
+	fn receive_deferred_messages_queue_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(1, 1)
+	}
+
+	/// Returns weight that needs to be accounted for every lane of the delivery transaction,
+	/// because dispatch statuses of its messages are stored separately from the lane data.
+	fn receive_dispatch_statuses_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(1, 1)
+	}
+
+	/// Returns weight of reading and updating the lanes cursor when deferred messages are
+	/// dispatched in `on_idle`.
+	fn dispatch_deferred_messages_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(1, 1)
+	}
+
+	/// Returns weight of visiting single lane when its deferred messages are dispatched in
+	/// `on_idle`.
+	///
+	/// We read the queue, the lane operating mode and the message that may not fit into the
+	/// remaining weight, update the queue and, once the queue is empty, try to close the lane.
+	fn dispatch_deferred_messages_lane_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(4, 1)
+	}
+
+	/// Returns weight of dispatching single deferred message, excluding the dispatch weight of
+	/// the message itself.
+	///
+	/// We read and remove the message, replace its dispatch result and status at the inbound lane
+	/// (or in the messages delivered ahead) and, if its dispatch fails, keep it for retry.
+	fn dispatch_deferred_message_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(6, 6).saturating_add(Self::pay_inbound_dispatch_fee_overhead())
+	}
+
+	/// Weight of `dispatch_deferred_message` call.
+	fn dispatch_deferred_message_weight(
+		dispatch_weight: Weight,
+		db_weight: RuntimeDbWeight,
+	) -> Weight {
+		// we read pallet operating mode, the lane is visited the same way as in `on_idle`
+		db_weight
+			.reads(1)
+			.saturating_add(Self::dispatch_deferred_messages_lane_overhead(db_weight))
+			.saturating_add(Self::dispatch_deferred_message_overhead(db_weight))
+			.saturating_add(dispatch_weight)
+	}
+
 	/// Returns weight overhead of delivery confirmation transaction
 	/// (`receive_messages_delivery_proof`).
 	fn receive_messages_delivery_proof_overhead() -> Weight {
@@ -386,6 +609,79 @@ pub trait WeightInfoExt: WeightInfo {
 	fn single_message_callback_overhead(db_weight: RuntimeDbWeight) -> Weight {
 		db_weight.reads_writes(1, 1)
 	}
+
+	/// Returns pre-dispatch weight of passing the dispatch result of single confirmed message to
+	/// the `OnMessageDeliveryResult` handler.
+	///
+	/// It includes taking the delivery callback of the message from the storage and the call
+	/// itself, which is limited by the `single_message_callback_overhead`.
+	fn single_message_delivery_result_callback_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight
+			.reads_writes(1, 1)
+			.saturating_add(Self::single_message_callback_overhead(db_weight))
+	}
+
+	/// Returns pre-dispatch weight of refunding the fee of single confirmed message that has been
+	/// cancelled by its sender.
+	///
+	/// It includes taking the cancelled message from the storage and refunding the fee, which
+	/// costs about the same as paying the dispatch fee.
+	fn cancelled_message_refund_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(1, 1).saturating_add(Self::pay_inbound_dispatch_fee_overhead())
+	}
+
+	/// Returns weight of clearing the messages commitment of the previous block in
+	/// `on_initialize` and building the commitment of the current block in `on_finalize`.
+	///
+	/// The cost of every committed message is paid by its sender.
+	fn commit_outbound_messages_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight
+			.reads_writes(1, 1)
+			.saturating_add(Self::commit_outbound_messages(0).max(db_weight.reads_writes(1, 2)))
+	}
+
+	/// Returns weight of committing to single message, accepted in the current block, in
+	/// `on_finalize`.
+	fn commit_outbound_message_overhead() -> Weight {
+		Self::commit_outbound_messages(1).saturating_sub(Self::commit_outbound_messages(0))
+	}
+
+	/// Returns weight of reading and updating the lanes cursor when outbound messages are
+	/// pruned in `on_idle`.
+	fn prune_outbound_messages_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(1, 1)
+	}
+
+	/// Returns weight of visiting single outbound lane when its messages are pruned in `on_idle`.
+	///
+	/// The benchmark also includes the cursor overhead, so the value is slightly overestimated.
+	/// It is never less than the cost of reading and updating the lane state.
+	fn prune_outbound_lane_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		Self::prune_outbound_messages(0).max(db_weight.reads_writes(1, 1))
+	}
+
+	/// Returns weight of pruning single outbound message in `on_idle`.
+	///
+	/// It is never less than the cost of removing the message and its sender from the storage.
+	fn prune_outbound_message_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		Self::prune_outbound_messages(1)
+			.saturating_sub(Self::prune_outbound_messages(0))
+			.max(db_weight.writes(2))
+	}
+
+	/// Returns weight of reading the sender rate limit and reading and updating the senders
+	/// cursor when expired sender rate limit usages are pruned in `on_idle`.
+	///
+	/// It also includes the read that finds out that there are no more usages to visit.
+	fn prune_sender_rate_limit_usages_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(3, 1)
+	}
+
+	/// Returns weight of visiting (and probably pruning) single sender rate limit usage in
+	/// `on_idle`.
+	fn prune_sender_rate_limit_usage_overhead(db_weight: RuntimeDbWeight) -> Weight {
+		db_weight.reads_writes(1, 1)
+	}
 }
 
 impl WeightInfoExt for () {}
@@ -29,6 +29,7 @@ pub mod benchmarking;
 #[cfg(test)]
 mod mock;
 
+pub mod migration;
 pub mod weights;
 pub use weights::WeightInfo;
 
@@ -228,7 +229,11 @@ pub mod pallet {
 	pub(super) type ImportedParaHashes<T: Config<I>, I: 'static = ()> =
 		StorageDoubleMap<_, Blake2_128Concat, ParaId, Twox64Concat, u32, ParaHash>;
 
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T, I = ()>(PhantomData<(T, I)>);
 
 	impl<T: Config<I>, I: 'static> OwnedBridgeModule<T> for Pallet<T, I> {
@@ -0,0 +1,123 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage migrations of the parachains pallet.
+
+// core
+use core::marker::PhantomData;
+// darwinia-network
+use crate::{Config, Pallet, PalletOperatingMode, LOG_TARGET};
+use bp_runtime::BasicOperatingMode;
+// substrate
+#[cfg(feature = "try-runtime")]
+use frame_support::ensure;
+use frame_support::{
+	log, migration,
+	traits::{Get, GetStorageVersion, OnRuntimeUpgrade, PalletInfoAccess, StorageVersion},
+	weights::Weight,
+};
+#[cfg(feature = "try-runtime")]
+use sp_std::prelude::*;
+
+/// Migration to the storage version 1.
+pub mod v1 {
+	// darwinia-network
+	use super::*;
+
+	/// Migrates storage of the pallet that has no storage version (version 0) to version 1.
+	///
+	/// The legacy `IsHalted` flag (if it is still stored) is replaced with the
+	/// `PalletOperatingMode`.
+	pub struct MigrateToV1<T, I = ()>(PhantomData<(T, I)>);
+
+	impl<T: Config<I>, I: 'static> OnRuntimeUpgrade for MigrateToV1<T, I> {
+		fn on_runtime_upgrade() -> Weight {
+			let db_weight = T::DbWeight::get();
+			if Pallet::<T, I>::on_chain_storage_version() != 0 {
+				log::info!(
+					target: LOG_TARGET,
+					"Skipping migration to v1: storage version is {:?}",
+					Pallet::<T, I>::on_chain_storage_version(),
+				);
+				return db_weight.reads(1);
+			}
+
+			let (reads, mut writes) = (2, 1);
+
+			let pallet_name = <Pallet<T, I> as PalletInfoAccess>::name().as_bytes();
+			if let Some(is_halted) =
+				migration::take_storage_value::<bool>(pallet_name, b"IsHalted", &[])
+			{
+				PalletOperatingMode::<T, I>::put(if is_halted {
+					BasicOperatingMode::Halted
+				} else {
+					BasicOperatingMode::Normal
+				});
+				writes += 2;
+			}
+
+			StorageVersion::new(1).put::<Pallet<T, I>>();
+			log::info!(target: LOG_TARGET, "Migrated storage to v1");
+
+			db_weight.reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			Ok(Vec::new())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(
+				Pallet::<T, I>::on_chain_storage_version() == 1,
+				"Storage version must be 1 after the migration"
+			);
+			ensure!(
+				migration::get_storage_value::<bool>(
+					<Pallet<T, I> as PalletInfoAccess>::name().as_bytes(),
+					b"IsHalted",
+					&[],
+				)
+				.is_none(),
+				"Legacy `IsHalted` flag has not been migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// darwinia-network
+	use super::*;
+	use crate::mock::{run_test, TestRuntime};
+
+	#[test]
+	fn migrate_to_v1_works() {
+		run_test(|| {
+			let pallet_name = <Pallet<TestRuntime> as PalletInfoAccess>::name().as_bytes();
+			migration::put_storage_value(pallet_name, b"IsHalted", &[], true);
+
+			v1::MigrateToV1::<TestRuntime>::on_runtime_upgrade();
+
+			assert_eq!(Pallet::<TestRuntime>::on_chain_storage_version(), 1);
+			assert_eq!(PalletOperatingMode::<TestRuntime>::get(), BasicOperatingMode::Halted);
+			assert_eq!(migration::get_storage_value::<bool>(pallet_name, b"IsHalted", &[]), None,);
+		});
+	}
+}
@@ -49,8 +49,8 @@ frame_support::construct_runtime! {
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
-		Grandpa1: pallet_bridge_grandpa::<Instance1>::{Pallet},
-		Grandpa2: pallet_bridge_grandpa::<Instance2>::{Pallet},
+		Grandpa1: pallet_bridge_grandpa::<Instance1>::{Pallet, Event<T>},
+		Grandpa2: pallet_bridge_grandpa::<Instance2>::{Pallet, Event<T>},
 		Parachains: pallet_bridge_parachains::{Call, Pallet, Event<T>},
 	}
 }
@@ -99,6 +99,7 @@ impl pallet_bridge_grandpa::Config<pallet_bridge_grandpa::Instance1> for TestRun
 	type MaxBridgedAuthorities = frame_support::traits::ConstU32<5>;
 	type MaxBridgedHeaderSize = frame_support::traits::ConstU32<512>;
 	type MaxRequests = ConstU32<2>;
+	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
 }
 
@@ -108,6 +109,7 @@ impl pallet_bridge_grandpa::Config<pallet_bridge_grandpa::Instance2> for TestRun
 	type MaxBridgedAuthorities = frame_support::traits::ConstU32<5>;
 	type MaxBridgedHeaderSize = frame_support::traits::ConstU32<512>;
 	type MaxRequests = ConstU32<2>;
+	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
 }
 
@@ -0,0 +1,55 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Relayers pallet benchmarking.
+
+// darwinia-network
+use crate::*;
+use bp_messages::LaneId;
+// substrate
+use frame_benchmarking::{benchmarks_instance_pallet, whitelisted_caller};
+use frame_system::RawOrigin;
+
+/// Reward amount that is (hopefully) larger than existential deposit across all chains.
+const REWARD_AMOUNT: u32 = u32::MAX;
+
+/// Pallet we're benchmarking here.
+pub struct Pallet<T: Config<I>, I: 'static>(crate::Pallet<T, I>);
+
+/// Trait that must be implemented by runtime.
+pub trait Config<I: 'static>: crate::Config<I> {
+	/// Lane id to use in benchmarks.
+	fn bench_lane_id() -> LaneId {
+		Default::default()
+	}
+	/// Prepare environment for paying given reward for serving given lane.
+	fn prepare_environment(lane_id: LaneId, reward: Self::Reward);
+}
+
+benchmarks_instance_pallet! {
+	// Benchmark `claim_rewards` call.
+	claim_rewards {
+		let lane_id = T::bench_lane_id();
+		let relayer: T::AccountId = whitelisted_caller();
+		let reward = T::Reward::from(REWARD_AMOUNT);
+
+		T::prepare_environment(lane_id, reward);
+		RelayerRewards::<T, I>::insert(&relayer, lane_id, reward);
+	}: _(RawOrigin::Signed(relayer.clone()), lane_id)
+	verify {
+		assert!(!RelayerRewards::<T, I>::contains_key(&relayer, lane_id));
+	}
+}
@@ -0,0 +1,333 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime module that is used to store relayer rewards and to pay them.
+//!
+//! Rewards are accumulated for every (relayer, lane) pair. They are registered by other modules:
+//! by the messages pallet, when delivery of messages is confirmed (see
+//! `DeliveryConfirmationPaymentsAdapter`), or by the signed extension that refunds messages
+//! delivery transactions. The relayer may claim its reward at any time using the
+//! `claim_rewards` call. The reward is paid using the `Config::PaymentProcedure`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "runtime-benchmarks")]
+pub mod benchmarking;
+
+#[cfg(test)]
+mod mock;
+
+mod payment_adapter;
+pub use payment_adapter::DeliveryConfirmationPaymentsAdapter;
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+// core
+use core::marker::PhantomData;
+// darwinia-network
+use bp_messages::LaneId;
+// substrate
+use frame_support::{
+	log,
+	traits::{Currency, ExistenceRequirement, Get},
+};
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, Saturating, Zero},
+	DispatchError,
+};
+use sp_std::fmt::Debug;
+
+/// The target that will be used when publishing logs related to this pallet.
+pub const LOG_TARGET: &str = "runtime::bridge-relayers";
+
+/// Procedure that is used to pay rewards to relayers.
+pub trait PaymentProcedure<Relayer, Reward> {
+	/// Error that may be returned by the procedure.
+	type Error: Debug;
+
+	/// Pay reward to the relayer for serving given lane.
+	fn pay_reward(relayer: &Relayer, lane_id: LaneId, reward: Reward) -> Result<(), Self::Error>;
+}
+
+/// Payment procedure that transfers rewards from the `FundAccount` to relayers.
+///
+/// The fund account is never allowed to go below the existential deposit.
+pub struct PayRewardFromAccount<C, FundAccount>(PhantomData<(C, FundAccount)>);
+impl<AccountId, C, FundAccount> PaymentProcedure<AccountId, C::Balance>
+	for PayRewardFromAccount<C, FundAccount>
+where
+	C: Currency<AccountId>,
+	FundAccount: Get<AccountId>,
+{
+	type Error = DispatchError;
+
+	fn pay_reward(
+		relayer: &AccountId,
+		_lane_id: LaneId,
+		reward: C::Balance,
+	) -> Result<(), Self::Error> {
+		C::transfer(&FundAccount::get(), relayer, reward, ExistenceRequirement::KeepAlive)
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+
+	#[pallet::config]
+	pub trait Config<I: 'static = ()>: frame_system::Config {
+		/// The overarching event type.
+		type RuntimeEvent: From<Event<Self, I>>
+			+ IsType<<Self as frame_system::Config>::RuntimeEvent>;
+		/// Type of relayer reward.
+		type Reward: AtLeast32BitUnsigned + Copy + Parameter + MaxEncodedLen;
+		/// Procedure that is used to pay rewards to relayers.
+		type PaymentProcedure: PaymentProcedure<Self::AccountId, Self::Reward>;
+		/// Benchmarks results from runtime we're plugged into.
+		type WeightInfo: WeightInfo;
+	}
+
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(0);
+
+	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
+	pub struct Pallet<T, I = ()>(PhantomData<(T, I)>);
+
+	#[pallet::call]
+	impl<T: Config<I>, I: 'static> Pallet<T, I> {
+		/// Claim accumulated reward of the relayer for serving given lane.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::claim_rewards())]
+		pub fn claim_rewards(origin: OriginFor<T>, lane_id: LaneId) -> DispatchResult {
+			let relayer = ensure_signed(origin)?;
+
+			RelayerRewards::<T, I>::try_mutate_exists(
+				&relayer,
+				lane_id,
+				|maybe_reward| -> DispatchResult {
+					let reward = maybe_reward.take().ok_or(Error::<T, I>::NoRewardForRelayer)?;
+					T::PaymentProcedure::pay_reward(&relayer, lane_id, reward).map_err(|e| {
+						log::trace!(
+							target: LOG_TARGET,
+							"Failed to pay lane {:?} rewards to {:?}: {:?}",
+							lane_id,
+							relayer,
+							e,
+						);
+						Error::<T, I>::FailedToPayReward
+					})?;
+
+					Self::deposit_event(Event::RewardPaid {
+						relayer: relayer.clone(),
+						lane_id,
+						reward,
+					});
+					Ok(())
+				},
+			)
+		}
+	}
+
+	impl<T: Config<I>, I: 'static> Pallet<T, I> {
+		/// Register reward for given relayer.
+		///
+		/// The reward is added to rewards that have been registered for this relayer before.
+		pub fn register_relayer_reward(lane_id: LaneId, relayer: &T::AccountId, reward: T::Reward) {
+			if reward.is_zero() {
+				return;
+			}
+
+			RelayerRewards::<T, I>::mutate(relayer, lane_id, |old_reward| {
+				let new_reward = old_reward.unwrap_or_else(Zero::zero).saturating_add(reward);
+				*old_reward = Some(new_reward);
+
+				log::trace!(
+					target: LOG_TARGET,
+					"Relayer {:?} can now claim reward for serving lane {:?}: {:?}",
+					relayer,
+					lane_id,
+					new_reward,
+				);
+			});
+
+			Self::deposit_event(Event::RewardRegistered {
+				relayer: relayer.clone(),
+				lane_id,
+				reward,
+			});
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config<I>, I: 'static = ()> {
+		/// Reward has been registered for the relayer.
+		RewardRegistered { relayer: T::AccountId, lane_id: LaneId, reward: T::Reward },
+		/// Reward has been paid to the relayer.
+		RewardPaid { relayer: T::AccountId, lane_id: LaneId, reward: T::Reward },
+	}
+
+	#[pallet::error]
+	pub enum Error<T, I = ()> {
+		/// No reward can be claimed by given relayer.
+		NoRewardForRelayer,
+		/// Reward payment procedure has failed.
+		FailedToPayReward,
+	}
+
+	/// Map of the relayer => lane => accumulated reward.
+	#[pallet::storage]
+	#[pallet::getter(fn relayer_reward)]
+	pub type RelayerRewards<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Identity,
+		LaneId,
+		T::Reward,
+		OptionQuery,
+	>;
+}
+pub use pallet::*;
+
+#[cfg(test)]
+mod tests {
+	// darwinia-network
+	use super::*;
+	use crate::mock::*;
+	// substrate
+	use frame_support::{assert_noop, assert_ok};
+	use frame_system::{EventRecord, Pallet as System, Phase};
+
+	fn get_ready_for_events() {
+		System::<TestRuntime>::set_block_number(1);
+		System::<TestRuntime>::reset_events();
+	}
+
+	#[test]
+	fn rewards_are_accumulated_per_lane() {
+		run_test(|| {
+			get_ready_for_events();
+
+			Pallet::<TestRuntime>::register_relayer_reward(TEST_LANE_ID, &REGULAR_RELAYER, 10);
+			Pallet::<TestRuntime>::register_relayer_reward(TEST_LANE_ID, &REGULAR_RELAYER, 20);
+			Pallet::<TestRuntime>::register_relayer_reward(TEST_LANE_ID_2, &REGULAR_RELAYER, 5);
+			Pallet::<TestRuntime>::register_relayer_reward(TEST_LANE_ID_2, &REGULAR_RELAYER, 0);
+
+			assert_eq!(
+				Pallet::<TestRuntime>::relayer_reward(REGULAR_RELAYER, TEST_LANE_ID),
+				Some(30)
+			);
+			assert_eq!(
+				Pallet::<TestRuntime>::relayer_reward(REGULAR_RELAYER, TEST_LANE_ID_2),
+				Some(5)
+			);
+			assert_eq!(
+				System::<TestRuntime>::events().last(),
+				Some(&EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Relayers(Event::RewardRegistered {
+						relayer: REGULAR_RELAYER,
+						lane_id: TEST_LANE_ID_2,
+						reward: 5,
+					}),
+					topics: vec![],
+				}),
+			);
+		});
+	}
+
+	#[test]
+	fn root_cant_claim_anything() {
+		run_test(|| {
+			assert_noop!(
+				Pallet::<TestRuntime>::claim_rewards(RuntimeOrigin::root(), TEST_LANE_ID),
+				DispatchError::BadOrigin,
+			);
+		});
+	}
+
+	#[test]
+	fn relayer_cant_claim_if_no_reward_exists() {
+		run_test(|| {
+			Pallet::<TestRuntime>::register_relayer_reward(TEST_LANE_ID_2, &REGULAR_RELAYER, 100);
+
+			assert_noop!(
+				Pallet::<TestRuntime>::claim_rewards(
+					RuntimeOrigin::signed(REGULAR_RELAYER),
+					TEST_LANE_ID
+				),
+				Error::<TestRuntime>::NoRewardForRelayer,
+			);
+		});
+	}
+
+	#[test]
+	fn relayer_cant_claim_if_payment_procedure_fails() {
+		run_test(|| {
+			Pallet::<TestRuntime>::register_relayer_reward(
+				TEST_LANE_ID,
+				&REGULAR_RELAYER,
+				RELAYERS_FUND_BALANCE,
+			);
+
+			// the fund account can't go below the existential deposit
+			assert_noop!(
+				Pallet::<TestRuntime>::claim_rewards(
+					RuntimeOrigin::signed(REGULAR_RELAYER),
+					TEST_LANE_ID
+				),
+				Error::<TestRuntime>::FailedToPayReward,
+			);
+		});
+	}
+
+	#[test]
+	fn relayer_can_claim_reward() {
+		run_test(|| {
+			get_ready_for_events();
+			Pallet::<TestRuntime>::register_relayer_reward(TEST_LANE_ID, &REGULAR_RELAYER, 100);
+			Pallet::<TestRuntime>::register_relayer_reward(TEST_LANE_ID_2, &REGULAR_RELAYER, 50);
+
+			assert_ok!(Pallet::<TestRuntime>::claim_rewards(
+				RuntimeOrigin::signed(REGULAR_RELAYER),
+				TEST_LANE_ID
+			));
+			assert_eq!(Pallet::<TestRuntime>::relayer_reward(REGULAR_RELAYER, TEST_LANE_ID), None);
+			assert_eq!(
+				Pallet::<TestRuntime>::relayer_reward(REGULAR_RELAYER, TEST_LANE_ID_2),
+				Some(50)
+			);
+			assert_eq!(Balances::free_balance(REGULAR_RELAYER), 100);
+			assert_eq!(Balances::free_balance(RELAYERS_FUND_ACCOUNT), RELAYERS_FUND_BALANCE - 100);
+			assert_eq!(
+				System::<TestRuntime>::events().last(),
+				Some(&EventRecord {
+					phase: Phase::Initialization,
+					event: TestEvent::Relayers(Event::RewardPaid {
+						relayer: REGULAR_RELAYER,
+						lane_id: TEST_LANE_ID,
+						reward: 100,
+					}),
+					topics: vec![],
+				}),
+			);
+		});
+	}
+}
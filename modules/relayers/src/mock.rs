@@ -0,0 +1,132 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+// darwinia-network
+use crate as pallet_bridge_relayers;
+use crate::*;
+use bp_messages::LaneId;
+// substrate
+use frame_support::{parameter_types, weights::RuntimeDbWeight};
+use frame_system::mocking::*;
+use sp_core::{ConstU64, H256};
+use sp_runtime::{
+	testing::Header as SubstrateHeader,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+pub type AccountId = u64;
+pub type Balance = u64;
+
+type Block = MockBlock<TestRuntime>;
+type UncheckedExtrinsic = MockUncheckedExtrinsic<TestRuntime>;
+
+/// Lane that we're using in tests.
+pub const TEST_LANE_ID: LaneId = [0, 0, 0, 1];
+
+/// Another lane that we're using in tests.
+pub const TEST_LANE_ID_2: LaneId = [0, 0, 0, 2];
+
+/// Account that pays rewards to relayers.
+pub const RELAYERS_FUND_ACCOUNT: AccountId = 0xDEAD;
+
+/// Initial balance of the relayers fund account.
+pub const RELAYERS_FUND_BALANCE: Balance = 1_000_000;
+
+/// Regular relayer that may receive rewards.
+pub const REGULAR_RELAYER: AccountId = 1;
+
+/// Another relayer that may receive rewards.
+pub const REGULAR_RELAYER_2: AccountId = 2;
+
+frame_support::construct_runtime! {
+	pub enum TestRuntime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Event<T>},
+		Relayers: pallet_bridge_relayers::{Pallet, Call, Event<T>},
+	}
+}
+
+pub type TestEvent = RuntimeEvent;
+
+parameter_types! {
+	pub const DbWeight: RuntimeDbWeight = RuntimeDbWeight { read: 1, write: 2 };
+	pub const RelayersFundAccount: AccountId = RELAYERS_FUND_ACCOUNT;
+}
+impl frame_system::Config for TestRuntime {
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type AccountId = AccountId;
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockHashCount = ConstU64<250>;
+	type BlockLength = ();
+	type BlockNumber = u64;
+	type BlockWeights = ();
+	type DbWeight = DbWeight;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type Header = SubstrateHeader;
+	type Index = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type MaxConsumers = frame_support::traits::ConstU32<16>;
+	type OnKilledAccount = ();
+	type OnNewAccount = ();
+	type OnSetCode = ();
+	type PalletInfo = PalletInfo;
+	type RuntimeCall = RuntimeCall;
+	type RuntimeEvent = RuntimeEvent;
+	type RuntimeOrigin = RuntimeOrigin;
+	type SS58Prefix = ();
+	type SystemWeightInfo = ();
+	type Version = ();
+}
+
+impl pallet_balances::Config for TestRuntime {
+	type AccountStore = frame_system::Pallet<TestRuntime>;
+	type Balance = Balance;
+	type DustRemoval = ();
+	type ExistentialDeposit = ConstU64<1>;
+	type FreezeIdentifier = ();
+	type HoldIdentifier = ();
+	type MaxFreezes = ();
+	type MaxHolds = ();
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = ();
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+}
+
+impl pallet_bridge_relayers::Config for TestRuntime {
+	type PaymentProcedure = PayRewardFromAccount<Balances, RelayersFundAccount>;
+	type Reward = Balance;
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = ();
+}
+
+/// Run pallet test.
+pub fn run_test<T>(test: impl FnOnce() -> T) -> T {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<TestRuntime>().unwrap();
+	pallet_balances::GenesisConfig::<TestRuntime> {
+		balances: vec![(RELAYERS_FUND_ACCOUNT, RELAYERS_FUND_BALANCE)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(test)
+}
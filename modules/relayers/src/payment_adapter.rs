@@ -0,0 +1,215 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Code that allows relayers pallet to be used as a payment mechanism for the messages pallet.
+
+// darwinia-network
+use crate::{Config, Pallet};
+use bp_messages::{
+	source_chain::MessageDeliveryAndDispatchPayment, LaneId, MessageNonce, UnrewardedRelayer,
+	VerificationError,
+};
+// substrate
+use frame_support::{traits::Get, weights::Weight};
+use sp_runtime::traits::Saturating;
+use sp_std::{
+	collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+	marker::PhantomData,
+	ops::RangeInclusive,
+};
+
+/// Adapter that allows relayers pallet to be used as a delivery+dispatch payment mechanism
+/// for the messages pallet.
+///
+/// Relayer rewards are not paid immediately. Instead, they're registered in the relayers pallet
+/// and relayers may claim them later. Every delivering relayer gets `DeliveryReward` for every
+/// confirmed message that it has delivered and the relayer that has submitted the confirmation
+/// gets `ConfirmationReward` for every confirmed message. All other operations (withdrawing
+/// message fees, refunding fees of cancelled messages, ...) are delegated to the `Payments`.
+pub struct DeliveryConfirmationPaymentsAdapter<T, I, Payments, DeliveryReward, ConfirmationReward>(
+	PhantomData<(T, I, Payments, DeliveryReward, ConfirmationReward)>,
+);
+
+impl<T, I, Payments, DeliveryReward, ConfirmationReward, SenderOrigin, Balance>
+	MessageDeliveryAndDispatchPayment<SenderOrigin, T::AccountId, Balance>
+	for DeliveryConfirmationPaymentsAdapter<T, I, Payments, DeliveryReward, ConfirmationReward>
+where
+	T: Config<I>,
+	I: 'static,
+	Payments: MessageDeliveryAndDispatchPayment<SenderOrigin, T::AccountId, Balance>,
+	DeliveryReward: Get<T::Reward>,
+	ConfirmationReward: Get<T::Reward>,
+{
+	fn pay_delivery_and_dispatch_fee(
+		submitter: &SenderOrigin,
+		fee: &Balance,
+		relayer_fund_account: &T::AccountId,
+	) -> Result<(), VerificationError> {
+		Payments::pay_delivery_and_dispatch_fee(submitter, fee, relayer_fund_account)
+	}
+
+	fn pay_relayers_rewards(
+		lane_id: LaneId,
+		messages_relayers: VecDeque<UnrewardedRelayer<T::AccountId>>,
+		confirmation_relayer: &T::AccountId,
+		received_range: &RangeInclusive<MessageNonce>,
+		_relayer_fund_account: &T::AccountId,
+	) {
+		let delivery_reward = DeliveryReward::get();
+		let mut confirmed_messages = 0u32;
+		for (relayer, messages) in
+			delivered_messages_by_relayer::<T::AccountId>(messages_relayers, received_range)
+		{
+			confirmed_messages = confirmed_messages.saturating_add(messages);
+			Pallet::<T, I>::register_relayer_reward(
+				lane_id,
+				&relayer,
+				delivery_reward.saturating_mul(messages.into()),
+			);
+		}
+
+		Pallet::<T, I>::register_relayer_reward(
+			lane_id,
+			confirmation_relayer,
+			ConfirmationReward::get().saturating_mul(confirmed_messages.into()),
+		);
+	}
+
+	fn refund_fee(
+		lane_id: LaneId,
+		nonce: MessageNonce,
+		sender: &T::AccountId,
+		fee: &Balance,
+		relayer_fund_account: &T::AccountId,
+	) -> Result<(), VerificationError> {
+		Payments::refund_fee(lane_id, nonce, sender, fee, relayer_fund_account)
+	}
+
+	fn on_message_fee_increased(
+		lane_id: LaneId,
+		nonce: MessageNonce,
+		submitter: &SenderOrigin,
+		additional_fee: &Balance,
+		fee: &Balance,
+	) -> Weight {
+		Payments::on_message_fee_increased(lane_id, nonce, submitter, additional_fee, fee)
+	}
+}
+
+/// Returns number of messages in the `received_range` that have been delivered by every relayer.
+fn delivered_messages_by_relayer<AccountId: Ord>(
+	messages_relayers: VecDeque<UnrewardedRelayer<AccountId>>,
+	received_range: &RangeInclusive<MessageNonce>,
+) -> BTreeMap<AccountId, u32> {
+	let mut delivered_messages = BTreeMap::<AccountId, u32>::new();
+	for entry in messages_relayers {
+		let nonce_begin = sp_std::cmp::max(entry.messages.begin, *received_range.start());
+		let nonce_end = sp_std::cmp::min(entry.messages.end, *received_range.end());
+		if nonce_end < nonce_begin {
+			continue;
+		}
+
+		let messages = (nonce_end - nonce_begin + 1) as u32;
+		let relayer_messages = delivered_messages.entry(entry.relayer).or_insert(0);
+		*relayer_messages = relayer_messages.saturating_add(messages);
+	}
+	delivered_messages
+}
+
+#[cfg(test)]
+mod tests {
+	// darwinia-network
+	use super::*;
+	use crate::mock::*;
+	use bp_messages::DeliveredMessages;
+	// substrate
+	use frame_support::parameter_types;
+
+	parameter_types! {
+		pub const DeliveryReward: Balance = 100;
+		pub const ConfirmationReward: Balance = 10;
+	}
+
+	type TestPaymentsAdapter = DeliveryConfirmationPaymentsAdapter<
+		TestRuntime,
+		(),
+		(),
+		DeliveryReward,
+		ConfirmationReward,
+	>;
+
+	fn unrewarded_relayers() -> VecDeque<UnrewardedRelayer<AccountId>> {
+		vec![
+			UnrewardedRelayer {
+				relayer: REGULAR_RELAYER,
+				messages: DeliveredMessages { begin: 1, end: 2, ..Default::default() },
+			},
+			UnrewardedRelayer {
+				relayer: REGULAR_RELAYER_2,
+				messages: DeliveredMessages { begin: 3, end: 4, ..Default::default() },
+			},
+		]
+		.into()
+	}
+
+	#[test]
+	fn confirmation_relayer_is_rewarded_if_it_has_also_delivered_messages() {
+		run_test(|| {
+			<TestPaymentsAdapter as MessageDeliveryAndDispatchPayment<(), _, Balance>>::pay_relayers_rewards(
+				TEST_LANE_ID,
+				unrewarded_relayers(),
+				&REGULAR_RELAYER,
+				&(1..=4),
+				&RELAYERS_FUND_ACCOUNT,
+			);
+
+			assert_eq!(
+				Pallet::<TestRuntime>::relayer_reward(REGULAR_RELAYER, TEST_LANE_ID),
+				Some(240)
+			);
+			assert_eq!(
+				Pallet::<TestRuntime>::relayer_reward(REGULAR_RELAYER_2, TEST_LANE_ID),
+				Some(200)
+			);
+		});
+	}
+
+	#[test]
+	fn only_confirmed_messages_are_rewarded() {
+		run_test(|| {
+			<TestPaymentsAdapter as MessageDeliveryAndDispatchPayment<(), _, Balance>>::pay_relayers_rewards(
+				TEST_LANE_ID,
+				unrewarded_relayers(),
+				&RELAYERS_FUND_ACCOUNT,
+				&(2..=3),
+				&RELAYERS_FUND_ACCOUNT,
+			);
+
+			assert_eq!(
+				Pallet::<TestRuntime>::relayer_reward(REGULAR_RELAYER, TEST_LANE_ID),
+				Some(100)
+			);
+			assert_eq!(
+				Pallet::<TestRuntime>::relayer_reward(REGULAR_RELAYER_2, TEST_LANE_ID),
+				Some(100)
+			);
+			assert_eq!(
+				Pallet::<TestRuntime>::relayer_reward(RELAYERS_FUND_ACCOUNT, TEST_LANE_ID),
+				Some(20)
+			);
+		});
+	}
+}
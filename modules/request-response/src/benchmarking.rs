@@ -0,0 +1,118 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Request-response pallet benchmarking.
+
+// darwinia-network
+use crate::*;
+use bp_messages::target_chain::DispatchMessageData;
+// substrate
+use frame_benchmarking::{account, benchmarks_instance_pallet, whitelisted_caller};
+use frame_support::traits::Currency;
+
+/// Size of response data that is used in benchmarks.
+const DATA_SIZE: usize = 32;
+
+/// Pallet we're benchmarking here.
+pub struct Pallet<T: Config<I>, I: 'static>(crate::Pallet<T, I>);
+
+/// Trait that must be implemented by runtime.
+pub trait Config<I: 'static>: crate::Config<I> {
+	/// Lane id to use in benchmarks.
+	fn bench_lane_id() -> LaneId {
+		Default::default()
+	}
+	/// Fee of messages that are sent in benchmarks.
+	fn bench_message_fee() -> Self::MessageFee;
+	/// Prepare environment for sending messages by the given account over the given lane.
+	fn prepare_environment(lane_id: LaneId, sender: &Self::AccountId);
+}
+
+fn dispatch_message<T: Config<I>, I: 'static>(
+	message: BridgeMessage<T::MessageFee>,
+) -> DispatchMessage<BridgeMessage<T::MessageFee>, T::MessageFee> {
+	DispatchMessage {
+		key: MessageKey { lane_id: T::bench_lane_id(), nonce: 1 },
+		data: DispatchMessageData {
+			payload: Ok(message),
+			fee: T::bench_message_fee(),
+			is_cancelled: false,
+			expiry: None,
+		},
+	}
+}
+
+benchmarks_instance_pallet! {
+	// Benchmark `send_request` call.
+	send_request {
+		let lane_id = T::bench_lane_id();
+		let requester: T::AccountId = whitelisted_caller();
+		let data = vec![0; T::MaxRequestSize::get() as usize];
+		let fee = T::bench_message_fee();
+		let response_fee = T::ResponseFee::get();
+		let pallet_account = crate::Pallet::<T, I>::account_id();
+
+		T::Currency::make_free_balance_be(
+			&requester,
+			fee.saturating_add(response_fee).saturating_add(T::Currency::minimum_balance()),
+		);
+		T::Currency::make_free_balance_be(&pallet_account, T::Currency::minimum_balance());
+		T::prepare_environment(lane_id, &pallet_account);
+	}: _(RawOrigin::Signed(requester), lane_id, data, fee, response_fee)
+	verify {
+		assert!(PendingRequests::<T, I>::contains_key(0));
+	}
+
+	// Benchmark dispatch of the request, received from the bridged chain. The weight of the
+	// `Config::RequestHandler` is not included.
+	serve_request {
+		let relayer: T::AccountId = account("relayer", 0, 0);
+		let message = dispatch_message::<T, I>(BridgeMessage::Request {
+			id: 0,
+			data: vec![0; T::MaxRequestSize::get() as usize],
+			response_fee: T::ResponseFee::get(),
+		});
+
+		T::prepare_environment(T::bench_lane_id(), &crate::Pallet::<T, I>::account_id());
+	}: {
+		assert!(crate::Pallet::<T, I>::dispatch(&relayer, message).dispatch_result);
+	}
+
+	// Benchmark dispatch of the response, received from the bridged chain. The weight of the
+	// `Config::OnResponse` callback is not included.
+	receive_response {
+		let relayer: T::AccountId = account("relayer", 0, 0);
+		let requester: T::AccountId = whitelisted_caller();
+		let message = dispatch_message::<T, I>(BridgeMessage::Response {
+			id: 0,
+			data: Some(vec![0; DATA_SIZE]),
+		});
+
+		PendingRequests::<T, I>::insert(
+			0,
+			PendingRequest {
+				requester,
+				lane_id: T::bench_lane_id(),
+				timeout_at: T::RequestTimeout::get(),
+			},
+		);
+	}: {
+		assert!(crate::Pallet::<T, I>::dispatch(&relayer, message).dispatch_result);
+	}
+	verify {
+		assert!(!PendingRequests::<T, I>::contains_key(0));
+	}
+}
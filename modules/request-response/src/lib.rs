@@ -0,0 +1,1099 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime module that implements request/response messaging on top of the messages module.
+//!
+//! Requests are sent over the lane using the `Config::MessagesBridge`. Every request gets the
+//! unique `RequestId` that is used to correlate it with the response. At the bridged chain,
+//! the pallet is plugged in as the `MessageDispatch` of the messages module. It passes the
+//! request to the `Config::RequestHandler` and sends the response back over the lane with the
+//! same id. When the response is dispatched at this chain, it is routed to the requester using
+//! the `Config::OnResponse` callback.
+//!
+//! Both requests and responses are sent by the pallet account. The requester pays the request
+//! message fee and the response fee to the pallet account of this chain. The response fee is
+//! carried in the request and the bridged chain refuses to serve requests that offer less than
+//! its `Config::ResponseFee`. So the pallet account of every chain collects response fees of
+//! local requesters and pays fees of responses to requests of the bridged chain.
+//!
+//! Responses are matched with requests by the lane and the request id only. So every lane
+//! that is used by the pallet must only accept messages from the pallet account at both chains
+//! (see `AllowedSenders` of the messages pallet). Otherwise anyone at the bridged chain is able
+//! to send forged responses to requests of this chain.
+//!
+//! The same callback is called with an error if the request has not been answered within the
+//! `Config::RequestTimeout` blocks, or if the delivery confirmation (reported to the pallet via
+//! `OnDeliveryConfirmed`) shows that the request has not been dispatched at the bridged chain.
+//! Requests whose dispatch has been deferred (or whose dispatch status is unknown) may still be
+//! served later, so they're only failed by the timeout. Responses that arrive after the request
+//! has failed are rejected.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "runtime-benchmarks")]
+pub mod benchmarking;
+
+#[cfg(test)]
+mod mock;
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+// crates.io
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+// darwinia-network
+use bp_messages::{
+	source_chain::{MessagesBridge, OnDeliveryConfirmed},
+	target_chain::{DispatchMessage, MessageDispatch},
+	DeliveredMessages, LaneId, MessageKey, MessageNonce,
+};
+use bp_runtime::messages::{MessageDispatchResult, MessageDispatchStatus};
+// substrate
+use frame_support::{
+	log,
+	traits::{Currency, ExistenceRequirement, Get},
+	weights::{RuntimeDbWeight, Weight},
+	PalletId, RuntimeDebug,
+};
+use frame_system::RawOrigin;
+use sp_runtime::{
+	traits::{AccountIdConversion, One, Saturating},
+	DispatchError,
+};
+use sp_std::prelude::*;
+
+/// The target that will be used when publishing logs related to this pallet.
+pub const LOG_TARGET: &str = "runtime::bridge-request-response";
+
+/// Unique identifier of the request, used to correlate it with the response.
+pub type RequestId = u64;
+
+/// Message that is sent over the bridge by this pallet.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum BridgeMessage<Fee> {
+	/// Request that needs to be answered by the bridged chain.
+	Request {
+		/// Request identifier.
+		id: RequestId,
+		/// Opaque request data that is interpreted by the `Config::RequestHandler`.
+		data: Vec<u8>,
+		/// Fee of the response message. It has been paid by the requester and it must be at
+		/// least the `Config::ResponseFee` of the bridged chain.
+		response_fee: Fee,
+	},
+	/// Response to the request.
+	Response {
+		/// Identifier of the request.
+		id: RequestId,
+		/// Opaque response data, or `None` if the request has been rejected by the handler.
+		data: Option<Vec<u8>>,
+	},
+}
+
+/// The reason why the request has failed.
+#[derive(Clone, Copy, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum RequestFailure {
+	/// The request has been delivered, but it has not been dispatched at the bridged chain.
+	NotDispatched,
+	/// The request has been rejected by the request handler at the bridged chain.
+	Rejected,
+	/// The response has not been received in time.
+	TimedOut,
+}
+
+/// Request that is waiting for the response.
+#[derive(Clone, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PendingRequest<AccountId, BlockNumber> {
+	/// Account that has sent the request.
+	pub requester: AccountId,
+	/// Lane that has been used to send the request. The response must be received over the
+	/// same lane.
+	pub lane_id: LaneId,
+	/// Number of the block, where the request times out.
+	pub timeout_at: BlockNumber,
+}
+
+/// Handler of requests, received from the bridged chain.
+pub trait RequestHandler {
+	/// Returns maximal weight of handling the request with given data.
+	fn request_weight(lane_id: LaneId, data: &[u8]) -> Weight;
+
+	/// Handle the request and return the response, along with the actually spent weight.
+	///
+	/// If `None` is returned, the request is rejected and the requester is notified about that.
+	fn handle_request(lane_id: LaneId, data: Vec<u8>) -> (Option<Vec<u8>>, Weight);
+}
+
+impl RequestHandler for () {
+	fn request_weight(_lane_id: LaneId, _data: &[u8]) -> Weight {
+		Weight::zero()
+	}
+
+	fn handle_request(_lane_id: LaneId, _data: Vec<u8>) -> (Option<Vec<u8>>, Weight) {
+		(None, Weight::zero())
+	}
+}
+
+/// Callback that is called when the request is completed.
+pub trait OnResponse<AccountId> {
+	/// Called when the response is received, or when the request has failed.
+	///
+	/// Should return the weight consumed by the call. It must not exceed the
+	/// `Config::CallbackWeight`.
+	fn on_response(
+		request_id: RequestId,
+		requester: &AccountId,
+		response: Result<Vec<u8>, RequestFailure>,
+	) -> Weight;
+}
+
+impl<AccountId> OnResponse<AccountId> for () {
+	fn on_response(
+		_request_id: RequestId,
+		_requester: &AccountId,
+		_response: Result<Vec<u8>, RequestFailure>,
+	) -> Weight {
+		Weight::zero()
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+
+	#[pallet::config]
+	pub trait Config<I: 'static = ()>: frame_system::Config {
+		/// The overarching event type.
+		type RuntimeEvent: From<Event<Self, I>>
+			+ IsType<<Self as frame_system::Config>::RuntimeEvent>;
+		/// Type of the message delivery and dispatch fee.
+		type MessageFee: Parameter + Copy + PartialOrd + Saturating;
+		/// Currency that is used to charge requesters.
+		type Currency: Currency<Self::AccountId, Balance = Self::MessageFee>;
+		/// Messages bridge that is used to send requests and responses.
+		type MessagesBridge: MessagesBridge<
+			Self::RuntimeOrigin,
+			Self::MessageFee,
+			BridgeMessage<Self::MessageFee>,
+		>;
+		/// Id of the pallet. The pallet account sends all requests and responses, so it is the
+		/// only account that may be allowed to send messages over lanes of the pallet.
+		///
+		/// Fees of requests and responses to local requests are paid to the pallet account by
+		/// requesters. Fees of responses to requests of the bridged chain are paid by the pallet
+		/// account, so it must have enough funds.
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+		/// Minimal fee that the requester of the bridged chain must offer to get the response.
+		#[pallet::constant]
+		type ResponseFee: Get<Self::MessageFee>;
+		/// Maximal size of the request data.
+		#[pallet::constant]
+		type MaxRequestSize: Get<u32>;
+		/// Handler of requests, received from the bridged chain.
+		type RequestHandler: RequestHandler;
+		/// Callback that is called when the request is completed.
+		type OnResponse: OnResponse<Self::AccountId>;
+		/// Maximal weight of the `OnResponse` callback.
+		#[pallet::constant]
+		type CallbackWeight: Get<Weight>;
+		/// Number of blocks after which the request times out, if the response has not been
+		/// received.
+		#[pallet::constant]
+		type RequestTimeout: Get<Self::BlockNumber>;
+		/// Maximal number of requests that may time out at the same block. If the limit is
+		/// reached, requests time out at the next block.
+		#[pallet::constant]
+		type MaxRequestsPerBlock: Get<u32>;
+		/// Benchmarks results from runtime we're plugged into.
+		type WeightInfo: WeightInfo;
+	}
+
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(0);
+
+	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
+	pub struct Pallet<T, I = ()>(PhantomData<(T, I)>);
+
+	#[pallet::hooks]
+	impl<T: Config<I>, I: 'static> Hooks<BlockNumberFor<T>> for Pallet<T, I> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let mut weight = db_weight.reads_writes(1, 1);
+			for request_id in RequestTimeouts::<T, I>::take(n) {
+				weight = weight.saturating_add(Self::fail_request(
+					request_id,
+					RequestFailure::TimedOut,
+					db_weight,
+				));
+			}
+			weight
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config<I>, I: 'static> Pallet<T, I> {
+		/// Send request over the lane.
+		///
+		/// The `fee` of the request message and the `response_fee` are paid by the requester.
+		/// The response (or failure) is reported to the `Config::OnResponse` callback.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::send_request())]
+		pub fn send_request(
+			origin: OriginFor<T>,
+			lane_id: LaneId,
+			data: Vec<u8>,
+			fee: T::MessageFee,
+			response_fee: T::MessageFee,
+		) -> DispatchResult {
+			let requester = ensure_signed(origin)?;
+			Self::request(requester, lane_id, data, fee, response_fee).map(drop)
+		}
+	}
+
+	impl<T: Config<I>, I: 'static> Pallet<T, I> {
+		/// Send request over the lane on behalf of the `requester`.
+		///
+		/// This may be used by other pallets. Both the request `fee` and the `response_fee` are
+		/// transferred from the `requester` to the pallet account, which then sends the request.
+		pub fn request(
+			requester: T::AccountId,
+			lane_id: LaneId,
+			data: Vec<u8>,
+			fee: T::MessageFee,
+			response_fee: T::MessageFee,
+		) -> Result<RequestId, DispatchError> {
+			ensure!(
+				data.len() <= T::MaxRequestSize::get() as usize,
+				Error::<T, I>::RequestTooLarge
+			);
+
+			frame_support::storage::with_storage_layer(|| {
+				let request_id = NextRequestId::<T, I>::get();
+				let timeout_at = Self::schedule_timeout(request_id)?;
+
+				T::Currency::transfer(
+					&requester,
+					&Self::account_id(),
+					fee.saturating_add(response_fee),
+					ExistenceRequirement::KeepAlive,
+				)?;
+
+				let artifacts = T::MessagesBridge::send_message(
+					RawOrigin::Signed(Self::account_id()).into(),
+					lane_id,
+					BridgeMessage::Request { id: request_id, data, response_fee },
+					fee,
+				)
+				.map_err(|err| {
+					log::trace!(
+						target: LOG_TARGET,
+						"Failed to send request {} over lane {:?}: {:?}",
+						request_id,
+						lane_id,
+						err,
+					);
+
+					Error::<T, I>::FailedToSendRequest
+				})?;
+
+				NextRequestId::<T, I>::put(request_id.saturating_add(1));
+				PendingRequests::<T, I>::insert(
+					request_id,
+					PendingRequest { requester: requester.clone(), lane_id, timeout_at },
+				);
+				RequestMessages::<T, I>::insert(
+					MessageKey { lane_id, nonce: artifacts.nonce },
+					request_id,
+				);
+
+				log::trace!(
+					target: LOG_TARGET,
+					"Sent request {} over lane {:?} as message {}",
+					request_id,
+					lane_id,
+					artifacts.nonce,
+				);
+				Self::deposit_event(Event::RequestSent {
+					request_id,
+					requester,
+					lane_id,
+					nonce: artifacts.nonce,
+				});
+
+				Ok(request_id)
+			})
+		}
+
+		/// Returns account of the pallet that sends requests and responses.
+		pub fn account_id() -> T::AccountId {
+			T::PalletId::get().into_account_truncating()
+		}
+
+		/// Schedule timeout of the request and return number of the block, where it times out.
+		///
+		/// Requests time out in the order they have been sent. If the block is already full, the
+		/// request times out at the next block, so the number of requests is never limited.
+		fn schedule_timeout(request_id: RequestId) -> Result<T::BlockNumber, DispatchError> {
+			let timeout_at = frame_system::Pallet::<T>::block_number()
+				.saturating_add(T::RequestTimeout::get())
+				.max(LatestTimeoutBlock::<T, I>::get());
+			let timeout_at = match RequestTimeouts::<T, I>::try_append(timeout_at, request_id) {
+				Ok(()) => timeout_at,
+				Err(()) => {
+					// nothing is scheduled after the latest timeout block, so there's a room
+					let timeout_at = timeout_at.saturating_add(One::one());
+					RequestTimeouts::<T, I>::try_append(timeout_at, request_id)
+						.map_err(|_| Error::<T, I>::TooManyRequests)?;
+					timeout_at
+				},
+			};
+			LatestTimeoutBlock::<T, I>::put(timeout_at);
+
+			Ok(timeout_at)
+		}
+
+		/// Handle the request and send the response back over the same lane.
+		///
+		/// The response is sent by the pallet account, which is paid the `response_fee` by the
+		/// requester at the bridged chain. Returns the weight of handling the request.
+		pub(crate) fn serve_request(
+			lane_id: LaneId,
+			request_id: RequestId,
+			data: Vec<u8>,
+			response_fee: T::MessageFee,
+		) -> Result<Weight, (DispatchError, MessageDispatchStatus)> {
+			if data.len() > T::MaxRequestSize::get() as usize {
+				log::trace!(
+					target: LOG_TARGET,
+					"Rejecting too large request {} at lane {:?}: {} bytes",
+					request_id,
+					lane_id,
+					data.len(),
+				);
+
+				return Err((
+					Error::<T, I>::RequestTooLarge.into(),
+					MessageDispatchStatus::Rejected,
+				));
+			}
+			if response_fee < T::ResponseFee::get() {
+				log::trace!(
+					target: LOG_TARGET,
+					"Rejecting request {} at lane {:?}: response fee is too low",
+					request_id,
+					lane_id,
+				);
+
+				return Err((
+					Error::<T, I>::ResponseFeeTooLow.into(),
+					MessageDispatchStatus::Rejected,
+				));
+			}
+
+			let max_handler_weight = T::RequestHandler::request_weight(lane_id, &data);
+			frame_support::storage::with_storage_layer::<_, DispatchError, _>(|| {
+				let (response, handler_weight) = T::RequestHandler::handle_request(lane_id, data);
+
+				let artifacts = T::MessagesBridge::send_message(
+					RawOrigin::Signed(Self::account_id()).into(),
+					lane_id,
+					BridgeMessage::Response { id: request_id, data: response },
+					response_fee,
+				)
+				.map_err(|err| {
+					log::trace!(
+						target: LOG_TARGET,
+						"Failed to send response to request {} over lane {:?}: {:?}",
+						request_id,
+						lane_id,
+						err,
+					);
+
+					Error::<T, I>::FailedToSendResponse
+				})?;
+
+				Self::deposit_event(Event::RequestServed {
+					lane_id,
+					request_id,
+					response_nonce: artifacts.nonce,
+				});
+
+				Ok(handler_weight.min(max_handler_weight))
+			})
+			.map_err(|error| (error, MessageDispatchStatus::CallFailed))
+		}
+
+		/// Route the response to the requester.
+		///
+		/// Returns the weight of the callback.
+		pub(crate) fn receive_response(
+			lane_id: LaneId,
+			request_id: RequestId,
+			data: Option<Vec<u8>>,
+		) -> Result<Weight, (DispatchError, MessageDispatchStatus)> {
+			let request = PendingRequests::<T, I>::get(request_id)
+				.filter(|request| request.lane_id == lane_id)
+				.ok_or_else(|| {
+					log::trace!(
+						target: LOG_TARGET,
+						"Rejecting unexpected response to request {} at lane {:?}",
+						request_id,
+						lane_id,
+					);
+
+					(Error::<T, I>::UnexpectedResponse.into(), MessageDispatchStatus::Rejected)
+				})?;
+			PendingRequests::<T, I>::remove(request_id);
+
+			match data {
+				Some(data) => {
+					Self::deposit_event(Event::ResponseReceived { request_id });
+					Ok(Self::call_on_response(request_id, &request.requester, Ok(data)))
+				},
+				None => {
+					Self::deposit_event(Event::RequestFailed {
+						request_id,
+						reason: RequestFailure::Rejected,
+					});
+					Ok(Self::call_on_response(
+						request_id,
+						&request.requester,
+						Err(RequestFailure::Rejected),
+					))
+				},
+			}
+		}
+
+		/// Forget about the pending request and notify the requester about the failure.
+		///
+		/// Returns the weight of the operation, including the weight of the callback.
+		pub(crate) fn fail_request(
+			request_id: RequestId,
+			reason: RequestFailure,
+			db_weight: RuntimeDbWeight,
+		) -> Weight {
+			let request = match PendingRequests::<T, I>::take(request_id) {
+				Some(request) => request,
+				None => return db_weight.reads(1),
+			};
+
+			log::trace!(target: LOG_TARGET, "Request {} has failed: {:?}", request_id, reason);
+			Self::deposit_event(Event::RequestFailed { request_id, reason });
+
+			db_weight.reads_writes(1, 1).saturating_add(Self::call_on_response(
+				request_id,
+				&request.requester,
+				Err(reason),
+			))
+		}
+
+		/// Call the `OnResponse` callback and return its weight, bounded by the
+		/// `Config::CallbackWeight`.
+		fn call_on_response(
+			request_id: RequestId,
+			requester: &T::AccountId,
+			response: Result<Vec<u8>, RequestFailure>,
+		) -> Weight {
+			let max_callback_weight = T::CallbackWeight::get();
+			let callback_weight = T::OnResponse::on_response(request_id, requester, response);
+			if callback_weight.any_gt(max_callback_weight) {
+				debug_assert!(false, "T::OnResponse callback consumed too much weight.");
+				log::error!(
+					target: LOG_TARGET,
+					"T::OnResponse callback has spent more weight that it is allowed to: {} vs {}",
+					callback_weight,
+					max_callback_weight,
+				);
+			}
+
+			callback_weight.min(max_callback_weight)
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config<I>, I: 'static = ()> {
+		/// Request has been sent over the lane.
+		RequestSent {
+			request_id: RequestId,
+			requester: T::AccountId,
+			lane_id: LaneId,
+			nonce: MessageNonce,
+		},
+		/// Request from the bridged chain has been handled and the response has been sent.
+		RequestServed { lane_id: LaneId, request_id: RequestId, response_nonce: MessageNonce },
+		/// Response to the request has been received and passed to the requester.
+		ResponseReceived { request_id: RequestId },
+		/// Request has failed and the requester has been notified.
+		RequestFailed { request_id: RequestId, reason: RequestFailure },
+	}
+
+	#[pallet::error]
+	pub enum Error<T, I = ()> {
+		/// Too many requests are going to time out at the same block.
+		TooManyRequests,
+		/// The request message has been rejected by the messages bridge.
+		FailedToSendRequest,
+		/// The request data is larger than the `Config::MaxRequestSize`.
+		RequestTooLarge,
+		/// The response fee, offered by the requester, is lower than the `Config::ResponseFee`.
+		ResponseFeeTooLow,
+		/// The response message has been rejected by the messages bridge.
+		FailedToSendResponse,
+		/// There's no pending request at the lane for the received response.
+		UnexpectedResponse,
+		/// The message payload can't be decoded.
+		FailedToDecodeMessage,
+	}
+
+	/// Identifier of the next request.
+	#[pallet::storage]
+	pub type NextRequestId<T: Config<I>, I: 'static = ()> = StorageValue<_, RequestId, ValueQuery>;
+
+	/// Requests that are waiting for responses.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_request)]
+	pub type PendingRequests<T: Config<I>, I: 'static = ()> = StorageMap<
+		_,
+		Blake2_128Concat,
+		RequestId,
+		PendingRequest<T::AccountId, T::BlockNumber>,
+		OptionQuery,
+	>;
+
+	/// Map of the request message key => request id.
+	///
+	/// The entry is removed once the delivery of the request message is confirmed.
+	#[pallet::storage]
+	pub type RequestMessages<T: Config<I>, I: 'static = ()> =
+		StorageMap<_, Blake2_128Concat, MessageKey, RequestId, OptionQuery>;
+
+	/// Map of the block number => requests that time out at this block.
+	///
+	/// Requests that have been completed before are not removed from this map. They're simply
+	/// ignored when their block comes.
+	#[pallet::storage]
+	pub type RequestTimeouts<T: Config<I>, I: 'static = ()> = StorageMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		BoundedVec<RequestId, T::MaxRequestsPerBlock>,
+		ValueQuery,
+	>;
+
+	/// Number of the latest block, where some request times out.
+	///
+	/// New requests never time out before this block. There are no timeouts after this block,
+	/// so if it is full, the request is scheduled to time out at the next block.
+	#[pallet::storage]
+	pub type LatestTimeoutBlock<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, T::BlockNumber, ValueQuery>;
+}
+pub use pallet::*;
+
+impl<T: Config<I>, I: 'static, Fee> MessageDispatch<T::AccountId, Fee> for Pallet<T, I> {
+	type DispatchPayload = BridgeMessage<T::MessageFee>;
+
+	fn dispatch_weight(message: &mut DispatchMessage<Self::DispatchPayload, Fee>) -> Weight {
+		match message.data.payload {
+			Ok(BridgeMessage::Request { ref data, .. })
+				if data.len() <= T::MaxRequestSize::get() as usize =>
+				T::WeightInfo::serve_request()
+					.saturating_add(T::RequestHandler::request_weight(message.key.lane_id, data)),
+			Ok(BridgeMessage::Request { .. }) => T::WeightInfo::serve_request(),
+			Ok(BridgeMessage::Response { .. }) =>
+				T::WeightInfo::receive_response().saturating_add(T::CallbackWeight::get()),
+			Err(_) => Weight::zero(),
+		}
+	}
+
+	fn pre_dispatch(
+		_relayer_account: &T::AccountId,
+		_message: &DispatchMessage<Self::DispatchPayload, Fee>,
+	) -> Result<(), &'static str> {
+		Ok(())
+	}
+
+	fn dispatch(
+		_relayer_account: &T::AccountId,
+		mut message: DispatchMessage<Self::DispatchPayload, Fee>,
+	) -> MessageDispatchResult {
+		let dispatch_weight = Self::dispatch_weight(&mut message);
+		let lane_id = message.key.lane_id;
+		let (base_weight, result) = match message.data.payload {
+			Ok(BridgeMessage::Request { id, data, response_fee }) => (
+				T::WeightInfo::serve_request(),
+				Self::serve_request(lane_id, id, data, response_fee),
+			),
+			Ok(BridgeMessage::Response { id, data }) =>
+				(T::WeightInfo::receive_response(), Self::receive_response(lane_id, id, data)),
+			Err(_) => (
+				Weight::zero(),
+				Err((
+					Error::<T, I>::FailedToDecodeMessage.into(),
+					MessageDispatchStatus::CallDecodeFailed,
+				)),
+			),
+		};
+
+		match result {
+			Ok(extra_weight) => MessageDispatchResult {
+				dispatch_result: true,
+				unspent_weight: dispatch_weight
+					.saturating_sub(base_weight.saturating_add(extra_weight)),
+				dispatch_fee_paid_during_dispatch: false,
+				dispatch_error: None,
+				dispatch_status: Some(MessageDispatchStatus::Dispatched),
+			},
+			Err((error, status)) => MessageDispatchResult {
+				dispatch_result: false,
+				unspent_weight: Weight::zero(),
+				dispatch_fee_paid_during_dispatch: false,
+				dispatch_error: Some(error),
+				dispatch_status: Some(status),
+			},
+		}
+	}
+}
+
+impl<T: Config<I>, I: 'static> OnDeliveryConfirmed for Pallet<T, I> {
+	fn on_messages_delivered(lane: &LaneId, messages: &DeliveredMessages) -> Weight {
+		let db_weight = T::DbWeight::get();
+		let mut weight = Weight::zero();
+		for nonce in messages.begin..=messages.end {
+			weight = weight.saturating_add(db_weight.reads(1));
+			let request_id =
+				match RequestMessages::<T, I>::take(MessageKey { lane_id: *lane, nonce }) {
+					Some(request_id) => request_id,
+					None => continue,
+				};
+			weight = weight.saturating_add(db_weight.writes(1));
+
+			// there'll be no response to the request that has not been dispatched. The deferred
+			// request is dispatched later, so it is left pending until the response is received
+			// or until it times out. The same applies to requests with unknown dispatch status,
+			// because they may have been deferred too
+			let dispatch_status = messages.message_dispatch_status(nonce);
+			let may_be_dispatched_later =
+				matches!(dispatch_status, None | Some(MessageDispatchStatus::Deferred));
+			if !messages.message_dispatch_result(nonce) && !may_be_dispatched_later {
+				weight = weight.saturating_add(Self::fail_request(
+					request_id,
+					RequestFailure::NotDispatched,
+					db_weight,
+				));
+			}
+		}
+		weight
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// darwinia-network
+	use super::*;
+	use crate::mock::*;
+	// substrate
+	use frame_support::{assert_noop, assert_ok, traits::Hooks};
+
+	fn send_request(data: Vec<u8>) -> RequestId {
+		send_request_over(TEST_LANE_ID, data)
+	}
+
+	fn send_request_over(lane_id: LaneId, data: Vec<u8>) -> RequestId {
+		this_chain::run(|| {
+			let request_id = NextRequestId::<this_chain::TestRuntime>::get();
+			assert_ok!(Pallet::<this_chain::TestRuntime>::send_request(
+				this_chain::RuntimeOrigin::signed(REQUESTER),
+				lane_id,
+				data,
+				REQUEST_FEE,
+				RESPONSE_FEE,
+			));
+			request_id
+		})
+	}
+
+	#[test]
+	fn request_is_answered() {
+		run_test(|| {
+			let request_id = send_request(vec![1, 2, 3]);
+
+			// request is delivered and served by the bridged chain
+			let results = relay::<this_chain::TestRuntime, bridged_chain::TestRuntime>(true);
+			assert_eq!(results, vec![(true, Some(MessageDispatchStatus::Dispatched))]);
+			bridged_chain::run(|| {
+				assert_eq!(
+					outbound_messages(),
+					vec![(
+						TEST_LANE_ID,
+						1,
+						BridgeMessage::Response { id: request_id, data: Some(vec![3, 2, 1]) }
+					)],
+				);
+			});
+
+			// response is delivered and routed to the requester
+			let results = relay::<bridged_chain::TestRuntime, this_chain::TestRuntime>(true);
+			assert_eq!(results, vec![(true, Some(MessageDispatchStatus::Dispatched))]);
+			this_chain::run(|| {
+				assert_eq!(responses(), vec![(request_id, REQUESTER, Ok(vec![3, 2, 1]))]);
+				assert_eq!(Pallet::<this_chain::TestRuntime>::pending_request(request_id), None);
+				assert!(RequestMessages::<this_chain::TestRuntime>::iter().next().is_none());
+			});
+		});
+	}
+
+	#[test]
+	fn requester_pays_request_and_response_fees() {
+		run_test(|| {
+			send_request(vec![1, 2, 3]);
+			this_chain::run(|| {
+				let pallet_account = Pallet::<this_chain::TestRuntime>::account_id();
+				assert_eq!(
+					this_chain::Balances::free_balance(REQUESTER),
+					INITIAL_BALANCE - REQUEST_FEE - RESPONSE_FEE,
+				);
+				assert_eq!(paid_fees(), vec![(pallet_account, REQUEST_FEE)]);
+			});
+
+			// the response is sent by the pallet account with the fee, offered by the requester
+			relay::<this_chain::TestRuntime, bridged_chain::TestRuntime>(true);
+			bridged_chain::run(|| {
+				let pallet_account = Pallet::<bridged_chain::TestRuntime>::account_id();
+				assert_eq!(paid_fees(), vec![(pallet_account, RESPONSE_FEE)]);
+			});
+		});
+	}
+
+	#[test]
+	fn request_with_too_low_response_fee_is_rejected() {
+		run_test(|| {
+			bridged_chain::run(|| {
+				let result = Pallet::<bridged_chain::TestRuntime>::dispatch(
+					&RELAYER,
+					dispatch_message(
+						TEST_LANE_ID,
+						1,
+						BridgeMessage::Request {
+							id: 0,
+							data: vec![1, 2, 3],
+							response_fee: RESPONSE_FEE - 1,
+						},
+					),
+				);
+				assert!(!result.dispatch_result);
+				assert_eq!(result.dispatch_status, Some(MessageDispatchStatus::Rejected));
+				assert_eq!(
+					result.dispatch_error,
+					Some(Error::<bridged_chain::TestRuntime>::ResponseFeeTooLow.into()),
+				);
+				assert_eq!(outbound_messages(), vec![]);
+			});
+		});
+	}
+
+	#[test]
+	fn too_large_request_is_rejected() {
+		run_test(|| {
+			bridged_chain::run(|| {
+				let mut message = dispatch_message(
+					TEST_LANE_ID,
+					1,
+					BridgeMessage::Request {
+						id: 0,
+						data: vec![1; MAX_REQUEST_SIZE as usize + 1],
+						response_fee: RESPONSE_FEE,
+					},
+				);
+				assert_eq!(
+					Pallet::<bridged_chain::TestRuntime>::dispatch_weight(&mut message),
+					<bridged_chain::TestRuntime as Config>::WeightInfo::serve_request(),
+				);
+
+				let result = Pallet::<bridged_chain::TestRuntime>::dispatch(&RELAYER, message);
+				assert!(!result.dispatch_result);
+				assert_eq!(result.dispatch_status, Some(MessageDispatchStatus::Rejected));
+				assert_eq!(
+					result.dispatch_error,
+					Some(Error::<bridged_chain::TestRuntime>::RequestTooLarge.into()),
+				);
+				assert_eq!(outbound_messages(), vec![]);
+			});
+		});
+	}
+
+	#[test]
+	fn request_fails_if_response_is_not_sent() {
+		run_test(|| {
+			let request_id = send_request(vec![1, 2, 3]);
+
+			bridged_chain::run(reject_outbound_messages);
+			let results = relay::<this_chain::TestRuntime, bridged_chain::TestRuntime>(true);
+			assert_eq!(results, vec![(false, Some(MessageDispatchStatus::CallFailed))]);
+			bridged_chain::run(|| {
+				assert!(frame_system::Pallet::<bridged_chain::TestRuntime>::events().is_empty())
+			});
+			this_chain::run(|| {
+				assert_eq!(
+					responses(),
+					vec![(request_id, REQUESTER, Err(RequestFailure::NotDispatched))]
+				);
+			});
+		});
+	}
+
+	#[test]
+	fn rejected_request_is_reported_to_requester() {
+		run_test(|| {
+			let request_id = send_request(Vec::new());
+
+			relay::<this_chain::TestRuntime, bridged_chain::TestRuntime>(true);
+			relay::<bridged_chain::TestRuntime, this_chain::TestRuntime>(true);
+			this_chain::run(|| {
+				assert_eq!(
+					responses(),
+					vec![(request_id, REQUESTER, Err(RequestFailure::Rejected))]
+				);
+			});
+		});
+	}
+
+	#[test]
+	fn request_fails_if_it_has_not_been_dispatched() {
+		run_test(|| {
+			let request_id = send_request(vec![1, 2, 3]);
+
+			relay::<this_chain::TestRuntime, bridged_chain::TestRuntime>(false);
+			bridged_chain::run(|| assert_eq!(outbound_messages(), vec![]));
+			this_chain::run(|| {
+				assert_eq!(
+					responses(),
+					vec![(request_id, REQUESTER, Err(RequestFailure::NotDispatched))]
+				);
+				assert_eq!(Pallet::<this_chain::TestRuntime>::pending_request(request_id), None);
+			});
+		});
+	}
+
+	#[test]
+	fn deferred_request_is_answered_later() {
+		run_test(|| {
+			let request_id = send_request(vec![1, 2, 3]);
+
+			// request delivery is confirmed before it is dispatched at the bridged chain
+			let messages = relay_deferred::<this_chain::TestRuntime>();
+			this_chain::run(|| {
+				assert_eq!(responses(), vec![]);
+				assert!(Pallet::<this_chain::TestRuntime>::pending_request(request_id).is_some());
+			});
+
+			// the request is served once it is dispatched
+			bridged_chain::run(|| {
+				for (lane_id, nonce, message) in messages {
+					let result = Pallet::<bridged_chain::TestRuntime>::dispatch(
+						&RELAYER,
+						dispatch_message(lane_id, nonce, message),
+					);
+					assert!(result.dispatch_result);
+				}
+			});
+			let results = relay::<bridged_chain::TestRuntime, this_chain::TestRuntime>(true);
+			assert_eq!(results, vec![(true, Some(MessageDispatchStatus::Dispatched))]);
+			this_chain::run(|| {
+				assert_eq!(responses(), vec![(request_id, REQUESTER, Ok(vec![3, 2, 1]))]);
+			});
+		});
+	}
+
+	#[test]
+	fn deferred_request_times_out() {
+		run_test(|| {
+			let request_id = send_request(vec![1, 2, 3]);
+			relay_deferred::<this_chain::TestRuntime>();
+
+			this_chain::run(|| {
+				Pallet::<this_chain::TestRuntime>::on_initialize(1 + REQUEST_TIMEOUT);
+				assert_eq!(
+					responses(),
+					vec![(request_id, REQUESTER, Err(RequestFailure::TimedOut))]
+				);
+			});
+		});
+	}
+
+	#[test]
+	fn request_times_out() {
+		run_test(|| {
+			let request_id = send_request(vec![1, 2, 3]);
+			relay::<this_chain::TestRuntime, bridged_chain::TestRuntime>(true);
+
+			this_chain::run(|| {
+				Pallet::<this_chain::TestRuntime>::on_initialize(REQUEST_TIMEOUT);
+				assert_eq!(responses(), vec![]);
+
+				Pallet::<this_chain::TestRuntime>::on_initialize(1 + REQUEST_TIMEOUT);
+				assert_eq!(
+					responses(),
+					vec![(request_id, REQUESTER, Err(RequestFailure::TimedOut))]
+				);
+			});
+
+			// late response is rejected
+			let results = relay::<bridged_chain::TestRuntime, this_chain::TestRuntime>(true);
+			assert_eq!(results, vec![(false, Some(MessageDispatchStatus::Rejected))]);
+			this_chain::run(|| assert_eq!(responses().len(), 1));
+		});
+	}
+
+	#[test]
+	fn response_is_rejected_if_received_over_another_lane() {
+		run_test(|| {
+			let request_id = send_request(vec![1, 2, 3]);
+
+			this_chain::run(|| {
+				let result = Pallet::<this_chain::TestRuntime>::dispatch(
+					&RELAYER,
+					dispatch_message(
+						TEST_LANE_ID_2,
+						1,
+						BridgeMessage::Response { id: request_id, data: Some(vec![1]) },
+					),
+				);
+				assert!(!result.dispatch_result);
+				assert_eq!(result.dispatch_status, Some(MessageDispatchStatus::Rejected));
+				assert!(Pallet::<this_chain::TestRuntime>::pending_request(request_id).is_some());
+			});
+		});
+	}
+
+	#[test]
+	fn requests_are_confirmed_lane_by_lane() {
+		run_test(|| {
+			let request_id_1 = send_request_over(TEST_LANE_ID, vec![1]);
+			let request_id_2 = send_request_over(TEST_LANE_ID_2, vec![2]);
+			this_chain::run(|| {
+				assert_eq!(
+					outbound_messages()
+						.into_iter()
+						.map(|(lane, nonce, _)| (lane, nonce))
+						.collect::<Vec<_>>(),
+					vec![(TEST_LANE_ID, 1), (TEST_LANE_ID_2, 1)],
+				);
+			});
+
+			relay::<this_chain::TestRuntime, bridged_chain::TestRuntime>(false);
+			this_chain::run(|| {
+				assert_eq!(
+					responses(),
+					vec![
+						(request_id_1, REQUESTER, Err(RequestFailure::NotDispatched)),
+						(request_id_2, REQUESTER, Err(RequestFailure::NotDispatched)),
+					]
+				);
+				assert!(RequestMessages::<this_chain::TestRuntime>::iter().next().is_none());
+			});
+		});
+	}
+
+	#[test]
+	fn requests_time_out_at_next_block_if_block_is_full() {
+		run_test(|| {
+			let request_id_1 = send_request(vec![1]);
+			let request_id_2 = send_request(vec![2]);
+			let request_id_3 = send_request(vec![3]);
+
+			this_chain::run(|| {
+				assert_eq!(
+					Pallet::<this_chain::TestRuntime>::pending_request(request_id_3)
+						.map(|request| request.timeout_at),
+					Some(2 + REQUEST_TIMEOUT),
+				);
+
+				// later requests never time out before the already scheduled ones
+				frame_system::Pallet::<this_chain::TestRuntime>::set_block_number(2);
+			});
+			let request_id_4 = send_request(vec![4]);
+
+			this_chain::run(|| {
+				assert_eq!(
+					Pallet::<this_chain::TestRuntime>::pending_request(request_id_4)
+						.map(|request| request.timeout_at),
+					Some(2 + REQUEST_TIMEOUT),
+				);
+
+				Pallet::<this_chain::TestRuntime>::on_initialize(1 + REQUEST_TIMEOUT);
+				assert_eq!(
+					responses(),
+					vec![
+						(request_id_1, REQUESTER, Err(RequestFailure::TimedOut)),
+						(request_id_2, REQUESTER, Err(RequestFailure::TimedOut)),
+					]
+				);
+
+				Pallet::<this_chain::TestRuntime>::on_initialize(2 + REQUEST_TIMEOUT);
+				assert_eq!(
+					responses()[2..].to_vec(),
+					vec![
+						(request_id_3, REQUESTER, Err(RequestFailure::TimedOut)),
+						(request_id_4, REQUESTER, Err(RequestFailure::TimedOut)),
+					]
+				);
+			});
+		});
+	}
+
+	#[test]
+	fn send_request_fails_if_request_is_too_large() {
+		run_test(|| {
+			this_chain::run(|| {
+				assert_noop!(
+					Pallet::<this_chain::TestRuntime>::send_request(
+						this_chain::RuntimeOrigin::signed(REQUESTER),
+						TEST_LANE_ID,
+						vec![1; MAX_REQUEST_SIZE as usize + 1],
+						REQUEST_FEE,
+						RESPONSE_FEE,
+					),
+					Error::<this_chain::TestRuntime>::RequestTooLarge,
+				);
+			});
+		});
+	}
+
+	#[test]
+	fn send_request_fails_if_message_is_rejected() {
+		run_test(|| {
+			this_chain::run(|| {
+				reject_outbound_messages();
+				assert_noop!(
+					Pallet::<this_chain::TestRuntime>::send_request(
+						this_chain::RuntimeOrigin::signed(REQUESTER),
+						TEST_LANE_ID,
+						vec![1],
+						REQUEST_FEE,
+						RESPONSE_FEE,
+					),
+					Error::<this_chain::TestRuntime>::FailedToSendRequest,
+				);
+			});
+		});
+	}
+}
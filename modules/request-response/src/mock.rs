@@ -0,0 +1,447 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Two mock runtimes, connected with the simplified messages bridge.
+//!
+//! Messages, sent by the runtime, are queued in its storage. Every lane has its own sequence of
+//! nonces. They're relayed to the other runtime using the `relay` function, which dispatches them
+//! using the `MessageDispatch` implementation of the pallet and then confirms their delivery
+//! (lane by lane) using the `OnDeliveryConfirmed` implementation.
+
+// core
+use core::cell::RefCell;
+// crates.io
+use codec::Encode;
+// darwinia-network
+use crate as pallet_bridge_request_response;
+use crate::*;
+use bp_messages::{
+	source_chain::{DeliveryCallbackId, SendMessageArtifacts},
+	target_chain::DispatchMessageData,
+	MessageExpiry,
+};
+// substrate
+use frame_support::parameter_types;
+use sp_io::TestExternalities;
+
+pub type AccountId = u64;
+pub type Balance = u64;
+
+/// Lane that we're using in tests.
+pub const TEST_LANE_ID: LaneId = [0, 0, 0, 1];
+
+/// Another lane that we're using in tests.
+pub const TEST_LANE_ID_2: LaneId = [0, 0, 0, 2];
+
+/// Account that sends requests.
+pub const REQUESTER: AccountId = 1;
+
+/// Relayer that delivers messages.
+pub const RELAYER: AccountId = 100;
+
+/// Fee that is paid for every request.
+pub const REQUEST_FEE: Balance = 10;
+
+/// Minimal fee of the response message.
+pub const RESPONSE_FEE: Balance = 5;
+
+/// Maximal size of the request data.
+pub const MAX_REQUEST_SIZE: u32 = 16;
+
+/// Initial balance of the requester and the pallet account.
+pub const INITIAL_BALANCE: Balance = 1_000;
+
+/// Number of blocks after which the request times out.
+pub const REQUEST_TIMEOUT: u64 = 10;
+
+parameter_types! {
+	pub const RequestResponsePalletId: PalletId = PalletId(*b"da/rqrsp");
+	pub const CallbackWeight: Weight = Weight::from_parts(1_000, 0);
+}
+
+/// Mock chain with its own storage.
+pub trait MockChain {
+	/// Execute given closure in the context of the chain.
+	fn run<R>(f: impl FnOnce() -> R) -> R;
+	/// Reset chain storage.
+	fn reset();
+}
+
+macro_rules! impl_mock_chain {
+	($chain:ident) => {
+		pub mod $chain {
+			// darwinia-network
+			use super::*;
+			// substrate
+			use frame_system::mocking::*;
+			use sp_core::{ConstU32, ConstU64, H256};
+			use sp_runtime::{
+				testing::Header as SubstrateHeader,
+				traits::{BlakeTwo256, IdentityLookup},
+			};
+
+			type Block = MockBlock<TestRuntime>;
+			type UncheckedExtrinsic = MockUncheckedExtrinsic<TestRuntime>;
+
+			frame_support::construct_runtime! {
+				pub enum TestRuntime where
+					Block = Block,
+					NodeBlock = Block,
+					UncheckedExtrinsic = UncheckedExtrinsic,
+				{
+					System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+					Balances: pallet_balances::{Pallet, Call, Storage, Event<T>},
+					RequestResponse: pallet_bridge_request_response::{Pallet, Call, Storage, Event<T>},
+				}
+			}
+
+			impl frame_system::Config for TestRuntime {
+				type AccountData = pallet_balances::AccountData<Balance>;
+				type AccountId = AccountId;
+				type BaseCallFilter = frame_support::traits::Everything;
+				type BlockHashCount = ConstU64<250>;
+				type BlockLength = ();
+				type BlockNumber = u64;
+				type BlockWeights = ();
+				type DbWeight = ();
+				type Hash = H256;
+				type Hashing = BlakeTwo256;
+				type Header = SubstrateHeader;
+				type Index = u64;
+				type Lookup = IdentityLookup<Self::AccountId>;
+				type MaxConsumers = ConstU32<16>;
+				type OnKilledAccount = ();
+				type OnNewAccount = ();
+				type OnSetCode = ();
+				type PalletInfo = PalletInfo;
+				type RuntimeCall = RuntimeCall;
+				type RuntimeEvent = RuntimeEvent;
+				type RuntimeOrigin = RuntimeOrigin;
+				type SS58Prefix = ();
+				type SystemWeightInfo = ();
+				type Version = ();
+			}
+
+			impl pallet_balances::Config for TestRuntime {
+				type AccountStore = frame_system::Pallet<TestRuntime>;
+				type Balance = Balance;
+				type DustRemoval = ();
+				type ExistentialDeposit = ConstU64<1>;
+				type FreezeIdentifier = ();
+				type HoldIdentifier = ();
+				type MaxFreezes = ();
+				type MaxHolds = ();
+				type MaxLocks = ();
+				type MaxReserves = ();
+				type ReserveIdentifier = ();
+				type RuntimeEvent = RuntimeEvent;
+				type WeightInfo = ();
+			}
+
+			impl pallet_bridge_request_response::Config for TestRuntime {
+				type CallbackWeight = CallbackWeight;
+				type Currency = Balances;
+				type MaxRequestSize = ConstU32<MAX_REQUEST_SIZE>;
+				type MaxRequestsPerBlock = ConstU32<2>;
+				type MessageFee = Balance;
+				type MessagesBridge = TestMessagesBridge;
+				type OnResponse = TestOnResponse;
+				type PalletId = RequestResponsePalletId;
+				type RequestHandler = TestRequestHandler;
+				type RequestTimeout = ConstU64<REQUEST_TIMEOUT>;
+				type ResponseFee = ConstU64<RESPONSE_FEE>;
+				type RuntimeEvent = RuntimeEvent;
+				type WeightInfo = ();
+			}
+
+			thread_local! {
+				static EXTERNALITIES: RefCell<TestExternalities> = RefCell::new(new_externalities());
+			}
+
+			fn new_externalities() -> TestExternalities {
+				let mut t =
+					frame_system::GenesisConfig::default().build_storage::<TestRuntime>().unwrap();
+				pallet_balances::GenesisConfig::<TestRuntime> {
+					balances: vec![
+						(REQUESTER, INITIAL_BALANCE),
+						(Pallet::<TestRuntime>::account_id(), INITIAL_BALANCE),
+					],
+				}
+				.assimilate_storage(&mut t)
+				.unwrap();
+				let mut ext = TestExternalities::new(t);
+				ext.execute_with(|| frame_system::Pallet::<TestRuntime>::set_block_number(1));
+				ext
+			}
+
+			impl MockChain for TestRuntime {
+				fn run<R>(f: impl FnOnce() -> R) -> R {
+					EXTERNALITIES.with(|ext| ext.borrow_mut().execute_with(f))
+				}
+
+				fn reset() {
+					EXTERNALITIES.with(|ext| *ext.borrow_mut() = new_externalities());
+				}
+			}
+
+			/// Execute given closure in the context of the chain.
+			pub fn run<R>(f: impl FnOnce() -> R) -> R {
+				<TestRuntime as MockChain>::run(f)
+			}
+		}
+	};
+}
+
+impl_mock_chain!(this_chain);
+impl_mock_chain!(bridged_chain);
+
+/// Message that is sent by the mock runtime.
+pub type TestMessage = BridgeMessage<Balance>;
+
+/// Messages bridge that queues all sent messages in the runtime storage.
+pub struct TestMessagesBridge;
+impl TestMessagesBridge {
+	fn queue_message<SenderOrigin>(
+		sender: SenderOrigin,
+		lane: LaneId,
+		message: TestMessage,
+		fee: Balance,
+	) -> Result<SendMessageArtifacts, &'static str>
+	where
+		SenderOrigin: Into<Result<frame_system::RawOrigin<AccountId>, SenderOrigin>>,
+	{
+		if frame_support::storage::unhashed::get_or_default(b":reject-outbound-messages:") {
+			return Err("Outbound messages are rejected");
+		}
+
+		let sender = match sender.into() {
+			Ok(frame_system::RawOrigin::Signed(sender)) => sender,
+			_ => return Err("Messages may only be sent by signed origins"),
+		};
+		let mut fees = paid_fees();
+		fees.push((sender, fee));
+		frame_support::storage::unhashed::put(b":paid-fees:", &fees);
+
+		let nonce_key = (b":latest-nonce:", lane).encode();
+		let nonce: MessageNonce =
+			frame_support::storage::unhashed::get_or_default::<MessageNonce>(&nonce_key) + 1;
+		frame_support::storage::unhashed::put(&nonce_key, &nonce);
+
+		let mut messages = outbound_messages();
+		messages.push((lane, nonce, message));
+		frame_support::storage::unhashed::put(b":outbound-messages:", &messages);
+
+		Ok(SendMessageArtifacts { nonce, weight: Weight::zero() })
+	}
+}
+impl<SenderOrigin> MessagesBridge<SenderOrigin, Balance, TestMessage> for TestMessagesBridge
+where
+	SenderOrigin: Into<Result<frame_system::RawOrigin<AccountId>, SenderOrigin>>,
+{
+	type Error = &'static str;
+
+	fn send_message(
+		sender: SenderOrigin,
+		lane: LaneId,
+		message: TestMessage,
+		delivery_and_dispatch_fee: Balance,
+	) -> Result<SendMessageArtifacts, Self::Error> {
+		Self::queue_message(sender, lane, message, delivery_and_dispatch_fee)
+	}
+
+	fn send_message_with_expiry(
+		sender: SenderOrigin,
+		lane: LaneId,
+		message: TestMessage,
+		delivery_and_dispatch_fee: Balance,
+		_expiry: MessageExpiry,
+	) -> Result<SendMessageArtifacts, Self::Error> {
+		Self::queue_message(sender, lane, message, delivery_and_dispatch_fee)
+	}
+
+	fn send_message_with_callback(
+		sender: SenderOrigin,
+		lane: LaneId,
+		message: TestMessage,
+		delivery_and_dispatch_fee: Balance,
+		_callback_id: DeliveryCallbackId,
+	) -> Result<SendMessageArtifacts, Self::Error> {
+		Self::queue_message(sender, lane, message, delivery_and_dispatch_fee)
+	}
+}
+
+/// Request handler that responds with reversed request data. Empty requests are rejected.
+pub struct TestRequestHandler;
+impl RequestHandler for TestRequestHandler {
+	fn request_weight(_lane_id: LaneId, data: &[u8]) -> Weight {
+		Weight::from_parts(data.len() as u64, 0)
+	}
+
+	fn handle_request(_lane_id: LaneId, data: Vec<u8>) -> (Option<Vec<u8>>, Weight) {
+		if data.is_empty() {
+			return (None, Weight::zero());
+		}
+
+		let weight = Weight::from_parts(data.len() as u64, 0);
+		(Some(data.into_iter().rev().collect()), weight)
+	}
+}
+
+/// Callback that records all responses in the runtime storage.
+pub struct TestOnResponse;
+impl OnResponse<AccountId> for TestOnResponse {
+	fn on_response(
+		request_id: RequestId,
+		requester: &AccountId,
+		response: Result<Vec<u8>, RequestFailure>,
+	) -> Weight {
+		let mut all_responses = responses();
+		all_responses.push((request_id, *requester, response));
+		frame_support::storage::unhashed::put(b":responses:", &all_responses);
+		CallbackWeight::get()
+	}
+}
+
+/// Return all messages that have been sent by the current chain and not yet relayed.
+pub fn outbound_messages() -> Vec<(LaneId, MessageNonce, TestMessage)> {
+	frame_support::storage::unhashed::get_or_default(b":outbound-messages:")
+}
+
+/// Return senders and fees of all messages that have been sent by the current chain.
+pub fn paid_fees() -> Vec<(AccountId, Balance)> {
+	frame_support::storage::unhashed::get_or_default(b":paid-fees:")
+}
+
+/// Reject all messages, sent by the current chain.
+pub fn reject_outbound_messages() {
+	frame_support::storage::unhashed::put(b":reject-outbound-messages:", &true);
+}
+
+/// Return all responses, received by the current chain.
+pub fn responses() -> Vec<(RequestId, AccountId, Result<Vec<u8>, RequestFailure>)> {
+	frame_support::storage::unhashed::get_or_default(b":responses:")
+}
+
+/// Prepare inbound message for dispatch.
+pub fn dispatch_message(
+	lane_id: LaneId,
+	nonce: MessageNonce,
+	message: TestMessage,
+) -> DispatchMessage<TestMessage, Balance> {
+	DispatchMessage {
+		key: MessageKey { lane_id, nonce },
+		data: DispatchMessageData {
+			payload: Ok(message),
+			fee: REQUEST_FEE,
+			is_cancelled: false,
+			expiry: None,
+		},
+	}
+}
+
+/// Relay all queued messages from the `Source` chain to the `Target` chain and confirm their
+/// delivery.
+///
+/// If `dispatch` is false, messages are delivered, but not dispatched at the target chain (as
+/// if they have expired). Returns dispatch results and statuses of all relayed messages.
+pub fn relay<Source, Target>(dispatch: bool) -> Vec<(bool, Option<MessageDispatchStatus>)>
+where
+	Source: Config + MockChain,
+	Target: Config<AccountId = AccountId, MessageFee = Balance> + MockChain,
+{
+	let messages = Source::run(|| {
+		let messages = outbound_messages();
+		frame_support::storage::unhashed::kill(b":outbound-messages:");
+		messages
+	});
+	if messages.is_empty() {
+		return Vec::new();
+	}
+
+	let results = Target::run(|| {
+		messages
+			.iter()
+			.cloned()
+			.map(|(lane_id, nonce, message)| {
+				if !dispatch {
+					return (false, Some(MessageDispatchStatus::Expired));
+				}
+
+				let result =
+					Pallet::<Target>::dispatch(&RELAYER, dispatch_message(lane_id, nonce, message));
+				(result.dispatch_result, result.dispatch_status)
+			})
+			.collect::<Vec<_>>()
+	});
+
+	confirm_delivery::<Source>(&messages, &results);
+	results
+}
+
+/// Take all queued messages of the `Source` chain and confirm their delivery, as if their
+/// dispatch has been deferred at the target chain.
+///
+/// Returns the relayed messages, so that they may be dispatched later.
+pub fn relay_deferred<Source>() -> Vec<(LaneId, MessageNonce, TestMessage)>
+where
+	Source: Config + MockChain,
+{
+	let messages = Source::run(|| {
+		let messages = outbound_messages();
+		frame_support::storage::unhashed::kill(b":outbound-messages:");
+		messages
+	});
+
+	let results = vec![(false, Some(MessageDispatchStatus::Deferred)); messages.len()];
+	confirm_delivery::<Source>(&messages, &results);
+	messages
+}
+
+/// Confirm delivery of given messages at the `Source` chain.
+fn confirm_delivery<Source>(
+	messages: &[(LaneId, MessageNonce, TestMessage)],
+	results: &[(bool, Option<MessageDispatchStatus>)],
+) where
+	Source: Config + MockChain,
+{
+	Source::run(|| {
+		let mut delivered_messages: Vec<(LaneId, DeliveredMessages)> = Vec::new();
+		for ((lane_id, nonce, _), (result, status)) in messages.iter().zip(results.iter()) {
+			match delivered_messages.iter_mut().find(|(lane, _)| lane == lane_id) {
+				Some((_, lane_messages)) => {
+					lane_messages.note_dispatched_message(*result);
+					lane_messages.dispatch_statuses.extend(*status);
+				},
+				None => {
+					let mut lane_messages = DeliveredMessages::new(*nonce, *result);
+					lane_messages.dispatch_statuses.extend(*status);
+					delivered_messages.push((*lane_id, lane_messages));
+				},
+			}
+		}
+
+		for (lane_id, lane_messages) in delivered_messages {
+			Pallet::<Source>::on_messages_delivered(&lane_id, &lane_messages);
+		}
+	});
+}
+
+/// Run test with fresh storage of both chains.
+pub fn run_test<T>(test: impl FnOnce() -> T) -> T {
+	<this_chain::TestRuntime as MockChain>::reset();
+	<bridged_chain::TestRuntime as MockChain>::reset();
+	test()
+}
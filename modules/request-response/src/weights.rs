@@ -0,0 +1,50 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Weights for `pallet_bridge_request_response`.
+
+#![allow(clippy::all)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+#![allow(missing_docs)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for `pallet_bridge_request_response`.
+pub trait WeightInfo {
+	fn send_request() -> Weight;
+	fn serve_request() -> Weight;
+	fn receive_response() -> Weight;
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn send_request() -> Weight {
+		sp_runtime::traits::Zero::zero()
+	}
+
+	fn serve_request() -> Weight {
+		sp_runtime::traits::Zero::zero()
+	}
+
+	fn receive_response() -> Weight {
+		sp_runtime::traits::Zero::zero()
+	}
+}
@@ -20,8 +20,8 @@
 #![warn(missing_docs)]
 
 // crates.io
-use codec::{Decode, Encode};
-use scale_info::TypeInfo;
+use codec::{Decode, Encode, EncodeLike, Error as CodecError, Input, Output};
+use scale_info::{build::Fields, type_params, Path, Type, TypeInfo};
 // darwinia-network
 use bp_runtime::{
 	messages::{DispatchFeePayment, MessageDispatchResult},
@@ -38,6 +38,16 @@ pub type Weight = frame_support::weights::Weight;
 /// Spec version type.
 pub type SpecVersion = u32;
 
+/// Version of the message payload format.
+pub type PayloadVersion = u8;
+
+/// Value that is written in place of the `spec_version` of the legacy (unversioned) payload
+/// to mark that the payload version follows.
+pub const VERSIONED_PAYLOAD_MARKER: SpecVersion = SpecVersion::MAX;
+
+/// Version of the `MessagePayload` format.
+pub const PAYLOAD_VERSION_V1: PayloadVersion = 1;
+
 /// A generic trait to dispatch arbitrary messages delivered over the bridge.
 pub trait MessageDispatch<AccountId, BridgeMessageId> {
 	/// A type of the message to be dispatched.
@@ -194,3 +204,269 @@ impl<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature> Size
 		self.call.len() as _
 	}
 }
+
+/// Versioned message payload.
+///
+/// The `V1` payload is encoded exactly as the bare `MessagePayload`, so it may still be decoded
+/// by chains that are not aware of payload versions. Payloads of other versions are encoded as
+/// `VERSIONED_PAYLOAD_MARKER`, followed by the `PayloadVersion` and the payload itself. When
+/// decoding, both legacy and tagged `V1` payloads are accepted. Tagged payloads of versions that
+/// are unknown to this chain are decoded as `Unsupported`, so that the dispatcher is able to
+/// reject them gracefully instead of failing to decode the whole message.
+///
+/// The payload of unknown version is not self-delimiting, so it takes all remaining bytes of the
+/// input. It means that the versioned payload may only be decoded from the whole message payload.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum VersionedMessagePayload<
+	SourceChainAccountId,
+	TargetChainAccountPublic,
+	TargetChainSignature,
+	Call,
+> {
+	/// The original `MessagePayload` format.
+	V1(MessagePayload<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature, Call>),
+	/// Payload of the version that is not supported by this chain. The payload itself is not
+	/// decoded, it is kept as raw bytes to be encoded back exactly as it has been received.
+	Unsupported(PayloadVersion, Vec<u8>),
+}
+impl<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature, Call>
+	VersionedMessagePayload<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature, Call>
+{
+	/// Return version of the payload.
+	pub fn version(&self) -> PayloadVersion {
+		match *self {
+			Self::V1(_) => PAYLOAD_VERSION_V1,
+			Self::Unsupported(version, _) => version,
+		}
+	}
+
+	/// Return weight of the call, declared by the message sender.
+	///
+	/// Unsupported payloads are never dispatched, so their weight is zero.
+	pub fn weight(&self) -> Weight {
+		match *self {
+			Self::V1(ref payload) => payload.weight,
+			Self::Unsupported(..) => Weight::zero(),
+		}
+	}
+}
+impl<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature, Call>
+	From<MessagePayload<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature, Call>>
+	for VersionedMessagePayload<
+		SourceChainAccountId,
+		TargetChainAccountPublic,
+		TargetChainSignature,
+		Call,
+	>
+{
+	fn from(
+		payload: MessagePayload<
+			SourceChainAccountId,
+			TargetChainAccountPublic,
+			TargetChainSignature,
+			Call,
+		>,
+	) -> Self {
+		Self::V1(payload)
+	}
+}
+impl<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature, Call> Encode
+	for VersionedMessagePayload<
+		SourceChainAccountId,
+		TargetChainAccountPublic,
+		TargetChainSignature,
+		Call,
+	> where
+	SourceChainAccountId: Encode,
+	TargetChainAccountPublic: Encode,
+	TargetChainSignature: Encode,
+	Call: Encode,
+{
+	fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+		match *self {
+			Self::V1(ref payload) => payload.encode_to(dest),
+			Self::Unsupported(version, ref payload) => {
+				VERSIONED_PAYLOAD_MARKER.encode_to(dest);
+				version.encode_to(dest);
+				dest.write(payload);
+			},
+		}
+	}
+}
+impl<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature, Call> EncodeLike
+	for VersionedMessagePayload<
+		SourceChainAccountId,
+		TargetChainAccountPublic,
+		TargetChainSignature,
+		Call,
+	> where
+	SourceChainAccountId: Encode,
+	TargetChainAccountPublic: Encode,
+	TargetChainSignature: Encode,
+	Call: Encode,
+{
+}
+impl<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature, Call> Decode
+	for VersionedMessagePayload<
+		SourceChainAccountId,
+		TargetChainAccountPublic,
+		TargetChainSignature,
+		Call,
+	> where
+	SourceChainAccountId: Decode,
+	TargetChainAccountPublic: Decode,
+	TargetChainSignature: Decode,
+	Call: Decode,
+{
+	fn decode<I: Input>(input: &mut I) -> Result<Self, CodecError> {
+		let spec_version = SpecVersion::decode(input)?;
+		if spec_version != VERSIONED_PAYLOAD_MARKER {
+			// legacy payload, the `spec_version` has already been read
+			return Ok(Self::V1(MessagePayload {
+				spec_version,
+				weight: Decode::decode(input)?,
+				origin: Decode::decode(input)?,
+				dispatch_fee_payment: Decode::decode(input)?,
+				call: Decode::decode(input)?,
+			}));
+		}
+
+		match PayloadVersion::decode(input)? {
+			PAYLOAD_VERSION_V1 => Ok(Self::V1(MessagePayload::decode(input)?)),
+			version => Ok(Self::Unsupported(version, read_remaining_bytes(input)?)),
+		}
+	}
+}
+// Untagged `V1` payloads and tagged payloads can't be described as a single SCALE type. So the
+// type is described in the metadata as the wrapper of the `V1` payload (which is encoded exactly
+// as the `MessagePayload`) and the tagged encoding is only described in the docs.
+impl<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature, Call> TypeInfo
+	for VersionedMessagePayload<
+		SourceChainAccountId,
+		TargetChainAccountPublic,
+		TargetChainSignature,
+		Call,
+	> where
+	SourceChainAccountId: TypeInfo + 'static,
+	TargetChainAccountPublic: TypeInfo + 'static,
+	TargetChainSignature: TypeInfo + 'static,
+	Call: TypeInfo + 'static,
+{
+	type Identity = Self;
+
+	fn type_info() -> Type {
+		Type::builder()
+			.path(Path::new("VersionedMessagePayload", module_path!()))
+			.type_params(type_params!(
+				SourceChainAccountId,
+				TargetChainAccountPublic,
+				TargetChainSignature,
+				Call
+			))
+			.docs(&[
+				"Versioned message payload. The `V1` payload is encoded as the bare `MessagePayload`.",
+				"Payloads of other versions are encoded as `u32::MAX`, followed by the `u8` version",
+				"and the payload itself, which takes all remaining bytes of the message.",
+			])
+			.composite(Fields::unnamed().field(|f| {
+				f.ty::<MessagePayload<
+					SourceChainAccountId,
+					TargetChainAccountPublic,
+					TargetChainSignature,
+					Call,
+				>>()
+				.type_name("MessagePayload")
+			}))
+	}
+}
+impl<SourceChainAccountId, TargetChainAccountPublic, TargetChainSignature> Size
+	for VersionedMessagePayload<
+		SourceChainAccountId,
+		TargetChainAccountPublic,
+		TargetChainSignature,
+		Vec<u8>,
+	>
+{
+	fn size(&self) -> u32 {
+		match *self {
+			Self::V1(ref payload) => payload.size(),
+			Self::Unsupported(_, ref payload) => payload.len() as _,
+		}
+	}
+}
+
+/// Read all remaining bytes of the input.
+fn read_remaining_bytes<I: Input>(input: &mut I) -> Result<Vec<u8>, CodecError> {
+	match input.remaining_len()? {
+		Some(len) => {
+			let mut bytes = sp_std::vec![0; len];
+			input.read(&mut bytes)?;
+			Ok(bytes)
+		},
+		None => {
+			let mut bytes = Vec::new();
+			while let Ok(byte) = input.read_byte() {
+				bytes.push(byte);
+			}
+			Ok(bytes)
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// darwinia-network
+	use super::*;
+
+	type TestPayload = MessagePayload<u64, u64, u64, Vec<u8>>;
+	type TestVersionedPayload = VersionedMessagePayload<u64, u64, u64, Vec<u8>>;
+
+	fn test_payload() -> TestPayload {
+		MessagePayload {
+			spec_version: 1,
+			weight: Weight::from_parts(100, 0),
+			origin: CallOrigin::SourceAccount(1),
+			dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
+			call: vec![1, 2, 3],
+		}
+	}
+
+	#[test]
+	fn v1_payload_is_encoded_as_legacy_payload() {
+		assert_eq!(TestVersionedPayload::V1(test_payload()).encode(), test_payload().encode());
+	}
+
+	#[test]
+	fn legacy_payload_is_decoded_as_v1() {
+		assert_eq!(
+			TestVersionedPayload::decode(&mut &test_payload().encode()[..]),
+			Ok(TestVersionedPayload::V1(test_payload())),
+		);
+	}
+
+	#[test]
+	fn tagged_v1_payload_is_decoded() {
+		let encoded = (VERSIONED_PAYLOAD_MARKER, PAYLOAD_VERSION_V1, test_payload()).encode();
+		assert_eq!(
+			TestVersionedPayload::decode(&mut &encoded[..]),
+			Ok(TestVersionedPayload::V1(test_payload())),
+		);
+	}
+
+	#[test]
+	fn payload_of_unknown_version_is_decoded_as_unsupported() {
+		let encoded = (VERSIONED_PAYLOAD_MARKER, 42u8, vec![1u8, 2, 3]).encode();
+		assert_eq!(
+			TestVersionedPayload::decode(&mut &encoded[..]),
+			Ok(TestVersionedPayload::Unsupported(42, vec![1u8, 2, 3].encode())),
+		);
+	}
+
+	#[test]
+	fn payload_of_unknown_version_is_encoded_back_as_received() {
+		let encoded = (VERSIONED_PAYLOAD_MARKER, 42u8, vec![1u8, 2, 3]).encode();
+		let payload = TestVersionedPayload::decode(&mut &encoded[..]).unwrap();
+		assert_eq!(payload.encode(), encoded);
+		assert_eq!(payload.size(), encoded.len() as u32 - 5);
+	}
+}
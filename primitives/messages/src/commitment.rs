@@ -0,0 +1,294 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Commitment of outbound messages that have been accepted in a single block.
+//!
+//! The commitment is a root of the binary Merkle tree, built over leaves that represent
+//! accepted messages. Messages may then be verified using the root and the inclusion proof,
+//! without verifying storage proof of the source chain.
+
+// crates.io
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+// darwinia-network
+use crate::{LaneId, MessageNonce};
+use bp_runtime::ChainId;
+// substrate
+use frame_support::RuntimeDebug;
+use sp_core::Hasher;
+use sp_std::prelude::*;
+
+/// Engine id of the digest item, which holds the `MessagesCommitment`.
+pub const MESSAGES_COMMITMENT_ENGINE_ID: [u8; 4] = *b"BMSG";
+
+/// Commitment of messages that have been accepted by the messages pallet in a single block.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct MessagesCommitment<Hash> {
+	/// Identifier of the chain messages are sent to.
+	pub bridged_chain_id: ChainId,
+	/// Root of the messages Merkle tree.
+	pub root: Hash,
+	/// Number of leaves (messages) in the tree.
+	pub messages_count: u32,
+}
+
+/// Leaf of the messages Merkle tree.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct MessageLeaf<Hash> {
+	/// Id of the lane the message has been sent over.
+	pub lane_id: LaneId,
+	/// Nonce of the message.
+	pub nonce: MessageNonce,
+	/// Hash of the message payload.
+	pub payload_hash: Hash,
+}
+
+impl<Hash: Encode> MessageLeaf<Hash> {
+	/// Returns hash of the leaf.
+	pub fn hash<H: Hasher<Out = Hash>>(&self) -> Hash {
+		H::hash(&self.encode())
+	}
+}
+
+/// Proof of message inclusion into the messages Merkle tree.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct MessageInclusionProof<Hash> {
+	/// Index of the leaf in the tree.
+	pub leaf_index: u32,
+	/// Number of leaves in the tree.
+	pub leaves_count: u32,
+	/// Hashes of sibling nodes, starting from the leaf level.
+	pub siblings: Vec<Hash>,
+}
+
+/// Returns hash of the leaf that represents the message with given payload.
+pub fn message_leaf_hash<H: Hasher>(
+	lane_id: LaneId,
+	nonce: MessageNonce,
+	payload: &[u8],
+) -> H::Out {
+	MessageLeaf { lane_id, nonce, payload_hash: H::hash(payload) }.hash::<H>()
+}
+
+/// Computes root of the Merkle tree with given leaves.
+///
+/// Every pair of nodes is hashed together. If the level has odd number of nodes, the last node
+/// is promoted to the next level as is. Root of the empty tree is the default hash.
+pub fn merkle_root<H: Hasher>(leaves: &[H::Out]) -> H::Out {
+	let mut level = leaves.to_vec();
+	while level.len() > 1 {
+		level = level
+			.chunks(2)
+			.map(|pair| match pair {
+				[left, right] => hash_node::<H>(left, right),
+				[single] => *single,
+				_ => unreachable!("chunks(2) never yields empty or larger chunks; qed"),
+			})
+			.collect();
+	}
+
+	level.pop().unwrap_or_default()
+}
+
+/// Builds inclusion proof for the leaf with given index.
+///
+/// Returns `None` if there's no such leaf.
+pub fn merkle_proof<H: Hasher>(
+	leaves: &[H::Out],
+	leaf_index: u32,
+) -> Option<MessageInclusionProof<H::Out>> {
+	if leaf_index as usize >= leaves.len() {
+		return None;
+	}
+
+	let mut siblings = Vec::new();
+	let mut level = leaves.to_vec();
+	let mut position = leaf_index as usize;
+	while level.len() > 1 {
+		if let Some(sibling) = level.get(position ^ 1) {
+			siblings.push(*sibling);
+		}
+
+		level = level
+			.chunks(2)
+			.map(|pair| match pair {
+				[left, right] => hash_node::<H>(left, right),
+				[single] => *single,
+				_ => unreachable!("chunks(2) never yields empty or larger chunks; qed"),
+			})
+			.collect();
+		position /= 2;
+	}
+
+	Some(MessageInclusionProof { leaf_index, leaves_count: leaves.len() as _, siblings })
+}
+
+/// Verifies that the message with given payload hash is included into the Merkle tree with given
+/// root.
+pub fn verify_message_inclusion<H: Hasher>(
+	root: H::Out,
+	lane_id: LaneId,
+	nonce: MessageNonce,
+	payload_hash: H::Out,
+	proof: &MessageInclusionProof<H::Out>,
+) -> bool {
+	if proof.leaf_index >= proof.leaves_count {
+		return false;
+	}
+
+	let mut node = MessageLeaf { lane_id, nonce, payload_hash }.hash::<H>();
+	let mut siblings = proof.siblings.iter();
+	let mut position = proof.leaf_index;
+	let mut width = proof.leaves_count;
+	while width > 1 {
+		// the last node of the level with odd number of nodes has no sibling
+		if position % 2 == 1 || position + 1 < width {
+			let sibling = match siblings.next() {
+				Some(sibling) => sibling,
+				None => return false,
+			};
+			node = if position % 2 == 1 {
+				hash_node::<H>(sibling, &node)
+			} else {
+				hash_node::<H>(&node, sibling)
+			};
+		}
+
+		position /= 2;
+		width = (width + 1) / 2;
+	}
+
+	siblings.next().is_none() && node == root
+}
+
+fn hash_node<H: Hasher>(left: &H::Out, right: &H::Out) -> H::Out {
+	let mut concat = Vec::with_capacity(left.as_ref().len() + right.as_ref().len());
+	concat.extend_from_slice(left.as_ref());
+	concat.extend_from_slice(right.as_ref());
+	H::hash(&concat)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{Blake2Hasher, KeccakHasher};
+
+	fn leaves<H: Hasher>(count: u64) -> Vec<H::Out> {
+		(1..=count)
+			.map(|nonce| message_leaf_hash::<H>([0, 0, 0, 1], nonce, &[nonce as u8]))
+			.collect()
+	}
+
+	fn verify_all_leaves<H: Hasher>(count: u64) {
+		let leaves = leaves::<H>(count);
+		let root = merkle_root::<H>(&leaves);
+		for nonce in 1..=count {
+			let proof = merkle_proof::<H>(&leaves, (nonce - 1) as _).unwrap();
+			assert!(verify_message_inclusion::<H>(
+				root,
+				[0, 0, 0, 1],
+				nonce,
+				H::hash(&[nonce as u8]),
+				&proof,
+			));
+		}
+	}
+
+	#[test]
+	fn inclusion_proofs_are_verified() {
+		for count in 1..=17 {
+			verify_all_leaves::<Blake2Hasher>(count);
+			verify_all_leaves::<KeccakHasher>(count);
+		}
+	}
+
+	#[test]
+	fn merkle_root_of_single_leaf_is_the_leaf() {
+		let leaves = leaves::<Blake2Hasher>(1);
+		assert_eq!(merkle_root::<Blake2Hasher>(&leaves), leaves[0]);
+		assert_eq!(merkle_root::<Blake2Hasher>(&[]), Default::default());
+	}
+
+	#[test]
+	fn invalid_inclusion_proofs_are_rejected() {
+		let leaves = leaves::<Blake2Hasher>(5);
+		let root = merkle_root::<Blake2Hasher>(&leaves);
+		let proof = merkle_proof::<Blake2Hasher>(&leaves, 2).unwrap();
+		let payload_hash = Blake2Hasher::hash(&[3]);
+		assert!(verify_message_inclusion::<Blake2Hasher>(
+			root,
+			[0, 0, 0, 1],
+			3,
+			payload_hash,
+			&proof
+		));
+
+		// wrong message
+		assert!(!verify_message_inclusion::<Blake2Hasher>(
+			root,
+			[0, 0, 0, 2],
+			3,
+			payload_hash,
+			&proof
+		));
+		assert!(!verify_message_inclusion::<Blake2Hasher>(
+			root,
+			[0, 0, 0, 1],
+			4,
+			payload_hash,
+			&proof
+		));
+		assert!(!verify_message_inclusion::<Blake2Hasher>(
+			root,
+			[0, 0, 0, 1],
+			3,
+			Blake2Hasher::hash(&[4]),
+			&proof,
+		));
+
+		// wrong proof
+		let mut wrong_proof = proof.clone();
+		wrong_proof.leaf_index = 3;
+		assert!(!verify_message_inclusion::<Blake2Hasher>(
+			root,
+			[0, 0, 0, 1],
+			3,
+			payload_hash,
+			&wrong_proof
+		));
+		let mut wrong_proof = proof.clone();
+		wrong_proof.siblings.push(Default::default());
+		assert!(!verify_message_inclusion::<Blake2Hasher>(
+			root,
+			[0, 0, 0, 1],
+			3,
+			payload_hash,
+			&wrong_proof
+		));
+		let mut wrong_proof = proof;
+		wrong_proof.leaves_count = 2;
+		assert!(!verify_message_inclusion::<Blake2Hasher>(
+			root,
+			[0, 0, 0, 1],
+			3,
+			payload_hash,
+			&wrong_proof
+		));
+
+		// there's no such leaf
+		assert_eq!(merkle_proof::<Blake2Hasher>(&leaves, 5), None);
+	}
+}
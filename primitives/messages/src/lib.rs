@@ -20,6 +20,7 @@
 // RuntimeApi generated functions
 #![allow(clippy::too_many_arguments)]
 
+pub mod commitment;
 pub mod source_chain;
 pub mod storage_keys;
 pub mod target_chain;
@@ -27,15 +28,18 @@ pub mod target_chain;
 // crates.io
 use bitvec::prelude::*;
 use codec::{Decode, Encode, MaxEncodedLen};
-use scale_info::TypeInfo;
+use scale_info::{build::Fields, type_params, Path, Type, TypeInfo};
 // darwinia-network
 use bp_runtime::{BasicOperatingMode, OperatingMode};
 // substrate
-use frame_support::{PalletError, RuntimeDebug};
-use sp_std::{collections::vec_deque::VecDeque, prelude::*};
+use frame_support::{
+	dispatch::DispatchError, sp_runtime::traits::Saturating, PalletError, RuntimeDebug,
+};
+use sp_core::H256;
+use sp_std::{collections::vec_deque::VecDeque, ops::RangeInclusive, prelude::*};
 
 // Weight is reexported to avoid additional frame-support dependencies in related crates.
-use bp_runtime::messages::MessageDispatchResult;
+use bp_runtime::messages::{MessageDispatchResult, MessageDispatchStatus};
 pub use frame_support::weights::Weight;
 
 /// Lane identifier.
@@ -53,6 +57,9 @@ pub type MessagePayload = Vec<u8>;
 /// Bit vector of message dispatch results.
 pub type DispatchResultsBitVec = BitVec<u8, Msb0>;
 
+/// Bit vector of delivered message nonces.
+pub type DeliveredNoncesBitVec = BitVec<u8, Msb0>;
+
 /// Messages pallet parameter.
 pub trait Parameter: frame_support::Parameter {
 	/// Save parameter value in the runtime storage.
@@ -91,6 +98,121 @@ impl OperatingMode for MessagesOperatingMode {
 	}
 }
 
+/// State of the lane that is registered at the messages pallet.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub enum LaneState {
+	/// The lane is opened and accepts new outbound messages.
+	Opened,
+	/// The lane rejects new outbound messages, but already sent messages are still delivered
+	/// and confirmed. The lane is closed once there are no pending messages left.
+	Closing,
+	/// All messages of the lane have been delivered and confirmed. Nonces of the lane are kept,
+	/// so messages can't be replayed and the lane continues from the same nonce if it is
+	/// re-opened.
+	Closed,
+}
+
+/// Kind of the lane that is registered at the messages pallet.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub enum LaneKind {
+	/// Messages are dispatched strictly in the order they have been sent.
+	Ordered,
+	/// Any undelivered message within the `MaxUnconfirmedMessagesAtInboundLane` window may be
+	/// dispatched, even if some of previous messages are not yet delivered.
+	///
+	/// Messages that are dispatched out of order are only appended to the unrewarded relayers
+	/// set once all previous messages are delivered, so the delivery confirmation format is
+	/// the same for both lane kinds. This kind must only be used for independent and
+	/// idempotent payloads.
+	Unordered,
+}
+impl Default for LaneKind {
+	fn default() -> Self {
+		LaneKind::Ordered
+	}
+}
+
+/// Metadata of the lane that is registered at the messages pallet.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct LaneMetadata<AccountId, BlockNumber> {
+	/// Number of the block where the lane has been opened.
+	pub created_at: BlockNumber,
+	/// Account that is allowed to close the lane, in addition to the pallet owner and root.
+	pub owner: Option<AccountId>,
+	/// Hash of the lane description. The description itself is stored off-chain.
+	pub description_hash: H256,
+	/// Kind of the lane. It must be the same at both bridged chains.
+	pub kind: LaneKind,
+	/// Current state of the lane.
+	pub state: LaneState,
+}
+
+/// Sender that may be allowed to send messages over the lane.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub enum AllowedSender<AccountId> {
+	/// Root origin.
+	Root,
+	/// Any signed origin.
+	AnySigned,
+	/// Signed origin of given account.
+	Signed(AccountId),
+}
+
+/// Limit of the outbound traffic that may be sent within a single rate limit window.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct OutboundRateLimit<BlockNumber> {
+	/// Number of blocks in the rate limit window.
+	pub window: BlockNumber,
+	/// Maximal number of messages that may be sent within the window.
+	pub max_messages: u32,
+	/// Maximal total size of payloads that may be sent within the window.
+	pub max_bytes: u32,
+}
+
+/// Outbound traffic that has been sent within the current rate limit window.
+#[derive(
+	Clone, Copy, Default, PartialEq, Eq, Encode, Decode, RuntimeDebug, MaxEncodedLen, TypeInfo,
+)]
+pub struct OutboundRateLimitUsage<BlockNumber> {
+	/// Number of the block where the current window has started.
+	pub window_start: BlockNumber,
+	/// Number of messages that have been sent within the window.
+	pub messages: u32,
+	/// Total size of payloads that have been sent within the window.
+	pub bytes: u32,
+}
+
+impl<BlockNumber: Copy + PartialOrd + Saturating> OutboundRateLimitUsage<BlockNumber> {
+	/// Returns usage at given block. If the window of this usage has already ended, the new
+	/// (empty) window is started at the given block.
+	pub fn at(self, limit: &OutboundRateLimit<BlockNumber>, now: BlockNumber) -> Self {
+		if self.is_expired(limit, now) {
+			OutboundRateLimitUsage { window_start: now, messages: 0, bytes: 0 }
+		} else {
+			self
+		}
+	}
+
+	/// Returns true if the window of this usage has ended before or at given block.
+	///
+	/// The expired usage is equivalent to the empty usage, so it doesn't need to be stored.
+	pub fn is_expired(&self, limit: &OutboundRateLimit<BlockNumber>, now: BlockNumber) -> bool {
+		now >= self.window_start.saturating_add(limit.window)
+	}
+
+	/// Returns true if one more message of given size may be sent within the current window.
+	pub fn can_accept(&self, limit: &OutboundRateLimit<BlockNumber>, message_size: u32) -> bool {
+		self.messages < limit.max_messages
+			&& self.bytes.saturating_add(message_size) <= limit.max_bytes
+	}
+
+	/// Account message of given size.
+	pub fn accept(&mut self, message_size: u32) {
+		self.messages = self.messages.saturating_add(1);
+		self.bytes = self.bytes.saturating_add(message_size);
+	}
+}
+
 /// Message key (unique message identifier) as it is stored in the storage.
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 pub struct MessageKey {
@@ -100,6 +222,22 @@ pub struct MessageKey {
 	pub nonce: MessageNonce,
 }
 
+/// Moment at the target chain, after which the message may no longer be dispatched.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum MessageExpiry {
+	/// The message expires if it is delivered in the target chain block with larger number.
+	BlockNumber(u64),
+	/// The message expires if it is delivered in the target chain block with larger timestamp
+	/// (in milliseconds).
+	Timestamp(u64),
+}
+
+/// Payload of the cancelled message.
+///
+/// The payload of the cancelled message is replaced with this marker at the source chain. Regular
+/// messages may not use this payload.
+pub const CANCELLED_MESSAGE_PAYLOAD: &[u8] = b"bridge-messages:cancelled-message";
+
 /// Message data as it is stored in the storage.
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
 pub struct MessageData<Fee> {
@@ -108,6 +246,116 @@ pub struct MessageData<Fee> {
 	/// Message delivery and dispatch fee, paid by the submitter.
 	pub fee: Fee,
 }
+impl<Fee> MessageData<Fee> {
+	/// Returns data of the cancelled message with given fee.
+	pub fn cancelled(fee: Fee) -> Self {
+		MessageData { payload: CANCELLED_MESSAGE_PAYLOAD.to_vec(), fee }
+	}
+
+	/// Returns true if the message has been cancelled by its sender.
+	///
+	/// The payload of the cancelled message is replaced with the `CANCELLED_MESSAGE_PAYLOAD` at
+	/// the source chain. The message is still delivered to keep the lane going, but it is never
+	/// dispatched.
+	pub fn is_cancelled(&self) -> bool {
+		self.payload == CANCELLED_MESSAGE_PAYLOAD
+	}
+}
+
+/// Outbound message data, as it is stored in the storage of the source chain.
+///
+/// The expiry is encoded right after the message data and only if the message has it. So the
+/// messages that are sent without expiry are encoded exactly as the `MessageData`, and the
+/// expiry is a part of the storage value that is proved to the bridged chain. Since the encoded
+/// expiry ends where the input ends, this type may only be decoded from the whole storage
+/// value, never as a part of another type.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct OutboundMessageData<Fee> {
+	/// Message data.
+	pub data: MessageData<Fee>,
+	/// Optional message expiry.
+	pub expiry: Option<MessageExpiry>,
+}
+
+impl<Fee> From<MessageData<Fee>> for OutboundMessageData<Fee> {
+	fn from(data: MessageData<Fee>) -> Self {
+		OutboundMessageData { data, expiry: None }
+	}
+}
+
+impl<Fee: Encode> Encode for OutboundMessageData<Fee> {
+	fn size_hint(&self) -> usize {
+		self.data.size_hint().saturating_add(self.expiry.map_or(0, |expiry| expiry.size_hint()))
+	}
+
+	fn encode_to<T: codec::Output + ?Sized>(&self, dest: &mut T) {
+		self.data.encode_to(dest);
+		if let Some(ref expiry) = self.expiry {
+			expiry.encode_to(dest);
+		}
+	}
+}
+
+impl<Fee: Decode> Decode for OutboundMessageData<Fee> {
+	fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let data = MessageData::decode(input)?;
+		let expiry = decode_message_expiry(input)?;
+		Ok(OutboundMessageData { data, expiry })
+	}
+}
+
+impl<Fee: TypeInfo + 'static> TypeInfo for OutboundMessageData<Fee> {
+	type Identity = Self;
+
+	fn type_info() -> Type {
+		Type::builder()
+			.path(Path::new("OutboundMessageData", module_path!()))
+			.type_params(type_params!(Fee))
+			.docs(&[
+				"Outbound message data, followed by the `MessageExpiry` if the message has it.",
+				"The expiry has no prefix and it ends where the storage value ends.",
+			])
+			.composite(
+				Fields::unnamed().field(|f| f.ty::<MessageData<Fee>>().type_name("MessageData")),
+			)
+	}
+}
+
+/// Decodes the optional message expiry that follows the encoded `MessageData` in the storage
+/// value of the outbound message.
+///
+/// If there are no more bytes in the input, the message has no expiry.
+pub fn decode_message_expiry<I: codec::Input>(
+	input: &mut I,
+) -> Result<Option<MessageExpiry>, codec::Error> {
+	match input.remaining_len()? {
+		Some(0) => Ok(None),
+		_ => MessageExpiry::decode(input).map(Some),
+	}
+}
+
+/// Inbound message, whose dispatch has failed, as it is stored in the storage.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct FailedMessage<Fee, BlockNumber> {
+	/// Encoded message payload.
+	pub payload: MessagePayload,
+	/// Message delivery and dispatch fee, paid at the bridged chain.
+	pub fee: Fee,
+	/// Number of the block where the message dispatch has failed.
+	pub failed_at: BlockNumber,
+}
+
+/// Inbound message, whose dispatch has been deferred, as it is stored in the storage.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct DeferredMessage<Fee, AccountId> {
+	/// Encoded message payload or `None` if the payload has failed to decode.
+	pub payload: Option<MessagePayload>,
+	/// Message delivery and dispatch fee, paid at the bridged chain.
+	pub fee: Fee,
+	/// Account of the relayer that has delivered the message. It is used as the dispatch
+	/// origin of the relayer when the message is dispatched.
+	pub relayer: AccountId,
+}
 
 /// Message as it is stored in the storage.
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
@@ -116,6 +364,11 @@ pub struct Message<Fee> {
 	pub key: MessageKey,
 	/// Message data.
 	pub data: MessageData<Fee>,
+	/// Optional message expiry. Expired messages are delivered, but never dispatched.
+	///
+	/// The expiry is stored after the message data (see `OutboundMessageData`), so the encoding
+	/// of messages that are sent without expiry is not changed.
+	pub expiry: Option<MessageExpiry>,
 }
 
 /// Inbound lane data.
@@ -230,9 +483,26 @@ impl<Result> ReceivedMessages<Result> {
 	pub fn push_skipped_for_not_enough_weight(&mut self, message: MessageNonce) {
 		self.skipped_for_not_enough_weight.push(message);
 	}
+
+	/// Convert results of received messages using given function.
+	pub fn map_results<R>(self, mut f: impl FnMut(Result) -> R) -> ReceivedMessages<R> {
+		ReceivedMessages {
+			lane: self.lane,
+			receive_results: self
+				.receive_results
+				.into_iter()
+				.map(|(nonce, result)| (nonce, f(result)))
+				.collect(),
+			skipped_for_not_enough_weight: self.skipped_for_not_enough_weight,
+		}
+	}
 }
 
 /// Result of single message receival.
+///
+/// Please note that the `LaneHalted`, `Cancelled`, `Expired` and `Deferred` variants have been
+/// added after the enum has been released. It is a breaking change for everyone who decodes it
+/// (e.g. as a part of the `MessagesReceived` event of the messages pallet).
 #[derive(RuntimeDebug, Encode, Decode, PartialEq, Eq, Clone, TypeInfo)]
 pub enum ReceivalResult {
 	/// Message has been received and dispatched. Note that we don't care whether dispatch has
@@ -248,6 +518,31 @@ pub enum ReceivalResult {
 	TooManyUnconfirmedMessages,
 	/// Pre-dispatch validation failed before message dispatch.
 	PreDispatchValidateFailed,
+	/// The lane is halted and rejects all inbound messages.
+	LaneHalted,
+	/// Message has been cancelled at the source chain. It is received, but not dispatched.
+	Cancelled,
+	/// Message has expired before it has been delivered. It is received, but not dispatched.
+	Expired,
+	/// Message has been received, but its dispatch has been deferred, because the relayer has
+	/// not declared enough dispatch weight. It is dispatched later, using spare block weight.
+	Deferred,
+}
+
+/// Result of single message receival, along with details of the received message.
+#[derive(RuntimeDebug, Encode, Decode, PartialEq, Eq, Clone, TypeInfo)]
+pub struct ReceivalResultWithDetails {
+	/// Result of the message receival.
+	pub result: ReceivalResult,
+	/// Hash of the encoded message payload.
+	pub payload_hash: H256,
+	/// Dispatch weight, declared by the message.
+	pub declared_dispatch_weight: Weight,
+	/// Weight that has actually been spent on the message dispatch. It is zero if the message
+	/// has not been dispatched.
+	pub actual_dispatch_weight: Weight,
+	/// Error that has happened during the message dispatch, if any.
+	pub dispatch_error: Option<DispatchError>,
 }
 
 /// Delivered messages with their dispatch result.
@@ -261,6 +556,15 @@ pub struct DeliveredMessages {
 	/// message in the `[begin; end]` range. See `dispatch_result` field of the
 	/// `bp_runtime::messages::MessageDispatchResult` structure for more information.
 	pub dispatch_results: DispatchResultsBitVec,
+	/// Compact dispatch statuses of messages in the `[begin; end]` range.
+	///
+	/// The vector is either empty, or contains status of every message in the range. It is not
+	/// encoded, so the encoding of the `InboundLaneData` is the same for chains that report
+	/// statuses and chains that don't. The target chain stores statuses separately (see
+	/// `InboundLaneDispatchStatuses`) and they're only set at the source chain, if the messages
+	/// delivery proof has them.
+	#[codec(skip)]
+	pub dispatch_statuses: Vec<MessageDispatchStatus>,
 }
 impl DeliveredMessages {
 	/// Create new `DeliveredMessages` struct that confirms delivery of single nonce with given
@@ -268,7 +572,12 @@ impl DeliveredMessages {
 	pub fn new(nonce: MessageNonce, dispatch_result: bool) -> Self {
 		let mut dispatch_results = BitVec::with_capacity(1);
 		dispatch_results.push(dispatch_result);
-		DeliveredMessages { begin: nonce, end: nonce, dispatch_results }
+		DeliveredMessages {
+			begin: nonce,
+			end: nonce,
+			dispatch_results,
+			dispatch_statuses: Vec::new(),
+		}
 	}
 
 	/// Return total count of delivered messages.
@@ -286,6 +595,16 @@ impl DeliveredMessages {
 		self.dispatch_results.push(dispatch_result);
 	}
 
+	/// Replace dispatch result of the message that has been noted before. Does nothing if the
+	/// message is not in the `begin..=end` range.
+	pub fn set_message_dispatch_result(&mut self, nonce: MessageNonce, dispatch_result: bool) {
+		if !self.contains_message(nonce) {
+			return;
+		}
+
+		self.dispatch_results.set((nonce - self.begin) as usize, dispatch_result);
+	}
+
 	/// Returns true if delivered messages contain message with given nonce.
 	pub fn contains_message(&self, nonce: MessageNonce) -> bool {
 		(self.begin..=self.end).contains(&nonce)
@@ -305,6 +624,154 @@ impl DeliveredMessages {
 		let index = nonce.checked_sub(self.begin).expect(INVALID_NONCE) as usize;
 		*self.dispatch_results.get(index).expect(INVALID_NONCE)
 	}
+
+	/// Get dispatch status by message nonce.
+	///
+	/// Returns `None` if the message is not in the `begin..=end` range or if statuses have not
+	/// been reported by the target chain dispatcher.
+	pub fn message_dispatch_status(&self, nonce: MessageNonce) -> Option<MessageDispatchStatus> {
+		let index = nonce.checked_sub(self.begin)? as usize;
+		self.dispatch_statuses.get(index).copied()
+	}
+}
+
+/// Dispatch statuses of messages that have been delivered to the inbound lane, but whose delivery
+/// has not been confirmed yet.
+///
+/// Statuses are not stored in the `InboundLaneData`, because the bridged chain decodes it from
+/// messages delivery proofs and changing its encoding would break the bridge until both chains
+/// are upgraded. Instead, they're stored in the separate storage map, which may be proved
+/// alongside the lane data. The type is versioned, so its format may be changed later: the
+/// bridged chain simply ignores statuses that it is unable to decode.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum InboundLaneDispatchStatuses {
+	/// Statuses of consecutive messages, starting from the message with the `begin` nonce.
+	///
+	/// The status is `None` if the dispatcher has not reported it.
+	#[codec(index = 0)]
+	V1 {
+		/// Nonce of the message, whose status is the first in the `statuses`.
+		begin: MessageNonce,
+		/// Dispatch statuses of messages.
+		statuses: VecDeque<Option<MessageDispatchStatus>>,
+	},
+}
+impl Default for InboundLaneDispatchStatuses {
+	fn default() -> Self {
+		InboundLaneDispatchStatuses::V1 { begin: 0, statuses: VecDeque::new() }
+	}
+}
+impl InboundLaneDispatchStatuses {
+	/// Returns approximate size of the struct, given a number of messages.
+	///
+	/// The struct is encoded as the version byte, the nonce of the first message, the compact
+	/// length prefix and the optional single byte status of every message. So in the worst case
+	/// every unconfirmed message adds two bytes to the size.
+	///
+	/// Returns `None` if size overflows `usize` limits.
+	pub fn encoded_size_hint(messages_count: usize) -> Option<usize> {
+		let version_size = 1;
+		let message_nonce_size = MessageNonce::max_encoded_len();
+		let length_prefix_size = codec::Compact::<u32>::max_encoded_len();
+		let statuses_size =
+			messages_count.checked_mul(Option::<MessageDispatchStatus>::max_encoded_len())?;
+		version_size
+			.checked_add(message_nonce_size)
+			.and_then(|result| result.checked_add(length_prefix_size))
+			.and_then(|result| result.checked_add(statuses_size))
+	}
+
+	/// Returns nonces of messages, whose statuses are stored, or `None` if there are no statuses.
+	pub fn nonces(&self) -> Option<RangeInclusive<MessageNonce>> {
+		match self {
+			InboundLaneDispatchStatuses::V1 { begin, statuses } if !statuses.is_empty() =>
+				Some(*begin..=begin.saturating_add(statuses.len() as MessageNonce - 1)),
+			_ => None,
+		}
+	}
+
+	/// Returns true if there are no statuses.
+	pub fn is_empty(&self) -> bool {
+		self.nonces().is_none()
+	}
+
+	/// Note dispatch status of the message that has been appended to the unrewarded relayers set.
+	///
+	/// Nothing is stored if there are no statuses and the status of the message is unknown. If the
+	/// message doesn't follow the last noted message, statuses of previous messages are dropped.
+	pub fn note_dispatch_status(
+		&mut self,
+		nonce: MessageNonce,
+		dispatch_status: Option<MessageDispatchStatus>,
+	) {
+		let is_next_message = self.nonces().map(|nonces| *nonces.end() + 1 == nonce);
+		match self {
+			InboundLaneDispatchStatuses::V1 { begin, statuses } => {
+				if is_next_message != Some(true) {
+					statuses.clear();
+					if dispatch_status.is_none() {
+						return;
+					}
+					*begin = nonce;
+				}
+				statuses.push_back(dispatch_status);
+			},
+		}
+	}
+
+	/// Replace dispatch status of the message that has been noted before. Does nothing if status
+	/// of the message is not stored.
+	pub fn set_dispatch_status(
+		&mut self,
+		nonce: MessageNonce,
+		dispatch_status: Option<MessageDispatchStatus>,
+	) {
+		match self {
+			InboundLaneDispatchStatuses::V1 { begin, statuses } => {
+				if let Some(status) =
+					nonce.checked_sub(*begin).and_then(|index| statuses.get_mut(index as usize))
+				{
+					*status = dispatch_status;
+				}
+			},
+		}
+	}
+
+	/// Drop statuses of messages with nonces that are less than or equal to the
+	/// `confirmed_nonce`.
+	pub fn prune(&mut self, confirmed_nonce: MessageNonce) {
+		match self {
+			InboundLaneDispatchStatuses::V1 { begin, statuses } => {
+				let pruned_statuses = confirmed_nonce
+					.saturating_add(1)
+					.saturating_sub(*begin)
+					.min(statuses.len() as MessageNonce);
+				statuses.drain(..pruned_statuses as usize);
+				*begin += pruned_statuses;
+			},
+		}
+	}
+
+	/// Returns dispatch status of the message, or `None` if it is unknown.
+	pub fn dispatch_status(&self, nonce: MessageNonce) -> Option<MessageDispatchStatus> {
+		match self {
+			InboundLaneDispatchStatuses::V1 { begin, statuses } =>
+				*nonce.checked_sub(*begin).and_then(|index| statuses.get(index as usize))?,
+		}
+	}
+
+	/// Returns dispatch statuses of messages in the `begin..=end` range, if statuses of all these
+	/// messages are known. Otherwise returns an empty vec.
+	pub fn known_dispatch_statuses(
+		&self,
+		begin: MessageNonce,
+		end: MessageNonce,
+	) -> Vec<MessageDispatchStatus> {
+		(begin..=end)
+			.map(|nonce| self.dispatch_status(nonce))
+			.collect::<Option<Vec<_>>>()
+			.unwrap_or_default()
+	}
 }
 
 /// Gist of `InboundLaneData::relayers` field used by runtime APIs.
@@ -324,6 +791,36 @@ pub struct UnrewardedRelayersState {
 	pub last_delivered_nonce: MessageNonce,
 }
 
+impl<RelayerId> From<&InboundLaneData<RelayerId>> for UnrewardedRelayersState {
+	fn from(lane: &InboundLaneData<RelayerId>) -> UnrewardedRelayersState {
+		UnrewardedRelayersState {
+			unrewarded_relayer_entries: lane.relayers.len() as _,
+			messages_in_oldest_entry: lane
+				.relayers
+				.front()
+				.map(|entry| entry.messages.total_messages())
+				.unwrap_or(0),
+			total_messages: total_unrewarded_messages(&lane.relayers).unwrap_or(MessageNonce::MAX),
+			last_delivered_nonce: lane.last_delivered_nonce(),
+		}
+	}
+}
+
+/// Outbound message details, returned by runtime APIs.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct OutboundMessageDetails<OutboundMessageFee> {
+	/// Nonce assigned to the message.
+	pub nonce: MessageNonce,
+	/// Message dispatch weight, declared by the submitter.
+	///
+	/// Zero if the message payload can't be decoded at this chain.
+	pub dispatch_weight: Weight,
+	/// Size of the encoded message payload.
+	pub size: u32,
+	/// Delivery and dispatch fee, paid by the message submitter.
+	pub delivery_and_dispatch_fee: OutboundMessageFee,
+}
+
 /// Outbound lane data.
 #[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, MaxEncodedLen, TypeInfo)]
 pub struct OutboundLaneData {
@@ -392,6 +889,51 @@ pub enum VerificationError {
 	FailedToDecodeOutboundLaneData,
 	/// Custom error
 	Other(#[codec(skip)] &'static str),
+	/// The same lane is mentioned more than once in the multi-lane messages proof.
+	DuplicateLaneInProof,
+	/// The lane has already sent too many messages (or bytes) within the rate limit window.
+	LaneRateLimitExceeded,
+	/// The sender has already sent too many messages (or bytes) within the rate limit window.
+	SenderRateLimitExceeded,
+	/// The message payload version is not supported.
+	UnsupportedPayloadVersion,
+	/// Error returned while reading/decoding message expiry from the storage proof.
+	FailedToDecodeMessageExpiry,
+}
+
+sp_api::decl_runtime_apis! {
+	/// API for querying the state of outbound lanes of the messages pallet.
+	///
+	/// The runtime may only implement it for the single messages pallet instance.
+	pub trait OutboundLaneApi<OutboundMessageFee>
+	where
+		OutboundMessageFee: codec::Codec,
+	{
+		/// Returns details of messages with nonces in the inclusive range `[begin; end]`.
+		///
+		/// Messages that are missing from the storage (i.e. already pruned or not yet sent)
+		/// are omitted from the result.
+		fn message_details(
+			lane: LaneId,
+			begin: MessageNonce,
+			end: MessageNonce,
+		) -> Vec<OutboundMessageDetails<OutboundMessageFee>>;
+		/// Returns the state of given outbound lane.
+		fn outbound_lane_data(lane: LaneId) -> OutboundLaneData;
+	}
+
+	/// API for querying the state of inbound lanes of the messages pallet.
+	///
+	/// The runtime may only implement it for the single messages pallet instance.
+	pub trait InboundLaneApi<InboundRelayer>
+	where
+		InboundRelayer: codec::Codec,
+	{
+		/// Returns the state of given inbound lane.
+		fn inbound_lane_data(lane: LaneId) -> InboundLaneData<InboundRelayer>;
+		/// Returns the gist of unrewarded relayers set at given inbound lane.
+		fn unrewarded_relayers_state(lane: LaneId) -> UnrewardedRelayersState;
+	}
 }
 
 #[cfg(test)]
@@ -399,6 +941,36 @@ mod tests {
 	// darwinia-network
 	use super::*;
 
+	#[test]
+	fn outbound_message_data_without_expiry_is_encoded_as_message_data() {
+		let data = MessageData { payload: vec![1, 2, 3], fee: 42u64 };
+		let encoded = OutboundMessageData::from(data.clone()).encode();
+		assert_eq!(encoded, data.encode());
+		assert_eq!(
+			OutboundMessageData::<u64>::decode(&mut &encoded[..]),
+			Ok(OutboundMessageData { data, expiry: None }),
+		);
+	}
+
+	#[test]
+	fn outbound_message_data_with_expiry_is_decoded() {
+		let message_data = OutboundMessageData {
+			data: MessageData { payload: vec![1, 2, 3], fee: 42u64 },
+			expiry: Some(MessageExpiry::Timestamp(100)),
+		};
+		let encoded = message_data.encode();
+		assert_eq!(encoded.len(), message_data.size_hint());
+		assert_eq!(OutboundMessageData::<u64>::decode(&mut &encoded[..]), Ok(message_data.clone()));
+
+		// decoders that are not aware of the expiry still decode the message data
+		assert_eq!(MessageData::<u64>::decode(&mut &encoded[..]), Ok(message_data.data));
+
+		// malformed expiry is rejected
+		let mut encoded = MessageData { payload: vec![1, 2, 3], fee: 42u64 }.encode();
+		encoded.push(42);
+		assert!(OutboundMessageData::<u64>::decode(&mut &encoded[..]).is_err());
+	}
+
 	#[test]
 	fn total_unrewarded_messages_does_not_overflow() {
 		assert_eq!(
@@ -463,8 +1035,12 @@ mod tests {
 
 	#[test]
 	fn message_dispatch_result_works() {
-		let delivered_messages =
-			DeliveredMessages { begin: 100, end: 150, dispatch_results: bitvec![u8, Msb0; 1; 151] };
+		let delivered_messages = DeliveredMessages {
+			begin: 100,
+			end: 150,
+			dispatch_results: bitvec![u8, Msb0; 1; 151],
+			dispatch_statuses: Vec::new(),
+		};
 
 		assert!(!delivered_messages.contains_message(99));
 		assert!(delivered_messages.contains_message(100));
@@ -473,4 +1049,116 @@ mod tests {
 
 		assert!(delivered_messages.message_dispatch_result(125));
 	}
+
+	#[test]
+	fn dispatch_statuses_are_not_encoded_in_delivered_messages() {
+		let mut delivered_messages = DeliveredMessages::new(100, true);
+		let encoded = delivered_messages.encode();
+		delivered_messages.dispatch_statuses = vec![MessageDispatchStatus::Dispatched];
+		assert_eq!(delivered_messages.encode(), encoded);
+		assert_eq!(
+			delivered_messages.message_dispatch_status(100),
+			Some(MessageDispatchStatus::Dispatched)
+		);
+		assert_eq!(delivered_messages.message_dispatch_status(99), None);
+		assert_eq!(delivered_messages.message_dispatch_status(101), None);
+	}
+
+	#[test]
+	fn inbound_lane_dispatch_statuses_are_noted_and_pruned() {
+		let mut statuses = InboundLaneDispatchStatuses::default();
+
+		// unknown statuses are not stored, until some status is known
+		statuses.note_dispatch_status(1, None);
+		assert!(statuses.is_empty());
+		statuses.note_dispatch_status(2, Some(MessageDispatchStatus::Dispatched));
+		statuses.note_dispatch_status(3, None);
+		statuses.note_dispatch_status(4, Some(MessageDispatchStatus::Deferred));
+		assert_eq!(statuses.nonces(), Some(2..=4));
+		assert_eq!(statuses.known_dispatch_statuses(1, 2), vec![]);
+		assert_eq!(statuses.known_dispatch_statuses(2, 3), vec![]);
+		assert_eq!(statuses.known_dispatch_statuses(4, 4), vec![MessageDispatchStatus::Deferred]);
+
+		// status of the deferred message is replaced once it is dispatched
+		statuses.set_dispatch_status(4, Some(MessageDispatchStatus::CallFailed));
+		statuses.set_dispatch_status(5, Some(MessageDispatchStatus::Dispatched));
+		assert_eq!(statuses.dispatch_status(4), Some(MessageDispatchStatus::CallFailed));
+		assert_eq!(statuses.nonces(), Some(2..=4));
+
+		statuses.prune(3);
+		assert_eq!(statuses.nonces(), Some(4..=4));
+		statuses.prune(10);
+		assert!(statuses.is_empty());
+
+		// statuses of previous messages are dropped if there's a gap
+		statuses.note_dispatch_status(11, Some(MessageDispatchStatus::Dispatched));
+		statuses.note_dispatch_status(13, Some(MessageDispatchStatus::Expired));
+		assert_eq!(statuses.nonces(), Some(13..=13));
+	}
+
+	#[test]
+	fn inbound_lane_dispatch_statuses_size_hint_is_correct() {
+		for messages_count in [0, 1, 64, 128, 1024] {
+			let statuses = InboundLaneDispatchStatuses::V1 {
+				begin: 1,
+				statuses: vec![Some(MessageDispatchStatus::Dispatched); messages_count].into(),
+			};
+			assert!(
+				statuses.encode().len()
+					<= InboundLaneDispatchStatuses::encoded_size_hint(messages_count).unwrap()
+			);
+		}
+	}
+
+	#[test]
+	fn unrewarded_relayers_state_is_computed_from_inbound_lane_data() {
+		let mut entry =
+			UnrewardedRelayer { relayer: 1, messages: DeliveredMessages::new(11, true) };
+		entry.messages.note_dispatched_message(true);
+		let lane_data = InboundLaneData {
+			relayers: vec![
+				entry,
+				UnrewardedRelayer { relayer: 2, messages: DeliveredMessages::new(13, true) },
+			]
+			.into_iter()
+			.collect(),
+			last_confirmed_nonce: 10,
+		};
+
+		assert_eq!(
+			UnrewardedRelayersState::from(&lane_data),
+			UnrewardedRelayersState {
+				unrewarded_relayer_entries: 2,
+				messages_in_oldest_entry: 2,
+				total_messages: 3,
+				last_delivered_nonce: 13,
+			},
+		);
+		assert_eq!(
+			UnrewardedRelayersState::from(&InboundLaneData::<u8>::default()),
+			UnrewardedRelayersState::default(),
+		);
+	}
+
+	#[test]
+	fn outbound_rate_limit_usage_is_reset_when_window_ends() {
+		let limit = OutboundRateLimit { window: 10u64, max_messages: 2, max_bytes: 100 };
+		let mut usage = OutboundRateLimitUsage::default().at(&limit, 5);
+		assert_eq!(usage, OutboundRateLimitUsage { window_start: 0, messages: 0, bytes: 0 });
+
+		assert!(usage.can_accept(&limit, 60));
+		usage.accept(60);
+		assert!(!usage.can_accept(&limit, 41));
+		assert!(usage.can_accept(&limit, 40));
+		usage.accept(40);
+		assert!(!usage.can_accept(&limit, 0));
+
+		assert!(!usage.is_expired(&limit, 9));
+		assert!(usage.is_expired(&limit, 10));
+		assert_eq!(usage.at(&limit, 9), usage);
+		assert_eq!(
+			usage.at(&limit, 10),
+			OutboundRateLimitUsage { window_start: 10, messages: 0, bytes: 0 },
+		);
+	}
 }
@@ -18,10 +18,10 @@
 
 // darwinia-network
 use crate::{
-	DeliveredMessages, InboundLaneData, LaneId, MessageNonce, OutboundLaneData, UnrewardedRelayer,
-	VerificationError,
+	DeliveredMessages, InboundLaneData, LaneId, MessageExpiry, MessageNonce, OutboundLaneData,
+	UnrewardedRelayer, VerificationError,
 };
-use bp_runtime::Size;
+use bp_runtime::{messages::MessageDispatchStatus, Size};
 // substrate
 use frame_support::{weights::Weight, Parameter, RuntimeDebug};
 use sp_std::{collections::vec_deque::VecDeque, fmt::Debug, ops::RangeInclusive};
@@ -129,6 +129,35 @@ pub trait MessageDeliveryAndDispatchPayment<SenderOrigin, AccountId, Balance> {
 		received_range: &RangeInclusive<MessageNonce>,
 		relayer_fund_account: &AccountId,
 	);
+
+	/// Refund delivery_and_dispatch_fee of the cancelled message from the relayers-fund account
+	/// back to the message sender.
+	///
+	/// It is called when the bridged chain confirms that the cancelled message has not been
+	/// dispatched. The `fee` is the part of the message fee that has been paid by the sender.
+	fn refund_fee(
+		lane_id: LaneId,
+		nonce: MessageNonce,
+		sender: &AccountId,
+		fee: &Balance,
+		relayer_fund_account: &AccountId,
+	) -> Result<(), VerificationError>;
+
+	/// Called when the submitter has paid `additional_fee` for the message that is not yet
+	/// delivered. The `fee` is the new (total) fee of the message.
+	///
+	/// Should return total weight consumed by the call. The messages pallet reserves
+	/// `WeightInfoExt::single_message_callback_overhead` for it, so implementations that may
+	/// spend more must be accounted for in that overhead.
+	fn on_message_fee_increased(
+		_lane_id: LaneId,
+		_nonce: MessageNonce,
+		_submitter: &SenderOrigin,
+		_additional_fee: &Balance,
+		_fee: &Balance,
+	) -> Weight {
+		Weight::zero()
+	}
 }
 impl<SenderOrigin, AccountId, Balance>
 	MessageDeliveryAndDispatchPayment<SenderOrigin, AccountId, Balance> for ()
@@ -149,14 +178,25 @@ impl<SenderOrigin, AccountId, Balance>
 		_relayer_fund_account: &AccountId,
 	) {
 	}
+
+	fn refund_fee(
+		_lane_id: LaneId,
+		_nonce: MessageNonce,
+		_sender: &AccountId,
+		_fee: &Balance,
+		_relayer_fund_account: &AccountId,
+	) -> Result<(), VerificationError> {
+		Ok(())
+	}
 }
 
 /// Handler for messages delivery confirmation.
 pub trait OnDeliveryConfirmed {
 	/// Called when we receive confirmation that our messages have been delivered to the
 	/// target chain. The confirmation also has single bit dispatch result for every
-	/// confirmed message (see `DeliveredMessages` for details). Guaranteed to be called
-	/// only when at least one message is delivered.
+	/// confirmed message and, if the target chain reports them, compact dispatch statuses
+	/// (see `DeliveredMessages` for details). Guaranteed to be called only when at least
+	/// one message is delivered.
 	///
 	/// Should return total weight consumed by the call.
 	///
@@ -166,6 +206,15 @@ pub trait OnDeliveryConfirmed {
 	/// If your pallet needs more time for processing single message, please do it
 	/// from `on_initialize` call(s) of the next block(s).
 	fn on_messages_delivered(_lane: &LaneId, _messages: &DeliveredMessages) -> Weight;
+
+	/// Called after `on_messages_delivered` with confirmed messages that have expired before they
+	/// have been delivered. Messages, whose dispatch has failed for other reasons, are not
+	/// reported here.
+	///
+	/// The same weight limits as in `on_messages_delivered` apply to this function.
+	fn on_messages_expired(_lane: &LaneId, _messages: &[MessageNonce]) -> Weight {
+		Weight::zero()
+	}
 }
 #[impl_trait_for_tuples::impl_for_tuples(30)]
 impl OnDeliveryConfirmed for Tuple {
@@ -179,6 +228,86 @@ impl OnDeliveryConfirmed for Tuple {
 		);
 		total_weight
 	}
+
+	fn on_messages_expired(lane: &LaneId, messages: &[MessageNonce]) -> Weight {
+		#[allow(clippy::let_and_return)]
+		let mut total_weight = Weight::zero();
+		for_tuples!(
+			#(
+				total_weight = total_weight.saturating_add(Tuple::on_messages_expired(lane, messages));
+			)*
+		);
+		total_weight
+	}
+}
+
+/// Identifier of the handler that is notified about the dispatch result of the outbound message.
+pub type DeliveryCallbackId = u16;
+
+/// Handler of dispatch results of messages that have been sent with a delivery callback.
+///
+/// Unlike `OnDeliveryConfirmed`, which is notified about all confirmed messages of the lane, the
+/// handler is only notified about messages that have been sent with one of its own callback
+/// identifiers (see `MessagesBridge::send_message_with_callback`). This allows pallets to react
+/// to results of their own messages without scanning every confirmation at every lane.
+pub trait OnMessageDeliveryResult {
+	/// Returns true if the handler accepts dispatch results of messages with given callback id.
+	fn is_delivery_callback_supported(callback_id: DeliveryCallbackId) -> bool;
+
+	/// Called when delivery of the message, that has been sent with given callback id, is
+	/// confirmed.
+	///
+	/// The `dispatch_status` is `None` if the target chain is not reporting dispatch statuses.
+	///
+	/// Should return the weight consumed by the call. For every confirmed message, the messages
+	/// pallet reserves a single DB read + single DB write to take the callback id from its storage
+	/// and the `WeightInfoExt::single_message_callback_overhead` (a single DB read + single DB
+	/// write by default) for the handler itself. The handler shall never return weight that is
+	/// larger than the latter. Otherwise the excess is not accounted and the error is logged.
+	fn on_message_delivery_result(
+		callback_id: DeliveryCallbackId,
+		lane: &LaneId,
+		nonce: MessageNonce,
+		dispatch_result: bool,
+		dispatch_status: Option<MessageDispatchStatus>,
+	) -> Weight;
+}
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+impl OnMessageDeliveryResult for Tuple {
+	fn is_delivery_callback_supported(callback_id: DeliveryCallbackId) -> bool {
+		for_tuples!(
+			#(
+				if Tuple::is_delivery_callback_supported(callback_id) {
+					return true;
+				}
+			)*
+		);
+		false
+	}
+
+	fn on_message_delivery_result(
+		callback_id: DeliveryCallbackId,
+		lane: &LaneId,
+		nonce: MessageNonce,
+		dispatch_result: bool,
+		dispatch_status: Option<MessageDispatchStatus>,
+	) -> Weight {
+		// the result is routed to the first handler that supports the callback
+		for_tuples!(
+			#(
+				if Tuple::is_delivery_callback_supported(callback_id) {
+					return Tuple::on_message_delivery_result(
+						callback_id,
+						lane,
+						nonce,
+						dispatch_result,
+						dispatch_status,
+					);
+				}
+			)*
+		);
+		Weight::zero()
+	}
 }
 
 /// Handler for messages have been accepted
@@ -215,6 +344,29 @@ pub trait MessagesBridge<SenderOrigin, Balance, Payload> {
 		message: Payload,
 		delivery_and_dispatch_fee: Balance,
 	) -> Result<SendMessageArtifacts, Self::Error>;
+
+	/// Send message that may no longer be dispatched at the target chain after given expiry.
+	///
+	/// Returns unique message nonce or error if send has failed.
+	fn send_message_with_expiry(
+		sender: SenderOrigin,
+		lane: LaneId,
+		message: Payload,
+		delivery_and_dispatch_fee: Balance,
+		expiry: MessageExpiry,
+	) -> Result<SendMessageArtifacts, Self::Error>;
+
+	/// Send message and report its dispatch result to the `OnMessageDeliveryResult` handler with
+	/// given callback id, once the delivery of the message is confirmed.
+	///
+	/// Returns unique message nonce or error if send has failed.
+	fn send_message_with_callback(
+		sender: SenderOrigin,
+		lane: LaneId,
+		message: Payload,
+		delivery_and_dispatch_fee: Balance,
+		callback_id: DeliveryCallbackId,
+	) -> Result<SendMessageArtifacts, Self::Error>;
 }
 
 /// Bridge that does nothing when message is being sent.
@@ -234,6 +386,26 @@ impl<SenderOrigin, Balance, Payload> MessagesBridge<SenderOrigin, Balance, Paylo
 	) -> Result<SendMessageArtifacts, Self::Error> {
 		Ok(SendMessageArtifacts { nonce: 0, weight: Weight::zero() })
 	}
+
+	fn send_message_with_expiry(
+		_sender: SenderOrigin,
+		_lane: LaneId,
+		_message: Payload,
+		_delivery_and_dispatch_fee: Balance,
+		_expiry: MessageExpiry,
+	) -> Result<SendMessageArtifacts, Self::Error> {
+		Ok(SendMessageArtifacts { nonce: 0, weight: Weight::zero() })
+	}
+
+	fn send_message_with_callback(
+		_sender: SenderOrigin,
+		_lane: LaneId,
+		_message: Payload,
+		_delivery_and_dispatch_fee: Balance,
+		_callback_id: DeliveryCallbackId,
+	) -> Result<SendMessageArtifacts, Self::Error> {
+		Ok(SendMessageArtifacts { nonce: 0, weight: Weight::zero() })
+	}
 }
 
 /// Structure that may be used in place of `TargetHeaderChain`, `LaneMessageVerifier` and
@@ -284,4 +456,14 @@ impl<SenderOrigin, AccountId, Balance>
 		_relayer_fund_account: &AccountId,
 	) {
 	}
+
+	fn refund_fee(
+		_lane_id: LaneId,
+		_nonce: MessageNonce,
+		_sender: &AccountId,
+		_fee: &Balance,
+		_relayer_fund_account: &AccountId,
+	) -> Result<(), VerificationError> {
+		Err(VerificationError::Other(ALL_OUTBOUND_MESSAGES_REJECTED))
+	}
 }
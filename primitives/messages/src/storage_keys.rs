@@ -32,6 +32,8 @@ pub const OUTBOUND_MESSAGES_MAP_NAME: &str = "OutboundMessages";
 pub const OUTBOUND_LANES_MAP_NAME: &str = "OutboundLanes";
 /// Name of the `InboundLanes` storage map.
 pub const INBOUND_LANES_MAP_NAME: &str = "InboundLanes";
+/// Name of the `InboundLanesDispatchStatuses` storage map.
+pub const INBOUND_LANES_DISPATCH_STATUSES_MAP_NAME: &str = "InboundLanesDispatchStatuses";
 
 /// Storage key of the `PalletOperatingMode` value in the runtime storage.
 pub fn operating_mode_key(pallet_prefix: &str) -> StorageKey {
@@ -71,6 +73,15 @@ pub fn inbound_lane_data_key(pallet_prefix: &str, lane: &LaneId) -> StorageKey {
 	)
 }
 
+/// Storage key of the inbound message lane dispatch statuses in the runtime storage.
+pub fn inbound_lane_dispatch_statuses_key(pallet_prefix: &str, lane: &LaneId) -> StorageKey {
+	bp_runtime::storage_map_final_key::<Blake2_128Concat>(
+		pallet_prefix,
+		INBOUND_LANES_DISPATCH_STATUSES_MAP_NAME,
+		lane,
+	)
+}
+
 #[cfg(test)]
 mod tests {
 	// darwinia-network
@@ -129,4 +140,17 @@ mod tests {
 			array_bytes::bytes2hex("", &storage_key),
 		);
 	}
+
+	#[test]
+	fn inbound_lane_dispatch_statuses_key_computed_properly() {
+		// If this test fails, then something has been changed in module storage that is breaking
+		// all previously crafted messages delivery proofs.
+		let storage_key = inbound_lane_dispatch_statuses_key("BridgeMessages", b"test").0;
+		assert_eq!(
+			storage_key,
+			array_bytes::hex2bytes_unchecked("dd16c784ebd3390a9bc0357c7511ed016c5c11645a1b22cb787a1a70d0066c8e44a8995dd50b6657a037a7839304535b74657374"),
+			"Unexpected storage key: {}",
+			array_bytes::bytes2hex("", &storage_key),
+		);
+	}
 }
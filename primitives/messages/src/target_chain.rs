@@ -16,7 +16,9 @@
 
 //! Primitives of messages module, that are used on the target chain.
 
-use crate::{LaneId, Message, MessageData, MessageKey, OutboundLaneData, VerificationError};
+use crate::{
+	LaneId, Message, MessageData, MessageExpiry, MessageKey, OutboundLaneData, VerificationError,
+};
 
 use bp_runtime::{messages::MessageDispatchResult, Size};
 use codec::{Decode, Encode, Error as CodecError};
@@ -56,6 +58,13 @@ pub trait SourceHeaderChain<Fee> {
 		proof: Self::MessagesProof,
 		messages_count: u32,
 	) -> Result<ProvedMessages<Message<Fee>>, VerificationError>;
+
+	/// Returns number of lanes whose messages are bundled in the proof.
+	///
+	/// It is only used to compute the weight of the delivery transaction, so it must not
+	/// require proof verification. Returning less lanes than the proof actually has leads to
+	/// underweight delivery transactions.
+	fn lanes_count(proof: &Self::MessagesProof) -> u32;
 }
 
 /// Called when inbound message is received.
@@ -115,14 +124,20 @@ pub struct DispatchMessageData<DispatchPayload, Fee> {
 	pub payload: Result<DispatchPayload, CodecError>,
 	/// Message delivery and dispatch fee, paid by the submitter.
 	pub fee: Fee,
+	/// Whether the message has been cancelled at the source chain.
+	pub is_cancelled: bool,
+	/// Optional message expiry.
+	pub expiry: Option<MessageExpiry>,
 }
 impl<DispatchPayload: Decode, Fee> From<MessageData<Fee>>
 	for DispatchMessageData<DispatchPayload, Fee>
 {
 	fn from(data: MessageData<Fee>) -> Self {
 		DispatchMessageData {
+			is_cancelled: data.is_cancelled(),
 			payload: DispatchPayload::decode(&mut &data.payload[..]),
 			fee: data.fee,
+			expiry: None,
 		}
 	}
 }
@@ -137,7 +152,9 @@ pub struct DispatchMessage<DispatchPayload, Fee> {
 }
 impl<DispatchPayload: Decode, Fee> From<Message<Fee>> for DispatchMessage<DispatchPayload, Fee> {
 	fn from(message: Message<Fee>) -> Self {
-		DispatchMessage { key: message.key, data: message.data.into() }
+		let mut data: DispatchMessageData<DispatchPayload, Fee> = message.data.into();
+		data.expiry = message.expiry;
+		DispatchMessage { key: message.key, data }
 	}
 }
 
@@ -153,6 +170,10 @@ impl<Fee> SourceHeaderChain<Fee> for ForbidInboundMessages {
 	) -> Result<ProvedMessages<Message<Fee>>, VerificationError> {
 		Err(VerificationError::Other(ALL_INBOUND_MESSAGES_REJECTED))
 	}
+
+	fn lanes_count(_proof: &Self::MessagesProof) -> u32 {
+		1
+	}
 }
 impl<AccountId, Fee> MessageDispatch<AccountId, Fee> for ForbidInboundMessages {
 	type DispatchPayload = ();
@@ -176,6 +197,8 @@ impl<AccountId, Fee> MessageDispatch<AccountId, Fee> for ForbidInboundMessages {
 			dispatch_result: false,
 			unspent_weight: Weight::zero(),
 			dispatch_fee_paid_during_dispatch: false,
+			dispatch_error: None,
+			dispatch_status: None,
 		}
 	}
 }
@@ -17,10 +17,11 @@
 //! Primitives that may be used by different message delivery and dispatch mechanisms.
 
 // crates.io
-use codec::{Decode, Encode};
+use codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
 // substrate
 use frame_support::{weights::Weight, RuntimeDebug};
+use sp_runtime::DispatchError;
 
 /// Where message dispatch fee is paid?
 #[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
@@ -36,6 +37,40 @@ pub enum DispatchFeePayment {
 	AtTargetChain,
 }
 
+/// Compact status of the message dispatch.
+///
+/// Unlike the single-bit dispatch result, the status tells why the message dispatch has failed.
+/// It is encoded using single byte, so it may be relayed back to the source chain along with
+/// the message delivery confirmation.
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum MessageDispatchStatus {
+	/// The message call has been dispatched successfully.
+	Dispatched,
+	/// The message call has been dispatched, but the dispatch has failed.
+	CallFailed,
+	/// The message has been rejected by the dispatcher before dispatch.
+	Rejected,
+	/// The message spec version doesn't match the spec version of the target chain runtime.
+	SpecVersionMismatch,
+	/// Failed to decode the message call.
+	CallDecodeFailed,
+	/// The proof of the message origin is invalid.
+	InvalidOrigin,
+	/// The message call has been rejected by the call validator.
+	CallValidationFailed,
+	/// The declared dispatch weight is lower than the weight of the message call.
+	WeightMismatch,
+	/// Failed to pay the message dispatch fee at the target chain.
+	DispatchFeePaymentFailed,
+	/// The message has been cancelled at the source chain, so it has not been dispatched.
+	Cancelled,
+	/// The message has expired before it has been delivered, so it has not been dispatched.
+	Expired,
+	/// The message dispatch has been deferred. The actual status replaces this one once the
+	/// message is dispatched, unless the message delivery has already been confirmed.
+	Deferred,
+}
+
 /// Message dispatch result.
 #[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
 pub struct MessageDispatchResult {
@@ -56,4 +91,17 @@ pub struct MessageDispatchResult {
 	/// configuration supports pay-dispatch-fee-at-target-chain option and message sender has
 	/// enabled this option.
 	pub dispatch_fee_paid_during_dispatch: bool,
+	/// Error that has happened during dispatch, if any.
+	///
+	/// The error is not encoded, so that encoding of this structure (and of the
+	/// `pallet_bridge_messages::Event::MessagesReceived` event) stays the same. It is reported
+	/// by the `pallet_bridge_messages::Event::MessagesReceivedV2` event instead.
+	#[codec(skip)]
+	pub dispatch_error: Option<DispatchError>,
+	/// Compact status of the dispatch, if the dispatcher is able to report it.
+	///
+	/// The status is not encoded here for the same reason as the error. It is relayed back to
+	/// the source chain in the `bp_messages::DeliveredMessages::dispatch_statuses`.
+	#[codec(skip)]
+	pub dispatch_status: Option<MessageDispatchStatus>,
 }
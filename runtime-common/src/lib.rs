@@ -22,10 +22,12 @@
 pub mod integrity;
 pub mod lanes;
 pub mod messages;
+pub mod messages_api;
 pub mod messages_benchmarking;
 pub mod messages_extension;
 pub mod pallets;
 pub mod parachains_benchmarking;
+pub mod refund_relayer_extension;
 
 // darwinia-network
 use bp_runtime::FilterCall;
@@ -59,67 +61,6 @@ where
 	}
 }
 
-pub fn put_pallet_operation_mode<Mode>(module: &[u8], mode: Mode)
-where
-	Mode: codec::FullCodec,
-{
-	// substrate
-	use frame_support::migration;
-
-	let item = b"PalletOperatingMode";
-	let hash = &[];
-
-	migration::put_storage_value(module, item, hash, mode);
-}
-
-pub fn migrate_pallet_operation_mode(module: &[u8]) {
-	// darwinia-network
-	use bp_runtime::BasicOperatingMode;
-	// substrate
-	use frame_support::migration;
-
-	let item = b"IsHalted";
-	let hash = &[];
-
-	if let Some(is_halted) = migration::take_storage_value::<bool>(module, item, hash) {
-		if is_halted {
-			put_pallet_operation_mode(module, BasicOperatingMode::Halted);
-		} else {
-			put_pallet_operation_mode(module, BasicOperatingMode::Normal);
-		}
-	}
-}
-
-pub fn migrate_message_pallet_operation_mode(module: &[u8]) {
-	// darwinia-network
-	use bp_messages::MessagesOperatingMode;
-	use bp_runtime::BasicOperatingMode;
-	// substrate
-	use frame_support::migration;
-
-	let item = b"PalletOperatingMode";
-	let hash = &[];
-
-	if let Some(mode) = migration::take_storage_value::<BasicOperatingMode>(module, item, hash) {
-		put_pallet_operation_mode(module, MessagesOperatingMode::Basic(mode));
-	}
-}
-
-pub fn migrate_best_finalized<BlockNumber, Hash>(module: &[u8], best_finalized: (BlockNumber, Hash))
-where
-	BlockNumber: codec::FullCodec,
-	Hash: codec::FullCodec,
-{
-	// substrate
-	use frame_support::migration;
-
-	let item = b"BestFinalized";
-	let hash = &[];
-
-	migration::take_storage_value::<Hash>(module, item, hash);
-	migration::put_storage_value(module, item, hash, best_finalized);
-}
-
 /// Declares a runtime-specific `BridgeRejectObsoleteHeadersAndMessages` signed extension.
 ///
 /// ## Example
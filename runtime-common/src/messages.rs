@@ -27,12 +27,12 @@ use codec::{Decode, DecodeLimit, Encode, MaxEncodedLen};
 use hash_db::Hasher;
 use scale_info::TypeInfo;
 // darwinia-network
-use bp_message_dispatch::MessageDispatch as _;
+use bp_message_dispatch::{MessageDispatch as _, VersionedMessagePayload};
 use bp_messages::{
 	source_chain::LaneMessageVerifier,
 	target_chain::{DispatchMessage, MessageDispatch, ProvedLaneMessages, ProvedMessages},
-	InboundLaneData, LaneId, Message, MessageData, MessageKey, MessageNonce, OutboundLaneData,
-	VerificationError,
+	InboundLaneData, InboundLaneDispatchStatuses, LaneId, Message, MessageData, MessageKey,
+	MessageNonce, OutboundLaneData, VerificationError,
 };
 use bp_polkadot_core::parachains::{ParaHash, ParaHasher, ParaId};
 use bp_runtime::{messages::MessageDispatchResult, ChainId, Size, StorageProofChecker};
@@ -46,7 +46,7 @@ use sp_runtime::{
 	traits::{CheckedAdd, CheckedDiv, CheckedMul, Header as HeaderT, Saturating, Zero},
 	FixedPointNumber, FixedPointOperand,
 };
-use sp_std::prelude::*;
+use sp_std::{collections::btree_set::BTreeSet, prelude::*};
 use sp_trie::StorageProof;
 
 /// Bidirectional message bridge.
@@ -143,7 +143,7 @@ pub mod source {
 	pub type BridgedChainOpaqueCall = Vec<u8>;
 
 	/// Message payload for This -> Bridged chain messages.
-	pub type FromThisChainMessagePayload<B> = bp_message_dispatch::MessagePayload<
+	pub type FromThisChainMessagePayload<B> = VersionedMessagePayload<
 		AccountIdOf<ThisChain<B>>,
 		SignerOf<BridgedChain<B>>,
 		SignatureOf<BridgedChain<B>>,
@@ -159,6 +159,23 @@ pub mod source {
 		}
 	}
 
+	/// Implementation of the `ThisChainWithMessages::is_message_accepted` that only accepts
+	/// messages from senders that are in the allowlist of the lane (see
+	/// `pallet_bridge_messages::AllowedSenders`). The allowlist is managed by the messages pallet
+	/// owner (or root), so granting access to the lane doesn't require runtime upgrade.
+	pub fn is_message_accepted_by_allowlist<Runtime, MessagesPalletInstance>(
+		origin: &<Runtime as frame_system::Config>::RuntimeOrigin,
+		lane: &LaneId,
+	) -> bool
+	where
+		Runtime: pallet_bridge_messages::Config<MessagesPalletInstance>,
+		MessagesPalletInstance: 'static,
+	{
+		pallet_bridge_messages::Pallet::<Runtime, MessagesPalletInstance>::is_sender_allowed(
+			origin, lane,
+		)
+	}
+
 	/// Messages delivery proof from bridged chain:
 	///
 	/// - hash of finalized header;
@@ -257,6 +274,11 @@ pub mod source {
 
 			// Do the dispatch-specific check. We assume that the target chain uses
 			// `Dispatch`, so we verify the message accordingly.
+			let payload = match *payload {
+				VersionedMessagePayload::V1(ref payload) => payload,
+				VersionedMessagePayload::Unsupported(..) =>
+					return Err(VerificationError::UnsupportedPayloadVersion),
+			};
 			let raw_origin_or_err: Result<
 				frame_system::RawOrigin<AccountIdOf<ThisChain<B>>>,
 				OriginOf<ThisChain<B>>,
@@ -317,6 +339,12 @@ pub mod source {
 	pub fn verify_chain_message<B: MessageBridge>(
 		payload: &FromThisChainMessagePayload<B>,
 	) -> Result<(), VerificationError> {
+		let payload = match *payload {
+			VersionedMessagePayload::V1(ref payload) => payload,
+			VersionedMessagePayload::Unsupported(..) =>
+				return Err(VerificationError::UnsupportedPayloadVersion),
+		};
+
 		if !BridgedChain::<B>::verify_dispatch_weight(&payload.call, &payload.weight) {
 			return Err(VerificationError::InvalidMessageWeight);
 		}
@@ -403,7 +431,7 @@ pub mod source {
 	}
 
 	/// The essense of This -> Bridged chain messages delivery proof verification.
-	fn do_verify_messages_delivery_proof<B: MessageBridge, H: Hasher>(
+	pub(crate) fn do_verify_messages_delivery_proof<B: MessageBridge, H: Hasher>(
 		lane: LaneId,
 		storage: bp_runtime::StorageProofChecker<H>,
 	) -> Result<ParsedMessagesDeliveryProofFromBridgedChain<B>, VerificationError> {
@@ -421,11 +449,32 @@ pub mod source {
 			.ok_or(VerificationError::Other(
 				"Inbound lane state is missing from the messages proof",
 			))?;
-		let inbound_lane_data =
-			InboundLaneData::decode(&mut &raw_inbound_lane_data[..]).map_err(|_| {
+		let mut inbound_lane_data = InboundLaneData::decode(&mut &raw_inbound_lane_data[..])
+			.map_err(|_| {
 				VerificationError::Other("Failed to decode inbound lane state from the proof")
 			})?;
 
+		// dispatch statuses are optional: the relayer may omit them from the proof and the
+		// bridged chain may store them in the format that we don't understand
+		let storage_dispatch_statuses_key =
+			bp_messages::storage_keys::inbound_lane_dispatch_statuses_key(
+				B::BRIDGED_MESSAGES_PALLET_NAME,
+				&lane,
+			);
+		let dispatch_statuses = storage
+			.read_and_decode_value::<InboundLaneDispatchStatuses>(
+				storage_dispatch_statuses_key.0.as_ref(),
+			)
+			.ok()
+			.flatten()
+			.unwrap_or_default();
+		if !dispatch_statuses.is_empty() {
+			for entry in inbound_lane_data.relayers.iter_mut() {
+				entry.messages.dispatch_statuses = dispatch_statuses
+					.known_dispatch_statuses(entry.messages.begin, entry.messages.end);
+			}
+		}
+
 		Ok((lane, inbound_lane_data))
 	}
 }
@@ -442,7 +491,7 @@ pub mod target {
 	>;
 
 	/// Decoded Bridged -> This message payload.
-	pub type FromBridgedChainMessagePayload<B> = bp_message_dispatch::MessagePayload<
+	pub type FromBridgedChainMessagePayload<B> = VersionedMessagePayload<
 		AccountIdOf<BridgedChain<B>>,
 		SignerOf<ThisChain<B>>,
 		SignatureOf<ThisChain<B>>,
@@ -477,6 +526,60 @@ pub mod target {
 		}
 	}
 
+	/// Nonces (inclusive range) of messages of the single lane, that are included in the
+	/// multi-lane messages proof.
+	#[derive(Clone, Encode, PartialEq, Eq, Decode, RuntimeDebug, TypeInfo)]
+	pub struct LaneMessagesRange {
+		/// Messages of this range are sent over this lane.
+		pub lane: LaneId,
+		/// Nonce of the first message being delivered.
+		pub nonces_start: MessageNonce,
+		/// Nonce of the last message being delivered.
+		pub nonces_end: MessageNonce,
+	}
+
+	/// Messages proof from bridged chain, that is carrying messages of multiple lanes:
+	///
+	/// - hash of finalized header;
+	/// - storage proof of messages and (optionally) outbound lane states of all bundled lanes;
+	/// - lane id and nonces (inclusive range) of messages for every bundled lane.
+	#[derive(Clone, Encode, PartialEq, Eq, Decode, RuntimeDebug, TypeInfo)]
+	pub struct FromBridgedChainMultiLaneMessagesProof<BridgedHeaderHash> {
+		/// Hash of the finalized bridged header the proof is for.
+		pub bridged_header_hash: BridgedHeaderHash,
+		/// A storage trie proof of messages of all lanes being delivered.
+		pub storage_proof: RawStorageProof,
+		/// Lanes and messages ranges, included in this proof. Every lane may only be
+		/// mentioned once.
+		pub lanes: Vec<LaneMessagesRange>,
+	}
+	impl<BridgedHeaderHash> Size for FromBridgedChainMultiLaneMessagesProof<BridgedHeaderHash> {
+		fn size(&self) -> u32 {
+			u32::try_from(
+				self.storage_proof.iter().fold(0usize, |sum, node| sum.saturating_add(node.len())),
+			)
+			.unwrap_or(u32::MAX)
+		}
+	}
+
+	/// Messages proof that knows lanes it is delivering messages to, before being verified.
+	pub trait MessagesProofLanes {
+		/// Returns all lanes of the proof, along with the nonce of the last bundled message.
+		fn lanes(&self) -> Vec<(LaneId, MessageNonce)>;
+	}
+	impl<BridgedHeaderHash> MessagesProofLanes for FromBridgedChainMessagesProof<BridgedHeaderHash> {
+		fn lanes(&self) -> Vec<(LaneId, MessageNonce)> {
+			vec![(self.lane, self.nonces_end)]
+		}
+	}
+	impl<BridgedHeaderHash> MessagesProofLanes
+		for FromBridgedChainMultiLaneMessagesProof<BridgedHeaderHash>
+	{
+		fn lanes(&self) -> Vec<(LaneId, MessageNonce)> {
+			self.lanes.iter().map(|range| (range.lane, range.nonces_end)).collect()
+		}
+	}
+
 	/// Encoded Call of This chain as it is transferred over bridge.
 	///
 	/// Our Call is opaque (`Vec<u8>`) for Bridged chain. So it is encoded, prefixed with
@@ -526,7 +629,7 @@ pub mod target {
 		fn dispatch_weight(
 			message: &mut DispatchMessage<Self::DispatchPayload, BalanceOf<BridgedChain<B>>>,
 		) -> frame_support::weights::Weight {
-			message.data.payload.as_ref().map(|payload| payload.weight).unwrap_or(Weight::zero())
+			message.data.payload.as_ref().map(|payload| payload.weight()).unwrap_or(Weight::zero())
 		}
 
 		fn pre_dispatch(
@@ -680,6 +783,90 @@ pub mod target {
 		)
 	}
 
+	/// Verify proof of Bridged -> This chain messages of multiple lanes.
+	///
+	/// This function is used when Bridged chain is directly using GRANDPA finality. For Bridged
+	/// parachains, please use the `verify_multi_lane_messages_proof_from_parachain`.
+	///
+	/// The `messages_count` argument verification (sane limits) is supposed to be made
+	/// outside of this function. This function only verifies that the proof declares exactly
+	/// `messages_count` messages over all lanes. The `SourceHeaderChain::lanes_count` of the
+	/// runtime should return the number of lanes in the proof (`MessagesProofLanes::lanes`), so
+	/// that the delivery transaction weight accounts every lane.
+	pub fn verify_multi_lane_messages_proof<
+		B: MessageBridge,
+		ThisRuntime,
+		GrandpaInstance: 'static,
+	>(
+		proof: FromBridgedChainMultiLaneMessagesProof<HashOf<BridgedChain<B>>>,
+		messages_count: u32,
+	) -> Result<ProvedMessages<Message<BalanceOf<BridgedChain<B>>>>, VerificationError>
+	where
+		ThisRuntime: pallet_bridge_grandpa::Config<GrandpaInstance>,
+		HashOf<BridgedChain<B>>: Into<
+			bp_runtime::HashOf<
+				<ThisRuntime as pallet_bridge_grandpa::Config<GrandpaInstance>>::BridgedChain,
+			>,
+		>,
+	{
+		verify_multi_lane_messages_proof_with_parser::<B, _, _>(
+			proof,
+			messages_count,
+			|bridged_header_hash, bridged_storage_proof| {
+				pallet_bridge_grandpa::Pallet::<ThisRuntime, GrandpaInstance>::parse_finalized_storage_proof(
+					bridged_header_hash.into(),
+					StorageProof::new(bridged_storage_proof),
+					|storage_adapter| storage_adapter,
+				)
+				.map(|storage| StorageProofCheckerAdapter::<_, B> {
+					storage,
+					_dummy: Default::default(),
+				})
+				.map_err(|err| VerificationError::Other(err.into()))
+			},
+		)
+	}
+
+	/// Verify proof of Bridged -> This chain messages of multiple lanes.
+	///
+	/// This function is used when Bridged chain is using parachain finality. For Bridged
+	/// chains with direct GRANDPA finality, please use the `verify_multi_lane_messages_proof`.
+	pub fn verify_multi_lane_messages_proof_from_parachain<
+		B,
+		BridgedHeader,
+		ThisRuntime,
+		ParachainsInstance: 'static,
+	>(
+		bridged_parachain: ParaId,
+		proof: FromBridgedChainMultiLaneMessagesProof<HashOf<BridgedChain<B>>>,
+		messages_count: u32,
+	) -> Result<ProvedMessages<Message<BalanceOf<BridgedChain<B>>>>, VerificationError>
+	where
+		B: MessageBridge,
+		B::BridgedChain: ChainWithMessages<Hash = ParaHash>,
+		BridgedHeader: HeaderT<Hash = HashOf<BridgedChain<B>>>,
+		ThisRuntime: pallet_bridge_parachains::Config<ParachainsInstance>,
+	{
+		verify_multi_lane_messages_proof_with_parser::<B, _, _>(
+			proof,
+			messages_count,
+			|bridged_header_hash, bridged_storage_proof| {
+				pallet_bridge_parachains::Pallet::<ThisRuntime, ParachainsInstance>::parse_finalized_storage_proof(
+					bridged_parachain,
+					bridged_header_hash,
+					StorageProof::new(bridged_storage_proof),
+					|para_head| BridgedHeader::decode(&mut &para_head.0[..]).ok().map(|h| *h.state_root()),
+					|storage_adapter| storage_adapter,
+				)
+				.map(|storage| StorageProofCheckerAdapter::<_, B> {
+					storage,
+					_dummy: Default::default(),
+				})
+				.map_err(|err| VerificationError::Other(err.into()))
+			},
+		)
+	}
+
 	pub(crate) trait MessageProofParser {
 		fn read_raw_outbound_lane_data(&self, lane_id: &LaneId) -> Option<Vec<u8>>;
 		fn read_raw_message(&self, message_key: &MessageKey) -> Option<Vec<u8>>;
@@ -748,7 +935,93 @@ pub mod target {
 			};
 
 		let parser = build_parser(bridged_header_hash, storage_proof)?;
+		let proved_lane_messages = read_lane_messages::<B, _>(
+			&parser,
+			lane,
+			nonces_start,
+			nonces_end,
+			messages_in_the_proof,
+		)?;
+
+		let mut proved_messages = ProvedMessages::new();
+		proved_messages.insert(lane, proved_lane_messages);
+
+		Ok(proved_messages)
+	}
+
+	/// Verify proof of Bridged -> This chain messages of multiple lanes using given message
+	/// proof parser.
+	pub(crate) fn verify_multi_lane_messages_proof_with_parser<
+		B: MessageBridge,
+		BuildParser,
+		Parser,
+	>(
+		proof: FromBridgedChainMultiLaneMessagesProof<HashOf<BridgedChain<B>>>,
+		messages_count: u32,
+		build_parser: BuildParser,
+	) -> Result<ProvedMessages<Message<BalanceOf<BridgedChain<B>>>>, VerificationError>
+	where
+		BuildParser:
+			FnOnce(HashOf<BridgedChain<B>>, RawStorageProof) -> Result<Parser, VerificationError>,
+		Parser: MessageProofParser,
+	{
+		let FromBridgedChainMultiLaneMessagesProof { bridged_header_hash, storage_proof, lanes } =
+			proof;
+		if lanes.is_empty() {
+			return Err(VerificationError::EmptyMessageProof);
+		}
+
+		// every lane may only be mentioned once and the user (relayer) must pass correct
+		// `messages_count` (this bounds maximal capacity of messages vecs below)
+		let mut known_lanes = BTreeSet::new();
+		let mut messages_in_the_proof: MessageNonce = 0;
+		for range in &lanes {
+			if !known_lanes.insert(range.lane) {
+				return Err(VerificationError::DuplicateLaneInProof);
+			}
+
+			messages_in_the_proof = messages_in_the_proof
+				.saturating_add(messages_in_range(range.nonces_start, range.nonces_end));
+		}
+		if messages_in_the_proof != MessageNonce::from(messages_count) {
+			return Err(VerificationError::MessagesCountMismatch);
+		}
+
+		// every lane is verified exactly as if it has been delivered in its own proof, so empty
+		// lane in the multi-lane proof is an error too
+		let parser = build_parser(bridged_header_hash, storage_proof)?;
+		let mut proved_messages = ProvedMessages::new();
+		for LaneMessagesRange { lane, nonces_start, nonces_end } in lanes {
+			let proved_lane_messages = read_lane_messages::<B, _>(
+				&parser,
+				lane,
+				nonces_start,
+				nonces_end,
+				messages_in_range(nonces_start, nonces_end),
+			)?;
+			proved_messages.insert(lane, proved_lane_messages);
+		}
 
+		Ok(proved_messages)
+	}
+
+	/// Returns number of messages in the inclusive nonces range. The range where end < begin is
+	/// considered empty.
+	fn messages_in_range(nonces_start: MessageNonce, nonces_end: MessageNonce) -> MessageNonce {
+		nonces_end
+			.checked_sub(nonces_start)
+			.map(|nonces_difference| nonces_difference.saturating_add(1))
+			.unwrap_or(0)
+	}
+
+	/// Read messages of the single lane and (optionally) the outbound lane state from the proof.
+	fn read_lane_messages<B: MessageBridge, Parser: MessageProofParser>(
+		parser: &Parser,
+		lane: LaneId,
+		nonces_start: MessageNonce,
+		nonces_end: MessageNonce,
+		messages_in_the_proof: MessageNonce,
+	) -> Result<ProvedLaneMessages<Message<BalanceOf<BridgedChain<B>>>>, VerificationError> {
 		// Read messages first. All messages that are claimed to be in the proof must
 		// be in the proof. So any error in `read_value`, or even missing value is fatal.
 		//
@@ -759,10 +1032,18 @@ pub mod target {
 			let raw_message_data = parser
 				.read_raw_message(&message_key)
 				.ok_or(VerificationError::MissingRequiredMessage)?;
+			let mut raw_message_data = &raw_message_data[..];
 			let message_data =
-				MessageData::<BalanceOf<BridgedChain<B>>>::decode(&mut &raw_message_data[..])
+				MessageData::<BalanceOf<BridgedChain<B>>>::decode(&mut raw_message_data)
 					.map_err(|_| VerificationError::FailedToDecodeMessage)?;
-			messages.push(Message { key: message_key, data: message_data });
+			// the optional expiry follows the message data in the same storage value, so it is
+			// always proved together with the message
+			let message_expiry = bp_messages::decode_message_expiry(&mut raw_message_data)
+				.map_err(|_| VerificationError::FailedToDecodeMessageExpiry)?;
+			if !raw_message_data.is_empty() {
+				return Err(VerificationError::FailedToDecodeMessageExpiry);
+			}
+			messages.push(Message { key: message_key, data: message_data, expiry: message_expiry });
 		}
 
 		// Now let's check if proof contains outbound lane state proof. It is optional, so we
@@ -781,11 +1062,7 @@ pub mod target {
 			return Err(VerificationError::EmptyMessageProof);
 		}
 
-		// We only support single lane messages in this generated_schema
-		let mut proved_messages = ProvedMessages::new();
-		proved_messages.insert(lane, proved_lane_messages);
-
-		Ok(proved_messages)
+		Ok(proved_lane_messages)
 	}
 }
 
@@ -797,9 +1074,15 @@ mod tests {
 	use codec::{Decode, Encode};
 	// darwinia-network
 	use super::*;
-	use bp_runtime::messages::DispatchFeePayment;
+	use bp_messages::{MessageExpiry, OutboundMessageData};
+	use bp_runtime::{
+		messages::{DispatchFeePayment, MessageDispatchStatus},
+		record_all_trie_keys,
+	};
 	// substrate
 	use frame_support::weights::Weight;
+	use sp_runtime::traits::BlakeTwo256;
+	use sp_trie::{trie_types::TrieDBMutBuilderV1, LayoutV1, MemoryDB, Recorder, TrieMut};
 
 	const BRIDGED_CHAIN_MAX_EXTRINSIC_WEIGHT: u64 = 2048;
 	const BRIDGED_CHAIN_MAX_EXTRINSIC_SIZE: u32 = 1024;
@@ -1025,13 +1308,15 @@ mod tests {
 	fn message_from_bridged_chain_is_decoded() {
 		// the message is encoded on the bridged chain
 		let message_on_bridged_chain =
-			source::FromThisChainMessagePayload::<OnBridgedChainBridge> {
-				spec_version: 1,
-				weight: Weight::from_parts(100, 0),
-				origin: bp_message_dispatch::CallOrigin::SourceRoot,
-				dispatch_fee_payment: DispatchFeePayment::AtTargetChain,
-				call: ThisChainCall::Transfer.encode(),
-			}
+			source::FromThisChainMessagePayload::<OnBridgedChainBridge>::V1(
+				bp_message_dispatch::MessagePayload {
+					spec_version: 1,
+					weight: Weight::from_parts(100, 0),
+					origin: bp_message_dispatch::CallOrigin::SourceRoot,
+					dispatch_fee_payment: DispatchFeePayment::AtTargetChain,
+					call: ThisChainCall::Transfer.encode(),
+				},
+			)
 			.encode();
 
 		// and sent to this chain where it is decoded
@@ -1040,19 +1325,47 @@ mod tests {
 				&mut &message_on_bridged_chain[..],
 			)
 			.unwrap();
+		let expected_message_on_this_chain = bp_message_dispatch::MessagePayload {
+			spec_version: 1,
+			weight: Weight::from_parts(100, 0),
+			origin: bp_message_dispatch::CallOrigin::SourceRoot,
+			dispatch_fee_payment: DispatchFeePayment::AtTargetChain,
+			call: target::FromBridgedChainEncodedMessageCall::<ThisChainCall>::new(
+				ThisChainCall::Transfer.encode(),
+			),
+		};
 		assert_eq!(
 			message_on_this_chain,
-			target::FromBridgedChainMessagePayload::<OnThisChainBridge> {
-				spec_version: 1,
-				weight: Weight::from_parts(100, 0),
-				origin: bp_message_dispatch::CallOrigin::SourceRoot,
-				dispatch_fee_payment: DispatchFeePayment::AtTargetChain,
-				call: target::FromBridgedChainEncodedMessageCall::<ThisChainCall>::new(
-					ThisChainCall::Transfer.encode(),
-				),
-			}
+			target::FromBridgedChainMessagePayload::<OnThisChainBridge>::V1(
+				expected_message_on_this_chain.clone()
+			),
 		);
-		assert_eq!(Ok(ThisChainCall::Transfer), message_on_this_chain.call.into());
+		assert_eq!(Ok(ThisChainCall::Transfer), expected_message_on_this_chain.call.into());
+	}
+
+	#[test]
+	fn message_of_unsupported_version_from_bridged_chain_is_decoded() {
+		let message_on_bridged_chain =
+			source::FromThisChainMessagePayload::<OnBridgedChainBridge>::Unsupported(
+				42,
+				vec![1, 2, 3],
+			)
+			.encode();
+
+		let message_on_this_chain =
+			target::FromBridgedChainMessagePayload::<OnThisChainBridge>::decode(
+				&mut &message_on_bridged_chain[..],
+			)
+			.unwrap();
+		assert_eq!(
+			message_on_this_chain,
+			target::FromBridgedChainMessagePayload::<OnThisChainBridge>::Unsupported(
+				42,
+				vec![1, 2, 3]
+			),
+		);
+		assert_eq!(message_on_this_chain.weight(), Weight::zero());
+		assert_eq!(message_on_this_chain.encode(), message_on_bridged_chain);
 	}
 
 	// #[test]
@@ -1118,13 +1431,15 @@ mod tests {
 	#[test]
 	fn verify_chain_message_rejects_message_with_too_small_declared_weight() {
 		assert!(source::verify_chain_message::<OnThisChainBridge>(
-			&source::FromThisChainMessagePayload::<OnThisChainBridge> {
-				spec_version: 1,
-				weight: Weight::from_parts(5, 0),
-				origin: bp_message_dispatch::CallOrigin::SourceRoot,
-				dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
-				call: vec![1, 2, 3, 4, 5, 6],
-			},
+			&source::FromThisChainMessagePayload::<OnThisChainBridge>::V1(
+				bp_message_dispatch::MessagePayload {
+					spec_version: 1,
+					weight: Weight::from_parts(5, 0),
+					origin: bp_message_dispatch::CallOrigin::SourceRoot,
+					dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
+					call: vec![1, 2, 3, 4, 5, 6],
+				}
+			),
 		)
 		.is_err());
 	}
@@ -1132,13 +1447,15 @@ mod tests {
 	#[test]
 	fn verify_chain_message_rejects_message_with_too_large_declared_weight() {
 		assert!(source::verify_chain_message::<OnThisChainBridge>(
-			&source::FromThisChainMessagePayload::<OnThisChainBridge> {
-				spec_version: 1,
-				weight: Weight::from_parts((BRIDGED_CHAIN_MAX_EXTRINSIC_WEIGHT + 1) as u64, 0),
-				origin: bp_message_dispatch::CallOrigin::SourceRoot,
-				dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
-				call: vec![1, 2, 3, 4, 5, 6],
-			},
+			&source::FromThisChainMessagePayload::<OnThisChainBridge>::V1(
+				bp_message_dispatch::MessagePayload {
+					spec_version: 1,
+					weight: Weight::from_parts((BRIDGED_CHAIN_MAX_EXTRINSIC_WEIGHT + 1) as u64, 0),
+					origin: bp_message_dispatch::CallOrigin::SourceRoot,
+					dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
+					call: vec![1, 2, 3, 4, 5, 6],
+				}
+			),
 		)
 		.is_err());
 	}
@@ -1146,13 +1463,15 @@ mod tests {
 	#[test]
 	fn verify_chain_message_rejects_message_too_large_message() {
 		assert!(source::verify_chain_message::<OnThisChainBridge>(
-			&source::FromThisChainMessagePayload::<OnThisChainBridge> {
-				spec_version: 1,
-				weight: Weight::from_parts(BRIDGED_CHAIN_MAX_EXTRINSIC_WEIGHT as u64, 0),
-				origin: bp_message_dispatch::CallOrigin::SourceRoot,
-				dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
-				call: vec![0; source::maximal_message_size::<OnThisChainBridge>() as usize + 1],
-			},
+			&source::FromThisChainMessagePayload::<OnThisChainBridge>::V1(
+				bp_message_dispatch::MessagePayload {
+					spec_version: 1,
+					weight: Weight::from_parts(BRIDGED_CHAIN_MAX_EXTRINSIC_WEIGHT as u64, 0),
+					origin: bp_message_dispatch::CallOrigin::SourceRoot,
+					dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
+					call: vec![0; source::maximal_message_size::<OnThisChainBridge>() as usize + 1],
+				}
+			),
 		)
 		.is_err());
 	}
@@ -1161,22 +1480,38 @@ mod tests {
 	fn verify_chain_message_accepts_maximal_message() {
 		assert_eq!(
 			source::verify_chain_message::<OnThisChainBridge>(
-				&source::FromThisChainMessagePayload::<OnThisChainBridge> {
-					spec_version: 1,
-					weight: Weight::from_parts(BRIDGED_CHAIN_MAX_EXTRINSIC_WEIGHT as u64, 0),
-					origin: bp_message_dispatch::CallOrigin::SourceRoot,
-					dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
-					call: vec![0; source::maximal_message_size::<OnThisChainBridge>() as _],
-				},
+				&source::FromThisChainMessagePayload::<OnThisChainBridge>::V1(
+					bp_message_dispatch::MessagePayload {
+						spec_version: 1,
+						weight: Weight::from_parts(BRIDGED_CHAIN_MAX_EXTRINSIC_WEIGHT as u64, 0),
+						origin: bp_message_dispatch::CallOrigin::SourceRoot,
+						dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
+						call: vec![0; source::maximal_message_size::<OnThisChainBridge>() as _],
+					}
+				),
 			),
 			Ok(()),
 		);
 	}
 
+	#[test]
+	fn verify_chain_message_rejects_message_of_unsupported_version() {
+		assert_eq!(
+			source::verify_chain_message::<OnThisChainBridge>(
+				&source::FromThisChainMessagePayload::<OnThisChainBridge>::Unsupported(
+					42,
+					vec![1, 2, 3]
+				),
+			),
+			Err(VerificationError::UnsupportedPayloadVersion),
+		);
+	}
+
 	#[derive(Debug)]
 	struct TestMessageProofParser {
 		failing: bool,
 		messages: RangeInclusive<MessageNonce>,
+		messages_expiry: Option<MessageExpiry>,
 		outbound_lane_data: Option<OutboundLaneData>,
 	}
 
@@ -1194,9 +1529,12 @@ mod tests {
 				Some(vec![])
 			} else if self.messages.contains(&message_key.nonce) {
 				Some(
-					MessageData::<BridgedChainBalance> {
-						payload: message_key.nonce.encode(),
-						fee: BridgedChainBalance(0),
+					OutboundMessageData {
+						data: MessageData::<BridgedChainBalance> {
+							payload: message_key.nonce.encode(),
+							fee: BridgedChainBalance(0),
+						},
+						expiry: self.messages_expiry,
 					}
 					.encode(),
 				)
@@ -1266,6 +1604,7 @@ mod tests {
 				|_, _| Ok(TestMessageProofParser {
 					failing: false,
 					messages: 1..=5,
+					messages_expiry: None,
 					outbound_lane_data: None,
 				}),
 			),
@@ -1282,6 +1621,7 @@ mod tests {
 				|_, _| Ok(TestMessageProofParser {
 					failing: true,
 					messages: 1..=10,
+					messages_expiry: None,
 					outbound_lane_data: None,
 				}),
 			),
@@ -1298,6 +1638,7 @@ mod tests {
 				|_, _| Ok(TestMessageProofParser {
 					failing: true,
 					messages: no_messages_range(),
+					messages_expiry: None,
 					outbound_lane_data: Some(OutboundLaneData {
 						oldest_unpruned_nonce: 1,
 						latest_received_nonce: 1,
@@ -1318,6 +1659,7 @@ mod tests {
 				|_, _| Ok(TestMessageProofParser {
 					failing: false,
 					messages: no_messages_range(),
+					messages_expiry: None,
 					outbound_lane_data: None,
 				}),
 			),
@@ -1334,6 +1676,7 @@ mod tests {
 				|_, _| Ok(TestMessageProofParser {
 					failing: false,
 					messages: no_messages_range(),
+					messages_expiry: None,
 					outbound_lane_data: Some(OutboundLaneData {
 						oldest_unpruned_nonce: 1,
 						latest_received_nonce: 1,
@@ -1366,6 +1709,7 @@ mod tests {
 				|_, _| Ok(TestMessageProofParser {
 					failing: false,
 					messages: 1..=1,
+					messages_expiry: None,
 					outbound_lane_data: Some(OutboundLaneData {
 						oldest_unpruned_nonce: 1,
 						latest_received_nonce: 1,
@@ -1384,6 +1728,66 @@ mod tests {
 					messages: vec![Message {
 						key: MessageKey { lane_id: Default::default(), nonce: 1 },
 						data: MessageData { payload: 1u64.encode(), fee: BridgedChainBalance(0) },
+						expiry: None,
+					}],
+				},
+			)]
+			.into_iter()
+			.collect()),
+		);
+	}
+
+	#[test]
+	fn message_proof_is_rejected_if_message_expiry_is_malformed() {
+		struct MalformedExpiryMessageProofParser;
+
+		impl target::MessageProofParser for MalformedExpiryMessageProofParser {
+			fn read_raw_outbound_lane_data(&self, _lane_id: &LaneId) -> Option<Vec<u8>> {
+				None
+			}
+
+			fn read_raw_message(&self, message_key: &MessageKey) -> Option<Vec<u8>> {
+				let mut raw_message_data = MessageData::<BridgedChainBalance> {
+					payload: message_key.nonce.encode(),
+					fee: BridgedChainBalance(0),
+				}
+				.encode();
+				raw_message_data.push(42);
+				Some(raw_message_data)
+			}
+		}
+
+		assert_eq!(
+			target::verify_messages_proof_with_parser::<OnThisChainBridge, _, _>(
+				messages_proof(1),
+				1,
+				|_, _| Ok(MalformedExpiryMessageProofParser),
+			),
+			Err(VerificationError::FailedToDecodeMessageExpiry),
+		);
+	}
+
+	#[test]
+	fn message_proof_with_message_expiry_is_accepted() {
+		assert_eq!(
+			target::verify_messages_proof_with_parser::<OnThisChainBridge, _, _>(
+				messages_proof(1),
+				1,
+				|_, _| Ok(TestMessageProofParser {
+					failing: false,
+					messages: 1..=1,
+					messages_expiry: Some(MessageExpiry::BlockNumber(100)),
+					outbound_lane_data: None,
+				}),
+			),
+			Ok(vec![(
+				Default::default(),
+				ProvedLaneMessages {
+					lane_state: None,
+					messages: vec![Message {
+						key: MessageKey { lane_id: Default::default(), nonce: 1 },
+						data: MessageData { payload: 1u64.encode(), fee: BridgedChainBalance(0) },
+						expiry: Some(MessageExpiry::BlockNumber(100)),
 					}],
 				},
 			)]
@@ -1392,6 +1796,122 @@ mod tests {
 		);
 	}
 
+	fn multi_lane_messages_proof(
+		lanes: Vec<(LaneId, MessageNonce)>,
+	) -> target::FromBridgedChainMultiLaneMessagesProof<()> {
+		target::FromBridgedChainMultiLaneMessagesProof {
+			bridged_header_hash: (),
+			storage_proof: vec![],
+			lanes: lanes
+				.into_iter()
+				.map(|(lane, nonces_end)| target::LaneMessagesRange {
+					lane,
+					nonces_start: 1,
+					nonces_end,
+				})
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn multi_lane_messages_proof_is_rejected_if_it_has_no_lanes() {
+		assert_eq!(
+			target::verify_multi_lane_messages_proof_with_parser::<
+				OnThisChainBridge,
+				_,
+				TestMessageProofParser,
+			>(multi_lane_messages_proof(vec![]), 0, |_, _| unreachable!()),
+			Err(VerificationError::EmptyMessageProof),
+		);
+	}
+
+	#[test]
+	fn multi_lane_messages_proof_is_rejected_if_lane_is_duplicated() {
+		assert_eq!(
+			target::verify_multi_lane_messages_proof_with_parser::<
+				OnThisChainBridge,
+				_,
+				TestMessageProofParser,
+			>(
+				multi_lane_messages_proof(vec![([0, 0, 0, 1], 1), ([0, 0, 0, 1], 1)]),
+				2,
+				|_, _| unreachable!(),
+			),
+			Err(VerificationError::DuplicateLaneInProof),
+		);
+	}
+
+	#[test]
+	fn multi_lane_messages_proof_is_rejected_if_declared_wrong_number_of_messages() {
+		assert_eq!(
+			target::verify_multi_lane_messages_proof_with_parser::<
+				OnThisChainBridge,
+				_,
+				TestMessageProofParser,
+			>(
+				multi_lane_messages_proof(vec![([0, 0, 0, 1], 5), ([0, 0, 0, 2], 5)]),
+				5,
+				|_, _| unreachable!(),
+			),
+			Err(VerificationError::MessagesCountMismatch),
+		);
+	}
+
+	#[test]
+	fn multi_lane_messages_proof_is_rejected_if_any_lane_is_empty() {
+		assert_eq!(
+			target::verify_multi_lane_messages_proof_with_parser::<OnThisChainBridge, _, _>(
+				multi_lane_messages_proof(vec![([0, 0, 0, 1], 1), ([0, 0, 0, 2], 0)]),
+				1,
+				|_, _| Ok(TestMessageProofParser {
+					failing: false,
+					messages: 1..=1,
+					messages_expiry: None,
+					outbound_lane_data: None,
+				}),
+			),
+			Err(VerificationError::EmptyMessageProof),
+		);
+	}
+
+	#[test]
+	fn multi_lane_messages_proof_is_accepted() {
+		let lane_messages = |lane_id: LaneId| ProvedLaneMessages {
+			lane_state: None,
+			messages: vec![
+				Message {
+					key: MessageKey { lane_id, nonce: 1 },
+					data: MessageData { payload: 1u64.encode(), fee: BridgedChainBalance(0) },
+					expiry: None,
+				},
+				Message {
+					key: MessageKey { lane_id, nonce: 2 },
+					data: MessageData { payload: 2u64.encode(), fee: BridgedChainBalance(0) },
+					expiry: None,
+				},
+			],
+		};
+
+		assert_eq!(
+			target::verify_multi_lane_messages_proof_with_parser::<OnThisChainBridge, _, _>(
+				multi_lane_messages_proof(vec![([0, 0, 0, 1], 2), ([0, 0, 0, 2], 2)]),
+				4,
+				|_, _| Ok(TestMessageProofParser {
+					failing: false,
+					messages: 1..=2,
+					messages_expiry: None,
+					outbound_lane_data: None,
+				}),
+			),
+			Ok(vec![
+				([0, 0, 0, 1], lane_messages([0, 0, 0, 1])),
+				([0, 0, 0, 2], lane_messages([0, 0, 0, 2]))
+			]
+			.into_iter()
+			.collect()),
+		);
+	}
+
 	#[test]
 	fn verify_messages_proof_with_parser_does_not_panic_if_messages_count_mismatches() {
 		assert_eq!(
@@ -1401,6 +1921,7 @@ mod tests {
 				|_, _| Ok(TestMessageProofParser {
 					failing: false,
 					messages: 0..=u64::MAX,
+					messages_expiry: None,
 					outbound_lane_data: Some(OutboundLaneData {
 						oldest_unpruned_nonce: 1,
 						latest_received_nonce: 1,
@@ -1411,4 +1932,76 @@ mod tests {
 			Err(VerificationError::MessagesCountMismatch),
 		);
 	}
+
+	fn messages_delivery_proof_storage(
+		inbound_lane_data: &InboundLaneData<ThisChainAccountId>,
+		dispatch_statuses: Option<Vec<u8>>,
+	) -> StorageProofChecker<BlakeTwo256> {
+		let mut root = Default::default();
+		let mut mdb = MemoryDB::default();
+		{
+			let mut trie = TrieDBMutBuilderV1::<BlakeTwo256>::new(&mut mdb, &mut root).build();
+			let storage_key = bp_messages::storage_keys::inbound_lane_data_key("", TEST_LANE_ID);
+			trie.insert(&storage_key.0, &inbound_lane_data.encode()).unwrap();
+			if let Some(dispatch_statuses) = dispatch_statuses {
+				let storage_key =
+					bp_messages::storage_keys::inbound_lane_dispatch_statuses_key("", TEST_LANE_ID);
+				trie.insert(&storage_key.0, &dispatch_statuses).unwrap();
+			}
+		}
+
+		let mut proof_recorder = Recorder::<LayoutV1<BlakeTwo256>>::new();
+		record_all_trie_keys::<LayoutV1<BlakeTwo256>, _>(&mdb, &root, &mut proof_recorder).unwrap();
+		let storage_proof = proof_recorder.drain().into_iter().map(|n| n.data.to_vec()).collect();
+		StorageProofChecker::new(root, StorageProof::new(storage_proof)).unwrap()
+	}
+
+	#[test]
+	fn messages_delivery_proof_brings_dispatch_statuses_if_they_are_proved() {
+		let inbound_lane_data = InboundLaneData {
+			last_confirmed_nonce: 1,
+			relayers: vec![
+				bp_messages::UnrewardedRelayer {
+					relayer: ThisChainAccountId(1),
+					messages: bp_messages::DeliveredMessages::new(2, true),
+				},
+				bp_messages::UnrewardedRelayer {
+					relayer: ThisChainAccountId(2),
+					messages: bp_messages::DeliveredMessages::new(3, false),
+				},
+			]
+			.into_iter()
+			.collect(),
+		};
+		let verify = |dispatch_statuses| {
+			source::do_verify_messages_delivery_proof::<OnThisChainBridge, BlakeTwo256>(
+				*TEST_LANE_ID,
+				messages_delivery_proof_storage(&inbound_lane_data, dispatch_statuses),
+			)
+		};
+
+		// statuses are not proved
+		assert_eq!(verify(None), Ok((*TEST_LANE_ID, inbound_lane_data.clone())));
+		// statuses have unknown format
+		assert_eq!(verify(Some(vec![42])), Ok((*TEST_LANE_ID, inbound_lane_data.clone())));
+		// status of the message 3 is unknown
+		let mut dispatch_statuses = InboundLaneDispatchStatuses::default();
+		dispatch_statuses.note_dispatch_status(2, Some(MessageDispatchStatus::Dispatched));
+		dispatch_statuses.note_dispatch_status(3, None);
+		let mut expected_lane_data = inbound_lane_data.clone();
+		expected_lane_data.relayers[0].messages.dispatch_statuses =
+			vec![MessageDispatchStatus::Dispatched];
+		assert_eq!(
+			verify(Some(dispatch_statuses.encode())),
+			Ok((*TEST_LANE_ID, expected_lane_data.clone()))
+		);
+		// all statuses are known
+		dispatch_statuses.set_dispatch_status(3, Some(MessageDispatchStatus::CallFailed));
+		expected_lane_data.relayers[1].messages.dispatch_statuses =
+			vec![MessageDispatchStatus::CallFailed];
+		assert_eq!(
+			verify(Some(dispatch_statuses.encode())),
+			Ok((*TEST_LANE_ID, expected_lane_data))
+		);
+	}
 }
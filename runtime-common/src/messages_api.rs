@@ -0,0 +1,169 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Helpers for implementing the `bp_messages::OutboundLaneApi` and `bp_messages::InboundLaneApi`
+//! runtime APIs on top of the messages pallet.
+
+// crates.io
+use codec::Decode;
+// darwinia-network
+use crate::messages::{source::FromThisChainMessagePayload, MessageBridge};
+use bp_messages::{
+	InboundLaneData, LaneId, MessageNonce, OutboundLaneData, OutboundMessageDetails,
+	UnrewardedRelayersState,
+};
+use pallet_bridge_messages::{Config, Pallet};
+// substrate
+use sp_std::prelude::*;
+
+/// Implementation of the `bp_messages::OutboundLaneApi::message_details`.
+///
+/// The requested range is clamped to the range of messages that are still stored at the lane.
+pub fn outbound_message_details<Runtime, MessagesPalletInstance, BridgeConfig>(
+	lane: LaneId,
+	begin: MessageNonce,
+	end: MessageNonce,
+) -> Vec<OutboundMessageDetails<<Runtime as Config<MessagesPalletInstance>>::OutboundMessageFee>>
+where
+	Runtime: Config<MessagesPalletInstance>,
+	MessagesPalletInstance: 'static,
+	BridgeConfig: MessageBridge,
+{
+	let lane_data = Pallet::<Runtime, MessagesPalletInstance>::outbound_lane_data(lane);
+	let begin = begin.max(lane_data.oldest_unpruned_nonce);
+	let end = end.min(lane_data.latest_generated_nonce);
+
+	(begin..=end)
+		.filter_map(|nonce| {
+			let message_data =
+				Pallet::<Runtime, MessagesPalletInstance>::outbound_message_data(lane, nonce)?;
+			let dispatch_weight =
+				FromThisChainMessagePayload::<BridgeConfig>::decode(&mut &message_data.payload[..])
+					.map(|payload| payload.weight())
+					.unwrap_or_default();
+
+			Some(OutboundMessageDetails {
+				nonce,
+				dispatch_weight,
+				size: message_data.payload.len() as _,
+				delivery_and_dispatch_fee: message_data.fee,
+			})
+		})
+		.collect()
+}
+
+/// Implementation of the `bp_messages::OutboundLaneApi::outbound_lane_data`.
+pub fn outbound_lane_data<Runtime, MessagesPalletInstance>(lane: LaneId) -> OutboundLaneData
+where
+	Runtime: Config<MessagesPalletInstance>,
+	MessagesPalletInstance: 'static,
+{
+	Pallet::<Runtime, MessagesPalletInstance>::outbound_lane_data(lane)
+}
+
+/// Implementation of the `bp_messages::InboundLaneApi::inbound_lane_data`.
+pub fn inbound_lane_data<Runtime, MessagesPalletInstance>(
+	lane: LaneId,
+) -> InboundLaneData<<Runtime as Config<MessagesPalletInstance>>::InboundRelayer>
+where
+	Runtime: Config<MessagesPalletInstance>,
+	MessagesPalletInstance: 'static,
+{
+	Pallet::<Runtime, MessagesPalletInstance>::inbound_lane_data(lane)
+}
+
+/// Implementation of the `bp_messages::InboundLaneApi::unrewarded_relayers_state`.
+pub fn unrewarded_relayers_state<Runtime, MessagesPalletInstance>(
+	lane: LaneId,
+) -> UnrewardedRelayersState
+where
+	Runtime: Config<MessagesPalletInstance>,
+	MessagesPalletInstance: 'static,
+{
+	(&Pallet::<Runtime, MessagesPalletInstance>::inbound_lane_data(lane)).into()
+}
+
+/// Wraps the `sp_api::impl_runtime_apis!` call, adding implementations of the
+/// `bp_messages::OutboundLaneApi` and `bp_messages::InboundLaneApi` for given messages
+/// pallet instance.
+///
+/// Everything that follows the `messages` declaration is passed to the
+/// `sp_api::impl_runtime_apis!` as is:
+///
+/// ```ignore
+/// bridge_runtime_common::impl_runtime_apis_with_messages! {
+/// 	messages: (Runtime, Block, WithCrabMessages, WithCrabMessageBridge);
+///
+/// 	impl sp_api::Core<Block> for Runtime {
+/// 		// ...
+/// 	}
+///
+/// 	// ...
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_runtime_apis_with_messages {
+	(
+		messages: ($runtime:ident, $block:ident, $instance:path, $bridge:path);
+		$($rest:tt)*
+	) => {
+		sp_api::impl_runtime_apis! {
+			impl bp_messages::OutboundLaneApi<
+				$block,
+				<$runtime as pallet_bridge_messages::Config<$instance>>::OutboundMessageFee,
+			> for $runtime {
+				fn message_details(
+					lane: bp_messages::LaneId,
+					begin: bp_messages::MessageNonce,
+					end: bp_messages::MessageNonce,
+				) -> sp_std::vec::Vec<
+					bp_messages::OutboundMessageDetails<
+						<$runtime as pallet_bridge_messages::Config<$instance>>::OutboundMessageFee,
+					>,
+				> {
+					$crate::messages_api::outbound_message_details::<$runtime, $instance, $bridge>(
+						lane, begin, end,
+					)
+				}
+
+				fn outbound_lane_data(lane: bp_messages::LaneId) -> bp_messages::OutboundLaneData {
+					$crate::messages_api::outbound_lane_data::<$runtime, $instance>(lane)
+				}
+			}
+
+			impl bp_messages::InboundLaneApi<
+				$block,
+				<$runtime as pallet_bridge_messages::Config<$instance>>::InboundRelayer,
+			> for $runtime {
+				fn inbound_lane_data(
+					lane: bp_messages::LaneId,
+				) -> bp_messages::InboundLaneData<
+					<$runtime as pallet_bridge_messages::Config<$instance>>::InboundRelayer,
+				> {
+					$crate::messages_api::inbound_lane_data::<$runtime, $instance>(lane)
+				}
+
+				fn unrewarded_relayers_state(
+					lane: bp_messages::LaneId,
+				) -> bp_messages::UnrewardedRelayersState {
+					$crate::messages_api::unrewarded_relayers_state::<$runtime, $instance>(lane)
+				}
+			}
+
+			$($rest)*
+		}
+	};
+}
@@ -54,13 +54,13 @@ where
 	let message_payload = vec![0; params.size as usize];
 	let dispatch_origin = CallOrigin::SourceAccount(params.sender_account);
 
-	FromThisChainMessagePayload::<B> {
+	FromThisChainMessagePayload::<B>::V1(bp_message_dispatch::MessagePayload {
 		spec_version: 0,
 		weight: bp_messages::Weight::from_parts(params.size.into(), 0),
 		origin: dispatch_origin,
 		call: message_payload,
 		dispatch_fee_payment: DispatchFeePayment::AtSourceChain,
-	}
+	})
 }
 
 /// Prepare proof of messages for the `receive_messages_proof` call.
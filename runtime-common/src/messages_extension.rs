@@ -16,9 +16,7 @@
 
 // darwinia-network
 use crate::{
-	messages::{
-		source::FromBridgedChainMessagesDeliveryProof, target::FromBridgedChainMessagesProof,
-	},
+	messages::{source::FromBridgedChainMessagesDeliveryProof, target::MessagesProofLanes},
 	BridgeRuntimeFilterCall,
 };
 use pallet_bridge_messages::{Config, Pallet};
@@ -32,9 +30,10 @@ use sp_runtime::transaction_validity::TransactionValidity;
 /// the same messages/confirmations.
 impl<
 		BridgedHeaderHash,
+		MessagesProof: MessagesProofLanes,
 		SourceHeaderChain: bp_messages::target_chain::SourceHeaderChain<
 			<T as Config<I>>::InboundMessageFee,
-			MessagesProof = FromBridgedChainMessagesProof<BridgedHeaderHash>,
+			MessagesProof = MessagesProof,
 		>,
 		TargetHeaderChain: bp_messages::source_chain::TargetHeaderChain<
 			<T as Config<I>>::OutboundPayload,
@@ -53,16 +52,27 @@ impl<
 				ref proof,
 				..
 			}) => {
-				let inbound_lane_data =
-					pallet_bridge_messages::InboundLanes::<T, I>::get(proof.lane);
-				if proof.nonces_end <= inbound_lane_data.last_delivered_nonce() {
+				// the transaction is obsolete only if it brings nothing new to every bundled lane
+				let is_obsolete = proof.lanes().into_iter().all(|(lane, nonces_end)| {
+					let inbound_lane_data = pallet_bridge_messages::InboundLanes::<T, I>::get(lane);
+					let is_lane_obsolete = nonces_end <= inbound_lane_data.last_delivered_nonce();
+					if is_lane_obsolete {
+						log::trace!(
+							target: pallet_bridge_messages::LOG_TARGET,
+							"Obsolete messages in delivery transaction: \
+								lane {:?}, bundled {:?}, best {:?}",
+							lane,
+							nonces_end,
+							inbound_lane_data.last_delivered_nonce(),
+						);
+					}
+
+					is_lane_obsolete
+				});
+				if is_obsolete {
 					log::trace!(
 						target: pallet_bridge_messages::LOG_TARGET,
-						"Rejecting obsolete messages delivery transaction: \
-                            lane {:?}, bundled {:?}, best {:?}",
-						proof.lane,
-						proof.nonces_end,
-						inbound_lane_data.last_delivered_nonce(),
+						"Rejecting obsolete messages delivery transaction",
 					);
 
 					return sp_runtime::transaction_validity::InvalidTransaction::Stale.into();
@@ -0,0 +1,547 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of Parity Bridges Common.
+
+// Parity Bridges Common is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Bridges Common is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Bridges Common.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signed extension that refunds relayer if it has delivered some new messages.
+//!
+//! The refund is not paid immediately. Instead, it is registered in the relayers pallet as the
+//! relayer reward for serving the lane, so the relayer may claim it later.
+
+// core
+use core::marker::PhantomData;
+// crates.io
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+// darwinia-network
+use crate::messages::target::MessagesProofLanes;
+use bp_messages::{LaneId, MessageNonce};
+use pallet_bridge_messages::{Config as MessagesConfig, InboundLanes, LOG_TARGET};
+use pallet_bridge_relayers::{Config as RelayersConfig, Pallet as RelayersPallet};
+// substrate
+use frame_support::{
+	dispatch::{CallableCallFor, DispatchInfo, PostDispatchInfo},
+	log,
+	traits::IsSubType,
+	CloneNoBound, DefaultNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound,
+};
+use pallet_transaction_payment::OnChargeTransaction;
+use sp_runtime::{
+	traits::{DispatchInfoOf, Dispatchable, PostDispatchInfoOf, SignedExtension, Zero},
+	transaction_validity::{TransactionValidity, TransactionValidityError, ValidTransaction},
+	DispatchResult, RuntimeDebug,
+};
+use sp_std::prelude::*;
+
+/// Signed extension that refunds relayer for new messages delivery.
+///
+/// The extension only works with the `receive_messages_proof` call of the messages pallet
+/// instance `MI`. If the call has succeeded and has delivered at least one new message, the
+/// actual fee of the transaction (without tip and length fee) is registered as the relayer reward
+/// in the relayers pallet instance `RI`. If the proof has brought messages for several lanes, the
+/// fee is split evenly between all lanes that have received new messages.
+///
+/// The length fee is never refunded. Otherwise the relayer could bloat the messages proof with
+/// unused trie nodes at no cost, leaving the relayers fund to pay for it.
+#[derive(
+	DefaultNoBound,
+	CloneNoBound,
+	Decode,
+	Encode,
+	EqNoBound,
+	PartialEqNoBound,
+	RuntimeDebugNoBound,
+	TypeInfo,
+)]
+#[scale_info(skip_type_params(Runtime, MI, RI))]
+pub struct RefundRelayerForMessagesDelivery<Runtime, MI, RI>(PhantomData<(Runtime, MI, RI)>);
+
+/// Data that is gathered in `pre_dispatch` and used in `post_dispatch`.
+#[derive(RuntimeDebug, PartialEq)]
+pub struct PreDispatchData<AccountId> {
+	/// Transaction submitter (relayer) account.
+	pub relayer: AccountId,
+	/// Lanes of the messages proof, along with the best delivered nonce before the call.
+	pub lanes: Vec<(LaneId, MessageNonce)>,
+}
+
+impl<Runtime, MI, RI> RefundRelayerForMessagesDelivery<Runtime, MI, RI>
+where
+	Runtime: MessagesConfig<MI> + RelayersConfig<RI>,
+	Runtime::RuntimeCall:
+		IsSubType<CallableCallFor<pallet_bridge_messages::Pallet<Runtime, MI>, Runtime>>,
+	<Runtime::SourceHeaderChain as bp_messages::target_chain::SourceHeaderChain<
+		Runtime::InboundMessageFee,
+	>>::MessagesProof: MessagesProofLanes,
+	MI: 'static,
+	RI: 'static,
+{
+	/// Returns lanes of the messages delivery call along with their best delivered nonces.
+	///
+	/// Returns `None` if the call is not a messages delivery call.
+	fn delivery_call_lanes(call: &Runtime::RuntimeCall) -> Option<Vec<(LaneId, MessageNonce)>> {
+		match call.is_sub_type() {
+			Some(pallet_bridge_messages::Call::<Runtime, MI>::receive_messages_proof {
+				ref proof,
+				..
+			}) => Some(
+				proof
+					.lanes()
+					.into_iter()
+					.map(|(lane, _)| {
+						(lane, InboundLanes::<Runtime, MI>::get(lane).last_delivered_nonce())
+					})
+					.collect(),
+			),
+			_ => None,
+		}
+	}
+}
+
+impl<Runtime, MI, RI> SignedExtension for RefundRelayerForMessagesDelivery<Runtime, MI, RI>
+where
+	Runtime:
+		MessagesConfig<MI> + RelayersConfig<RI> + pallet_transaction_payment::Config + Send + Sync,
+	Runtime::RuntimeCall: Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>
+		+ IsSubType<CallableCallFor<pallet_bridge_messages::Pallet<Runtime, MI>, Runtime>>,
+	<Runtime::SourceHeaderChain as bp_messages::target_chain::SourceHeaderChain<
+		Runtime::InboundMessageFee,
+	>>::MessagesProof: MessagesProofLanes,
+	<Runtime as pallet_transaction_payment::Config>::OnChargeTransaction:
+		OnChargeTransaction<Runtime, Balance = <Runtime as RelayersConfig<RI>>::Reward>,
+	MI: 'static + Send + Sync,
+	RI: 'static + Send + Sync,
+{
+	type AccountId = Runtime::AccountId;
+	type AdditionalSigned = ();
+	type Call = Runtime::RuntimeCall;
+	type Pre = Option<PreDispatchData<Runtime::AccountId>>;
+
+	const IDENTIFIER: &'static str = "RefundRelayerForMessagesDelivery";
+
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		_who: &Self::AccountId,
+		_call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> TransactionValidity {
+		Ok(ValidTransaction::default())
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> Result<Self::Pre, TransactionValidityError> {
+		Ok(Self::delivery_call_lanes(call)
+			.map(|lanes| PreDispatchData { relayer: who.clone(), lanes }))
+	}
+
+	fn post_dispatch(
+		pre: Option<Self::Pre>,
+		info: &DispatchInfoOf<Self::Call>,
+		post_info: &PostDispatchInfoOf<Self::Call>,
+		_len: usize,
+		result: &DispatchResult,
+	) -> Result<(), TransactionValidityError> {
+		// we only refund successful messages delivery transactions
+		let pre = match pre.flatten() {
+			Some(pre) if result.is_ok() => pre,
+			_ => return Ok(()),
+		};
+
+		// only lanes that have received new messages are rewarded
+		let progressed_lanes = pre
+			.lanes
+			.into_iter()
+			.filter(|(lane, best_nonce_before)| {
+				InboundLanes::<Runtime, MI>::get(*lane).last_delivered_nonce() > *best_nonce_before
+			})
+			.map(|(lane, _)| lane)
+			.collect::<Vec<_>>();
+		if progressed_lanes.is_empty() {
+			log::trace!(
+				target: LOG_TARGET,
+				"Relayer {:?} has not delivered any new messages. Not refunding",
+				pre.relayer,
+			);
+			return Ok(());
+		}
+
+		// we only refund the base and weight parts of the fee, see the extension docs
+		let fee = pallet_transaction_payment::Pallet::<Runtime>::compute_actual_fee(
+			0,
+			info,
+			post_info,
+			Zero::zero(),
+		);
+		let lane_reward = fee / (progressed_lanes.len() as u32).into();
+		for lane in progressed_lanes {
+			log::trace!(
+				target: LOG_TARGET,
+				"Refunding {:?} to relayer {:?} for delivering messages at lane {:?}",
+				lane_reward,
+				pre.relayer,
+				lane,
+			);
+			RelayersPallet::<Runtime, RI>::register_relayer_reward(lane, &pre.relayer, lane_reward);
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	// darwinia-network
+	use super::*;
+	use crate::messages::target::{FromBridgedChainMultiLaneMessagesProof, LaneMessagesRange};
+	use bp_messages::{
+		source_chain::ForbidOutboundMessages,
+		target_chain::{ForbidInboundMessages, ProvedMessages, SourceHeaderChain},
+		InboundLaneData, Message, VerificationError,
+	};
+	use pallet_bridge_relayers::PayRewardFromAccount;
+	// substrate
+	use frame_support::{
+		dispatch::{DispatchClass, Pays},
+		parameter_types,
+		traits::Get,
+		weights::{IdentityFee, Weight},
+	};
+	use frame_system::mocking::*;
+	use pallet_transaction_payment::{ConstantMultiplier, CurrencyAdapter};
+	use sp_core::{ConstBool, ConstU32, ConstU64, H256};
+	use sp_runtime::{
+		testing::Header as SubstrateHeader,
+		traits::{BlakeTwo256, IdentityLookup},
+		DispatchError,
+	};
+
+	type AccountId = u64;
+	type Balance = u64;
+	type Block = MockBlock<TestRuntime>;
+	type UncheckedExtrinsic = MockUncheckedExtrinsic<TestRuntime>;
+	type TestExtension = RefundRelayerForMessagesDelivery<TestRuntime, (), ()>;
+
+	const TEST_LANE_ID: LaneId = [0, 0, 0, 1];
+	const TEST_LANE_ID_2: LaneId = [0, 0, 0, 2];
+	const RELAYER: AccountId = 1;
+	const RELAYERS_FUND_ACCOUNT: AccountId = 0xDEAD;
+
+	frame_support::construct_runtime! {
+		pub enum TestRuntime where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+			Balances: pallet_balances::{Pallet, Call, Event<T>},
+			TransactionPayment: pallet_transaction_payment::{Pallet, Storage, Event<T>},
+			Messages: pallet_bridge_messages::{Pallet, Call, Event<T>},
+			Relayers: pallet_bridge_relayers::{Pallet, Call, Event<T>},
+		}
+	}
+
+	pub struct BlockWeights;
+	impl Get<frame_system::limits::BlockWeights> for BlockWeights {
+		fn get() -> frame_system::limits::BlockWeights {
+			frame_system::limits::BlockWeights::builder()
+				.base_block(Weight::zero())
+				.for_class(DispatchClass::all(), |weights| {
+					weights.base_extrinsic = Weight::zero();
+				})
+				.for_class(DispatchClass::non_mandatory(), |weights| {
+					weights.max_total = Weight::from_parts(1024, u64::MAX).into();
+				})
+				.build_or_panic()
+		}
+	}
+
+	impl frame_system::Config for TestRuntime {
+		type AccountData = pallet_balances::AccountData<Balance>;
+		type AccountId = AccountId;
+		type BaseCallFilter = frame_support::traits::Everything;
+		type BlockHashCount = ConstU64<250>;
+		type BlockLength = ();
+		type BlockNumber = u64;
+		type BlockWeights = BlockWeights;
+		type DbWeight = ();
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Header = SubstrateHeader;
+		type Index = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type MaxConsumers = ConstU32<16>;
+		type OnKilledAccount = ();
+		type OnNewAccount = ();
+		type OnSetCode = ();
+		type PalletInfo = PalletInfo;
+		type RuntimeCall = RuntimeCall;
+		type RuntimeEvent = RuntimeEvent;
+		type RuntimeOrigin = RuntimeOrigin;
+		type SS58Prefix = ();
+		type SystemWeightInfo = ();
+		type Version = ();
+	}
+
+	impl pallet_balances::Config for TestRuntime {
+		type AccountStore = frame_system::Pallet<TestRuntime>;
+		type Balance = Balance;
+		type DustRemoval = ();
+		type ExistentialDeposit = ConstU64<1>;
+		type FreezeIdentifier = ();
+		type HoldIdentifier = ();
+		type MaxFreezes = ();
+		type MaxHolds = ();
+		type MaxLocks = ();
+		type MaxReserves = ();
+		type ReserveIdentifier = ();
+		type RuntimeEvent = RuntimeEvent;
+		type WeightInfo = ();
+	}
+
+	impl pallet_transaction_payment::Config for TestRuntime {
+		type FeeMultiplierUpdate = ();
+		type LengthToFee = ConstantMultiplier<Balance, ConstU64<1>>;
+		type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
+		type OperationalFeeMultiplier = frame_support::traits::ConstU8<1>;
+		type RuntimeEvent = RuntimeEvent;
+		type WeightToFee = IdentityFee<Balance>;
+	}
+
+	pub struct AccountIdConverter;
+	impl sp_runtime::traits::Convert<H256, AccountId> for AccountIdConverter {
+		fn convert(hash: H256) -> AccountId {
+			hash.to_low_u64_ne()
+		}
+	}
+
+	pub struct TestUnixTime;
+	impl frame_support::traits::UnixTime for TestUnixTime {
+		fn now() -> core::time::Duration {
+			core::time::Duration::from_millis(0)
+		}
+	}
+
+	/// Source header chain that only knows lanes of the multi-lane proof. Proofs are never
+	/// verified in these tests.
+	pub struct TestSourceHeaderChain;
+	impl SourceHeaderChain<Balance> for TestSourceHeaderChain {
+		type MessagesProof = FromBridgedChainMultiLaneMessagesProof<H256>;
+
+		fn verify_messages_proof(
+			_proof: Self::MessagesProof,
+			_messages_count: u32,
+		) -> Result<ProvedMessages<Message<Balance>>, VerificationError> {
+			Err(VerificationError::Other("not supported in tests"))
+		}
+
+		fn lanes_count(proof: &Self::MessagesProof) -> u32 {
+			proof.lanes.len() as u32
+		}
+	}
+
+	impl pallet_bridge_messages::Config for TestRuntime {
+		type AccountIdConverter = AccountIdConverter;
+		type BridgedChainId = ();
+		type DepositLegacyEvents = ConstBool<false>;
+		type FailedMessageLifetime = ConstU64<10>;
+		type InboundMessageFee = Balance;
+		type InboundPayload = ();
+		type InboundRelayer = AccountId;
+		type LaneMessageVerifier = ForbidOutboundMessages;
+		type MaxDeferredMessagesAtInboundLane = ConstU32<0>;
+		type MaxFailedMessagePayloadSize = ConstU32<1024>;
+		type MaxFailedMessagesAtInboundLane = ConstU64<2>;
+		type MaxMessagesToPruneAtOnce = ConstU64<10>;
+		type MaxOutboundMessagesInBatch = ConstU32<4>;
+		type MaxUnconfirmedMessagesAtInboundLane = ConstU64<32>;
+		type MaxUnrewardedRelayerEntriesAtInboundLane = ConstU64<16>;
+		type MaximalOutboundPayloadSize = ConstU32<1024>;
+		type MessageCancellationTimeout = ConstU64<10>;
+		type MessageDeliveryAndDispatchPayment = ForbidOutboundMessages;
+		type MessageDispatch = ForbidInboundMessages;
+		type MessagesCommitmentHasher = BlakeTwo256;
+		type OnDeliveryConfirmed = ();
+		type OnMessageAccepted = ();
+		type OnMessageDeliveryResult = ();
+		type OutboundMessageFee = Balance;
+		type OutboundPayload = ();
+		type Parameter = ();
+		type RuntimeEvent = RuntimeEvent;
+		type SourceHeaderChain = TestSourceHeaderChain;
+		type TargetHeaderChain = ForbidOutboundMessages;
+		type UnixTime = TestUnixTime;
+		type WeightInfo = ();
+	}
+
+	parameter_types! {
+		pub const RelayersFundAccount: AccountId = RELAYERS_FUND_ACCOUNT;
+	}
+	impl pallet_bridge_relayers::Config for TestRuntime {
+		type PaymentProcedure = PayRewardFromAccount<Balances, RelayersFundAccount>;
+		type Reward = Balance;
+		type RuntimeEvent = RuntimeEvent;
+		type WeightInfo = ();
+	}
+
+	fn run_test(test: impl FnOnce()) {
+		let t = frame_system::GenesisConfig::default().build_storage::<TestRuntime>().unwrap();
+		sp_io::TestExternalities::new(t).execute_with(test)
+	}
+
+	fn delivery_call(lanes: Vec<LaneId>) -> RuntimeCall {
+		RuntimeCall::Messages(pallet_bridge_messages::Call::receive_messages_proof {
+			relayer_id_at_bridged_chain: RELAYER,
+			proof: FromBridgedChainMultiLaneMessagesProof {
+				bridged_header_hash: Default::default(),
+				storage_proof: vec![],
+				lanes: lanes
+					.into_iter()
+					.map(|lane| LaneMessagesRange { lane, nonces_start: 1, nonces_end: 1 })
+					.collect(),
+			},
+			messages_count: 1,
+			dispatch_weight: Weight::zero(),
+		})
+	}
+
+	fn deliver_message(lane: LaneId, nonce: MessageNonce) {
+		InboundLanes::<TestRuntime>::insert(
+			lane,
+			InboundLaneData::<AccountId> { last_confirmed_nonce: nonce, ..Default::default() },
+		);
+	}
+
+	fn dispatch_info() -> DispatchInfo {
+		DispatchInfo {
+			weight: Weight::from_parts(1_000, 0),
+			class: DispatchClass::Normal,
+			pays_fee: Pays::Yes,
+		}
+	}
+
+	fn post_dispatch_info() -> PostDispatchInfo {
+		PostDispatchInfo { actual_weight: Some(Weight::from_parts(800, 0)), pays_fee: Pays::Yes }
+	}
+
+	fn run_extension(call: RuntimeCall, deliver: impl FnOnce(), result: DispatchResult) {
+		let pre = TestExtension::default().pre_dispatch(&RELAYER, &call, &dispatch_info(), 100);
+		deliver();
+		assert_eq!(
+			TestExtension::post_dispatch(
+				Some(pre.unwrap()),
+				&dispatch_info(),
+				&post_dispatch_info(),
+				100,
+				&result,
+			),
+			Ok(()),
+		);
+	}
+
+	#[test]
+	fn pre_dispatch_ignores_non_delivery_calls() {
+		run_test(|| {
+			let call = RuntimeCall::System(frame_system::Call::remark { remark: vec![] });
+			assert_eq!(
+				TestExtension::default().pre_dispatch(&RELAYER, &call, &dispatch_info(), 0),
+				Ok(None),
+			);
+		});
+	}
+
+	#[test]
+	fn pre_dispatch_remembers_best_delivered_nonces() {
+		run_test(|| {
+			deliver_message(TEST_LANE_ID, 5);
+			assert_eq!(
+				TestExtension::default().pre_dispatch(
+					&RELAYER,
+					&delivery_call(vec![TEST_LANE_ID, TEST_LANE_ID_2]),
+					&dispatch_info(),
+					0,
+				),
+				Ok(Some(PreDispatchData {
+					relayer: RELAYER,
+					lanes: vec![(TEST_LANE_ID, 5), (TEST_LANE_ID_2, 0)],
+				})),
+			);
+		});
+	}
+
+	#[test]
+	fn post_dispatch_refunds_successful_delivery() {
+		run_test(|| {
+			run_extension(
+				delivery_call(vec![TEST_LANE_ID]),
+				|| deliver_message(TEST_LANE_ID, 1),
+				Ok(()),
+			);
+
+			// actual weight fee is refunded, length fee is not
+			assert_eq!(Relayers::relayer_reward(RELAYER, TEST_LANE_ID), Some(800));
+		});
+	}
+
+	#[test]
+	fn post_dispatch_does_not_refund_failed_delivery() {
+		run_test(|| {
+			run_extension(
+				delivery_call(vec![TEST_LANE_ID]),
+				|| deliver_message(TEST_LANE_ID, 1),
+				Err(DispatchError::BadOrigin),
+			);
+
+			assert_eq!(Relayers::relayer_reward(RELAYER, TEST_LANE_ID), None);
+		});
+	}
+
+	#[test]
+	fn post_dispatch_does_not_refund_delivery_without_new_messages() {
+		run_test(|| {
+			deliver_message(TEST_LANE_ID, 1);
+			run_extension(delivery_call(vec![TEST_LANE_ID]), || (), Ok(()));
+
+			assert_eq!(Relayers::relayer_reward(RELAYER, TEST_LANE_ID), None);
+		});
+	}
+
+	#[test]
+	fn post_dispatch_splits_refund_between_progressed_lanes() {
+		run_test(|| {
+			let third_lane = [0, 0, 0, 3];
+			run_extension(
+				delivery_call(vec![TEST_LANE_ID, TEST_LANE_ID_2, third_lane]),
+				|| {
+					deliver_message(TEST_LANE_ID, 1);
+					deliver_message(TEST_LANE_ID_2, 1);
+				},
+				Ok(()),
+			);
+
+			assert_eq!(Relayers::relayer_reward(RELAYER, TEST_LANE_ID), Some(400));
+			assert_eq!(Relayers::relayer_reward(RELAYER, TEST_LANE_ID_2), Some(400));
+			assert_eq!(Relayers::relayer_reward(RELAYER, third_lane), None);
+		});
+	}
+}